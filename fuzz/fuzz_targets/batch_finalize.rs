@@ -0,0 +1,80 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Splits arbitrary bytes into a batch of evaluation elements followed by a
+//! proof, and finalizes them against a fixed batch of clients. Batches of the
+//! wrong size or with tampered elements have to be rejected without panicking.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rand::{rngs::StdRng, SeedableRng};
+use voprf::{CipherSuite, EvaluationElement, Metadata, Proof, VerifiableClient, VerifiableServer};
+
+struct Ristretto255Sha512;
+impl CipherSuite for Ristretto255Sha512 {
+    type Group = curve25519_dalek::ristretto::RistrettoPoint;
+    type Hash = sha2::Sha512;
+}
+
+const ELEM_LEN: usize = 32;
+const PROOF_LEN: usize = 64;
+
+fuzz_target!(|data: &[u8]| {
+    let mut rng = StdRng::seed_from_u64(0);
+    let inputs = [b"input 1", b"input 2", b"input 3"];
+    let metadata = Metadata::none();
+
+    let server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng).unwrap();
+    let mut clients = Vec::new();
+    let mut blinded_elements = Vec::new();
+
+    for input in &inputs {
+        let client_blind_result = VerifiableClient::blind(*input, &mut rng).unwrap();
+        clients.push(client_blind_result.state);
+        blinded_elements.push(client_blind_result.message);
+    }
+
+    let server_result = server
+        .batch_evaluate_derandomized(&blinded_elements, &metadata, &[])
+        .unwrap();
+
+    if data.len() < PROOF_LEN {
+        return;
+    }
+
+    let (elements, proof) = data.split_at(data.len() - PROOF_LEN);
+    let messages: Result<Vec<_>, _> = elements
+        .chunks(ELEM_LEN)
+        .map(EvaluationElement::deserialize)
+        .collect();
+    let (messages, proof) = match (messages, Proof::deserialize(proof)) {
+        (Ok(messages), Ok(proof)) => (messages, proof),
+        _ => return,
+    };
+
+    let accepted = VerifiableClient::batch_finalize(
+        &inputs,
+        &clients,
+        &messages,
+        proof,
+        server.get_public_key(),
+        &metadata,
+    )
+    .map(|outputs| outputs.collect::<Result<Vec<_>, _>>().is_ok())
+    .unwrap_or(false);
+
+    if accepted {
+        let genuine: Vec<u8> = server_result
+            .messages
+            .iter()
+            .flat_map(EvaluationElement::serialize)
+            .chain(server_result.proof.serialize())
+            .collect();
+        assert_eq!(data, genuine.as_slice(), "accepted a forged batch");
+    }
+});
@@ -0,0 +1,70 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Finalizes a genuine batch after reordering, resizing and substituting its
+//! evaluation elements, and changing its metadata, as chosen by the fuzzer.
+//! Anything but the genuine batch has to be rejected.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rand::{rngs::StdRng, SeedableRng};
+use voprf::{CipherSuite, EvaluationElement, Metadata, VerifiableClient, VerifiableServer};
+
+struct Ristretto255Sha512;
+impl CipherSuite for Ristretto255Sha512 {
+    type Group = curve25519_dalek::ristretto::RistrettoPoint;
+    type Hash = sha2::Sha512;
+}
+
+type Substitution = Option<(u8, EvaluationElement<Ristretto255Sha512>)>;
+
+fuzz_target!(|input: (Vec<u8>, Substitution, Metadata)| {
+    let (order, substitution, metadata) = input;
+    let mut rng = StdRng::seed_from_u64(0);
+    let inputs = [b"input 1", b"input 2", b"input 3"];
+
+    let server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng).unwrap();
+    let mut clients = Vec::new();
+    let mut blinded_elements = Vec::new();
+
+    for input in &inputs {
+        let client_blind_result = VerifiableClient::blind(*input, &mut rng).unwrap();
+        clients.push(client_blind_result.state);
+        blinded_elements.push(client_blind_result.message);
+    }
+
+    let server_result = server
+        .batch_evaluate_derandomized(&blinded_elements, &Metadata::none(), &[])
+        .unwrap();
+
+    let mut messages: Vec<_> = order
+        .iter()
+        .map(|index| server_result.messages[usize::from(*index) % inputs.len()].clone())
+        .collect();
+    if let Some((index, element)) = substitution {
+        if let Some(message) = messages.get_mut(usize::from(index)) {
+            *message = element;
+        }
+    }
+
+    let accepted = VerifiableClient::batch_finalize(
+        &inputs,
+        &clients,
+        &messages,
+        server_result.proof,
+        server.get_public_key(),
+        &metadata,
+    )
+    .map(|outputs| outputs.collect::<Result<Vec<_>, _>>().is_ok())
+    .unwrap_or(false);
+
+    if accepted {
+        assert_eq!(messages, server_result.messages, "accepted a forged batch");
+        assert_eq!(metadata, Metadata::none(), "accepted forged metadata");
+    }
+});
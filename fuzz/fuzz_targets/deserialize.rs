@@ -0,0 +1,47 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Deserializes arbitrary bytes as every message and state type. Whatever is
+//! accepted has to serialize back to the exact same bytes, otherwise the
+//! encoding is malleable.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use voprf::{
+    BlindedElement, CipherSuite, EvaluationElement, NonVerifiableClient, NonVerifiableServer,
+    Proof, VerifiableClient, VerifiableServer,
+};
+
+struct Ristretto255Sha512;
+impl CipherSuite for Ristretto255Sha512 {
+    type Group = curve25519_dalek::ristretto::RistrettoPoint;
+    type Hash = sha2::Sha512;
+}
+
+macro_rules! check_round_trip {
+    ($data:expr, $($type:ident),+$(,)?) => {
+        $(
+            if let Ok(value) = $type::<Ristretto255Sha512>::deserialize($data) {
+                assert_eq!(value.serialize(), $data, "{} is malleable", stringify!($type));
+            }
+        )+
+    };
+}
+
+fuzz_target!(|data: &[u8]| {
+    check_round_trip!(
+        data,
+        BlindedElement,
+        EvaluationElement,
+        Proof,
+        NonVerifiableClient,
+        VerifiableClient,
+        NonVerifiableServer,
+        VerifiableServer,
+    );
+});
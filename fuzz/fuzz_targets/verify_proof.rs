@@ -0,0 +1,56 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Verifies arbitrary proofs against a fixed, valid evaluation. The only proof
+//! which may be accepted is the one produced by the server.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rand::{rngs::StdRng, SeedableRng};
+use voprf::{CipherSuite, Metadata, Proof, VerifiableClient, VerifiableServer};
+
+struct Ristretto255Sha512;
+impl CipherSuite for Ristretto255Sha512 {
+    type Group = curve25519_dalek::ristretto::RistrettoPoint;
+    type Hash = sha2::Sha512;
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut rng = StdRng::seed_from_u64(0);
+    let input = b"input";
+    let metadata = Metadata::none();
+
+    let server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng).unwrap();
+    let client_blind_result = VerifiableClient::blind(input, &mut rng).unwrap();
+    let server_result = server
+        .evaluate_derandomized(client_blind_result.message, &metadata, &[])
+        .unwrap();
+
+    let proof = match Proof::deserialize(data) {
+        Ok(proof) => proof,
+        Err(_) => return,
+    };
+
+    if client_blind_result
+        .state
+        .finalize_once(
+            input,
+            server_result.message,
+            proof,
+            server.get_public_key(),
+            &metadata,
+        )
+        .is_ok()
+    {
+        assert_eq!(
+            data,
+            server_result.proof.serialize(),
+            "accepted a forged proof"
+        );
+    }
+});
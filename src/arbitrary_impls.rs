@@ -0,0 +1,152 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Implementations of [Arbitrary] for messages, proofs and states, so
+//! structure-aware fuzzers can explore the protocol instead of only the byte
+//! decoding. Scalars and elements are derived from fuzzer-provided seeds, so
+//! they are always valid, and any mismatch between values (e.g. a reordered
+//! batch) is the one the fuzzer chose.
+
+use crate::{
+    ciphersuite::CipherSuite, group::Group, modes::NoElement, BlindedElement, EvaluationElement,
+    Metadata, NonVerifiableClient, NonVerifiableServer, Proof, VerifiableClient, VerifiableServer,
+};
+use arbitrary::{Arbitrary, Error, Result, Unstructured};
+
+static STR_ARBITRARY: &[u8] = b"VOPRF arbitrary";
+
+// Derives a non-zero scalar from a seed
+fn scalar<CS: CipherSuite>(u: &mut Unstructured<'_>) -> Result<<CS::Group as Group>::Scalar> {
+    let seed: [u8; 32] = u.arbitrary()?;
+    let scalar = CS::Group::hash_to_scalar::<CS>(&[&seed], &[STR_ARBITRARY])
+        .map_err(|_| Error::IncorrectFormat)?;
+
+    if CS::Group::ct_equal_scalar(&scalar, &CS::Group::scalar_zero()) {
+        return Err(Error::IncorrectFormat);
+    }
+
+    Ok(scalar)
+}
+
+// Derives an optional non-zero scalar from a seed
+fn optional_scalar<CS: CipherSuite>(
+    u: &mut Unstructured<'_>,
+) -> Result<Option<<CS::Group as Group>::Scalar>> {
+    if u.arbitrary()? {
+        scalar::<CS>(u).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+// Derives an element, which is never the identity, from a seed
+fn element<CS: CipherSuite>(u: &mut Unstructured<'_>) -> Result<CS::Group> {
+    Ok(CS::Group::base_point() * &scalar::<CS>(u)?)
+}
+
+impl<'a> Arbitrary<'a> for Metadata {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self(u.arbitrary()?))
+    }
+}
+
+macro_rules! impl_arbitrary_for {
+    ($name:ident, $($field:ident => $generator:ident),+$(,)?) => {
+        impl<'a, CS: CipherSuite> Arbitrary<'a> for $name<CS> {
+            fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+                Ok(Self {
+                    $($field: $generator::<CS>(u)?),+
+                })
+            }
+        }
+    };
+}
+
+impl_arbitrary_for!(BlindedElement, value => element);
+impl_arbitrary_for!(EvaluationElement, value => element);
+impl_arbitrary_for!(Proof, c_scalar => scalar, s_scalar => scalar);
+impl_arbitrary_for!(
+    VerifiableClient,
+    blind => scalar,
+    blinded_element => element,
+    input_binding => optional_scalar,
+);
+
+impl<'a, CS: CipherSuite> Arbitrary<'a> for NonVerifiableClient<CS> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            blind: scalar::<CS>(u)?,
+            blinded_element: NoElement,
+            input_binding: optional_scalar::<CS>(u)?,
+        })
+    }
+}
+
+impl<'a, CS: CipherSuite> Arbitrary<'a> for NonVerifiableServer<CS> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Self::new_with_key(&CS::Group::scalar_as_bytes(scalar::<CS>(u)?))
+            .map_err(|_| Error::IncorrectFormat)
+    }
+}
+
+impl<'a, CS: CipherSuite> Arbitrary<'a> for VerifiableServer<CS> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Self::new_with_key(&CS::Group::scalar_as_bytes(scalar::<CS>(u)?))
+            .map_err(|_| Error::IncorrectFormat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Ristretto255Sha512;
+
+    #[test]
+    fn test_arbitrary() -> Result<()> {
+        let data = [0x5a; 512];
+        let mut u = Unstructured::new(&data);
+
+        let server = VerifiableServer::<Ristretto255Sha512>::arbitrary(&mut u)?;
+        let client = VerifiableClient::<Ristretto255Sha512>::arbitrary(&mut u)?;
+        let proof = Proof::<Ristretto255Sha512>::arbitrary(&mut u)?;
+        let metadata = Metadata::arbitrary(&mut u)?;
+
+        // Derived values are valid, so they survive a round trip
+        assert_eq!(
+            VerifiableServer::<Ristretto255Sha512>::deserialize(&server.serialize())
+                .unwrap()
+                .serialize(),
+            server.serialize()
+        );
+        assert_eq!(
+            VerifiableClient::<Ristretto255Sha512>::deserialize(&client.serialize())
+                .unwrap()
+                .serialize(),
+            client.serialize()
+        );
+        assert_eq!(
+            Proof::<Ristretto255Sha512>::deserialize(&proof.serialize())
+                .unwrap()
+                .serialize(),
+            proof.serialize()
+        );
+
+        // An arbitrary evaluation is rejected by the client
+        let evaluation_element = EvaluationElement::arbitrary(&mut u)?;
+        assert!(client
+            .finalize_once(
+                b"input",
+                evaluation_element,
+                proof,
+                server.get_public_key(),
+                &metadata,
+            )
+            .is_err());
+
+        Ok(())
+    }
+}
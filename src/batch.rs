@@ -0,0 +1,229 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Collections of batch results which are zeroized when dropped
+
+use crate::{
+    ciphersuite::CipherSuite, BlindedElement, EvaluationElement, NonVerifiableClient,
+    VerifiableClient,
+};
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+use core::ops::{Deref, DerefMut};
+use core::slice;
+use generic_array::{ArrayLength, GenericArray};
+use zeroize::{Zeroize, Zeroizing};
+
+/// A collection of batch results, like the messages returned by
+/// [VerifiableServer::batch_evaluate](crate::VerifiableServer::batch_evaluate),
+/// the outputs returned by
+/// [VerifiableClient::batch_finalize], or the client states of a batch, which
+/// is zeroized when dropped or when [Zeroize::zeroize] is called. This allows
+/// services buffering large batches in memory to scrub them deterministically
+/// when a request completes:
+///
+/// ```
+/// # use voprf::CipherSuite;
+/// # struct Default;
+/// # impl CipherSuite for Default {
+/// #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+/// #     type Hash = sha2::Sha512;
+/// # }
+/// use rand::rngs::OsRng;
+/// use voprf::{Metadata, VerifiableClient, VerifiableServer, ZeroizingBatch};
+///
+/// let mut rng = OsRng;
+/// let inputs = [b"input 1", b"input 2"];
+/// let mut clients = ZeroizingBatch::new();
+/// let mut messages = Vec::new();
+/// for input in &inputs {
+///     let client_blind_result = VerifiableClient::<Default>::blind(*input, &mut rng)?;
+///     clients.push(client_blind_result.state);
+///     messages.push(client_blind_result.message);
+/// }
+///
+/// let server = VerifiableServer::<Default>::new(&mut rng)?;
+/// let server_result = server.batch_evaluate(&mut rng, &messages, &Metadata::none())?;
+/// let messages = ZeroizingBatch::from(server_result.messages);
+///
+/// let outputs: ZeroizingBatch<_> = VerifiableClient::batch_finalize(
+///     &inputs,
+///     &*clients,
+///     &*messages,
+///     server_result.proof,
+///     server.get_public_key(),
+///     &Metadata::none(),
+/// )?
+/// .collect::<Result<_, _>>()?;
+/// assert_eq!(outputs.len(), 2);
+/// // `clients`, `messages` and `outputs` are zeroized here
+/// # Ok::<(), voprf::errors::InternalError>(())
+/// ```
+///
+/// Only the current allocation is zeroized: growing the collection may leave
+/// copies of earlier items in the memory it reallocated from. Use
+/// [ZeroizingBatch::with_capacity] to avoid that.
+pub struct ZeroizingBatch<T: BatchItem>(Vec<T>);
+
+/// An item of a [ZeroizingBatch]
+pub trait BatchItem {
+    /// Overwrites the item with zeroes
+    fn zeroize_item(&mut self);
+}
+
+impl<CS: CipherSuite> BatchItem for NonVerifiableClient<CS> {
+    fn zeroize_item(&mut self) {
+        self.zeroize()
+    }
+}
+
+impl<CS: CipherSuite> BatchItem for VerifiableClient<CS> {
+    fn zeroize_item(&mut self) {
+        self.zeroize()
+    }
+}
+
+impl<CS: CipherSuite> BatchItem for BlindedElement<CS> {
+    fn zeroize_item(&mut self) {
+        self.zeroize()
+    }
+}
+
+impl<CS: CipherSuite> BatchItem for EvaluationElement<CS> {
+    fn zeroize_item(&mut self) {
+        self.zeroize()
+    }
+}
+
+impl<N: ArrayLength<u8>> BatchItem for GenericArray<u8, N> {
+    fn zeroize_item(&mut self) {
+        self.as_mut_slice().zeroize()
+    }
+}
+
+impl<Z: Zeroize> BatchItem for Zeroizing<Z> {
+    fn zeroize_item(&mut self) {
+        self.zeroize()
+    }
+}
+
+impl<T: BatchItem> ZeroizingBatch<T> {
+    /// Creates an empty collection
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Creates an empty collection, which can hold `capacity` items without
+    /// reallocating
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(Vec::with_capacity(capacity))
+    }
+
+    /// Appends an item to the collection
+    pub fn push(&mut self, item: T) {
+        self.0.push(item)
+    }
+}
+
+impl<T: BatchItem> Zeroize for ZeroizingBatch<T> {
+    fn zeroize(&mut self) {
+        for item in self.0.iter_mut() {
+            item.zeroize_item();
+        }
+
+        self.0.clear();
+    }
+}
+
+impl<T: BatchItem> Drop for ZeroizingBatch<T> {
+    fn drop(&mut self) {
+        self.zeroize()
+    }
+}
+
+impl<T: BatchItem> Default for ZeroizingBatch<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: BatchItem> From<Vec<T>> for ZeroizingBatch<T> {
+    fn from(items: Vec<T>) -> Self {
+        Self(items)
+    }
+}
+
+impl<T: BatchItem> FromIterator<T> for ZeroizingBatch<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<T: BatchItem> Deref for ZeroizingBatch<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.0
+    }
+}
+
+impl<T: BatchItem> DerefMut for ZeroizingBatch<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.0
+    }
+}
+
+impl<'a, T: BatchItem> IntoIterator for &'a ZeroizingBatch<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{errors::InternalError, tests::Ristretto255Sha512, Metadata, VerifiableServer};
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_zeroize() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng)?;
+        let client_blind_result = VerifiableClient::blind(b"input", &mut rng)?;
+        let server_result =
+            server.batch_evaluate(&mut rng, &[client_blind_result.message], &Metadata::none())?;
+
+        let mut clients: ZeroizingBatch<_> = core::iter::once(client_blind_result.state).collect();
+        let mut messages = ZeroizingBatch::from(server_result.messages);
+        let mut outputs: ZeroizingBatch<_> = VerifiableClient::batch_finalize(
+            &[b"input"],
+            &*clients,
+            &*messages,
+            server_result.proof,
+            server.get_public_key(),
+            &Metadata::none(),
+        )?
+        .collect::<Result<_, _>>()?;
+        assert_eq!(outputs.len(), 1);
+
+        clients.zeroize();
+        messages.zeroize();
+        outputs.zeroize();
+        assert!(clients.is_empty());
+        assert!(messages.is_empty());
+        assert!(outputs.is_empty());
+
+        let mut output = GenericArray::<u8, generic_array::typenum::U4>::from([1, 2, 3, 4]);
+        output.zeroize_item();
+        assert_eq!(output.as_slice(), [0; 4]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Canonical CBOR encoding of keys and protocol messages
+//!
+//! The encodings follow the core deterministic encoding requirements of
+//! [RFC 8949](https://www.rfc-editor.org/rfc/rfc8949.html#section-4.2.1):
+//! lengths and integers are encoded in their shortest form, maps have
+//! definite lengths and their keys are sorted. Decoding accepts nothing else,
+//! so every value has a single encoding and encodings can be compared
+//! byte-wise.
+//!
+//! - [BlindedElement] and [EvaluationElement] are byte strings of their
+//!   `serialize` bytes.
+//! - [Proof] is the map `{1: c, 2: s}` of the byte strings of its scalars.
+//! - [PrivateKey] and [PublicKey] are the map `{1: suite_id, 2: key}` of the
+//!   [SUITE_ID](Group::SUITE_ID) of the group and the byte string of the key.
+//!   Decoding fails with [InternalError::SuiteMismatch] if the key is for
+//!   another group.
+//!
+//! Decoding fails with [InternalError::CborError] if the input isn't the
+//! encoding of the type, and with the error of its `deserialize` function if
+//! the bytes it contains are invalid.
+//!
+//! ```
+//! # use voprf::CipherSuite;
+//! # struct Default;
+//! # impl CipherSuite for Default {
+//! #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+//! #     type Hash = sha2::Sha512;
+//! # }
+//! use rand::rngs::OsRng;
+//! use voprf::PublicKey;
+//!
+//! let pk = voprf::PrivateKey::<Default>::new(&mut OsRng)?.public_key();
+//! let cbor = pk.to_cbor();
+//! // A map of two entries, the suite ID 1 and a byte string of 32 bytes
+//! assert_eq!(cbor[..6], [0xA2, 0x01, 0x01, 0x02, 0x58, 0x20]);
+//! assert_eq!(PublicKey::<Default>::from_cbor(&cbor)?, pk);
+//! # Ok::<(), voprf::errors::InternalError>(())
+//! ```
+
+use crate::{
+    ciphersuite::CipherSuite,
+    errors::InternalError,
+    group::Group,
+    keys::{PrivateKey, PublicKey},
+    voprf::{BlindedElement, EvaluationElement, Proof},
+};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use generic_array::typenum::Unsigned;
+
+// The major types of RFC 8949 used by the encodings
+const UNSIGNED: u8 = 0;
+const BYTES: u8 = 2;
+const MAP: u8 = 5;
+
+macro_rules! impl_cbor_bytes_for {
+    ($t:ident) => {
+        impl<CS: CipherSuite> $t<CS> {
+            /// Encodes into canonical CBOR, see the
+            /// [module documentation](crate::cbor)
+            pub fn to_cbor(&self) -> Vec<u8> {
+                let mut output = Vec::new();
+                encode_bytes(&mut output, &self.serialize());
+                output
+            }
+
+            /// Decodes from canonical CBOR, see the
+            /// [module documentation](crate::cbor)
+            pub fn from_cbor(input: &[u8]) -> Result<Self, InternalError> {
+                let mut decoder = Decoder { input };
+                let bytes = decoder.bytes()?;
+                decoder.finish()?;
+                Self::deserialize(bytes)
+            }
+        }
+    };
+}
+
+impl_cbor_bytes_for!(BlindedElement);
+impl_cbor_bytes_for!(EvaluationElement);
+
+macro_rules! impl_cbor_key_for {
+    ($t:ident) => {
+        impl<CS: CipherSuite> $t<CS> {
+            /// Encodes into canonical CBOR, see the
+            /// [module documentation](crate::cbor)
+            pub fn to_cbor(&self) -> Vec<u8> {
+                let mut output = Vec::new();
+                encode_head(&mut output, MAP, 2);
+                encode_head(&mut output, UNSIGNED, 1);
+                encode_head(&mut output, UNSIGNED, CS::Group::SUITE_ID as u64);
+                encode_head(&mut output, UNSIGNED, 2);
+                encode_bytes(&mut output, &self.serialize());
+                output
+            }
+
+            /// Decodes from canonical CBOR, see the
+            /// [module documentation](crate::cbor)
+            pub fn from_cbor(input: &[u8]) -> Result<Self, InternalError> {
+                let mut decoder = Decoder { input };
+                decoder.map(2)?;
+                decoder.key(1)?;
+                if decoder.unsigned()? != CS::Group::SUITE_ID as u64 {
+                    return Err(InternalError::SuiteMismatch);
+                }
+                decoder.key(2)?;
+                let bytes = decoder.bytes()?;
+                decoder.finish()?;
+                Self::deserialize(bytes)
+            }
+        }
+    };
+}
+
+impl_cbor_key_for!(PrivateKey);
+impl_cbor_key_for!(PublicKey);
+
+impl<CS: CipherSuite> Proof<CS> {
+    /// Encodes into canonical CBOR, see the
+    /// [module documentation](crate::cbor)
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let bytes = self.serialize();
+        let (c, s) = bytes.split_at(<CS::Group as Group>::ScalarLen::USIZE);
+        let mut output = Vec::new();
+        encode_head(&mut output, MAP, 2);
+        encode_head(&mut output, UNSIGNED, 1);
+        encode_bytes(&mut output, c);
+        encode_head(&mut output, UNSIGNED, 2);
+        encode_bytes(&mut output, s);
+        output
+    }
+
+    /// Decodes from canonical CBOR, see the
+    /// [module documentation](crate::cbor)
+    pub fn from_cbor(input: &[u8]) -> Result<Self, InternalError> {
+        let mut decoder = Decoder { input };
+        decoder.map(2)?;
+        decoder.key(1)?;
+        let c = decoder.bytes()?;
+        decoder.key(2)?;
+        let s = decoder.bytes()?;
+        decoder.finish()?;
+        Self::deserialize(&[c, s].concat())
+    }
+}
+
+// Encodes the head of a data item in its shortest form
+fn encode_head(output: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => output.push(major | value as u8),
+        24..=0xFF => output.extend_from_slice(&[major | 24, value as u8]),
+        0x100..=0xFFFF => {
+            output.push(major | 25);
+            output.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xFFFF_FFFF => {
+            output.push(major | 26);
+            output.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            output.push(major | 27);
+            output.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+fn encode_bytes(output: &mut Vec<u8>, bytes: &[u8]) {
+    encode_head(output, BYTES, bytes.len() as u64);
+    output.extend_from_slice(bytes);
+}
+
+struct Decoder<'a> {
+    input: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], InternalError> {
+        if self.input.len() < len {
+            return Err(InternalError::CborError);
+        }
+        let (taken, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(taken)
+    }
+
+    // Decodes the head of a data item of the `major` type, rejecting
+    // indefinite lengths and values not encoded in their shortest form
+    fn head(&mut self, major: u8) -> Result<u64, InternalError> {
+        let initial = self.take(1)?[0];
+        if initial >> 5 != major {
+            return Err(InternalError::CborError);
+        }
+        let (value, min) = match initial & 0x1F {
+            info @ 0..=23 => return Ok(u64::from(info)),
+            24 => (u64::from(self.take(1)?[0]), 24),
+            25 => {
+                let mut bytes = [0; 2];
+                bytes.copy_from_slice(self.take(2)?);
+                (u64::from(u16::from_be_bytes(bytes)), 0x100)
+            }
+            26 => {
+                let mut bytes = [0; 4];
+                bytes.copy_from_slice(self.take(4)?);
+                (u64::from(u32::from_be_bytes(bytes)), 0x1_0000)
+            }
+            27 => {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(self.take(8)?);
+                (u64::from_be_bytes(bytes), 0x1_0000_0000)
+            }
+            _ => return Err(InternalError::CborError),
+        };
+        if value < min {
+            return Err(InternalError::CborError);
+        }
+
+        Ok(value)
+    }
+
+    fn unsigned(&mut self) -> Result<u64, InternalError> {
+        self.head(UNSIGNED)
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], InternalError> {
+        let len = usize::try_from(self.head(BYTES)?).map_err(|_| InternalError::CborError)?;
+        self.take(len)
+    }
+
+    fn map(&mut self, len: u64) -> Result<(), InternalError> {
+        match self.head(MAP)? == len {
+            true => Ok(()),
+            false => Err(InternalError::CborError),
+        }
+    }
+
+    // Map keys are checked in order, which also rejects unsorted maps
+    fn key(&mut self, key: u64) -> Result<(), InternalError> {
+        match self.unsigned()? == key {
+            true => Ok(()),
+            false => Err(InternalError::CborError),
+        }
+    }
+
+    fn finish(&self) -> Result<(), InternalError> {
+        match self.input.is_empty() {
+            true => Ok(()),
+            false => Err(InternalError::CborError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::Ristretto255Sha512, Metadata, VerifiableClient, VerifiableServer};
+    use rand::rngs::OsRng;
+
+    type CS = Ristretto255Sha512;
+
+    #[test]
+    fn test_cbor() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let sk = PrivateKey::<CS>::new(&mut rng)?;
+        let pk = sk.public_key();
+        assert_eq!(PrivateKey::<CS>::from_cbor(&sk.to_cbor())?, sk);
+        assert_eq!(PublicKey::<CS>::from_cbor(&pk.to_cbor())?, pk);
+
+        let server = VerifiableServer::<CS>::new_with_private_key(&sk)?;
+        let message = VerifiableClient::<CS>::blind(b"input", &mut rng)?.message;
+        let cbor = message.to_cbor();
+        assert_eq!(cbor[..2], [0x58, 0x20]);
+        assert_eq!(cbor[2..], message.serialize()[..]);
+        assert_eq!(BlindedElement::from_cbor(&cbor)?, message);
+        let server_result = server.evaluate(&mut rng, message, &Metadata::none())?;
+        let evaluation_element = server_result.message;
+        assert_eq!(
+            EvaluationElement::from_cbor(&evaluation_element.to_cbor())?,
+            evaluation_element
+        );
+        let proof = server_result.proof;
+        let cbor = proof.to_cbor();
+        assert_eq!(cbor[..4], [0xA2, 0x01, 0x58, 0x20]);
+        assert_eq!(Proof::from_cbor(&cbor)?, proof);
+
+        // Keys of another group are rejected
+        let mut cbor = pk.to_cbor();
+        cbor[2] = 0x03;
+        assert!(matches!(
+            PublicKey::<CS>::from_cbor(&cbor),
+            Err(InternalError::SuiteMismatch)
+        ));
+
+        // Non-canonical encodings are rejected: a length not in its shortest
+        // form, an indefinite length, unsorted map keys and trailing bytes
+        let bytes = evaluation_element.serialize();
+        let long_length = [&[0x59, 0x00, 0x20][..], &bytes].concat();
+        assert!(matches!(
+            EvaluationElement::<CS>::from_cbor(&long_length),
+            Err(InternalError::CborError)
+        ));
+        let indefinite = [&[0x5F, 0x58, 0x20][..], &bytes, &[0xFF]].concat();
+        assert!(EvaluationElement::<CS>::from_cbor(&indefinite).is_err());
+        let cbor = proof.to_cbor();
+        let unsorted = [&[0xA2, 0x02][..], &cbor[2..36], &[0x01], &cbor[37..]].concat();
+        assert!(matches!(
+            Proof::<CS>::from_cbor(&unsorted),
+            Err(InternalError::CborError)
+        ));
+        let trailing = [&pk.to_cbor()[..], &[0x00]].concat();
+        assert!(matches!(
+            PublicKey::<CS>::from_cbor(&trailing),
+            Err(InternalError::CborError)
+        ));
+        assert!(PublicKey::<CS>::from_cbor(&[]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_head() {
+        let mut output = Vec::new();
+        for &value in &[0, 23, 24, 0xFF, 0x100, 0xFFFF, 0x1_0000, 0x1_0000_0000] {
+            output.clear();
+            encode_head(&mut output, UNSIGNED, value);
+            let mut decoder = Decoder { input: &output };
+            assert_eq!(decoder.unsigned(), Ok(value));
+            assert!(decoder.finish().is_ok());
+        }
+        output.clear();
+        encode_head(&mut output, MAP, 0x100);
+        assert_eq!(output, [0xB9, 0x01, 0x00]);
+    }
+}
@@ -0,0 +1,329 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Generation of a server key from the shares of several operators
+
+use crate::{
+    ciphersuite::CipherSuite,
+    errors::InternalError,
+    group::Group,
+    secret::Secret,
+    voprf::{NonVerifiableServer, VerifiableServer},
+};
+use alloc::vec::Vec;
+use generic_array::{typenum::Unsigned, GenericArray};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+/// The secret share of a single operator in a [KeyCeremony]. The private key
+/// of the server is the sum of all shares, so it stays unknown as long as a
+/// single share stays secret.
+pub struct KeyShare<CS: CipherSuite> {
+    share: <CS::Group as Group>::Scalar,
+}
+impl_redacted_debug_hash_for!(
+    struct KeyShare<CS: CipherSuite>,
+    [],
+    redacted [share],
+    [<CS::Group as Group>::Scalar],
+);
+impl_clone_for!(
+    struct KeyShare<CS: CipherSuite>,
+    [share],
+    [<CS::Group as Group>::Scalar],
+);
+impl_zeroize_on_drop_for!(
+    struct KeyShare<CS: CipherSuite>,
+    [share],
+    [<CS::Group as Group>::Scalar],
+);
+impl_serialize_and_deserialize_for!(KeyShare);
+
+impl<CS: CipherSuite> PartialEq for KeyShare<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.share, &other.share)
+    }
+}
+
+impl<CS: CipherSuite> Eq for KeyShare<CS> {}
+
+/// The public commitment to a [KeyShare], published by its operator before
+/// the ceremony. The commitments add up to the public key of the server.
+pub struct ShareCommitment<CS: CipherSuite> {
+    value: CS::Group,
+}
+impl_debug_eq_hash_for!(
+    struct ShareCommitment<CS: CipherSuite>,
+    [value],
+    [CS::Group],
+);
+impl_clone_for!(
+    struct ShareCommitment<CS: CipherSuite>,
+    [value],
+    [CS::Group],
+);
+impl_serialize_and_deserialize_for!(ShareCommitment);
+
+/// Combines the [KeyShare]s of several operators into the private key of a
+/// server, so that no operator ever learns the key on their own. Every
+/// operator publishes the [ShareCommitment] of their share beforehand, and
+/// each contributed share is checked against its commitment:
+///
+/// ```
+/// # use voprf::CipherSuite;
+/// # struct Default;
+/// # impl CipherSuite for Default {
+/// #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+/// #     type Hash = sha2::Sha512;
+/// # }
+/// use rand::rngs::OsRng;
+/// use voprf::{KeyCeremony, KeyShare};
+///
+/// let mut rng = OsRng;
+/// // Each operator generates their share on their own machine
+/// let shares = [
+///     KeyShare::<Default>::new(&mut rng),
+///     KeyShare::<Default>::new(&mut rng),
+/// ];
+///
+/// let mut ceremony = KeyCeremony::new(shares.iter().map(KeyShare::commitment).collect())?;
+/// let public_key = ceremony.public_key();
+///
+/// for share in shares.iter() {
+///     ceremony.contribute(share)?;
+/// }
+///
+/// let server = ceremony.finish_verifiable()?;
+/// assert_eq!(server.get_public_key(), public_key);
+/// # Ok::<(), voprf::errors::InternalError>(())
+/// ```
+pub struct KeyCeremony<CS: CipherSuite> {
+    commitments: Vec<ShareCommitment<CS>>,
+    sk: <CS::Group as Group>::Scalar,
+    contributed: usize,
+}
+
+impl<CS: CipherSuite> KeyShare<CS> {
+    /// Generates a random share
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self {
+            share: CS::Group::random_nonzero_scalar(rng),
+        }
+    }
+
+    /// The commitment to this share, to be published before the ceremony
+    pub fn commitment(&self) -> ShareCommitment<CS> {
+        ShareCommitment {
+            value: CS::Group::base_point() * &self.share,
+        }
+    }
+
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        CS::Group::scalar_as_bytes(self.share).to_vec()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        if input.len() != <CS::Group as Group>::ScalarLen::USIZE {
+            return Err(InternalError::SizeError {
+                expected: <CS::Group as Group>::ScalarLen::USIZE,
+                got: input.len(),
+            });
+        }
+
+        Ok(Self {
+            share: CS::Group::from_scalar_slice(GenericArray::from_slice(input))?,
+        })
+    }
+}
+
+impl<CS: CipherSuite> ShareCommitment<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        self.value.to_arr().to_vec()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        if input.len() != <CS::Group as Group>::ElemLen::USIZE {
+            return Err(InternalError::SizeError {
+                expected: <CS::Group as Group>::ElemLen::USIZE,
+                got: input.len(),
+            });
+        }
+
+        Ok(Self {
+            value: CS::Group::from_element_slice(GenericArray::from_slice(input))?,
+        })
+    }
+}
+
+impl<CS: CipherSuite> KeyCeremony<CS> {
+    /// Starts a ceremony with the published commitments of all operators, in
+    /// the order their shares will be contributed
+    pub fn new(commitments: Vec<ShareCommitment<CS>>) -> Result<Self, InternalError> {
+        if commitments.is_empty() {
+            return Err(InternalError::ShareCommitmentError);
+        }
+
+        if commitments.iter().fold(false, |identity, commitment| {
+            identity | commitment.value.is_identity()
+        }) {
+            return Err(InternalError::IdentityElementError);
+        }
+
+        Ok(Self {
+            commitments,
+            sk: CS::Group::scalar_zero(),
+            contributed: 0,
+        })
+    }
+
+    /// The public key of the resulting server, known before any share is
+    /// contributed
+    pub fn public_key(&self) -> CS::Group {
+        let mut commitments = self.commitments.iter();
+        let first = commitments.next().map(|commitment| commitment.value);
+
+        commitments.fold(
+            first.unwrap_or_else(CS::Group::identity),
+            |pk, commitment| pk + &commitment.value,
+        )
+    }
+
+    /// Adds the share of the next operator, after checking it against their
+    /// commitment
+    pub fn contribute(&mut self, share: &KeyShare<CS>) -> Result<(), InternalError> {
+        let commitment = self
+            .commitments
+            .get(self.contributed)
+            .ok_or(InternalError::ShareCommitmentError)?;
+
+        if !(CS::Group::base_point() * &share.share).ct_equal(&commitment.value) {
+            return Err(InternalError::ShareCommitmentError);
+        }
+
+        self.sk = self.sk + &share.share;
+        self.contributed += 1;
+
+        Ok(())
+    }
+
+    /// Finishes the ceremony with a [NonVerifiableServer], once every share
+    /// was contributed
+    pub fn finish_non_verifiable(self) -> Result<NonVerifiableServer<CS>, InternalError> {
+        NonVerifiableServer::from_private_key(self.private_key()?)
+    }
+
+    /// Finishes the ceremony with a [VerifiableServer], once every share was
+    /// contributed
+    pub fn finish_verifiable(self) -> Result<VerifiableServer<CS>, InternalError> {
+        let sk = Zeroizing::new(self.private_key()?);
+        let pk = CS::Group::base_point() * &sk;
+
+        if !pk.ct_equal(&self.public_key()) {
+            return Err(InternalError::InvalidPublicKey);
+        }
+
+        Ok(VerifiableServer {
+            sk: Secret::new(*sk)?,
+            pk,
+        })
+    }
+
+    fn private_key(&self) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+        if self.contributed != self.commitments.len() {
+            return Err(InternalError::ShareCommitmentError);
+        }
+
+        // The shares cancelled each other out
+        if CS::Group::ct_equal_scalar(&self.sk, &CS::Group::scalar_zero()) {
+            return Err(InternalError::ZeroScalarError);
+        }
+
+        Ok(self.sk)
+    }
+}
+
+impl<CS: CipherSuite> Drop for KeyCeremony<CS> {
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        self.sk.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::Ristretto255Sha512, Metadata, VerifiableClient};
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_key_ceremony() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let shares = [
+            KeyShare::<Ristretto255Sha512>::new(&mut rng),
+            KeyShare::<Ristretto255Sha512>::new(&mut rng),
+            KeyShare::<Ristretto255Sha512>::new(&mut rng),
+        ];
+        let commitments: Vec<_> = shares
+            .iter()
+            .map(|share| ShareCommitment::deserialize(&share.commitment().serialize()))
+            .collect::<Result<_, _>>()?;
+
+        // Shares are checked against the commitment of their operator
+        let mut ceremony = KeyCeremony::new(commitments.clone())?;
+        assert_eq!(
+            ceremony.contribute(&shares[1]),
+            Err(InternalError::ShareCommitmentError)
+        );
+
+        for share in shares.iter() {
+            ceremony.contribute(&KeyShare::deserialize(&share.serialize())?)?;
+        }
+        assert_eq!(
+            ceremony.contribute(&shares[0]),
+            Err(InternalError::ShareCommitmentError)
+        );
+
+        let pk = ceremony.public_key();
+        let server = ceremony.finish_verifiable()?;
+        assert_eq!(server.get_public_key(), pk);
+
+        let client_blind_result =
+            VerifiableClient::<Ristretto255Sha512>::blind(b"input", &mut rng)?;
+        let server_result =
+            server.evaluate(&mut rng, client_blind_result.message, &Metadata::none())?;
+        let client_finalize_result = client_blind_result.state.finalize_once(
+            b"input",
+            server_result.message,
+            server_result.proof,
+            pk,
+            &Metadata::none(),
+        )?;
+        assert_eq!(
+            *client_finalize_result.output,
+            server.full_evaluate(b"input", &Metadata::none())?
+        );
+
+        // The key can't be used before every share was contributed
+        let mut ceremony = KeyCeremony::new(commitments)?;
+        ceremony.contribute(&shares[0])?;
+        assert!(matches!(
+            ceremony.finish_non_verifiable(),
+            Err(InternalError::ShareCommitmentError)
+        ));
+
+        assert!(matches!(
+            KeyCeremony::<Ristretto255Sha512>::new(Vec::new()),
+            Err(InternalError::ShareCommitmentError)
+        ));
+
+        Ok(())
+    }
+}
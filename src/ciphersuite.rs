@@ -7,6 +7,9 @@
 
 //! Defines the CipherSuite trait to specify the underlying primitives for VOPRF
 
+use crate::errors::InternalError;
+use generic_array::{ArrayLength, GenericArray};
+
 /// Configures the underlying primitives used in VOPRF
 pub trait CipherSuite {
     /// A finite cyclic group along with a point representation that allows some
@@ -14,4 +17,35 @@ pub trait CipherSuite {
     type Group: crate::group::Group;
     /// The main hash function to use (for HKDF computations and hashing transcripts).
     type Hash: crate::hash::Hash;
+
+    /// The maximum length of client inputs, longer inputs are rejected with
+    /// [InputLengthError](crate::errors::InternalError::InputLengthError)
+    /// before any hashing is done. Defaults to, and can't exceed, the limit of
+    /// the protocol: `u16::MAX`.
+    const MAX_INPUT_LEN: usize = u16::MAX as usize;
+    /// The maximum length of [Metadata](crate::Metadata), longer metadata is
+    /// rejected with
+    /// [MetadataLengthError](crate::errors::InternalError::MetadataLengthError)
+    /// before any hashing is done. Defaults to, and can't exceed, the limit of
+    /// the protocol: `u16::MAX`.
+    const MAX_METADATA_LEN: usize = u16::MAX as usize;
+    /// The identifier of the whole ciphersuite, group and hash, written into
+    /// suite-tagged serializations so that messages of another suite are
+    /// rejected. Defaults to [SUITE_ID](crate::group::Group::SUITE_ID) of the
+    /// group, suites which pair a group with another hash than the one the
+    /// draft registers for it have to override it.
+    const SUITE_ID: usize = <Self::Group as crate::group::Group>::SUITE_ID;
+
+    /// The `expand_message` variant used to hash to the group, which expands
+    /// the message and the DST, both given as a list of parts, to `L`
+    /// uniformly random bytes. Defaults to
+    /// [expand_message_xmd](crate::group::expand_message_xmd) with
+    /// [CipherSuite::Hash], suites based on an extendable-output function
+    /// override it with [expand_message_xof](crate::group::expand_message_xof).
+    fn expand_message<L: ArrayLength<u8>>(
+        msg: &[&[u8]],
+        dst: &[&[u8]],
+    ) -> Result<GenericArray<u8, L>, InternalError> {
+        crate::group::expand_message_xmd::<Self::Hash, L>(msg, dst)
+    }
 }
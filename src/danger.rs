@@ -0,0 +1,222 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! ⚠️ Dangerous utilities, which expose secret intermediate values of the
+//! protocol. Only use them to debug, never in production.
+
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+use zeroize::Zeroize;
+
+std::thread_local! {
+    // A `const` initializer is newer than the MSRV
+    #[allow(clippy::missing_const_for_thread_local)]
+    static TRACE: RefCell<Option<Vec<TraceEntry>>> = RefCell::new(None);
+}
+
+/// An intermediate value recorded in a [Trace]
+pub struct TraceEntry {
+    label: &'static str,
+    value: Vec<u8>,
+}
+
+impl TraceEntry {
+    /// The name of the value, e.g. `"hashed_point"`
+    pub fn label(&self) -> &'static str {
+        self.label
+    }
+
+    /// The serialization of the value, as it would be sent over the wire or
+    /// hashed
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+}
+
+impl Drop for TraceEntry {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+/// The intermediate values recorded by [capture], in the order they were
+/// computed. Its [Display](fmt::Display) implementation prints one value per
+/// line in hex, to be compared with the trace of another implementation.
+///
+/// The following values are recorded:
+///
+/// - `hashed_point`, `blind` and `blinded_element` when blinding an input.
+/// - `metadata_scalar`, when hashing the metadata.
+/// - `evaluation_element`, when evaluating a blinded element.
+/// - `composite_seed`, `composite_m` and `composite_z`, when computing the
+///   composites of a proof.
+/// - `challenge_input` and `challenge`, when computing the challenge of a proof.
+/// - `proof_c` and `proof_s`, when generating a proof.
+/// - `unblinded_element`, `finalize_input` and `output`, when finalizing.
+///
+/// The values include the blind and the output, so a trace has to be handled
+/// like a secret.
+#[derive(Default)]
+pub struct Trace(Vec<TraceEntry>);
+
+impl Trace {
+    /// All recorded values
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.0
+    }
+
+    /// The first recorded value with the given label
+    pub fn get(&self, label: &str) -> Option<&[u8]> {
+        self.0
+            .iter()
+            .find(|entry| entry.label == label)
+            .map(TraceEntry::value)
+    }
+}
+
+impl fmt::Debug for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Trace").finish()
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.0 {
+            write!(f, "{}: ", entry.label)?;
+
+            for byte in &entry.value {
+                write!(f, "{:02x}", byte)?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Restores the previously active trace, also if the traced closure panics
+struct Restore(Option<Vec<TraceEntry>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        TRACE.with(|trace| *trace.borrow_mut() = previous);
+    }
+}
+
+/// Runs `f` and records the intermediate values of every protocol step it
+/// runs on the current thread. Meant to capture a single exchange, with both
+/// the client and the server running inside `f`:
+///
+/// ```
+/// # use voprf::CipherSuite;
+/// # struct Default;
+/// # impl CipherSuite for Default {
+/// #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+/// #     type Hash = sha2::Sha512;
+/// # }
+/// use rand::rngs::OsRng;
+/// use voprf::{Metadata, NonVerifiableClient, NonVerifiableServer};
+///
+/// let mut rng = OsRng;
+/// let server = NonVerifiableServer::<Default>::new(&mut rng)?;
+///
+/// let (result, trace) = voprf::danger::capture(|| {
+///     let client_blind_result = NonVerifiableClient::<Default>::blind(b"input", &mut rng)?;
+///     let server_evaluate_result =
+///         server.evaluate(client_blind_result.message, &Metadata::none())?;
+///     client_blind_result.state.finalize_once(
+///         b"input",
+///         server_evaluate_result.message,
+///         &Metadata::none(),
+///     )
+/// });
+///
+/// assert_eq!(trace.get("output"), Some(result?.output.as_slice()));
+/// println!("{}", trace);
+/// # Ok::<(), voprf::errors::InternalError>(())
+/// ```
+///
+/// Captures can be nested, values are only recorded in the innermost one.
+pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Trace) {
+    let restore = Restore(TRACE.with(|trace| trace.borrow_mut().replace(Vec::new())));
+    let result = f();
+    let entries = TRACE.with(|trace| trace.borrow_mut().take());
+    drop(restore);
+
+    (result, Trace(entries.unwrap_or_default()))
+}
+
+// Records a value in the active trace, if any
+pub(crate) fn record<V: AsRef<[u8]>>(label: &'static str, value: impl FnOnce() -> V) {
+    TRACE.with(|trace| {
+        if let Some(entries) = trace.borrow_mut().as_mut() {
+            entries.push(TraceEntry {
+                label,
+                value: value().as_ref().to_vec(),
+            });
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        errors::InternalError, group::Group, tests::Ristretto255Sha512, Metadata, VerifiableClient,
+        VerifiableServer,
+    };
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_capture() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng)?;
+
+        let (result, trace) = capture(|| {
+            let client_blind_result =
+                VerifiableClient::<Ristretto255Sha512>::blind(b"input", &mut rng)?;
+            let blinded_element = client_blind_result.message.value;
+            let message = client_blind_result.message;
+
+            // Values computed in a nested capture don't show up in this one
+            let (server_result, inner) =
+                capture(|| server.evaluate(&mut rng, message, &Metadata::none()));
+            let server_result = server_result?;
+            assert!(inner.get("proof_c").is_some());
+
+            let client_finalize_result = client_blind_result.state.finalize_once(
+                b"input",
+                server_result.message,
+                server_result.proof,
+                server.get_public_key(),
+                &Metadata::none(),
+            )?;
+
+            Ok::<_, InternalError>((blinded_element, client_finalize_result.output))
+        });
+        let (blinded_element, output) = result?;
+
+        assert_eq!(
+            trace.get("blinded_element"),
+            Some(blinded_element.to_arr().as_slice())
+        );
+        assert_eq!(trace.get("output"), Some(output.as_slice()));
+        assert!(trace.get("proof_c").is_none());
+        assert!(trace.get("challenge").is_some());
+        assert!(trace.to_string().contains("composite_m: "));
+
+        // Nothing is recorded outside of a capture
+        let (_, trace) = capture(|| ());
+        assert!(trace.entries().is_empty());
+
+        Ok(())
+    }
+}
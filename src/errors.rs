@@ -6,36 +6,125 @@
 // of this source tree.
 
 //! A list of error types which are produced during an execution of the protocol
-use core::fmt::Debug;
+use alloc::string::{String, ToString};
+use core::fmt::{Debug, Display};
 #[cfg(feature = "std")]
 use std::error::Error;
 
-use displaydoc::Display;
+use displaydoc::Display as DeriveDisplay;
 
 /// Represents an error in the manipulation of internal cryptographic data
-#[derive(Clone, Display, Eq, Hash, PartialEq)]
+#[derive(Clone, Eq, Hash, PartialEq)]
 pub enum InternalError {
-    /// Could not parse byte sequence for key
+    /// Could not deserialize a scalar, e.g. a key or a blind, or its encoding
+    /// isn't canonical
     InvalidByteSequence,
-    /// Could not deserialize element, or deserialized to the identity element
+    /// Could not deserialize element, or its encoding isn't canonical
     PointError,
+    /// An element is the identity element, which a message, key or hash to
+    /// the group may not be. Returned when decoding or constructing such an
+    /// element locally, see [InternalError::InvalidElement] for the elements a
+    /// server receives
+    IdentityElementError,
     /// Computing the hash-to-curve function failed
     HashToCurveError,
     /// Failure to serialize or deserialize bytes
     SerializationError,
     /// Use of incompatible modes (base vs. verifiable)
     IncompatibleModeError,
-    /**
-     * Internal error thrown when different-lengthed slices are supplied
-     * to the compute_composites() function.
-     */
+    /// A batch yielded fewer results than it was given items. Collections of
+    /// a batch with different lengths fail with [InternalError::BatchMismatch]
     MismatchedLengthsForCompositeInputs,
     /// In verifiable mode, occurs when the proof failed to verify
     ProofVerificationError,
-    /// Encountered insufficient bytes when attempting to deserialize
-    SizeError,
+    /// The proof contains a non-canonically encoded scalar
+    InvalidProofError,
+    /// Attempted to deserialize bytes of the wrong length. For types of
+    /// variable length, `expected` is their minimum length, or the length
+    /// without the trailing bytes which don't form a whole element
+    SizeError {
+        /// The length that was expected
+        expected: usize,
+        /// The length of the bytes
+        got: usize,
+    },
     /// Encountered a zero scalar
     ZeroScalarError,
+    /// DeriveKeyPair() derived a zero private key for every counter up to
+    /// 255
+    DeriveKeyPairError,
+    /// The public key is the identity element, or doesn't match the private
+    /// key
+    InvalidPublicKey,
+    /// The public key was rejected by the [KeyPolicy](crate::KeyPolicy) of
+    /// the client
+    UntrustedPublicKey,
+    /// Failed to lock the memory of a secret, see the `mlock` feature
+    MemoryLockError,
+    /// The input is longer than allowed by the ciphersuite
+    InputLengthError,
+    /// The metadata is longer than allowed by the ciphersuite
+    MetadataLengthError,
+    /// The input doesn't match the input the client state is bound to
+    InputMismatchError,
+    /// The known-answer self-test failed, or has no known answer for the
+    /// ciphersuite
+    SelfTestError,
+    /// The RNG failed a health check, see [HealthCheckedRng](crate::HealthCheckedRng)
+    RngHealthError,
+    /// The RNG failed to produce random bytes
+    RngError {
+        /// The error of the RNG
+        source: Option<ErrorSource>,
+    },
+    /// A key share doesn't match its commitment, or not every committed
+    /// share was contributed to the [KeyCeremony](crate::KeyCeremony)
+    ShareCommitmentError,
+    /// Signing an evaluation receipt failed, or the receipt doesn't match
+    /// the evaluation or its signature doesn't verify
+    ReceiptError {
+        /// The error of the signer or verifier, if one of them failed
+        source: Option<ErrorSource>,
+    },
+    /// The blinded element of a client is missing from the batch the proof
+    /// was generated over
+    MissingFromBatchError,
+    /// The second share of a split key was used to evaluate the blinded
+    /// elements, or the first share to evaluate the partial elements
+    SplitKeyShareError,
+    /// Signing or verifying a key log failed, its entries aren't ordered by
+    /// epoch, or it doesn't extend the log seen before
+    KeyLogError {
+        /// The error of the signer or verifier, if one of them failed
+        source: Option<ErrorSource>,
+    },
+    /// A Privacy Pass token request is for another token type or issuer key,
+    /// or a token was finalized with another issuer key than it was requested
+    /// from
+    TokenError,
+    /// The PEM encoding is malformed, or its label doesn't match the type
+    PemError,
+    /// The input isn't the canonical CBOR encoding of the type, see the
+    /// `cbor` feature
+    CborError,
+    /// A suite-tagged message was serialized with another ciphersuite
+    SuiteMismatch,
+    /// More output was requested from an expansion than the hash function
+    /// can derive
+    OutputLengthError,
+    /// A server was asked to evaluate the identity or another element of
+    /// small order, which a client following the protocol never sends
+    InvalidElement,
+    /// Two collections of a batch which must have the same length, e.g. the
+    /// inputs and the clients, have `left` and `right` items. Collections
+    /// given as iterators aren't read past the end of the shorter one, so the
+    /// longer one is reported with one item more
+    BatchMismatch {
+        /// The length of the first collection
+        left: usize,
+        /// The length of the second collection
+        right: usize,
+    },
 }
 
 impl Debug for InternalError {
@@ -43,6 +132,7 @@ impl Debug for InternalError {
         match self {
             Self::InvalidByteSequence => f.debug_tuple("InvalidByteSequence").finish(),
             Self::PointError => f.debug_tuple("PointError").finish(),
+            Self::IdentityElementError => f.debug_tuple("IdentityElementError").finish(),
             Self::HashToCurveError => f.debug_tuple("HashToCurveError").finish(),
             Self::SerializationError => f.debug_tuple("SerializationError").finish(),
             Self::IncompatibleModeError => f.debug_tuple("IncompatibleModeError").finish(),
@@ -50,11 +140,261 @@ impl Debug for InternalError {
                 .debug_tuple("MismatchedLengthsForCompositeInputs")
                 .finish(),
             Self::ProofVerificationError => f.debug_tuple("ProofVerificationError").finish(),
-            Self::SizeError => f.debug_tuple("SizeError").finish(),
+            Self::InvalidProofError => f.debug_tuple("InvalidProofError").finish(),
+            Self::SizeError { expected, got } => f
+                .debug_struct("SizeError")
+                .field("expected", expected)
+                .field("got", got)
+                .finish(),
             Self::ZeroScalarError => f.debug_tuple("ZeroScalarError").finish(),
+            Self::DeriveKeyPairError => f.debug_tuple("DeriveKeyPairError").finish(),
+            Self::InvalidPublicKey => f.debug_tuple("InvalidPublicKey").finish(),
+            Self::UntrustedPublicKey => f.debug_tuple("UntrustedPublicKey").finish(),
+            Self::MemoryLockError => f.debug_tuple("MemoryLockError").finish(),
+            Self::InputLengthError => f.debug_tuple("InputLengthError").finish(),
+            Self::MetadataLengthError => f.debug_tuple("MetadataLengthError").finish(),
+            Self::InputMismatchError => f.debug_tuple("InputMismatchError").finish(),
+            Self::SelfTestError => f.debug_tuple("SelfTestError").finish(),
+            Self::RngHealthError => f.debug_tuple("RngHealthError").finish(),
+            Self::RngError { source } => {
+                f.debug_struct("RngError").field("source", source).finish()
+            }
+            Self::ShareCommitmentError => f.debug_tuple("ShareCommitmentError").finish(),
+            Self::ReceiptError { source } => f
+                .debug_struct("ReceiptError")
+                .field("source", source)
+                .finish(),
+            Self::MissingFromBatchError => f.debug_tuple("MissingFromBatchError").finish(),
+            Self::SplitKeyShareError => f.debug_tuple("SplitKeyShareError").finish(),
+            Self::KeyLogError { source } => f
+                .debug_struct("KeyLogError")
+                .field("source", source)
+                .finish(),
+            Self::TokenError => f.debug_tuple("TokenError").finish(),
+            Self::PemError => f.debug_tuple("PemError").finish(),
+            Self::CborError => f.debug_tuple("CborError").finish(),
+            Self::SuiteMismatch => f.debug_tuple("SuiteMismatch").finish(),
+            Self::OutputLengthError => f.debug_tuple("OutputLengthError").finish(),
+            Self::InvalidElement => f.debug_tuple("InvalidElement").finish(),
+            Self::BatchMismatch { left, right } => f
+                .debug_struct("BatchMismatch")
+                .field("left", left)
+                .field("right", right)
+                .finish(),
+        }
+    }
+}
+
+impl Display for InternalError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // Unlike the documentation of the variants, these messages are written
+        // for the users of an application and suggest how to fix the error
+        let message = match self {
+            Self::SizeError { expected, got } => {
+                return write!(f, "expected {} bytes, got {}", expected, got)
+            }
+            Self::BatchMismatch { left, right } => {
+                return write!(f, "the batch has mismatched lengths {} and {}", left, right)
+            }
+            Self::InvalidByteSequence => "invalid or non-canonical scalar encoding",
+            Self::PointError => "invalid or non-canonical element encoding",
+            Self::IdentityElementError => "encountered the identity element",
+            Self::HashToCurveError => "hashing to the group failed, the DST may be too long",
+            Self::SerializationError => "the bytes have the wrong length or format for the type",
+            Self::IncompatibleModeError => "the message was created for another protocol mode",
+            Self::MismatchedLengthsForCompositeInputs => "a batch yielded fewer results than items",
+            Self::ProofVerificationError => {
+                "the proof doesn't verify against the public key of the server"
+            }
+            Self::InvalidProofError => "the proof contains a non-canonically encoded scalar",
+            Self::ZeroScalarError => "encountered a zero scalar",
+            Self::DeriveKeyPairError => "DeriveKeyPair() failed, use another seed or info",
+            Self::InvalidPublicKey => {
+                "the public key is the identity or doesn't match the private key"
+            }
+            Self::UntrustedPublicKey => {
+                "the public key was rejected by the key policy of the client"
+            }
+            Self::MemoryLockError => {
+                "failed to lock memory, raise `RLIMIT_MEMLOCK` or disable `mlock`"
+            }
+            Self::InputLengthError => "the input is longer than allowed by the ciphersuite",
+            Self::MetadataLengthError => "the metadata is longer than allowed by the ciphersuite",
+            Self::InputMismatchError => {
+                "finalize with the same input the client state was blinded with"
+            }
+            Self::SelfTestError => "the self-test failed, don't use the ciphersuite",
+            Self::RngHealthError => "the RNG failed a health check, don't use its output",
+            Self::RngError { .. } => "the RNG failed to produce random bytes",
+            Self::ShareCommitmentError => "a key share is missing or doesn't match its commitment",
+            Self::ReceiptError { .. } => {
+                "the receipt failed to sign, or doesn't match the evaluation"
+            }
+            Self::MissingFromBatchError => "the blinded element is missing from the proven batch",
+            Self::SplitKeyShareError => "the shares of the split key were used out of order",
+            Self::KeyLogError { .. } => {
+                "the key log doesn't verify or doesn't extend the log seen before"
+            }
+            Self::TokenError => "the token is for another token type or issuer key",
+            Self::PemError => "malformed PEM encoding, or its label doesn't match the type",
+            Self::CborError => "the input isn't the canonical CBOR encoding of the type",
+            Self::SuiteMismatch => "the message was serialized with another ciphersuite",
+            Self::OutputLengthError => "at most 255 times the hash output size can be expanded",
+            Self::InvalidElement => "the blinded element is the identity or of small order",
+        };
+        f.write_str(message)
+    }
+}
+
+impl InternalError {
+    /// Returns the [ErrorKind] of this error, to handle failures by category
+    /// instead of matching every variant
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Self::IncompatibleModeError
+            | Self::MismatchedLengthsForCompositeInputs
+            | Self::InputLengthError
+            | Self::MetadataLengthError
+            | Self::InputMismatchError
+            | Self::MissingFromBatchError
+            | Self::SplitKeyShareError
+            | Self::TokenError
+            | Self::SuiteMismatch
+            | Self::OutputLengthError
+            | Self::BatchMismatch { .. } => ErrorKind::Protocol,
+            Self::InvalidByteSequence
+            | Self::PointError
+            | Self::IdentityElementError
+            | Self::SerializationError
+            | Self::InvalidProofError
+            | Self::SizeError { .. }
+            | Self::PemError
+            | Self::CborError => ErrorKind::Encoding,
+            Self::HashToCurveError
+            | Self::ProofVerificationError
+            | Self::ZeroScalarError
+            | Self::DeriveKeyPairError
+            | Self::InvalidPublicKey
+            | Self::UntrustedPublicKey
+            | Self::SelfTestError
+            | Self::ShareCommitmentError
+            | Self::ReceiptError { .. }
+            | Self::InvalidElement
+            | Self::KeyLogError { .. } => ErrorKind::Crypto,
+            Self::MemoryLockError | Self::RngHealthError | Self::RngError { .. } => {
+                ErrorKind::Resource
+            }
         }
     }
 }
 
 #[cfg(feature = "std")]
-impl Error for InternalError {}
+impl Error for InternalError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::RngError { source }
+            | Self::ReceiptError { source }
+            | Self::KeyLogError { source } => source.as_ref().map(|source| source as _),
+            _ => None,
+        }
+    }
+}
+
+/// The error of another crate which caused an [InternalError], e.g. of a
+/// signer or an RNG, returned by `source` of [InternalError] with the `std`
+/// feature. Only its message is kept, as the errors of those crates can't be
+/// cloned or compared.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ErrorSource(String);
+
+impl ErrorSource {
+    pub(crate) fn new(error: impl Display) -> Self {
+        Self(error.to_string())
+    }
+}
+
+impl Display for ErrorSource {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ErrorSource {}
+
+/// The category of an [InternalError], as returned by [InternalError::kind]
+#[derive(Clone, Copy, Debug, DeriveDisplay, Eq, Hash, PartialEq)]
+pub enum ErrorKind {
+    /// The protocol was used incorrectly, e.g. with mismatched modes or batch
+    /// sizes, or with an input or metadata that is too long
+    Protocol,
+    /// A message or state could not be decoded from its byte representation
+    Encoding,
+    /// A cryptographic operation failed, e.g. a proof didn't verify or a
+    /// public key was invalid
+    Crypto,
+    /// The system ran out of a resource, e.g. lockable memory, or a resource
+    /// failed, e.g. the RNG
+    Resource,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kind() {
+        assert_eq!(
+            InternalError::IncompatibleModeError.kind(),
+            ErrorKind::Protocol
+        );
+        assert_eq!(
+            InternalError::SizeError {
+                expected: 32,
+                got: 31
+            }
+            .kind(),
+            ErrorKind::Encoding
+        );
+        assert_eq!(
+            InternalError::ProofVerificationError.kind(),
+            ErrorKind::Crypto
+        );
+        assert_eq!(InternalError::MemoryLockError.kind(), ErrorKind::Resource);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_display_context() {
+        use std::string::ToString;
+
+        let error = InternalError::SizeError {
+            expected: 32,
+            got: 31,
+        };
+        assert_eq!(error.to_string(), "expected 32 bytes, got 31");
+        let error = InternalError::BatchMismatch { left: 2, right: 3 };
+        assert_eq!(
+            error.to_string(),
+            "the batch has mismatched lengths 2 and 3"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_source() {
+        use std::string::ToString;
+
+        let error = InternalError::ReceiptError {
+            source: Some(ErrorSource::new("the signer is unreachable")),
+        };
+        assert_eq!(
+            error.source().map(ToString::to_string).as_deref(),
+            Some("the signer is unreachable")
+        );
+        assert!(InternalError::ReceiptError { source: None }
+            .source()
+            .is_none());
+        assert!(InternalError::PointError.source().is_none());
+    }
+}
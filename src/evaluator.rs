@@ -0,0 +1,291 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Evaluation with a private key which is held outside of the process
+
+use crate::{
+    ciphersuite::CipherSuite,
+    errors::InternalError,
+    group::Group,
+    rfc9497::{compute_challenge, compute_composites, VoprfServer},
+    voprf::{Mode, Proof},
+};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+/// The future returned by the methods of an [AsyncBlindEvaluator]
+pub type EvaluatorFuture<'a, T> =
+    Pin<Box<dyn Future<Output = Result<T, InternalError>> + Send + 'a>>;
+
+/// Holds the private key `k` of a server of the VOPRF mode of
+/// [rfc9497](crate::rfc9497), so that the key can stay inside of an HSM or a
+/// KMS while the rest of the protocol runs in the process.
+///
+/// The evaluator only multiplies elements by the private key and proves that
+/// it did: [VoprfServer::batch_blind_evaluate_with] checks the blinded
+/// elements, computes the composite elements the proof is over, and verifies
+/// the proof before returning it, so a faulty evaluator can't hand out a wrong
+/// evaluation. [VoprfServer] itself is the evaluator of a private key in
+/// memory.
+///
+/// The draft-07 [VerifiableServer](crate::VerifiableServer) has no evaluator:
+/// it multiplies by the inverse of the key tweaked with the metadata,
+/// `(k + m)^-1`, and proves knowledge of `k + m`, neither of which an
+/// evaluator that only multiplies by `k` can compute.
+pub trait BlindEvaluator<CS: CipherSuite> {
+    /// Returns the public key `k * G`
+    fn public_key(&self) -> CS::Group;
+
+    /// Returns every element multiplied by the private key, in the same order
+    fn evaluate(&self, elements: &[CS::Group]) -> Result<Vec<CS::Group>, InternalError>;
+
+    /// Proves knowledge of the private key: draws a random non-zero nonce
+    /// `r`, computes the challenge `c` with [ProofTranscript::challenge] of
+    /// `r * G` and `r * M`, where `M` is [ProofTranscript::composite_element],
+    /// and returns [ProofTranscript::proof] of `c` and `r - c * k`
+    fn prove<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        transcript: &ProofTranscript<CS>,
+    ) -> Result<Proof<CS>, InternalError>;
+}
+
+/// The asynchronous variant of [BlindEvaluator], for keys which are reached
+/// over the network, used by [VoprfServer::batch_blind_evaluate_async]
+pub trait AsyncBlindEvaluator<CS: CipherSuite> {
+    /// Returns the public key `k * G`
+    fn public_key(&self) -> CS::Group;
+
+    /// Returns every element multiplied by the private key, in the same order
+    fn evaluate<'a>(&'a self, elements: &'a [CS::Group]) -> EvaluatorFuture<'a, Vec<CS::Group>>;
+
+    /// Proves knowledge of the private key, see [BlindEvaluator::prove]
+    fn prove<'a, R: RngCore + CryptoRng + Send>(
+        &'a self,
+        rng: &'a mut R,
+        transcript: &'a ProofTranscript<CS>,
+    ) -> EvaluatorFuture<'a, Proof<CS>>;
+}
+
+/// The composite elements of a batch, which the proof of an evaluator is
+/// generated over
+pub struct ProofTranscript<CS: CipherSuite> {
+    pk: CS::Group,
+    m: CS::Group,
+    z: CS::Group,
+}
+
+impl<CS: CipherSuite> ProofTranscript<CS> {
+    pub(crate) fn new(
+        pk: CS::Group,
+        blinded_elements: &[CS::Group],
+        evaluated_elements: &[CS::Group],
+    ) -> Result<Self, InternalError> {
+        let (m, z) = compute_composites::<CS>(
+            None,
+            pk,
+            blinded_elements.iter().copied(),
+            evaluated_elements.iter().copied(),
+            Mode::Verifiable,
+        )?;
+
+        Ok(Self { pk, m, z })
+    }
+
+    /// The composite element `M` of the blinded elements
+    pub fn composite_element(&self) -> CS::Group {
+        self.m
+    }
+
+    /// Computes the challenge `c` of the proof from `r * G` and `r * M`
+    pub fn challenge(
+        &self,
+        t2: CS::Group,
+        t3: CS::Group,
+    ) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+        compute_challenge::<CS>(self.pk, self.m, self.z, t2, t3, Mode::Verifiable)
+    }
+
+    /// Assembles the proof from the challenge `c` and the response
+    /// `r - c * k`
+    pub fn proof(
+        &self,
+        c_scalar: <CS::Group as Group>::Scalar,
+        s_scalar: <CS::Group as Group>::Scalar,
+    ) -> Proof<CS> {
+        Proof { c_scalar, s_scalar }
+    }
+}
+
+impl<CS: CipherSuite> BlindEvaluator<CS> for VoprfServer<CS> {
+    fn public_key(&self) -> CS::Group {
+        self.pk
+    }
+
+    fn evaluate(&self, elements: &[CS::Group]) -> Result<Vec<CS::Group>, InternalError> {
+        Ok(elements.iter().map(|element| *element * &self.sk).collect())
+    }
+
+    fn prove<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        transcript: &ProofTranscript<CS>,
+    ) -> Result<Proof<CS>, InternalError> {
+        let r = Zeroizing::new(CS::Group::random_nonzero_scalar(rng));
+        let t2 = CS::Group::base_point() * &r;
+        let t3 = transcript.m * &r;
+
+        let c_scalar = transcript.challenge(t2, t3)?;
+        let s_scalar = *r - &Zeroizing::new(c_scalar * &self.sk);
+
+        Ok(transcript.proof(c_scalar, s_scalar))
+    }
+}
+
+// Evaluates right away, the returned futures are always ready
+impl<CS: CipherSuite> AsyncBlindEvaluator<CS> for VoprfServer<CS>
+where
+    CS::Group: Send,
+    <CS::Group as Group>::Scalar: Send,
+{
+    fn public_key(&self) -> CS::Group {
+        self.pk
+    }
+
+    fn evaluate<'a>(&'a self, elements: &'a [CS::Group]) -> EvaluatorFuture<'a, Vec<CS::Group>> {
+        Box::pin(core::future::ready(BlindEvaluator::evaluate(
+            self, elements,
+        )))
+    }
+
+    fn prove<'a, R: RngCore + CryptoRng + Send>(
+        &'a self,
+        rng: &'a mut R,
+        transcript: &'a ProofTranscript<CS>,
+    ) -> EvaluatorFuture<'a, Proof<CS>> {
+        Box::pin(core::future::ready(BlindEvaluator::prove(
+            self, rng, transcript,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rfc9497::VoprfClient;
+    use crate::tests::Ristretto255Sha512;
+    use rand::rngs::OsRng;
+
+    type CS = Ristretto255Sha512;
+
+    // Evaluates with another key than its public key
+    struct FaultyEvaluator {
+        server: VoprfServer<CS>,
+        other: VoprfServer<CS>,
+    }
+
+    impl BlindEvaluator<CS> for FaultyEvaluator {
+        fn public_key(&self) -> <CS as CipherSuite>::Group {
+            BlindEvaluator::public_key(&self.server)
+        }
+
+        fn evaluate(
+            &self,
+            elements: &[<CS as CipherSuite>::Group],
+        ) -> Result<Vec<<CS as CipherSuite>::Group>, InternalError> {
+            BlindEvaluator::evaluate(&self.other, elements)
+        }
+
+        fn prove<R: RngCore + CryptoRng>(
+            &self,
+            rng: &mut R,
+            transcript: &ProofTranscript<CS>,
+        ) -> Result<Proof<CS>, InternalError> {
+            BlindEvaluator::prove(&self.server, rng, transcript)
+        }
+    }
+
+    #[test]
+    fn test_blind_evaluate_with() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let inputs = [b"input 1", b"input 2"];
+        let server = VoprfServer::<CS>::new(&mut rng)?;
+        let (clients, messages): (Vec<_>, Vec<_>) = inputs
+            .iter()
+            .map(|input| {
+                let result = VoprfClient::<CS>::blind(*input, &mut rng).unwrap();
+                (result.state, result.message)
+            })
+            .unzip();
+
+        let result = VoprfServer::batch_blind_evaluate_with(&server, &mut rng, &messages)?;
+        let outputs = VoprfClient::batch_finalize(
+            &inputs,
+            &clients,
+            &result.messages,
+            &result.proof,
+            server.get_public_key(),
+        )?;
+        for (input, output) in inputs.iter().zip(outputs) {
+            assert_eq!(*output, server.evaluate(*input)?);
+        }
+
+        let faulty = FaultyEvaluator {
+            server,
+            other: VoprfServer::<CS>::new(&mut rng)?,
+        };
+        assert!(matches!(
+            VoprfServer::blind_evaluate_with(&faulty, &mut rng, &messages[0]),
+            Err(InternalError::ProofVerificationError)
+        ));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_blind_evaluate_async() -> Result<(), InternalError> {
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake};
+
+        struct NoopWaker;
+
+        impl Wake for NoopWaker {
+            fn wake(self: Arc<Self>) {}
+        }
+
+        let mut rng = OsRng;
+        let server = VoprfServer::<CS>::new(&mut rng)?;
+        let client_result = VoprfClient::<CS>::blind(b"input", &mut rng)?;
+
+        // The futures of the local evaluator are always ready
+        let waker = Arc::new(NoopWaker).into();
+        let mut future = Box::pin(VoprfServer::blind_evaluate_async(
+            &server,
+            &mut rng,
+            &client_result.message,
+        ));
+        let result = match future.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(result) => result?,
+            Poll::Pending => panic!("the local evaluator never blocks"),
+        };
+
+        let output = client_result.state.finalize(
+            b"input",
+            &result.message,
+            &result.proof,
+            server.get_public_key(),
+        )?;
+        assert_eq!(*output.output, server.evaluate(b"input")?);
+
+        Ok(())
+    }
+}
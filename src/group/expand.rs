@@ -7,64 +7,136 @@
 
 use crate::errors::InternalError;
 use crate::hash::Hash;
-use crate::serialization::i2osp;
-use alloc::vec::Vec;
-use digest::{BlockInput, Digest};
-use generic_array::typenum::Unsigned;
+use crate::serialization::{i2osp_1, i2osp_2};
+use digest::{BlockInput, Digest, ExtendableOutput, Update, XofReader};
+use generic_array::{typenum::Unsigned, ArrayLength, GenericArray};
 
 // Computes ceil(x / y)
+// `usize::is_multiple_of` is newer than the MSRV
+#[allow(clippy::manual_is_multiple_of)]
 fn div_ceil(x: usize, y: usize) -> usize {
     let additive = (x % y != 0) as usize;
     x / y + additive
 }
 
-fn xor(x: &[u8], y: &[u8]) -> Result<Vec<u8>, InternalError> {
-    if x.len() != y.len() {
-        return Err(InternalError::HashToCurveError);
-    }
+fn xor<L: ArrayLength<u8>>(
+    x: &GenericArray<u8, L>,
+    y: &GenericArray<u8, L>,
+) -> GenericArray<u8, L> {
+    x.iter().zip(y).map(|(&x1, &x2)| x1 ^ x2).collect()
+}
 
-    Ok(x.iter().zip(y).map(|(&x1, &x2)| x1 ^ x2).collect())
+// Computes the length of DST_prime, shared by both expansions. Takes only byte
+// strings, so it's compiled once instead of once per hash function
+fn dst_len(dst: &[&[u8]]) -> Result<[u8; 1], InternalError> {
+    // Summing with `Iterator::sum` could overflow on 16-bit targets
+    dst.iter()
+        .try_fold(0_usize, |len, part| len.checked_add(part.len()))
+        .ok_or(InternalError::HashToCurveError)
+        .and_then(|len| i2osp_1(len).map_err(|_| InternalError::HashToCurveError))
 }
 
 /// Corresponds to the expand_message_xmd() function defined in
 /// <https://www.ietf.org/archive/id/draft-irtf-cfrg-hash-to-curve-10.txt>
-pub fn expand_message_xmd<H: Hash>(
-    msg: &[u8],
-    dst: &[u8],
-    len_in_bytes: usize,
-) -> Result<Vec<u8>, InternalError> {
+///
+/// The message and the DST are passed as slices of parts which are hashed in
+/// order, so that callers don't have to concatenate them beforehand. The output
+/// length is given by `L`.
+pub fn expand_message_xmd<H: Hash, L: ArrayLength<u8>>(
+    msg: &[&[u8]],
+    dst: &[&[u8]],
+) -> Result<GenericArray<u8, L>, InternalError> {
+    let mut uniform_bytes = GenericArray::<u8, L>::default();
+    expand_message_xmd_into::<H>(msg, dst, &mut uniform_bytes)?;
+    Ok(uniform_bytes)
+}
+
+// Fills `uniform_bytes` with the output of `expand_message_xmd`, so that the
+// expansion is only generic over the hash function and not the output length
+fn expand_message_xmd_into<H: Hash>(
+    msg: &[&[u8]],
+    dst: &[&[u8]],
+    uniform_bytes: &mut [u8],
+) -> Result<(), InternalError> {
     let b_in_bytes = <H as Digest>::OutputSize::USIZE;
-    let r_in_bytes = <H as BlockInput>::BlockSize::USIZE;
+    let len_in_bytes = uniform_bytes.len();
 
     let ell = div_ceil(len_in_bytes, b_in_bytes);
     if ell > 255 {
         return Err(InternalError::HashToCurveError);
     }
-    let dst_prime = [dst, &i2osp(dst.len(), 1)?].concat();
-    let z_pad = i2osp(0, r_in_bytes)?;
-    let l_i_b_str = i2osp(len_in_bytes, 2)?;
-    let msg_prime = [&z_pad, msg, &l_i_b_str, &i2osp(0, 1)?, &dst_prime].concat();
-
-    let mut b: Vec<Vec<u8>> = alloc::vec![H::digest(&msg_prime).to_vec()]; // b[0]
+    let dst_len = dst_len(dst)?;
+    let z_pad = GenericArray::<u8, <H as BlockInput>::BlockSize>::default();
+    let l_i_b_str = i2osp_2(len_in_bytes)?;
 
     let mut h = H::new();
-    h.update(&b[0]);
-    h.update(&i2osp(1, 1)?);
-    h.update(&dst_prime);
-    b.push(h.finalize_reset().to_vec()); // b[1]
-
-    let mut uniform_bytes: Vec<u8> = Vec::new();
-    uniform_bytes.extend_from_slice(&b[1]);
-
-    for i in 2..(ell + 1) {
-        h.update(xor(&b[0], &b[i - 1])?);
-        h.update(&i2osp(i, 1)?);
-        h.update(&dst_prime);
-        b.push(h.finalize_reset().to_vec()); // b[i]
-        uniform_bytes.extend_from_slice(&b[i]);
+
+    // msg_prime = Z_pad || msg || l_i_b_str || I2OSP(0, 1) || DST_prime
+    h.update(&z_pad);
+    msg.iter().for_each(|part| h.update(part));
+    h.update(l_i_b_str);
+    h.update(i2osp_1(0)?);
+    dst.iter().for_each(|part| h.update(part));
+    h.update(dst_len);
+    let b_0 = h.finalize_reset();
+
+    let mut b_i = GenericArray::default();
+
+    for (i, chunk) in (1..).zip(uniform_bytes.chunks_mut(b_in_bytes)) {
+        // b_1 = H(b_0 || I2OSP(1, 1) || DST_prime)
+        // b_i = H(strxor(b_0, b_(i - 1)) || I2OSP(i, 1) || DST_prime)
+        if i == 1 {
+            h.update(&b_0);
+        } else {
+            h.update(xor(&b_0, &b_i));
+        }
+        h.update(i2osp_1(i)?);
+        dst.iter().for_each(|part| h.update(part));
+        h.update(dst_len);
+        b_i = h.finalize_reset();
+
+        chunk.copy_from_slice(&b_i[..chunk.len()]);
     }
 
-    Ok(uniform_bytes[..len_in_bytes].to_vec())
+    Ok(())
+}
+
+/// Corresponds to the expand_message_xof() function defined in
+/// <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-hash-to-curve-11#section-5.4.2>
+///
+/// Takes the same parts as [expand_message_xmd], but uses an extendable-output
+/// function, e.g. SHAKE-256, instead of a fixed-output hash function. The
+/// output length is given by `L`.
+pub fn expand_message_xof<X: ExtendableOutput + Update + Default, L: ArrayLength<u8>>(
+    msg: &[&[u8]],
+    dst: &[&[u8]],
+) -> Result<GenericArray<u8, L>, InternalError> {
+    let mut uniform_bytes = GenericArray::<u8, L>::default();
+    expand_message_xof_into::<X>(msg, dst, &mut uniform_bytes)?;
+    Ok(uniform_bytes)
+}
+
+// Fills `uniform_bytes` with the output of `expand_message_xof`, see
+// `expand_message_xmd_into`
+fn expand_message_xof_into<X: ExtendableOutput + Update + Default>(
+    msg: &[&[u8]],
+    dst: &[&[u8]],
+    uniform_bytes: &mut [u8],
+) -> Result<(), InternalError> {
+    let dst_len = dst_len(dst)?;
+    let l_i_b_str = i2osp_2(uniform_bytes.len())?;
+
+    let mut h = X::default();
+
+    // msg_prime = msg || I2OSP(len_in_bytes, 2) || DST_prime
+    msg.iter().for_each(|part| h.update(part));
+    h.update(l_i_b_str);
+    dst.iter().for_each(|part| h.update(part));
+    h.update(dst_len);
+
+    h.finalize_xof().read(uniform_bytes);
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -76,6 +148,9 @@ mod tests {
         uniform_bytes: &'static str,
     }
 
+    use generic_array::typenum::{U128, U32};
+    use sha3::Shake128;
+
     #[test]
     fn test_expand_message_xmd() {
         // Test vectors taken from Section K.1 of https://www.ietf.org/archive/id/draft-irtf-cfrg-hash-to-curve-10.txt
@@ -180,12 +255,72 @@ mod tests {
         let dst = "QUUX-V01-CS02-with-expander";
 
         for tv in test_vectors {
-            let uniform_bytes = super::expand_message_xmd::<sha2::Sha256>(
-                tv.msg.as_bytes(),
-                dst.as_bytes(),
-                tv.len_in_bytes,
-            )
-            .unwrap();
+            let uniform_bytes = match tv.len_in_bytes {
+                0x20 => super::expand_message_xmd::<sha2::Sha256, U32>(
+                    &[tv.msg.as_bytes()],
+                    &[dst.as_bytes()],
+                )
+                .unwrap()
+                .to_vec(),
+                0x80 => super::expand_message_xmd::<sha2::Sha256, U128>(
+                    &[tv.msg.as_bytes()],
+                    &[dst.as_bytes()],
+                )
+                .unwrap()
+                .to_vec(),
+                _ => unreachable!(),
+            };
+            assert_eq!(tv.uniform_bytes, hex::encode(uniform_bytes));
+        }
+    }
+
+    #[test]
+    fn test_expand_message_xof() {
+        // Test vectors taken from the expand_message_xof(SHAKE128) vectors in
+        // Appendix K of https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-hash-to-curve-11
+        let test_vectors: alloc::vec::Vec<Params> = alloc::vec![
+            Params {
+                msg: "",
+                len_in_bytes: 0x20,
+                uniform_bytes: "86518c9cd86581486e9485aa74ab35ba150d1c75c88e26b7043e44e2acd735a2",
+            },
+            Params {
+                msg: "abc",
+                len_in_bytes: 0x20,
+                uniform_bytes: "8696af52a4d862417c0763556073f47bc9b9ba43c99b505305cb1ec04a9ab468",
+            },
+            Params {
+                msg: "abcdef0123456789",
+                len_in_bytes: 0x20,
+                uniform_bytes: "912c58deac4821c3509dbefa094df54b34b8f5d01a191d1d3108a2c89077acca",
+            },
+            Params {
+                msg: "",
+                len_in_bytes: 0x80,
+                uniform_bytes: "7314ff1a155a2fb99a0171dc71b89ab6e3b2b7d59e38e64419b8b6294d03ffee\
+                42491f11370261f436220ef787f8f76f5b26bdcd850071920ce023f3ac468477\
+                44f4612b8714db8f5db83205b2e625d95afd7d7b4d3094d3bdde815f52850bb4\
+                1ead9822e08f22cf41d615a303b0d9dde73263c049a7b9898208003a739a2e57",
+            },
+        ];
+        let dst = "QUUX-V01-CS02-with-expander-SHAKE128";
+
+        for tv in test_vectors {
+            let uniform_bytes = match tv.len_in_bytes {
+                0x20 => super::expand_message_xof::<Shake128, U32>(
+                    &[tv.msg.as_bytes()],
+                    &[dst.as_bytes()],
+                )
+                .unwrap()
+                .to_vec(),
+                0x80 => super::expand_message_xof::<Shake128, U128>(
+                    &[tv.msg.as_bytes()],
+                    &[dst.as_bytes()],
+                )
+                .unwrap()
+                .to_vec(),
+                _ => unreachable!(),
+            };
             assert_eq!(tv.uniform_bytes, hex::encode(uniform_bytes));
         }
     }
@@ -0,0 +1,230 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! An insecure group for fast unit tests, enabled by the `mock` feature
+
+use super::Group;
+use crate::errors::InternalError;
+use crate::CipherSuite;
+use core::convert::TryInto;
+use core::ops::{Add, Mul, Sub};
+use generic_array::{
+    typenum::{U16, U8},
+    GenericArray,
+};
+use rand::{CryptoRng, RngCore};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+#[cfg(not(debug_assertions))]
+compile_error!(
+    "the `mock` feature provides an insecure group for tests and can't be enabled in release \
+     builds"
+);
+
+// The Mersenne prime 2^61 - 1, the order of the group
+const ORDER: u64 = (1 << 61) - 1;
+
+fn reduce(value: u128) -> u64 {
+    (value % u128::from(ORDER)) as u64
+}
+
+/// ⚠️ **INSECURE** ⚠️ The additive group of integers modulo 2^61 - 1, which
+/// makes running the protocol nearly free. Discrete logarithms in this group
+/// are a single division, so it offers no security at all and exists only to
+/// speed up unit tests of applications built on this crate.
+///
+/// Its suite identifier, `0xFFFF`, isn't assigned to any ciphersuite, so
+/// outputs never collide with those of a real group. Enabling the `mock`
+/// feature fails to compile unless debug assertions are enabled, so it can't
+/// end up in a release build.
+///
+/// ```
+/// struct Mock;
+/// impl voprf::CipherSuite for Mock {
+///     type Group = voprf::group::MockGroup;
+///     type Hash = sha2::Sha256;
+/// }
+///
+/// let mut rng = rand::rngs::OsRng;
+/// let client_blind_result = voprf::NonVerifiableClient::<Mock>::blind(b"input", &mut rng)?;
+/// # Ok::<(), voprf::errors::InternalError>(())
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MockGroup(u64);
+
+/// The scalars of [MockGroup], integers modulo 2^61 - 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MockScalar(u64);
+
+impl Zeroize for MockScalar {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<'a> Add<&'a MockScalar> for MockScalar {
+    type Output = MockScalar;
+
+    fn add(self, other: &'a MockScalar) -> MockScalar {
+        MockScalar(reduce(u128::from(self.0) + u128::from(other.0)))
+    }
+}
+
+impl<'a> Sub<&'a MockScalar> for MockScalar {
+    type Output = MockScalar;
+
+    fn sub(self, other: &'a MockScalar) -> MockScalar {
+        MockScalar(reduce(
+            u128::from(self.0) + u128::from(ORDER) - u128::from(other.0),
+        ))
+    }
+}
+
+impl<'a> Mul<&'a MockScalar> for MockScalar {
+    type Output = MockScalar;
+
+    fn mul(self, other: &'a MockScalar) -> MockScalar {
+        MockScalar(reduce(u128::from(self.0) * u128::from(other.0)))
+    }
+}
+
+impl<'a> Add<&'a MockGroup> for MockGroup {
+    type Output = MockGroup;
+
+    fn add(self, other: &'a MockGroup) -> MockGroup {
+        MockGroup(reduce(u128::from(self.0) + u128::from(other.0)))
+    }
+}
+
+impl<'a> Mul<&'a MockScalar> for MockGroup {
+    type Output = MockGroup;
+
+    fn mul(self, scalar: &'a MockScalar) -> MockGroup {
+        MockGroup(reduce(u128::from(self.0) * u128::from(scalar.0)))
+    }
+}
+
+// Decodes a big-endian integer, rejecting values which aren't reduced
+fn from_bytes(bytes: &GenericArray<u8, U8>) -> Result<u64, InternalError> {
+    let value = u64::from_be_bytes(
+        bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| InternalError::InvalidByteSequence)?,
+    );
+
+    if value >= ORDER {
+        return Err(InternalError::InvalidByteSequence);
+    }
+
+    Ok(value)
+}
+
+// Reduces 16 uniformly random bytes, the bias is negligible for tests
+fn from_uniform_bytes(bytes: &GenericArray<u8, U16>) -> Result<u64, InternalError> {
+    Ok(reduce(u128::from_be_bytes(
+        bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| InternalError::HashToCurveError)?,
+    )))
+}
+
+impl Group for MockGroup {
+    const SUITE_ID: usize = 0xFFFF;
+    const IDENTIFIER: &'static str = "mock-SHA256";
+
+    fn hash_to_curve<CS: CipherSuite>(msg: &[&[u8]], dst: &[&[u8]]) -> Result<Self, InternalError> {
+        let uniform_bytes = CS::expand_message::<U16>(msg, dst)?;
+
+        Ok(MockGroup(from_uniform_bytes(&uniform_bytes)?))
+    }
+
+    fn hash_to_scalar<CS: CipherSuite>(
+        input: &[&[u8]],
+        dst: &[&[u8]],
+    ) -> Result<Self::Scalar, InternalError> {
+        let uniform_bytes = CS::expand_message::<U16>(input, dst)?;
+
+        Ok(MockScalar(from_uniform_bytes(&uniform_bytes)?))
+    }
+
+    type Scalar = MockScalar;
+    type ScalarLen = U8;
+    fn from_scalar_slice_unchecked(
+        scalar_bits: &GenericArray<u8, Self::ScalarLen>,
+    ) -> Result<Self::Scalar, InternalError> {
+        from_bytes(scalar_bits).map(MockScalar)
+    }
+
+    fn random_nonzero_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Scalar {
+        loop {
+            // Rejection sampling of 61 bits
+            let scalar = rng.next_u64() >> 3;
+
+            if scalar != 0 && scalar < ORDER {
+                break MockScalar(scalar);
+            }
+        }
+    }
+
+    fn scalar_as_bytes(scalar: Self::Scalar) -> GenericArray<u8, Self::ScalarLen> {
+        scalar.0.to_be_bytes().into()
+    }
+
+    fn scalar_invert(scalar: &Self::Scalar) -> Self::Scalar {
+        // Fermat's little theorem: scalar^(ORDER - 2)
+        let mut result = MockScalar(1);
+        let mut base = *scalar;
+        let mut exponent = ORDER - 2;
+
+        while exponent != 0 {
+            if exponent & 1 == 1 {
+                result = result * &base;
+            }
+
+            base = base * &base;
+            exponent >>= 1;
+        }
+
+        result
+    }
+
+    type ElemLen = U8;
+    fn from_element_slice_unchecked(
+        element_bits: &GenericArray<u8, Self::ElemLen>,
+    ) -> Result<Self, InternalError> {
+        from_bytes(element_bits)
+            .map(MockGroup)
+            .map_err(|_| InternalError::PointError)
+    }
+
+    fn to_arr(&self) -> GenericArray<u8, Self::ElemLen> {
+        self.0.to_be_bytes().into()
+    }
+
+    fn base_point() -> Self {
+        MockGroup(1)
+    }
+
+    fn identity() -> Self {
+        MockGroup(0)
+    }
+
+    fn scalar_zero() -> Self::Scalar {
+        MockScalar(0)
+    }
+
+    fn ct_equal(&self, other: &Self) -> bool {
+        self.0.ct_eq(&other.0).into()
+    }
+
+    fn ct_equal_scalar(s1: &Self::Scalar, s2: &Self::Scalar) -> bool {
+        s1.0.ct_eq(&s2.0).into()
+    }
+}
@@ -8,16 +8,27 @@
 //! Defines the Group trait to specify the underlying prime order group
 
 mod expand;
+#[cfg(feature = "mock")]
+mod mock;
 #[cfg(feature = "p256")]
 pub(crate) mod p256;
 mod ristretto;
+#[cfg(feature = "p256")]
+mod sswu;
+
+pub use expand::{expand_message_xmd, expand_message_xof};
+#[cfg(feature = "mock")]
+pub use mock::{MockGroup, MockScalar};
 
 use crate::errors::InternalError;
-use crate::hash::Hash;
+use crate::CipherSuite;
+use alloc::vec::Vec;
 use core::ops::{Add, Mul, Sub};
+use generic_array::typenum::{IsLess, True, U65536};
 use generic_array::{ArrayLength, GenericArray};
 use rand::{CryptoRng, RngCore};
-use zeroize::Zeroize;
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, Zeroizing};
 
 /// A prime-order subgroup of a base field (EC, prime-order field ...). This
 /// subgroup is noted additively — as in the draft RFC — in this trait.
@@ -31,11 +42,42 @@ pub trait Group:
     /// <https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-05.txt>
     const SUITE_ID: usize;
 
+    /// The identifier of the ciphersuite in the context string of
+    /// [RFC 9497](https://www.rfc-editor.org/rfc/rfc9497.html#section-4), see
+    /// [rfc9497](crate::rfc9497)
+    const IDENTIFIER: &'static str;
+
     /// transforms a password and domain separation tag (DST) into a curve point
-    fn hash_to_curve<H: Hash>(msg: &[u8], dst: &[u8]) -> Result<Self, InternalError>;
+    ///
+    /// Both the message and the DST are given as a list of parts, which are
+    /// treated as if they were concatenated. They are expanded with
+    /// [CipherSuite::expand_message] of `CS`.
+    fn hash_to_curve<CS: CipherSuite>(msg: &[&[u8]], dst: &[&[u8]]) -> Result<Self, InternalError>;
+
+    /// transforms a message and domain separation tag (DST) into a curve point
+    /// with the nonuniform `encode_to_curve` variant, which maps to the curve
+    /// only once. Its output isn't uniformly distributed, so it must never
+    /// replace [Group::hash_to_curve] in the protocol, only in other uses which
+    /// don't require a random oracle.
+    ///
+    /// Groups without a cheaper nonuniform encoding, like ristretto255, use
+    /// [Group::hash_to_curve].
+    fn encode_to_curve<CS: CipherSuite>(
+        msg: &[&[u8]],
+        dst: &[&[u8]],
+    ) -> Result<Self, InternalError> {
+        Self::hash_to_curve::<CS>(msg, dst)
+    }
 
     /// Hashes a slice of pseudo-random bytes to a scalar
-    fn hash_to_scalar<H: Hash>(input: &[u8], dst: &[u8]) -> Result<Self::Scalar, InternalError>;
+    ///
+    /// Both the input and the DST are given as a list of parts, which are
+    /// treated as if they were concatenated. They are expanded with
+    /// [CipherSuite::expand_message] of `CS`.
+    fn hash_to_scalar<CS: CipherSuite>(
+        input: &[&[u8]],
+        dst: &[&[u8]],
+    ) -> Result<Self::Scalar, InternalError>;
 
     /// The type of base field scalars
     type Scalar: Zeroize
@@ -43,8 +85,10 @@ pub trait Group:
         + for<'a> Add<&'a Self::Scalar, Output = Self::Scalar>
         + for<'a> Sub<&'a Self::Scalar, Output = Self::Scalar>
         + for<'a> Mul<&'a Self::Scalar, Output = Self::Scalar>;
-    /// The byte length necessary to represent scalars
-    type ScalarLen: ArrayLength<u8> + 'static;
+    /// The byte length necessary to represent scalars. It is framed with a
+    /// 2-byte length prefix by the protocol, so it has to be less than 2^16,
+    /// which is checked at compile time.
+    type ScalarLen: ArrayLength<u8> + IsLess<U65536, Output = True> + 'static;
 
     /// Return a scalar from its fixed-length bytes representation, without
     /// checking if the scalar is zero.
@@ -53,11 +97,21 @@ pub trait Group:
     ) -> Result<Self::Scalar, InternalError>;
 
     /// Return a scalar from its fixed-length bytes representation. If the scalar
-    /// is zero, then return an error.
+    /// is zero, or the representation is not canonical, then return an error.
     fn from_scalar_slice(
         scalar_bits: &GenericArray<u8, Self::ScalarLen>,
     ) -> Result<Self::Scalar, InternalError> {
         let scalar = Self::from_scalar_slice_unchecked(scalar_bits)?;
+
+        // Reject any encoding which doesn't round-trip, e.g. values larger than
+        // the group order, regardless of what the implementation does
+        let mut canonical = Self::scalar_as_bytes(scalar);
+        let is_canonical: bool = canonical.as_slice().ct_eq(scalar_bits.as_slice()).into();
+        canonical.as_mut_slice().zeroize();
+        if !is_canonical {
+            return Err(InternalError::InvalidByteSequence);
+        }
+
         if Self::ct_equal_scalar(&scalar, &Self::scalar_zero()) {
             return Err(InternalError::ZeroScalarError);
         }
@@ -71,8 +125,36 @@ pub trait Group:
     /// The multiplicative inverse of this scalar
     fn scalar_invert(scalar: &Self::Scalar) -> Self::Scalar;
 
-    /// The byte length necessary to represent group elements
-    type ElemLen: ArrayLength<u8> + 'static;
+    /// Replaces each of the nonzero `scalars` by its multiplicative inverse.
+    /// The default uses Montgomery's trick, which costs a single inversion
+    /// and 3(n-1) multiplications, with a zeroized buffer of n scalars.
+    fn invert_scalars_batch(scalars: &mut [Self::Scalar]) {
+        let (first, rest) = match scalars.split_first() {
+            Some((first, rest)) => (*first, rest),
+            None => return,
+        };
+
+        // The products of the scalars up to each index
+        let mut products = Zeroizing::new(Vec::with_capacity(scalars.len()));
+        products.push(first);
+        for scalar in rest {
+            let product = *products.last().unwrap_or(&first) * scalar;
+            products.push(product);
+        }
+
+        let mut inverse = Zeroizing::new(Self::scalar_invert(&products[scalars.len() - 1]));
+        for index in (1..scalars.len()).rev() {
+            let scalar = scalars[index];
+            scalars[index] = *inverse * &products[index - 1];
+            *inverse = *inverse * &scalar;
+        }
+        scalars[0] = *inverse;
+    }
+
+    /// The byte length necessary to represent group elements. It is framed
+    /// with a 2-byte length prefix by the protocol, so it has to be less than
+    /// 2^16, which is checked at compile time.
+    type ElemLen: ArrayLength<u8> + IsLess<U65536, Output = True> + 'static;
 
     /// Return an element from its fixed-length bytes representation. This is
     /// the unchecked version, which does not check for deserializing the identity
@@ -82,15 +164,21 @@ pub trait Group:
     ) -> Result<Self, InternalError>;
 
     /// Return an element from its fixed-length bytes representation. If the element
-    /// is the identity element, return an error.
+    /// is the identity element, or the representation is not canonical, return an
+    /// error.
     fn from_element_slice(
         element_bits: &GenericArray<u8, Self::ElemLen>,
     ) -> Result<Self, InternalError> {
         let elem = Self::from_element_slice_unchecked(element_bits)?;
 
+        // Reject any encoding which doesn't round-trip
+        if &elem.to_arr() != element_bits {
+            return Err(InternalError::PointError);
+        }
+
         if Self::ct_equal(&elem, &<Self as Group>::identity()) {
             // found the identity element
-            return Err(InternalError::PointError);
+            return Err(InternalError::IdentityElementError);
         }
 
         Ok(elem)
@@ -110,6 +198,21 @@ pub trait Group:
     /// Returns the identity group element
     fn identity() -> Self;
 
+    /// Computes the sum of `scalars[i]·elements[i]`, e.g. `a·s + b·c` in
+    /// proof verification, for slices of the same length. It may run in
+    /// variable time, so it must only be used with public values. The default
+    /// multiplies each element on its own; groups with a faster algorithm
+    /// override it, like ristretto255 for sums of two elements where the
+    /// first is the base point. Implementations must not allocate.
+    fn vartime_multiscalar_mul(scalars: &[Self::Scalar], elements: &[Self]) -> Self {
+        scalars
+            .iter()
+            .zip(elements)
+            .fold(<Self as Group>::identity(), |sum, (scalar, element)| {
+                sum + &(*element * scalar)
+            })
+    }
+
     /// Returns the scalar representing zero
     fn scalar_zero() -> Self::Scalar;
 
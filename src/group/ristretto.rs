@@ -7,7 +7,7 @@
 
 use super::Group;
 use crate::errors::InternalError;
-use crate::hash::Hash;
+use crate::CipherSuite;
 use core::convert::TryInto;
 use curve25519_dalek::{
     constants::RISTRETTO_BASEPOINT_POINT,
@@ -15,18 +15,22 @@ use curve25519_dalek::{
     scalar::Scalar,
     traits::Identity,
 };
-use generic_array::{typenum::U32, GenericArray};
+use generic_array::{
+    typenum::{U32, U64},
+    GenericArray,
+};
 use rand::{CryptoRng, RngCore};
 use subtle::ConstantTimeEq;
 
 /// The implementation of such a subgroup for Ristretto
 impl Group for RistrettoPoint {
     const SUITE_ID: usize = 0x0001;
+    const IDENTIFIER: &'static str = "ristretto255-SHA512";
 
     // Implements the `hash_to_ristretto255()` function from
     // https://www.ietf.org/archive/id/draft-irtf-cfrg-hash-to-curve-10.txt
-    fn hash_to_curve<H: Hash>(msg: &[u8], dst: &[u8]) -> Result<Self, InternalError> {
-        let uniform_bytes = super::expand::expand_message_xmd::<H>(msg, dst, 64)?;
+    fn hash_to_curve<CS: CipherSuite>(msg: &[&[u8]], dst: &[&[u8]]) -> Result<Self, InternalError> {
+        let uniform_bytes = CS::expand_message::<U64>(msg, dst)?;
 
         Ok(RistrettoPoint::from_uniform_bytes(
             uniform_bytes
@@ -38,8 +42,11 @@ impl Group for RistrettoPoint {
 
     // Implements the `HashToScalar()` function from
     // https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-07.html#section-4.1
-    fn hash_to_scalar<H: Hash>(input: &[u8], dst: &[u8]) -> Result<Self::Scalar, InternalError> {
-        let uniform_bytes = super::expand::expand_message_xmd::<H>(input, dst, 64)?;
+    fn hash_to_scalar<CS: CipherSuite>(
+        input: &[&[u8]],
+        dst: &[&[u8]],
+    ) -> Result<Self::Scalar, InternalError> {
+        let uniform_bytes = CS::expand_message::<U64>(input, dst)?;
 
         Ok(Scalar::from_bytes_mod_order_wide(
             uniform_bytes
@@ -54,7 +61,9 @@ impl Group for RistrettoPoint {
     fn from_scalar_slice_unchecked(
         scalar_bits: &GenericArray<u8, Self::ScalarLen>,
     ) -> Result<Self::Scalar, InternalError> {
-        Ok(Scalar::from_bytes_mod_order(*scalar_bits.as_ref()))
+        // Reject non-canonical encodings, which would otherwise be reduced
+        Scalar::from_canonical_bytes(*scalar_bits.as_ref())
+            .ok_or(InternalError::InvalidByteSequence)
     }
 
     fn random_nonzero_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Scalar {
@@ -112,6 +121,22 @@ impl Group for RistrettoPoint {
         <Self as Identity>::identity()
     }
 
+    // Straus's algorithm for any number of elements allocates, which
+    // verification must not do, so only sums with the base point use it
+    fn vartime_multiscalar_mul(scalars: &[Self::Scalar], elements: &[Self]) -> Self {
+        match (scalars, elements) {
+            ([s, c], [a, b]) if *a == RISTRETTO_BASEPOINT_POINT => {
+                RistrettoPoint::vartime_double_scalar_mul_basepoint(c, b, s)
+            }
+            _ => scalars
+                .iter()
+                .zip(elements)
+                .fold(<Self as Group>::identity(), |sum, (scalar, element)| {
+                    sum + element * scalar
+                }),
+        }
+    }
+
     fn scalar_zero() -> Self::Scalar {
         Self::Scalar::zero()
     }
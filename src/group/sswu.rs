@@ -0,0 +1,257 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+// The simplified SWU map of the NIST curve groups, generic over the curve
+// parameters and the byte length of field elements, so that it isn't tied to
+// P-256.
+
+use core::ops::{Add, Div, Mul, Neg, Sub};
+use generic_array::{ArrayLength, GenericArray};
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+use num_traits::{One, ToPrimitive, Zero};
+
+/// Corresponds to the hash_to_curve_simple_swu() function defined in
+/// <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-hash-to-curve-11#appendix-F.2>
+///
+/// `cmov`, `mod_floor` and `modpow` needs to be made constant-time, which
+/// will be supported after crypto-bigint is no longer experimental. See
+/// https://github.com/novifinancial/voprf/issues/13 for more context.
+#[allow(clippy::many_single_char_names)]
+pub(super) fn hash_to_curve_simple_swu<N: ArrayLength<u8>>(
+    u: &[u8],
+    a: &BigInt,
+    b: &BigInt,
+    p: &BigInt,
+    z: &BigInt,
+) -> (GenericArray<u8, N>, GenericArray<u8, N>) {
+    #[derive(Clone)]
+    struct Field<'a>(&'a BigInt);
+
+    impl<'a> Field<'a> {
+        fn new(p: &'a BigInt) -> Self {
+            Self(p)
+        }
+
+        fn element(&'a self, number: &BigInt) -> FieldElement<'a> {
+            FieldElement {
+                number: number.mod_floor(self.0),
+                f: self,
+            }
+        }
+
+        fn one(&'a self) -> FieldElement<'a> {
+            self.element(&BigInt::one())
+        }
+
+        /// See <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-hash-to-curve-11#section-4>
+        fn inv0(&'a self, number: &FieldElement<'a>) -> FieldElement<'a> {
+            number.pow_internal(&(self.0 - 2))
+        }
+    }
+
+    /// Finite field arithmetic
+    #[derive(Clone)]
+    struct FieldElement<'a> {
+        number: BigInt,
+        f: &'a Field<'a>,
+    }
+
+    impl<'a> Add for FieldElement<'a> {
+        type Output = FieldElement<'a>;
+
+        fn add(self, rhs: Self) -> Self::Output {
+            &self + &rhs
+        }
+    }
+
+    impl<'a> Add for &FieldElement<'a> {
+        type Output = FieldElement<'a>;
+
+        fn add(self, rhs: Self) -> Self::Output {
+            self.f.element(&(&self.number + &rhs.number))
+        }
+    }
+
+    impl<'a> Sub for &FieldElement<'a> {
+        type Output = FieldElement<'a>;
+
+        fn sub(self, rhs: Self) -> Self::Output {
+            self.f.element(&(&self.number - &rhs.number))
+        }
+    }
+
+    impl<'a> Neg for FieldElement<'a> {
+        type Output = FieldElement<'a>;
+
+        fn neg(self) -> Self::Output {
+            -&self
+        }
+    }
+
+    impl<'a> Neg for &FieldElement<'a> {
+        type Output = FieldElement<'a>;
+
+        fn neg(self) -> Self::Output {
+            self.f.element(&-&self.number)
+        }
+    }
+
+    impl<'a> Mul for FieldElement<'a> {
+        type Output = FieldElement<'a>;
+
+        fn mul(self, rhs: Self) -> Self::Output {
+            &self * &rhs
+        }
+    }
+
+    impl<'a> Mul<&Self> for FieldElement<'a> {
+        type Output = FieldElement<'a>;
+
+        fn mul(self, rhs: &Self) -> Self::Output {
+            &self * rhs
+        }
+    }
+
+    impl<'a> Mul<FieldElement<'a>> for &FieldElement<'a> {
+        type Output = FieldElement<'a>;
+
+        fn mul(self, rhs: FieldElement<'a>) -> Self::Output {
+            self * &rhs
+        }
+    }
+
+    impl<'a> Mul for &FieldElement<'a> {
+        type Output = FieldElement<'a>;
+
+        fn mul(self, rhs: Self) -> Self::Output {
+            self.f.element(&(&self.number * &rhs.number))
+        }
+    }
+
+    impl<'a> Div<&Self> for FieldElement<'a> {
+        type Output = FieldElement<'a>;
+
+        #[allow(clippy::suspicious_arithmetic_impl)]
+        fn div(self, rhs: &Self) -> Self::Output {
+            self * rhs.f.inv0(rhs)
+        }
+    }
+
+    impl<'a> FieldElement<'a> {
+        fn square(&self) -> Self {
+            self * self
+        }
+
+        fn pow_internal(&self, exponent: &BigInt) -> Self {
+            let exponent = exponent.mod_floor(&(self.f.0 - 1));
+            self.f.element(&self.number.modpow(&exponent, self.f.0))
+        }
+
+        /// Corresponds to the sqrt_3mod4() function defined in
+        /// <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-hash-to-curve-11#appendix-I.1>
+        fn sqrt(&self) -> Self {
+            // constant
+            let c1 = (self.f.0 + 1) >> 2;
+
+            self.pow_internal(&c1)
+        }
+
+        /// Corresponds to the sgn0_m_eq_1() function defined in
+        /// <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-hash-to-curve-11#section-4.1>
+        fn sgn0(&self) -> i32 {
+            (&self.number % 2_usize).to_i32().unwrap()
+        }
+
+        fn is_zero(&self) -> bool {
+            self.number.is_zero()
+        }
+
+        /// Corresponds to the is_square() function defined in
+        /// <https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-hash-to-curve-11#section-4>
+        fn is_square(&self) -> bool {
+            // constant
+            let exponent = (self.f.0 - 1) >> 1;
+
+            let result = self.pow_internal(&exponent);
+            result.is_zero() || result.number.is_one()
+        }
+
+        fn to_bytes<N: ArrayLength<u8>>(&self) -> GenericArray<u8, N> {
+            let val = self.number.mod_floor(self.f.0).to_bytes_be().1;
+            let mut bytes = alloc::vec![0u8; N::USIZE - val.len()];
+            bytes.extend_from_slice(&val);
+            GenericArray::clone_from_slice(&bytes)
+        }
+    }
+
+    fn cmov<'a>(x: &FieldElement<'a>, y: &FieldElement<'a>, b: bool) -> FieldElement<'a> {
+        if b {
+            y.clone()
+        } else {
+            x.clone()
+        }
+    }
+
+    let f = Field::new(p);
+    let a = f.element(a);
+    let b = f.element(b);
+    let z = f.element(z);
+    let u = f.element(&BigInt::from_bytes_be(Sign::Plus, u));
+
+    // Constants:
+    // 1.  c1 = -B / A
+    let c1 = -&b / &a;
+    // 2.  c2 = -1 / Z
+    let c2 = -f.one() / &z;
+
+    // Steps:
+    // 1.  tv1 = Z * u^2
+    let tv1 = z * u.square();
+    // 2.  tv2 = tv1^2
+    let mut tv2 = tv1.square();
+    // 3.   x1 = tv1 + tv2
+    let mut x1 = &tv1 + &tv2;
+    // 4.   x1 = inv0(x1)
+    x1 = f.inv0(&x1);
+    // 5.   e1 = x1 == 0
+    let e1 = x1.is_zero();
+    // 6.   x1 = x1 + 1
+    x1 = x1 + f.one();
+    // 7.   x1 = CMOV(x1, c2, e1)    # If (tv1 + tv2) == 0, set x1 = -1 / Z
+    x1 = cmov(&x1, &c2, e1);
+    // 8.   x1 = x1 * c1      # x1 = (-B / A) * (1 + (1 / (Z^2 * u^4 + Z * u^2)))
+    x1 = x1 * c1;
+    // 9.  gx1 = x1^2
+    let mut gx1 = x1.square();
+    // 10. gx1 = gx1 + A
+    gx1 = gx1 + a;
+    // 11. gx1 = gx1 * x1
+    gx1 = gx1 * &x1;
+    // 12. gx1 = gx1 + B             # gx1 = g(x1) = x1^3 + A * x1 + B
+    gx1 = gx1 + b;
+    // 13.  x2 = tv1 * x1            # x2 = Z * u^2 * x1
+    let x2 = &tv1 * &x1;
+    // 14. tv2 = tv1 * tv2
+    tv2 = tv1 * tv2;
+    // 15. gx2 = gx1 * tv2           # gx2 = (Z * u^2)^3 * gx1
+    let gx2 = &gx1 * tv2;
+    // 16.  e2 = is_square(gx1)
+    let e2 = gx1.is_square();
+    // 17.   x = CMOV(x2, x1, e2)    # If is_square(gx1), x = x1, else x = x2
+    let x = cmov(&x2, &x1, e2);
+    // 18.  y2 = CMOV(gx2, gx1, e2)  # If is_square(gx1), y2 = gx1, else y2 = gx2
+    let y2 = cmov(&gx2, &gx1, e2);
+    // 19.   y = sqrt(y2)
+    let mut y = y2.sqrt();
+    // 20.  e3 = sgn0(u) == sgn0(y)  # Fix sign of y
+    let e3 = u.sgn0() == y.sgn0();
+    // 21.   y = CMOV(-y, y, e3)
+    y = cmov(&-&y, &y, e3);
+    // 22. return (x, y)
+    (x.to_bytes(), y.to_bytes())
+}
@@ -10,6 +10,8 @@
 use crate::errors::InternalError;
 use crate::group::Group;
 use crate::CipherSuite;
+use generic_array::GenericArray;
+use rand::rngs::OsRng;
 
 // Test that the deserialization of a group element should throw an error
 // if the identity element can be deserialized properly
@@ -20,6 +22,44 @@ fn test_group_properties() -> Result<(), InternalError> {
 
     test_identity_element_error::<Ristretto255Sha512>()?;
     test_zero_scalar_error::<Ristretto255Sha512>()?;
+    test_encode_to_curve::<Ristretto255Sha512>()?;
+    test_vartime_multiscalar_mul::<Ristretto255Sha512>()?;
+    test_invert_scalars_batch::<Ristretto255Sha512>();
+    test_non_canonical_scalar_error::<Ristretto255Sha512>(&[
+        // The group order
+        "edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010",
+        // The group order + 1
+        "eed3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010",
+        "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+    ])?;
+    test_non_canonical_element_error::<Ristretto255Sha512>(&[
+        // The field modulus
+        "edffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff7f",
+        // A negative field element
+        "0100000000000000000000000000000000000000000000000000000000000000",
+        "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+    ])?;
+
+    #[cfg(feature = "mock")]
+    {
+        use crate::tests::MockSha256;
+
+        test_identity_element_error::<MockSha256>()?;
+        test_zero_scalar_error::<MockSha256>()?;
+        test_encode_to_curve::<MockSha256>()?;
+        test_vartime_multiscalar_mul::<MockSha256>()?;
+        test_invert_scalars_batch::<MockSha256>();
+        test_non_canonical_scalar_error::<MockSha256>(&[
+            // The group order
+            "1fffffffffffffff",
+            "ffffffffffffffff",
+        ])?;
+        test_non_canonical_element_error::<MockSha256>(&[
+            // The group order
+            "1fffffffffffffff",
+            "ffffffffffffffff",
+        ])?;
+    }
 
     #[cfg(feature = "p256")]
     {
@@ -27,6 +67,24 @@ fn test_group_properties() -> Result<(), InternalError> {
 
         test_identity_element_error::<P256Sha256>()?;
         test_zero_scalar_error::<P256Sha256>()?;
+        test_encode_to_curve::<P256Sha256>()?;
+        test_vartime_multiscalar_mul::<P256Sha256>()?;
+        test_invert_scalars_batch::<P256Sha256>();
+        test_non_canonical_scalar_error::<P256Sha256>(&[
+            // The group order
+            "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551",
+            // The group order + 1
+            "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632552",
+            "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        ])?;
+        test_non_canonical_element_error::<P256Sha256>(&[
+            // The field modulus as the x-coordinate
+            "02ffffffff00000001000000000000000000000000ffffffffffffffffffffffff",
+            // An invalid tag
+            "046b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296",
+            // The identity encoding, padded
+            "000000000000000000000000000000000000000000000000000000000000000000",
+        ])?;
     }
 
     Ok(())
@@ -36,22 +94,101 @@ fn test_group_properties() -> Result<(), InternalError> {
 fn test_identity_element_error<CS: CipherSuite>() -> Result<(), InternalError> {
     let identity = CS::Group::identity();
     let result = CS::Group::from_element_slice(&identity.to_arr());
-    assert!(match result {
-        Err(InternalError::PointError) => true,
-        _ => false,
-    });
+    assert!(matches!(result, Err(InternalError::IdentityElementError)));
 
     Ok(())
 }
 
+// Checks that the nonuniform encoding is deterministic and separated by the DST
+fn test_encode_to_curve<CS: CipherSuite>() -> Result<(), InternalError> {
+    let point = CS::Group::encode_to_curve::<CS>(&[b"input"], &[b"DST"])?;
+    assert!(point.ct_equal(&CS::Group::encode_to_curve::<CS>(
+        &[b"in", b"put"],
+        &[b"D", b"ST"]
+    )?));
+    assert!(!point.ct_equal(&CS::Group::encode_to_curve::<CS>(
+        &[b"input"],
+        &[b"other DST"]
+    )?));
+    assert!(!point.is_identity());
+
+    Ok(())
+}
+
+// Checks that the multiscalar multiplication matches the multiplications on
+// their own
+fn test_vartime_multiscalar_mul<CS: CipherSuite>() -> Result<(), InternalError> {
+    let mut rng = OsRng;
+    let scalars = [
+        CS::Group::random_nonzero_scalar(&mut rng),
+        CS::Group::random_nonzero_scalar(&mut rng),
+        CS::Group::random_nonzero_scalar(&mut rng),
+    ];
+    let elements = [
+        CS::Group::base_point(),
+        CS::Group::hash_to_curve::<CS>(&[b"element"], &[b"DST"])?,
+        CS::Group::base_point() * &scalars[0],
+    ];
+    let expected = (elements[0] * &scalars[0]) + &(elements[1] * &scalars[1]);
+    assert!(CS::Group::vartime_multiscalar_mul(&scalars[..2], &elements[..2]).ct_equal(&expected));
+    let expected_swapped = (elements[1] * &scalars[0]) + &(elements[0] * &scalars[1]);
+    let swapped = [elements[1], elements[0]];
+    assert!(CS::Group::vartime_multiscalar_mul(&scalars[..2], &swapped).ct_equal(&expected_swapped));
+    let expected = expected + &(elements[2] * &scalars[2]);
+    assert!(CS::Group::vartime_multiscalar_mul(&scalars, &elements).ct_equal(&expected));
+    assert!(CS::Group::vartime_multiscalar_mul(&[], &[]).is_identity());
+
+    Ok(())
+}
+
+// Checks that inverting a batch matches inverting each scalar on its own
+fn test_invert_scalars_batch<CS: CipherSuite>() {
+    let mut rng = OsRng;
+    for len in 0..4 {
+        let scalars: Vec<_> = (0..len)
+            .map(|_| CS::Group::random_nonzero_scalar(&mut rng))
+            .collect();
+        let mut inverted = scalars.clone();
+        CS::Group::invert_scalars_batch(&mut inverted);
+        for (scalar, inverted) in scalars.iter().zip(&inverted) {
+            assert!(CS::Group::ct_equal_scalar(
+                inverted,
+                &CS::Group::scalar_invert(scalar)
+            ));
+        }
+    }
+}
+
 // Checks that the zero scalar cannot be deserialized
 fn test_zero_scalar_error<CS: CipherSuite>() -> Result<(), InternalError> {
     let zero_scalar = CS::Group::scalar_zero();
     let result = CS::Group::from_scalar_slice(&CS::Group::scalar_as_bytes(zero_scalar));
-    assert!(match result {
-        Err(InternalError::ZeroScalarError) => true,
-        _ => false,
-    });
+    assert!(matches!(result, Err(InternalError::ZeroScalarError)));
+
+    Ok(())
+}
+
+// Checks that scalars which are not canonically encoded cannot be deserialized
+fn test_non_canonical_scalar_error<CS: CipherSuite>(vectors: &[&str]) -> Result<(), InternalError> {
+    for vector in vectors {
+        let bytes = hex::decode(vector).unwrap();
+        let result = CS::Group::from_scalar_slice(GenericArray::from_slice(&bytes));
+        assert!(matches!(result, Err(InternalError::InvalidByteSequence)));
+    }
+
+    Ok(())
+}
+
+// Checks that elements which are not canonically encoded cannot be
+// deserialized
+fn test_non_canonical_element_error<CS: CipherSuite>(
+    vectors: &[&str],
+) -> Result<(), InternalError> {
+    for vector in vectors {
+        let bytes = hex::decode(vector).unwrap();
+        let result = CS::Group::from_element_slice(GenericArray::from_slice(&bytes));
+        assert!(matches!(result, Err(InternalError::PointError)));
+    }
 
     Ok(())
 }
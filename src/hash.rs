@@ -10,6 +10,11 @@
 use digest::{BlockInput, FixedOutput, Reset, Update};
 
 /// Trait inheriting the requirements from digest::Digest for compatibility with HKDF and HMAC
+///
+/// Any fixed-output hash function implementing the `digest` traits can be
+/// used, e.g. SHA-2, SHA-3 or BLAKE2. The block size is only used by
+/// [expand_message_xmd](crate::group::expand_message_xmd), which suites can
+/// replace through [CipherSuite::expand_message](crate::CipherSuite::expand_message).
 // Associated types could be simplified when they are made as defaults:
 // https://github.com/rust-lang/rust/issues/29661
 pub trait Hash: Update + BlockInput + FixedOutput + Reset + Default + Clone {}
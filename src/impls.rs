@@ -5,19 +5,70 @@
 // License, Version 2.0 found in the LICENSE-APACHE file in the root directory
 // of this source tree.
 
-macro_rules! impl_debug_eq_hash_for {
+// Records an intermediate value in the trace captured by `danger::capture`.
+// The value is only computed while a trace is captured, and the macro compiles
+// to nothing without the `danger` feature.
+macro_rules! trace {
+    ($label:expr, $value:expr) => {
+        #[cfg(feature = "danger")]
+        crate::danger::record($label, || $value);
+    };
+}
+
+macro_rules! impl_debug_hash_for {
     (struct $name:ident$(<$($gen:ident$(: $bound:tt)?),+$(,)?>)?, [$field1:ident$(, $field2:ident)*$(,)?]$(, )?$([$($type:ty),+$(,)?]$(,)?)?) => {
         impl$(<$($gen$(: $bound)?),+>)? core::fmt::Debug for $name$(<$($gen),+>)?
         $(where $($type: core::fmt::Debug,)+)?
         {
             fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-                f.debug_struct("$name")
+                f.debug_struct(stringify!($name))
                 .field("$field1", &self.$field1)
                 $(.field("$field2", &self.$field2))*
                 .finish()
             }
         }
 
+        impl$(<$($gen$(: $bound)?),+>)? core::hash::Hash for $name$(<$($gen),+>)?
+        $(where $($type: core::hash::Hash,)+)?
+        {
+            fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+                core::hash::Hash::hash(&self.$field1, state);
+                $(core::hash::Hash::hash(&self.$field2, state);)*
+            }
+        }
+    };
+}
+
+// Like `impl_debug_hash_for`, but the fields listed after `redacted` hold
+// secrets, which are printed through `secret::Redacted`
+macro_rules! impl_redacted_debug_hash_for {
+    (struct $name:ident$(<$($gen:ident$(: $bound:tt)?),+$(,)?>)?, [$($field:ident),*$(,)?], redacted [$($secret:ident),+$(,)?]$(, )?$([$($type:ty),+$(,)?]$(,)?)?) => {
+        impl$(<$($gen$(: $bound)?),+>)? core::fmt::Debug for $name$(<$($gen),+>)?
+        $(where $($type: core::fmt::Debug,)+)?
+        {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.debug_struct(stringify!($name))
+                $(.field(stringify!($field), &self.$field))*
+                $(.field(stringify!($secret), &crate::secret::Redacted(&self.$secret)))+
+                .finish()
+            }
+        }
+
+        impl$(<$($gen$(: $bound)?),+>)? core::hash::Hash for $name$(<$($gen),+>)?
+        $(where $($type: core::hash::Hash,)+)?
+        {
+            fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+                $(core::hash::Hash::hash(&self.$field, state);)*
+                $(core::hash::Hash::hash(&self.$secret, state);)+
+            }
+        }
+    };
+}
+
+macro_rules! impl_debug_eq_hash_for {
+    (struct $name:ident$(<$($gen:ident$(: $bound:tt)?),+$(,)?>)?, [$field1:ident$(, $field2:ident)*$(,)?]$(, )?$([$($type:ty),+$(,)?]$(,)?)?) => {
+        impl_debug_hash_for!(struct $name$(<$($gen$(: $bound)?),+>)?, [$field1$(, $field2)*], $([$($type),+])?);
+
         impl$(<$($gen$(: $bound)?),+>)? Eq for $name$(<$($gen),+>)?
         $(where $($type: Eq,)+)?
         {}
@@ -30,22 +81,13 @@ macro_rules! impl_debug_eq_hash_for {
                 $(&& PartialEq::eq(&self.$field2, &other.$field2))*
             }
         }
-
-        impl$(<$($gen$(: $bound)?),+>)? core::hash::Hash for $name$(<$($gen),+>)?
-        $(where $($type: core::hash::Hash,)+)?
-        {
-            fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
-                core::hash::Hash::hash(&self.$field1, state);
-                $(core::hash::Hash::hash(&self.$field2, state);)*
-            }
-        }
     };
     (tuple $name:ident$(<$($gen:ident$(: $bound:tt)?),+$(,)?>)?, [$field1:tt$(, $field2:tt)*$(,)?]$(, )?$([$($type:ty),+$(,)?]$(,)?)?) => {
         impl$(<$($gen$(: $bound)?),+>)? core::fmt::Debug for $name$(<$($gen),+>)?
         $(where $($type: core::fmt::Debug,)+)?
         {
             fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-                f.debug_tuple("$name")
+                f.debug_tuple(stringify!($name))
                 .field(&self.$field1)
                 $(.field(&self.$field2))*
                 .finish()
@@ -149,9 +191,40 @@ macro_rules! impl_serialize_and_deserialize_for {
                 D: serde::Deserializer<'de>,
             {
                 if deserializer.is_human_readable() {
-                    let s = <&str>::deserialize(deserializer)?;
-                    $t::<CS>::deserialize(&base64::decode(s).map_err(serde::de::Error::custom)?)
-                        .map_err(serde::de::Error::custom)
+                    // Visits strings instead of deserializing a `&str`, which
+                    // also accepts strings the deserializer can't borrow from
+                    // its input, e.g. from a `serde_json::Value`
+                    struct StrVisitor<CS: CipherSuite> {
+                        marker: core::marker::PhantomData<CS>,
+                    }
+                    impl<'de, CS: CipherSuite> serde::de::Visitor<'de> for StrVisitor<CS> {
+                        type Value = $t<CS>;
+                        fn expecting(
+                            &self,
+                            formatter: &mut core::fmt::Formatter,
+                        ) -> core::fmt::Result {
+                            formatter.write_str(core::concat!(
+                                "the base64 encoding of a ",
+                                core::stringify!($t)
+                            ))
+                        }
+
+                        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+                        where
+                            E: serde::de::Error,
+                        {
+                            let bytes = base64::decode(value).map_err(|_| {
+                                serde::de::Error::invalid_value(
+                                    serde::de::Unexpected::Str(value),
+                                    &self,
+                                )
+                            })?;
+                            $t::<CS>::deserialize(&bytes).map_err(serde::de::Error::custom)
+                        }
+                    }
+                    deserializer.deserialize_str(StrVisitor::<CS> {
+                        marker: core::marker::PhantomData,
+                    })
                 } else {
                     struct ByteVisitor<CS: CipherSuite> {
                         marker: core::marker::PhantomData<CS>,
@@ -199,5 +272,22 @@ macro_rules! impl_traits_for {
         impl_clone_for!(struct $name$(<$($gen$(: $bound)?),+>)?, [$field1$(, $field2)*], $([$($type),+])?);
         impl_zeroize_on_drop_for!(struct $name$(<$($gen$(: $bound)?),+>)?, [$field1$(, $field2)*], $([$($type),+])?);
         impl_serialize_and_deserialize_for!($name);
-    }
+    };
+}
+
+// Like `impl_traits_for`, but without `PartialEq` and `Eq`, which types holding
+// secrets implement by hand to compare in constant time
+macro_rules! impl_secret_traits_for {
+    (struct $name:ident$(<$($gen:ident$(: $bound:tt)?),+$(,)?>)?, [$field1:ident$(, $field2:ident)*$(,)?]$(, )?$([$($type:ty),+$(,)?]$(,)?)?) => {
+        impl_debug_hash_for!(struct $name$(<$($gen$(: $bound)?),+>)?, [$field1$(, $field2)*], $([$($type),+])?);
+        impl_clone_for!(struct $name$(<$($gen$(: $bound)?),+>)?, [$field1$(, $field2)*], $([$($type),+])?);
+        impl_zeroize_on_drop_for!(struct $name$(<$($gen$(: $bound)?),+>)?, [$field1$(, $field2)*], $([$($type),+])?);
+        impl_serialize_and_deserialize_for!($name);
+    };
+    (struct $name:ident$(<$($gen:ident$(: $bound:tt)?),+$(,)?>)?, [$($field:ident),*$(,)?], redacted [$($secret:ident),+$(,)?]$(, )?$([$($type:ty),+$(,)?]$(,)?)?) => {
+        impl_redacted_debug_hash_for!(struct $name$(<$($gen$(: $bound)?),+>)?, [$($field),*], redacted [$($secret),+], $([$($type),+])?);
+        impl_clone_for!(struct $name$(<$($gen$(: $bound)?),+>)?, [$($secret,)+ $($field),*], $([$($type),+])?);
+        impl_zeroize_on_drop_for!(struct $name$(<$($gen$(: $bound)?),+>)?, [$($secret,)+ $($field),*], $([$($type),+])?);
+        impl_serialize_and_deserialize_for!($name);
+    };
 }
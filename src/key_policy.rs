@@ -0,0 +1,155 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Policies deciding which server public keys a client trusts
+
+use crate::{ciphersuite::CipherSuite, group::Group};
+use alloc::vec::Vec;
+use digest::Digest;
+use generic_array::GenericArray;
+use subtle::ConstantTimeEq;
+
+/// Decides whether a server public key is trusted, consulted by
+/// [VerifiableClient::finalize_with_key_policy_once](crate::VerifiableClient::finalize_with_key_policy_once)
+/// and
+/// [VerifiableClient::batch_finalize_with_key_policy](crate::VerifiableClient::batch_finalize_with_key_policy)
+/// before the proof is verified against the key.
+///
+/// Implemented by [PinnedKeys], [KeyFingerprints], and by closures taking
+/// the public key, so applications can implement their own pinning:
+///
+/// ```
+/// # use voprf::CipherSuite;
+/// # struct Default;
+/// # impl CipherSuite for Default {
+/// #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+/// #     type Hash = sha2::Sha512;
+/// # }
+/// use voprf::group::Group;
+/// use voprf::KeyPolicy;
+///
+/// let policy = |pk: &<Default as CipherSuite>::Group| !pk.is_identity();
+/// assert!(KeyPolicy::<Default>::is_trusted(
+///     &policy,
+///     &<Default as CipherSuite>::Group::base_point()
+/// ));
+/// ```
+pub trait KeyPolicy<CS: CipherSuite> {
+    /// Returns `true` if the client accepts proofs made with `pk`
+    fn is_trusted(&self, pk: &CS::Group) -> bool;
+}
+
+impl<CS: CipherSuite, F: Fn(&CS::Group) -> bool> KeyPolicy<CS> for F {
+    fn is_trusted(&self, pk: &CS::Group) -> bool {
+        self(pk)
+    }
+}
+
+/// A [KeyPolicy] trusting a fixed set of public keys
+pub struct PinnedKeys<CS: CipherSuite>(Vec<CS::Group>);
+
+impl<CS: CipherSuite> PinnedKeys<CS> {
+    /// Trusts exactly the given public keys
+    pub fn new(keys: Vec<CS::Group>) -> Self {
+        Self(keys)
+    }
+}
+
+impl<CS: CipherSuite> KeyPolicy<CS> for PinnedKeys<CS> {
+    fn is_trusted(&self, pk: &CS::Group) -> bool {
+        self.0
+            .iter()
+            .fold(false, |trusted, key| trusted | key.ct_equal(pk))
+    }
+}
+
+/// A [KeyPolicy] trusting the public keys whose fingerprint is in an
+/// allowlist. The fingerprint of a key is the hash of its serialization with
+/// the hash function of the ciphersuite, see [KeyFingerprints::fingerprint],
+/// so the allowlist can be distributed without the keys themselves.
+pub struct KeyFingerprints<CS: CipherSuite>(
+    Vec<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>,
+);
+
+impl<CS: CipherSuite> KeyFingerprints<CS> {
+    /// Trusts the public keys with the given fingerprints
+    pub fn new(fingerprints: Vec<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>) -> Self {
+        Self(fingerprints)
+    }
+
+    /// Computes the fingerprint of a public key
+    pub fn fingerprint(pk: &CS::Group) -> GenericArray<u8, <CS::Hash as Digest>::OutputSize> {
+        CS::Hash::digest(&pk.to_arr())
+    }
+}
+
+impl<CS: CipherSuite> KeyPolicy<CS> for KeyFingerprints<CS> {
+    fn is_trusted(&self, pk: &CS::Group) -> bool {
+        let fingerprint = Self::fingerprint(pk);
+
+        self.0.iter().fold(false, |trusted, allowed| {
+            trusted | bool::from(allowed.as_slice().ct_eq(fingerprint.as_slice()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        errors::InternalError, tests::Ristretto255Sha512, Metadata, VerifiableClient,
+        VerifiableServer,
+    };
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_key_policy() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng)?;
+        let other_server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng)?;
+        let pk = server.get_public_key();
+        let other_pk = other_server.get_public_key();
+
+        let pinned = PinnedKeys::<Ristretto255Sha512>::new(alloc::vec![other_pk, pk]);
+        assert!(pinned.is_trusted(&pk));
+        assert!(!PinnedKeys::<Ristretto255Sha512>::new(alloc::vec![other_pk]).is_trusted(&pk));
+
+        let fingerprint = KeyFingerprints::<Ristretto255Sha512>::fingerprint(&pk);
+        let fingerprints = KeyFingerprints::<Ristretto255Sha512>::new(alloc::vec![fingerprint]);
+        assert!(fingerprints.is_trusted(&pk));
+        assert!(!fingerprints.is_trusted(&other_pk));
+
+        let client_blind_result =
+            VerifiableClient::<Ristretto255Sha512>::blind(b"input", &mut rng)?;
+        let server_result =
+            server.evaluate(&mut rng, client_blind_result.message, &Metadata::none())?;
+        assert!(matches!(
+            client_blind_result
+                .state
+                .clone()
+                .finalize_with_key_policy_once(
+                    b"input",
+                    server_result.message.clone(),
+                    server_result.proof.clone(),
+                    pk,
+                    &Metadata::none(),
+                    &|key: &_| other_pk.ct_equal(key),
+                ),
+            Err(InternalError::UntrustedPublicKey)
+        ));
+        let _client_finalize_result = client_blind_result.state.finalize_with_key_policy_once(
+            b"input",
+            server_result.message,
+            server_result.proof,
+            pk,
+            &Metadata::none(),
+            &pinned,
+        )?;
+
+        Ok(())
+    }
+}
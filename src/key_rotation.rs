@@ -0,0 +1,123 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Rotation of server keys with update tokens
+
+use crate::{
+    ciphersuite::CipherSuite,
+    errors::InternalError,
+    group::Group,
+    modes::ProtocolMode,
+    secret::Secret,
+    voprf::{compute_metadata_scalar, EvaluationElement, Metadata, Server},
+};
+use zeroize::Zeroizing;
+
+/// An update token from an old to a new server key, which moves
+/// [EvaluationElement]s computed with the old key to the new key, without
+/// running the protocol again. A client which stored its state together with
+/// the [EvaluationElement] finalizes the updated element to the output under
+/// the new key.
+///
+/// Both keys are tweaked by the [Metadata], so a token only updates elements
+/// evaluated with the metadata it was created for. An element updated to the
+/// new key is finalized like an element evaluated with it, but its proof
+/// isn't updated, so only a [NonVerifiableClient](crate::NonVerifiableClient)
+/// can finalize it.
+///
+/// The token is derived from both private keys: anyone who holds the token and
+/// one of the keys can compute the other one.
+pub struct KeyRotation<CS: CipherSuite> {
+    pub(crate) delta: Secret<<CS::Group as Group>::Scalar>,
+}
+impl_secret_traits_for!(
+    struct KeyRotation<CS: CipherSuite>,
+    [delta],
+    [<CS::Group as Group>::Scalar],
+);
+
+impl<CS: CipherSuite> PartialEq for KeyRotation<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.delta, &other.delta)
+    }
+}
+
+impl<CS: CipherSuite> Eq for KeyRotation<CS> {}
+
+impl<CS: CipherSuite> KeyRotation<CS> {
+    /// Creates the update token from the key of `old` to the key of `new`
+    /// for elements evaluated with `metadata`
+    pub fn new<M: ProtocolMode<CS>>(
+        old: &Server<CS, M>,
+        new: &Server<CS, M>,
+        metadata: &Metadata,
+    ) -> Result<Self, InternalError> {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(&metadata.0, M::MODE)?);
+        let old_t = Zeroizing::new(*old.sk + &m);
+        let new_t = Zeroizing::new(*new.sk + &m);
+        // A key tweaked to zero can't be inverted
+        if CS::Group::ct_equal_scalar(&new_t, &CS::Group::scalar_zero()) {
+            return Err(InternalError::ZeroScalarError);
+        }
+
+        let delta = Zeroizing::new(*old_t * &Zeroizing::new(CS::Group::scalar_invert(&new_t)));
+        Ok(Self {
+            delta: Secret::new(*delta)?,
+        })
+    }
+
+    /// Moves an [EvaluationElement] evaluated with the old key to the new key
+    pub fn update_evaluation_element(
+        &self,
+        evaluation_element: &EvaluationElement<CS>,
+    ) -> EvaluationElement<CS> {
+        EvaluationElement {
+            value: evaluation_element.value * &self.delta,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::Ristretto255Sha512, NonVerifiableClient, NonVerifiableServer};
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_key_rotation() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let input = b"input";
+        let metadata = Metadata(b"info".to_vec());
+        let old = NonVerifiableServer::<Ristretto255Sha512>::new(&mut rng)?;
+        let new = NonVerifiableServer::<Ristretto255Sha512>::new(&mut rng)?;
+
+        let client_blind_result =
+            NonVerifiableClient::<Ristretto255Sha512>::blind(input, &mut rng)?;
+        let server_result = old.evaluate(client_blind_result.message, &metadata)?;
+
+        let rotation = KeyRotation::new(&old, &new, &metadata)?;
+        let rotation = KeyRotation::deserialize(&rotation.serialize())?;
+        let evaluation_element = rotation.update_evaluation_element(&server_result.message);
+        let output = client_blind_result
+            .state
+            .clone()
+            .finalize_once(input, evaluation_element, &metadata)?
+            .output;
+        assert_eq!(*output, new.full_evaluate(input, &metadata)?);
+
+        // The token only updates elements evaluated with its metadata
+        let rotation = KeyRotation::new(&old, &new, &Metadata::none())?;
+        let evaluation_element = rotation.update_evaluation_element(&server_result.message);
+        let output = client_blind_result
+            .state
+            .finalize_once(input, evaluation_element, &metadata)?
+            .output;
+        assert_ne!(*output, new.full_evaluate(input, &metadata)?);
+
+        Ok(())
+    }
+}
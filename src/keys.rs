@@ -0,0 +1,105 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Private and public keys of servers
+
+use crate::{ciphersuite::CipherSuite, errors::InternalError, group::Group, secret::Secret};
+use rand::{CryptoRng, RngCore};
+
+/// The private key of a server, which is never zero. It is compared in
+/// constant time and zeroized on drop.
+pub struct PrivateKey<CS: CipherSuite> {
+    pub(crate) value: Secret<<CS::Group as Group>::Scalar>,
+}
+impl_secret_traits_for!(
+    struct PrivateKey<CS: CipherSuite>,
+    [value],
+    [<CS::Group as Group>::Scalar],
+);
+
+impl<CS: CipherSuite> PartialEq for PrivateKey<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.value, &other.value)
+    }
+}
+
+impl<CS: CipherSuite> Eq for PrivateKey<CS> {}
+
+/// The public key of a server, which is never the identity element
+pub struct PublicKey<CS: CipherSuite> {
+    pub(crate) value: CS::Group,
+}
+impl_traits_for!(
+    struct PublicKey<CS: CipherSuite>,
+    [value],
+    [CS::Group],
+);
+
+impl<CS: CipherSuite> PrivateKey<CS> {
+    /// Generates a random private key
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self, InternalError> {
+        Self::from_scalar(CS::Group::random_nonzero_scalar(rng))
+    }
+
+    pub(crate) fn from_scalar(sk: <CS::Group as Group>::Scalar) -> Result<Self, InternalError> {
+        Ok(Self {
+            value: Secret::new(sk)?,
+        })
+    }
+
+    /// Computes the public key of this private key
+    pub fn public_key(&self) -> PublicKey<CS> {
+        PublicKey {
+            value: CS::Group::base_point() * &self.value,
+        }
+    }
+}
+
+impl<CS: CipherSuite> PublicKey<CS> {
+    /// The group element of the public key, which clients verify proofs
+    /// against
+    pub fn to_element(&self) -> CS::Group {
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        rfc9497::VoprfServer, tests::Ristretto255Sha512, NonVerifiableServer, VerifiableServer,
+    };
+    use rand::rngs::OsRng;
+
+    type CS = Ristretto255Sha512;
+    type G = <CS as CipherSuite>::Group;
+
+    #[test]
+    fn test_keys() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let sk = PrivateKey::<CS>::new(&mut rng)?;
+        let sk = PrivateKey::<CS>::deserialize(&sk.serialize())?;
+        let pk = PublicKey::<CS>::deserialize(&sk.public_key().serialize())?;
+        assert_eq!(pk, sk.public_key());
+
+        let server = VerifiableServer::<CS>::new_with_private_key(&sk)?;
+        assert_eq!(server.public_key(), pk);
+        assert!(server.get_public_key().ct_equal(&pk.to_element()));
+        let server = NonVerifiableServer::<CS>::new_with_private_key(&sk)?;
+        assert_eq!(server.serialize(), sk.serialize());
+        let server = VoprfServer::<CS>::new_with_private_key(&sk)?;
+        assert_eq!(server.public_key(), pk);
+
+        // Zero private keys and identity public keys are rejected
+        let zero = G::scalar_as_bytes(G::scalar_zero());
+        assert!(PrivateKey::<CS>::deserialize(&zero).is_err());
+        let identity = G::identity().to_arr();
+        assert!(PublicKey::<CS>::deserialize(&identity).is_err());
+
+        Ok(())
+    }
+}
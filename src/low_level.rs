@@ -0,0 +1,209 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! ⚠️ The building blocks of the protocol, to prototype variants of it
+//! without forking this crate
+//!
+//! These functions work on bare scalars and group elements, and leave
+//! everything else to the caller: they don't check which values may be
+//! combined, don't zeroize blinds and never hold a private key. Applications
+//! should use the [Client](crate::Client) and [Server](crate::Server) types
+//! instead, which compose these building blocks safely.
+//!
+//! Proofs are discrete log equivalence proofs for a batch: given `b = k·a`,
+//! they prove that `ds[i] = k·cs[i]` for every `i`, without revealing `k`. In
+//! the protocol, `a` is the base point, `k` the private key tweaked by the
+//! metadata, `cs` the evaluation elements and `ds` the blinded elements.
+
+use crate::{
+    ciphersuite::CipherSuite,
+    errors::InternalError,
+    group::Group,
+    voprf::{self, Mode, Proof},
+};
+use alloc::vec::Vec;
+use digest::Digest;
+use generic_array::GenericArray;
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
+
+/// Blinds `input` with a random blind, returning the blind and the blinded
+/// element. Fails if the input is longer than allowed by the ciphersuite.
+pub fn blind<CS: CipherSuite, R: RngCore + CryptoRng>(
+    input: &[u8],
+    blinding_factor_rng: &mut R,
+    mode: Mode,
+) -> Result<(<CS::Group as Group>::Scalar, CS::Group), InternalError> {
+    voprf::blind::<CS, R>(&[input], blinding_factor_rng, mode)
+}
+
+/// Blinds `input` with the given blind, without checking the input length
+pub fn deterministic_blind_unchecked<CS: CipherSuite>(
+    input: &[u8],
+    blind: &<CS::Group as Group>::Scalar,
+    mode: Mode,
+) -> Result<CS::Group, InternalError> {
+    voprf::deterministic_blind_unchecked::<CS>(&[input], blind, mode)
+}
+
+/// Like [deterministic_blind_unchecked], for an input made of several parts,
+/// which are hashed as if they were concatenated
+pub fn deterministic_blind_unchecked_parts<CS: CipherSuite>(
+    input: &[&[u8]],
+    blind: &<CS::Group as Group>::Scalar,
+    mode: Mode,
+) -> Result<CS::Group, InternalError> {
+    voprf::deterministic_blind_unchecked::<CS>(input, blind, mode)
+}
+
+/// Verifies the proof over a batch of `blinded_elements` and the
+/// `evaluation_elements` returned for them by the holder of `pk`, with the
+/// key tweaked by `info`, then unblinds the evaluation elements with their
+/// `blinds`
+pub fn verifiable_unblind<CS: CipherSuite>(
+    blinds: &[<CS::Group as Group>::Scalar],
+    blinded_elements: &[CS::Group],
+    evaluation_elements: &[CS::Group],
+    pk: CS::Group,
+    proof: &Proof<CS>,
+    info: &[u8],
+    mode: Mode,
+) -> Result<Vec<CS::Group>, InternalError> {
+    voprf::check_batch_lengths(blinds.len(), evaluation_elements.len())?;
+
+    let _ = voprf::verify_batch_elements::<CS>(
+        blinded_elements.iter().copied(),
+        evaluation_elements.iter().copied(),
+        pk,
+        proof.clone(),
+        info,
+        mode,
+    )?;
+
+    let mut blinds_inverted = Zeroizing::new(blinds.to_vec());
+    CS::Group::invert_scalars_batch(&mut blinds_inverted);
+
+    Ok(blinds_inverted
+        .iter()
+        .zip(evaluation_elements)
+        .map(|(blind_inverted, element)| *element * blind_inverted)
+        .collect())
+}
+
+/// Proves that `b = k·a` and `ds[i] = k·cs[i]` for every `i`, with a nonce
+/// drawn from `rng`
+#[allow(clippy::too_many_arguments)]
+pub fn generate_proof<CS: CipherSuite, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    k: &<CS::Group as Group>::Scalar,
+    a: CS::Group,
+    b: CS::Group,
+    cs: &[CS::Group],
+    ds: &[CS::Group],
+    mode: Mode,
+) -> Result<Proof<CS>, InternalError> {
+    voprf::generate_proof(
+        voprf::random_proof_nonce::<CS, R>(rng),
+        k,
+        a,
+        b,
+        cs.iter().copied(),
+        ds.iter().copied(),
+        mode,
+    )
+}
+
+/// Verifies a proof produced by [generate_proof], failing with
+/// [InternalError::ProofVerificationError] if it doesn't verify
+pub fn verify_proof<CS: CipherSuite>(
+    a: CS::Group,
+    b: CS::Group,
+    cs: &[CS::Group],
+    ds: &[CS::Group],
+    proof: &Proof<CS>,
+    mode: Mode,
+) -> Result<(), InternalError> {
+    voprf::verify_proof(
+        a,
+        b,
+        cs.iter().copied(),
+        ds.iter().copied(),
+        proof.clone(),
+        mode,
+    )
+    .map(|_| ())
+}
+
+/// Computes the output of the protocol from the input and its unblinded
+/// element
+pub fn finalize_after_unblind<CS: CipherSuite>(
+    input: &[u8],
+    unblinded_element: &CS::Group,
+    info: &[u8],
+    mode: Mode,
+) -> Result<GenericArray<u8, <CS::Hash as Digest>::OutputSize>, InternalError> {
+    voprf::finalize_after_unblind::<CS>(input, unblinded_element, info, mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::Ristretto255Sha512, Metadata, VerifiableServer};
+    use rand::rngs::OsRng;
+
+    type CS = Ristretto255Sha512;
+    type G = <CS as CipherSuite>::Group;
+
+    #[test]
+    fn test_low_level() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let input = b"input";
+        let server = VerifiableServer::<CS>::new(&mut rng)?;
+
+        // Blinding and finalizing with the building blocks gives the output of
+        // the server
+        let (blind, blinded_element) = blind::<CS, _>(input, &mut rng, Mode::Verifiable)?;
+        assert!(
+            deterministic_blind_unchecked::<CS>(input, &blind, Mode::Verifiable)?
+                .ct_equal(&blinded_element)
+        );
+        let result = server.evaluate(
+            &mut rng,
+            crate::BlindedElement {
+                value: blinded_element,
+            },
+            &Metadata::none(),
+        )?;
+        let unblinded_elements = verifiable_unblind::<CS>(
+            &[blind],
+            &[blinded_element],
+            &[result.message.value],
+            server.get_public_key(),
+            &result.proof,
+            b"",
+            Mode::Verifiable,
+        )?;
+        let output =
+            finalize_after_unblind::<CS>(input, &unblinded_elements[0], b"", Mode::Verifiable)?;
+        assert_eq!(output, server.full_evaluate(input, &Metadata::none())?);
+
+        // Proofs of a custom relation
+        let k = G::random_nonzero_scalar(&mut rng);
+        let a = voprf::hash_to_group::<CS>(b"a", Mode::Base)?;
+        let cs = [voprf::hash_to_group::<CS>(b"c", Mode::Base)?];
+        let ds = [cs[0] * k];
+        let b = a * k;
+        let proof = generate_proof::<CS, _>(&mut rng, &k, a, b, &cs, &ds, Mode::Base)?;
+        verify_proof::<CS>(a, b, &cs, &ds, &proof, Mode::Base)?;
+        assert!(matches!(
+            verify_proof::<CS>(a, b, &ds, &cs, &proof, Mode::Base),
+            Err(InternalError::ProofVerificationError)
+        ));
+
+        Ok(())
+    }
+}
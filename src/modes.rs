@@ -0,0 +1,97 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! The modes of the protocol as types, which select the mode of a
+//! [Client](crate::Client) and a [Server](crate::Server) at compile time
+
+use crate::{ciphersuite::CipherSuite, group::Group, voprf::Mode};
+
+/// A mode of the protocol on the type level, implemented by [Base],
+/// [Verifiable] and [Poprf]. It can't be implemented outside of this crate.
+pub trait ProtocolMode<CS: CipherSuite>: private::Sealed {
+    /// The mode on the value level
+    const MODE: Mode;
+
+    /// The group element stored by a client and a server in this mode: the
+    /// blinded element of the client and the public key of the server. It is
+    /// [NoElement] in [Base] mode, which doesn't need either.
+    type Element: Copy;
+
+    /// Converts `value` into the element stored in this mode
+    fn element(value: CS::Group) -> Self::Element;
+
+    /// Compares in constant time if the elements are equal
+    fn ct_equal_element(e1: &Self::Element, e2: &Self::Element) -> bool;
+
+    /// Set the contents of `element` to the identity value
+    fn zeroize_element(element: &mut Self::Element);
+}
+
+/// Base mode, in which the outputs are not verifiable, see [Mode::Base]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Base;
+
+/// Verifiable mode, see [Mode::Verifiable]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Verifiable;
+
+/// Partially oblivious mode, see [Mode::Poprf]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Poprf;
+
+/// The element stored in [Base] mode, which stores none
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct NoElement;
+
+impl<CS: CipherSuite> ProtocolMode<CS> for Base {
+    const MODE: Mode = Mode::Base;
+
+    type Element = NoElement;
+
+    fn element(_: CS::Group) -> Self::Element {
+        NoElement
+    }
+
+    fn ct_equal_element(_: &Self::Element, _: &Self::Element) -> bool {
+        true
+    }
+
+    fn zeroize_element(_: &mut Self::Element) {}
+}
+
+macro_rules! impl_protocol_mode_with_element_for {
+    ($mode:ident) => {
+        impl<CS: CipherSuite> ProtocolMode<CS> for $mode {
+            const MODE: Mode = Mode::$mode;
+
+            type Element = CS::Group;
+
+            fn element(value: CS::Group) -> Self::Element {
+                value
+            }
+
+            fn ct_equal_element(e1: &Self::Element, e2: &Self::Element) -> bool {
+                e1.ct_equal(e2)
+            }
+
+            fn zeroize_element(element: &mut Self::Element) {
+                Group::zeroize(element)
+            }
+        }
+    };
+}
+
+impl_protocol_mode_with_element_for!(Verifiable);
+impl_protocol_mode_with_element_for!(Poprf);
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for super::Base {}
+    impl Sealed for super::Verifiable {}
+    impl Sealed for super::Poprf {}
+}
@@ -0,0 +1,206 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! A pool of random scalars sampled in advance
+
+use crate::{ciphersuite::CipherSuite, group::Group};
+use alloc::vec::Vec;
+use rand::{CryptoRng, Error, RngCore};
+use zeroize::{Zeroize, Zeroizing};
+
+// The number of bytes read from the RNG at once while refilling, enough for
+// a few dozen scalars of every supported group
+const BUFFER_LEN: usize = 2048;
+
+/// Random non-zero scalars sampled in advance, in batches, to be used as the
+/// blinds of [NonVerifiableClient::blind_with_pool](crate::NonVerifiableClient::blind_with_pool)
+/// and
+/// [VerifiableClient::blind_with_pool](crate::VerifiableClient::blind_with_pool)
+/// or as the proof nonces of
+/// [VerifiableServer::evaluate_with_pool](crate::VerifiableServer::evaluate_with_pool)
+/// and
+/// [VerifiableServer::batch_evaluate_with_pool](crate::VerifiableServer::batch_evaluate_with_pool).
+///
+/// A refill reads the RNG in large chunks instead of once per scalar, and
+/// runs the rejection sampling of all scalars of the batch at once. The pool
+/// refills itself when it runs empty, call [ScalarPool::refill] outside of
+/// the request path to keep that cost off the tail latency:
+///
+/// ```
+/// # use voprf::CipherSuite;
+/// # struct Default;
+/// # impl CipherSuite for Default {
+/// #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+/// #     type Hash = sha2::Sha512;
+/// # }
+/// use rand::rngs::OsRng;
+/// use voprf::{ScalarPool, VerifiableClient};
+///
+/// let mut pool = ScalarPool::<Default, _>::new(OsRng, 64);
+/// let client_blind_result = VerifiableClient::blind_with_pool(b"input", &mut pool)?;
+///
+/// // Later, while idle
+/// pool.refill();
+/// assert_eq!(pool.len(), 64);
+/// # Ok::<(), voprf::errors::InternalError>(())
+/// ```
+///
+/// Every scalar is handed out once and the remaining ones are zeroized when
+/// the pool is dropped, but until then they are kept in memory like any other
+/// secret.
+pub struct ScalarPool<CS: CipherSuite, R: RngCore + CryptoRng> {
+    rng: R,
+    batch_size: usize,
+    scalars: Zeroizing<Vec<<CS::Group as Group>::Scalar>>,
+}
+
+impl<CS: CipherSuite, R: RngCore + CryptoRng> ScalarPool<CS, R> {
+    /// Creates an empty pool, which samples `batch_size` scalars from `rng`
+    /// whenever it's refilled. A `batch_size` of zero is treated as one.
+    pub fn new(rng: R, batch_size: usize) -> Self {
+        let batch_size = batch_size.max(1);
+
+        Self {
+            rng,
+            batch_size,
+            scalars: Zeroizing::new(Vec::with_capacity(batch_size)),
+        }
+    }
+
+    /// Samples scalars until the pool holds a full batch
+    pub fn refill(&mut self) {
+        let mut rng = BufferedRng {
+            rng: &mut self.rng,
+            buffer: Zeroizing::new(alloc::vec![0; BUFFER_LEN]),
+            position: BUFFER_LEN,
+        };
+
+        while self.scalars.len() < self.batch_size {
+            self.scalars
+                .push(CS::Group::random_nonzero_scalar(&mut rng));
+        }
+    }
+
+    /// The number of scalars left in the pool
+    pub fn len(&self) -> usize {
+        self.scalars.len()
+    }
+
+    /// Returns `true` if the pool has to be refilled before the next scalar
+    /// is taken
+    pub fn is_empty(&self) -> bool {
+        self.scalars.is_empty()
+    }
+
+    /// Returns the wrapped RNG, discarding the remaining scalars
+    pub fn into_inner(self) -> R {
+        self.rng
+    }
+
+    // Takes a scalar from the pool, refilling it first if it's empty
+    pub(crate) fn take(&mut self) -> <CS::Group as Group>::Scalar {
+        if self.scalars.is_empty() {
+            self.refill();
+        }
+
+        self.scalars.pop().expect("the pool was just refilled")
+    }
+}
+
+// Serves the bytes of an RNG from a buffer, which is refilled with a single
+// call to the RNG
+struct BufferedRng<'a, R: RngCore + CryptoRng> {
+    rng: &'a mut R,
+    buffer: Zeroizing<Vec<u8>>,
+    position: usize,
+}
+
+impl<R: RngCore + CryptoRng> RngCore for BufferedRng<'_, R> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let mut filled = 0;
+
+        while filled < dest.len() {
+            if self.position == BUFFER_LEN {
+                self.rng.fill_bytes(&mut self.buffer);
+                self.position = 0;
+            }
+
+            let len = (dest.len() - filled).min(BUFFER_LEN - self.position);
+            dest[filled..filled + len]
+                .copy_from_slice(&self.buffer[self.position..self.position + len]);
+            self.buffer[self.position..self.position + len].zeroize();
+            self.position += len;
+            filled += len;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl<R: RngCore + CryptoRng> CryptoRng for BufferedRng<'_, R> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        errors::InternalError, tests::Ristretto255Sha512, Metadata, VerifiableClient,
+        VerifiableServer,
+    };
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_scalar_pool() -> Result<(), InternalError> {
+        let mut pool = ScalarPool::<Ristretto255Sha512, _>::new(OsRng, 3);
+        assert!(pool.is_empty());
+
+        let server = VerifiableServer::<Ristretto255Sha512>::new(&mut OsRng)?;
+        let client_blind_result = VerifiableClient::blind_with_pool(b"input", &mut pool)?;
+        assert_eq!(pool.len(), 2);
+
+        let server_result =
+            server.evaluate_with_pool(&mut pool, client_blind_result.message, &Metadata::none())?;
+        assert_eq!(pool.len(), 1);
+
+        let client_finalize_result = client_blind_result.state.finalize_once(
+            b"input",
+            server_result.message,
+            server_result.proof,
+            server.get_public_key(),
+            &Metadata::none(),
+        )?;
+        assert_eq!(
+            *client_finalize_result.output,
+            server.full_evaluate(b"input", &Metadata::none())?
+        );
+
+        // Scalars are never handed out twice
+        let first = pool.take();
+        let second = pool.take();
+        assert!(!<Ristretto255Sha512 as CipherSuite>::Group::ct_equal_scalar(&first, &second));
+        assert_eq!(pool.len(), 2);
+
+        pool.refill();
+        assert_eq!(pool.len(), 3);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,563 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Issuance and redemption of Privacy Pass tokens
+//!
+//! Implements the messages of the issuance protocol for privately verifiable
+//! tokens of [RFC 9578](https://www.rfc-editor.org/rfc/rfc9578.html#section-5)
+//! and of its batched variant from
+//! [draft-ietf-privacypass-batched-tokens](https://datatracker.ietf.org/doc/draft-ietf-privacypass-batched-tokens/),
+//! on top of the VOPRF mode of [rfc9497](crate::rfc9497):
+//! - A client creates a [TokenRequest] or a [BatchTokenRequest] for a
+//!   challenge of an origin, keeping a [PendingToken] per requested token.
+//! - The [Issuer] answers with a [TokenResponse] or a [BatchTokenResponse].
+//! - The client finalizes its [PendingToken]s into [Token]s, which the issuer
+//!   redeems with [Issuer::redeem].
+//!
+//! Tokens are bound to the issuer key by its key ID, the SHA-256 hash of the
+//! public key. The registered token type [TOKEN_TYPE_VOPRF] is specified with
+//! P384-SHA384, so tokens of other ciphersuites should use a token type agreed
+//! on by the deployment. Keeping track of redeemed tokens, to reject tokens
+//! spent twice, is up to the issuer.
+//!
+//! ```
+//! # use voprf::CipherSuite;
+//! # struct Default;
+//! # impl CipherSuite for Default {
+//! #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+//! #     type Hash = sha2::Sha512;
+//! # }
+//! use rand::rngs::OsRng;
+//! use voprf::privacypass::{Issuer, PendingToken, TOKEN_TYPE_VOPRF};
+//! use voprf::rfc9497::VoprfServer;
+//!
+//! let mut rng = OsRng;
+//! let issuer = Issuer::new(VoprfServer::<Default>::new(&mut rng)?, TOKEN_TYPE_VOPRF);
+//! let pk = issuer.get_public_key();
+//!
+//! let request_result = PendingToken::request(&mut rng, TOKEN_TYPE_VOPRF, pk, b"challenge")?;
+//! let response = issuer.issue(&mut rng, &request_result.message)?;
+//! let token = request_result.state.finalize(&response, pk)?;
+//! assert!(issuer.redeem(&token)?);
+//! # Ok::<(), voprf::errors::InternalError>(())
+//! ```
+
+use crate::{
+    ciphersuite::CipherSuite,
+    errors::InternalError,
+    group::Group,
+    rfc9497::{VoprfClient, VoprfServer},
+    serialization::i2osp_2,
+    voprf::{BlindedElement, EvaluationElement, Proof},
+};
+use alloc::vec::Vec;
+use digest::Digest;
+use generic_array::{typenum::Unsigned, GenericArray};
+use rand::{CryptoRng, RngCore};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+/// The token type of privately verifiable tokens, registered for the VOPRF
+/// mode with P384-SHA384
+pub const TOKEN_TYPE_VOPRF: u16 = 0x0001;
+
+// The length of the nonce, the challenge digest and the key ID of a token
+pub(crate) const NK: usize = 32;
+
+/// The request of a client for a single token
+pub struct TokenRequest<CS: CipherSuite> {
+    pub(crate) token_type: u16,
+    pub(crate) truncated_token_key_id: u8,
+    pub(crate) blinded_msg: BlindedElement<CS>,
+}
+impl_traits_for!(
+    struct TokenRequest<CS: CipherSuite>,
+    [token_type, truncated_token_key_id, blinded_msg],
+    [BlindedElement<CS>],
+);
+
+/// The response of the [Issuer] to a [TokenRequest]
+pub struct TokenResponse<CS: CipherSuite> {
+    pub(crate) evaluate_msg: EvaluationElement<CS>,
+    pub(crate) evaluate_proof: Proof<CS>,
+}
+impl_traits_for!(
+    struct TokenResponse<CS: CipherSuite>,
+    [evaluate_msg, evaluate_proof],
+    [EvaluationElement<CS>, Proof<CS>],
+);
+
+/// The request of a client for several tokens at once
+pub struct BatchTokenRequest<CS: CipherSuite> {
+    pub(crate) token_type: u16,
+    pub(crate) truncated_token_key_id: u8,
+    pub(crate) blinded_elements: Vec<BlindedElement<CS>>,
+}
+impl_traits_for!(
+    struct BatchTokenRequest<CS: CipherSuite>,
+    [token_type, truncated_token_key_id, blinded_elements],
+    [BlindedElement<CS>],
+);
+
+/// The response of the [Issuer] to a [BatchTokenRequest], with a single proof
+/// for all tokens
+pub struct BatchTokenResponse<CS: CipherSuite> {
+    pub(crate) evaluated_elements: Vec<EvaluationElement<CS>>,
+    pub(crate) evaluated_proof: Proof<CS>,
+}
+impl_traits_for!(
+    struct BatchTokenResponse<CS: CipherSuite>,
+    [evaluated_elements, evaluated_proof],
+    [EvaluationElement<CS>, Proof<CS>],
+);
+
+/// A token, which the client presents to an origin to be redeemed by the
+/// [Issuer]
+pub struct Token<CS: CipherSuite> {
+    pub(crate) token_type: u16,
+    pub(crate) nonce: [u8; NK],
+    pub(crate) challenge_digest: [u8; NK],
+    pub(crate) token_key_id: [u8; NK],
+    pub(crate) authenticator: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+}
+impl_debug_eq_hash_for!(
+    struct Token<CS: CipherSuite>,
+    [token_type, nonce, challenge_digest, token_key_id, authenticator],
+);
+impl_clone_for!(
+    struct Token<CS: CipherSuite>,
+    [token_type, nonce, challenge_digest, token_key_id, authenticator],
+);
+impl_serialize_and_deserialize_for!(Token);
+
+/// The state of a client for a requested token, to be finalized with the
+/// response of the [Issuer]
+pub struct PendingToken<CS: CipherSuite> {
+    pub(crate) token_type: u16,
+    pub(crate) nonce: [u8; NK],
+    pub(crate) challenge_digest: [u8; NK],
+    pub(crate) token_key_id: [u8; NK],
+    pub(crate) client: VoprfClient<CS>,
+}
+impl_secret_traits_for!(
+    struct PendingToken<CS: CipherSuite>,
+    [token_type, nonce, challenge_digest, token_key_id, client],
+    [VoprfClient<CS>],
+);
+
+impl<CS: CipherSuite> PartialEq for PendingToken<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.token_type == other.token_type)
+            & bool::from(self.token_input().ct_eq(&other.token_input()))
+            & (self.client == other.client)
+    }
+}
+
+impl<CS: CipherSuite> Eq for PendingToken<CS> {}
+
+/// An issuer of tokens, which evaluates requests with its key and redeems
+/// the resulting tokens
+pub struct Issuer<CS: CipherSuite> {
+    server: VoprfServer<CS>,
+    token_type: u16,
+    token_key_id: [u8; NK],
+}
+
+impl<CS: CipherSuite> PendingToken<CS> {
+    /// Requests a token of `token_type` for the encoded `challenge` of an
+    /// origin from the issuer with the public key `pk`
+    pub fn request<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        token_type: u16,
+        pk: CS::Group,
+        challenge: &[u8],
+    ) -> Result<TokenRequestResult<CS>, InternalError> {
+        let (state, blinded_msg) = Self::new(rng, token_type, pk, challenge)?;
+
+        Ok(TokenRequestResult {
+            message: TokenRequest {
+                token_type,
+                truncated_token_key_id: truncate_token_key_id(&state.token_key_id),
+                blinded_msg,
+            },
+            state,
+        })
+    }
+
+    /// Requests `count` tokens of `token_type` at once, like
+    /// [PendingToken::request]
+    pub fn batch_request<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        token_type: u16,
+        pk: CS::Group,
+        challenge: &[u8],
+        count: usize,
+    ) -> Result<BatchTokenRequestResult<CS>, InternalError> {
+        check_batch_len::<CS>(count)?;
+
+        let (states, blinded_elements): (Vec<_>, Vec<_>) = (0..count)
+            .map(|_| Self::new(rng, token_type, pk, challenge))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .unzip();
+
+        Ok(BatchTokenRequestResult {
+            message: BatchTokenRequest {
+                token_type,
+                truncated_token_key_id: truncate_token_key_id(&token_key_id::<CS>(pk)),
+                blinded_elements,
+            },
+            states,
+        })
+    }
+
+    /// Finalizes the [TokenResponse] of the issuer with the public key `pk`
+    /// into a [Token], failing if the proof doesn't verify
+    pub fn finalize(
+        &self,
+        response: &TokenResponse<CS>,
+        pk: CS::Group,
+    ) -> Result<Token<CS>, InternalError> {
+        let output = self
+            .client
+            .finalize(
+                &self.token_input(),
+                &response.evaluate_msg,
+                &response.evaluate_proof,
+                pk,
+            )?
+            .output;
+
+        self.token(GenericArray::clone(&output), pk)
+    }
+
+    /// Finalizes the [BatchTokenResponse] of the issuer with the public key
+    /// `pk` into a [Token] per pending token, in the order they were
+    /// requested in
+    pub fn batch_finalize(
+        states: &[Self],
+        response: &BatchTokenResponse<CS>,
+        pk: CS::Group,
+    ) -> Result<Vec<Token<CS>>, InternalError> {
+        let inputs: Vec<_> = states.iter().map(Self::token_input).collect();
+        let clients: Vec<_> = states.iter().map(|state| state.client.clone()).collect();
+        let outputs = VoprfClient::batch_finalize(
+            &inputs,
+            &clients,
+            &response.evaluated_elements,
+            &response.evaluated_proof,
+            pk,
+        )?;
+
+        states
+            .iter()
+            .zip(outputs)
+            .map(|(state, output)| state.token(GenericArray::clone(&output), pk))
+            .collect()
+    }
+
+    fn new<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        token_type: u16,
+        pk: CS::Group,
+        challenge: &[u8],
+    ) -> Result<(Self, BlindedElement<CS>), InternalError> {
+        let mut nonce = [0; NK];
+        rng.fill_bytes(&mut nonce);
+        let mut challenge_digest = [0; NK];
+        challenge_digest.copy_from_slice(&Sha256::digest(challenge));
+        let token_key_id = token_key_id::<CS>(pk);
+
+        let token_input = token_input(token_type, &nonce, &challenge_digest, &token_key_id);
+        let blind_result = VoprfClient::blind(&token_input, rng)?;
+
+        Ok((
+            Self {
+                token_type,
+                nonce,
+                challenge_digest,
+                token_key_id,
+                client: blind_result.state,
+            },
+            blind_result.message,
+        ))
+    }
+
+    fn token(
+        &self,
+        authenticator: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+        pk: CS::Group,
+    ) -> Result<Token<CS>, InternalError> {
+        // The key ID was derived from the key the token was requested from
+        if token_key_id::<CS>(pk) != self.token_key_id {
+            return Err(InternalError::TokenError);
+        }
+
+        Ok(Token {
+            token_type: self.token_type,
+            nonce: self.nonce,
+            challenge_digest: self.challenge_digest,
+            token_key_id: self.token_key_id,
+            authenticator,
+        })
+    }
+
+    fn token_input(&self) -> Vec<u8> {
+        token_input(
+            self.token_type,
+            &self.nonce,
+            &self.challenge_digest,
+            &self.token_key_id,
+        )
+    }
+}
+
+impl<CS: CipherSuite> Token<CS> {
+    /// The token type
+    pub fn token_type(&self) -> u16 {
+        self.token_type
+    }
+
+    /// The digest of the challenge the token was requested for, to be matched
+    /// by the origin against its challenge
+    pub fn challenge_digest(&self) -> &[u8; NK] {
+        &self.challenge_digest
+    }
+
+    /// The ID of the issuer key the token was issued with
+    pub fn token_key_id(&self) -> &[u8; NK] {
+        &self.token_key_id
+    }
+}
+
+impl<CS: CipherSuite> Issuer<CS> {
+    /// Issues tokens of `token_type` with the key of `server`
+    pub fn new(server: VoprfServer<CS>, token_type: u16) -> Self {
+        let token_key_id = token_key_id::<CS>(server.get_public_key());
+
+        Self {
+            server,
+            token_type,
+            token_key_id,
+        }
+    }
+
+    /// Retrieves the public key of the issuer, which clients request tokens
+    /// from
+    pub fn get_public_key(&self) -> CS::Group {
+        self.server.get_public_key()
+    }
+
+    /// The ID of the issuer key, the SHA-256 hash of its public key
+    pub fn token_key_id(&self) -> &[u8; NK] {
+        &self.token_key_id
+    }
+
+    /// Answers a [TokenRequest], failing with [InternalError::TokenError] if
+    /// it's for another token type or issuer key
+    pub fn issue<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        request: &TokenRequest<CS>,
+    ) -> Result<TokenResponse<CS>, InternalError> {
+        self.check_request(request.token_type, request.truncated_token_key_id)?;
+        let result = self.server.blind_evaluate(rng, &request.blinded_msg)?;
+
+        Ok(TokenResponse {
+            evaluate_msg: result.message,
+            evaluate_proof: result.proof,
+        })
+    }
+
+    /// Answers a [BatchTokenRequest] with a single proof, failing with
+    /// [InternalError::TokenError] if it's for another token type or issuer
+    /// key
+    pub fn issue_batch<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        request: &BatchTokenRequest<CS>,
+    ) -> Result<BatchTokenResponse<CS>, InternalError> {
+        self.check_request(request.token_type, request.truncated_token_key_id)?;
+        check_batch_len::<CS>(request.blinded_elements.len())?;
+        let result = self
+            .server
+            .batch_blind_evaluate(rng, &request.blinded_elements)?;
+
+        Ok(BatchTokenResponse {
+            evaluated_elements: result.messages,
+            evaluated_proof: result.proof,
+        })
+    }
+
+    /// Returns `true` if `token` was issued by this issuer. The authenticator
+    /// is compared in constant time.
+    pub fn redeem(&self, token: &Token<CS>) -> Result<bool, InternalError> {
+        if token.token_type != self.token_type || token.token_key_id != self.token_key_id {
+            return Ok(false);
+        }
+
+        let authenticator = self.server.evaluate(&token_input(
+            token.token_type,
+            &token.nonce,
+            &token.challenge_digest,
+            &token.token_key_id,
+        ))?;
+        Ok(authenticator.ct_eq(&token.authenticator).into())
+    }
+
+    fn check_request(
+        &self,
+        token_type: u16,
+        truncated_token_key_id: u8,
+    ) -> Result<(), InternalError> {
+        if token_type != self.token_type
+            || truncated_token_key_id != truncate_token_key_id(&self.token_key_id)
+        {
+            return Err(InternalError::TokenError);
+        }
+
+        Ok(())
+    }
+}
+
+/// Contains the fields that are returned by [PendingToken::request]
+#[must_use]
+pub struct TokenRequestResult<CS: CipherSuite> {
+    /// The state to be persisted on the client
+    pub state: PendingToken<CS>,
+    /// The message to send to the issuer
+    pub message: TokenRequest<CS>,
+}
+
+/// Contains the fields that are returned by [PendingToken::batch_request]
+#[must_use]
+pub struct BatchTokenRequestResult<CS: CipherSuite> {
+    /// The states to be persisted on the client, one per requested token
+    pub states: Vec<PendingToken<CS>>,
+    /// The message to send to the issuer
+    pub message: BatchTokenRequest<CS>,
+}
+
+fn token_key_id<CS: CipherSuite>(pk: CS::Group) -> [u8; NK] {
+    let mut token_key_id = [0; NK];
+    token_key_id.copy_from_slice(&Sha256::digest(&pk.to_arr()));
+    token_key_id
+}
+
+// The least significant byte of the key ID
+fn truncate_token_key_id(token_key_id: &[u8; NK]) -> u8 {
+    token_key_id[NK - 1]
+}
+
+pub(crate) fn token_input(
+    token_type: u16,
+    nonce: &[u8; NK],
+    challenge_digest: &[u8; NK],
+    token_key_id: &[u8; NK],
+) -> Vec<u8> {
+    [
+        &token_type.to_be_bytes()[..],
+        nonce,
+        challenge_digest,
+        token_key_id,
+    ]
+    .concat()
+}
+
+// The elements of a batch are prefixed by their total length in two bytes
+pub(crate) fn check_batch_len<CS: CipherSuite>(count: usize) -> Result<(), InternalError> {
+    count
+        .checked_mul(<CS::Group as Group>::ElemLen::USIZE)
+        .ok_or(InternalError::SerializationError)
+        .and_then(i2osp_2)
+        .map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Ristretto255Sha512;
+    use rand::rngs::OsRng;
+
+    type CS = Ristretto255Sha512;
+
+    #[test]
+    fn test_issuance_and_redemption() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let issuer = Issuer::new(VoprfServer::<CS>::new(&mut rng)?, TOKEN_TYPE_VOPRF);
+        let pk = issuer.get_public_key();
+
+        let request_result =
+            PendingToken::<CS>::request(&mut rng, TOKEN_TYPE_VOPRF, pk, b"challenge")?;
+        let state = PendingToken::<CS>::deserialize(&request_result.state.serialize())?;
+        let request = TokenRequest::deserialize(&request_result.message.serialize())?;
+        let response = issuer.issue(&mut rng, &request)?;
+        let response = TokenResponse::deserialize(&response.serialize())?;
+        let token = state.finalize(&response, pk)?;
+        let token = Token::<CS>::deserialize(&token.serialize())?;
+        assert_eq!(
+            token.challenge_digest()[..],
+            Sha256::digest(b"challenge")[..]
+        );
+        assert!(issuer.redeem(&token)?);
+
+        // A token of another issuer isn't redeemed
+        let other_issuer = Issuer::new(VoprfServer::<CS>::new(&mut rng)?, TOKEN_TYPE_VOPRF);
+        assert!(!other_issuer.redeem(&token)?);
+        assert!(
+            matches!(
+                other_issuer.issue(&mut rng, &request),
+                Err(InternalError::TokenError)
+            ) || truncate_token_key_id(other_issuer.token_key_id())
+                == truncate_token_key_id(issuer.token_key_id())
+        );
+
+        // A tampered token isn't redeemed
+        let mut tampered = token.clone();
+        tampered.nonce[0] ^= 1;
+        assert!(!issuer.redeem(&tampered)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_issuance() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let issuer = Issuer::new(VoprfServer::<CS>::new(&mut rng)?, TOKEN_TYPE_VOPRF);
+        let pk = issuer.get_public_key();
+
+        let request_result =
+            PendingToken::<CS>::batch_request(&mut rng, TOKEN_TYPE_VOPRF, pk, b"challenge", 3)?;
+        let request = BatchTokenRequest::deserialize(&request_result.message.serialize())?;
+        let response = issuer.issue_batch(&mut rng, &request)?;
+        let response = BatchTokenResponse::deserialize(&response.serialize())?;
+        let tokens = PendingToken::batch_finalize(&request_result.states, &response, pk)?;
+        assert_eq!(tokens.len(), 3);
+        for token in &tokens {
+            assert!(issuer.redeem(token)?);
+        }
+        // Every token has its own nonce
+        assert_ne!(tokens[0], tokens[1]);
+
+        // The batch proof doesn't verify for a subset of the batch
+        let response = BatchTokenResponse {
+            evaluated_elements: response.evaluated_elements[..2].to_vec(),
+            evaluated_proof: response.evaluated_proof.clone(),
+        };
+        assert!(PendingToken::batch_finalize(&request_result.states[..2], &response, pk).is_err());
+
+        // Requests for another token type are rejected
+        let request_result =
+            PendingToken::<CS>::batch_request(&mut rng, 0xface, pk, b"challenge", 1)?;
+        assert!(matches!(
+            issuer.issue_batch(&mut rng, &request_result.message),
+            Err(InternalError::TokenError)
+        ));
+
+        Ok(())
+    }
+}
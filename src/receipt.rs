@@ -0,0 +1,360 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Signed receipts of batch evaluations, enabled by the `receipts` feature
+
+use crate::{
+    ciphersuite::CipherSuite,
+    errors::{ErrorSource, InternalError},
+    group::Group,
+    key_policy::KeyFingerprints,
+    voprf::{
+        check_batch_lengths, create_context_string, BlindedElement, EvaluationElement, Metadata,
+        Mode, Proof, VerifiableServer, VerifiableServerBatchEvaluateResult,
+    },
+};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use digest::Digest;
+use generic_array::{typenum::Unsigned, GenericArray};
+use signature::{Signature, Signer, Verifier};
+
+static STR_RECEIPT: &[u8] = b"Receipt-";
+
+/// A statement signed by a server, that it evaluated a batch of
+/// [BlindedElement]s with the key identified by [EvaluationReceipt::key_id]
+/// in the given epoch and at the given time. Clients can keep receipts to
+/// hold the server accountable, e.g. by handing them to an auditor, who
+/// checks them with [EvaluationReceipt::verify].
+///
+/// The signature scheme is chosen by the server, through any
+/// [Signer](signature::Signer) of the [signature] crate. The epoch and the
+/// timestamp are supplied by the server and aren't interpreted by this crate;
+/// the timestamp would usually be in seconds since the Unix epoch.
+///
+/// The signed message is the concatenation of `"Receipt-"`, the context
+/// string, the key ID, the epoch and the timestamp as 8-byte big-endian
+/// integers, and the batch digest. The batch digest is the hash of the batch
+/// size as an 8-byte big-endian integer, the [BlindedElement]s, the
+/// [EvaluationElement]s, the [Proof] and the length-prefixed [Metadata].
+pub struct EvaluationReceipt<CS: CipherSuite, S: Signature> {
+    batch_digest: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+    key_id: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+    epoch: u64,
+    timestamp: u64,
+    signature: S,
+}
+
+impl<CS: CipherSuite, S: Signature> EvaluationReceipt<CS, S> {
+    /// Signs a receipt for the batch evaluation `evaluation` of
+    /// `blinded_elements` by `server`
+    pub fn sign(
+        signer: &impl Signer<S>,
+        server: &VerifiableServer<CS>,
+        blinded_elements: &[BlindedElement<CS>],
+        evaluation: &VerifiableServerBatchEvaluateResult<CS>,
+        metadata: &Metadata,
+        epoch: u64,
+        timestamp: u64,
+    ) -> Result<Self, InternalError> {
+        let batch_digest = batch_digest::<CS>(
+            blinded_elements,
+            &evaluation.messages,
+            &evaluation.proof,
+            metadata,
+        )?;
+        let key_id = KeyFingerprints::<CS>::fingerprint(&server.get_public_key());
+        let message = signed_message::<CS>(&key_id, epoch, timestamp, &batch_digest)?;
+        let signature = signer
+            .try_sign(&message)
+            .map_err(|error| InternalError::ReceiptError {
+                source: Some(ErrorSource::new(error)),
+            })?;
+
+        Ok(Self {
+            batch_digest,
+            key_id,
+            epoch,
+            timestamp,
+            signature,
+        })
+    }
+
+    /// Checks that the receipt was signed for the evaluation of
+    /// `blinded_elements` into `evaluation_elements` with the public key
+    /// `pk`. The [Proof] isn't verified, as clients already did so when
+    /// finalizing.
+    pub fn verify(
+        &self,
+        verifier: &impl Verifier<S>,
+        pk: &CS::Group,
+        blinded_elements: &[BlindedElement<CS>],
+        evaluation_elements: &[EvaluationElement<CS>],
+        proof: &Proof<CS>,
+        metadata: &Metadata,
+    ) -> Result<(), InternalError> {
+        let batch_digest =
+            batch_digest::<CS>(blinded_elements, evaluation_elements, proof, metadata)?;
+
+        if batch_digest != self.batch_digest
+            || KeyFingerprints::<CS>::fingerprint(pk) != self.key_id
+        {
+            return Err(InternalError::ReceiptError { source: None });
+        }
+
+        let message =
+            signed_message::<CS>(&self.key_id, self.epoch, self.timestamp, &batch_digest)?;
+        verifier
+            .verify(&message, &self.signature)
+            .map_err(|error| InternalError::ReceiptError {
+                source: Some(ErrorSource::new(error)),
+            })
+    }
+
+    /// The digest of the evaluated batch
+    pub fn batch_digest(&self) -> &GenericArray<u8, <CS::Hash as Digest>::OutputSize> {
+        &self.batch_digest
+    }
+
+    /// The ID of the key the batch was evaluated with, its
+    /// [fingerprint](KeyFingerprints::fingerprint)
+    pub fn key_id(&self) -> &GenericArray<u8, <CS::Hash as Digest>::OutputSize> {
+        &self.key_id
+    }
+
+    /// The epoch given by the server
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The timestamp given by the server
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// The signature of the server
+    pub fn signature(&self) -> &S {
+        &self.signature
+    }
+
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        [
+            self.batch_digest.as_slice(),
+            &self.key_id,
+            &self.epoch.to_be_bytes(),
+            &self.timestamp.to_be_bytes(),
+            self.signature.as_bytes(),
+        ]
+        .concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let digest_len = <CS::Hash as Digest>::OutputSize::USIZE;
+        if input.len() < 2 * digest_len + 16 {
+            return Err(InternalError::SizeError {
+                expected: 2 * digest_len + 16,
+                got: input.len(),
+            });
+        }
+
+        let (batch_digest, input) = input.split_at(digest_len);
+        let (key_id, input) = input.split_at(digest_len);
+        let (epoch, input) = input.split_at(8);
+        let (timestamp, signature) = input.split_at(8);
+
+        Ok(Self {
+            batch_digest: GenericArray::clone_from_slice(batch_digest),
+            key_id: GenericArray::clone_from_slice(key_id),
+            epoch: u64::from_be_bytes(<[u8; 8]>::try_from(epoch).map_err(|_| {
+                InternalError::SizeError {
+                    expected: 8,
+                    got: epoch.len(),
+                }
+            })?),
+            timestamp: u64::from_be_bytes(<[u8; 8]>::try_from(timestamp).map_err(|_| {
+                InternalError::SizeError {
+                    expected: 8,
+                    got: timestamp.len(),
+                }
+            })?),
+            signature: S::from_bytes(signature).map_err(|_| InternalError::SerializationError)?,
+        })
+    }
+}
+
+impl<CS: CipherSuite, S: Signature + Clone> Clone for EvaluationReceipt<CS, S> {
+    fn clone(&self) -> Self {
+        Self {
+            batch_digest: self.batch_digest.clone(),
+            key_id: self.key_id.clone(),
+            epoch: self.epoch,
+            timestamp: self.timestamp,
+            signature: self.signature.clone(),
+        }
+    }
+}
+
+impl<CS: CipherSuite, S: Signature> core::fmt::Debug for EvaluationReceipt<CS, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("EvaluationReceipt")
+            .field("batch_digest", &self.batch_digest)
+            .field("key_id", &self.key_id)
+            .field("epoch", &self.epoch)
+            .field("timestamp", &self.timestamp)
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+fn batch_digest<CS: CipherSuite>(
+    blinded_elements: &[BlindedElement<CS>],
+    evaluation_elements: &[EvaluationElement<CS>],
+    proof: &Proof<CS>,
+    metadata: &Metadata,
+) -> Result<GenericArray<u8, <CS::Hash as Digest>::OutputSize>, InternalError> {
+    check_batch_lengths(blinded_elements.len(), evaluation_elements.len())?;
+
+    let batch_size =
+        u64::try_from(blinded_elements.len()).map_err(|_| InternalError::SerializationError)?;
+    let metadata_len =
+        u64::try_from(metadata.0.len()).map_err(|_| InternalError::SerializationError)?;
+
+    let mut hash = CS::Hash::new();
+    hash.update(batch_size.to_be_bytes());
+    for blinded_element in blinded_elements {
+        hash.update(blinded_element.value.to_arr());
+    }
+    for evaluation_element in evaluation_elements {
+        hash.update(evaluation_element.value.to_arr());
+    }
+    hash.update(CS::Group::scalar_as_bytes(proof.c_scalar));
+    hash.update(CS::Group::scalar_as_bytes(proof.s_scalar));
+    hash.update(metadata_len.to_be_bytes());
+    hash.update(&metadata.0);
+
+    Ok(hash.finalize())
+}
+
+fn signed_message<CS: CipherSuite>(
+    key_id: &[u8],
+    epoch: u64,
+    timestamp: u64,
+    batch_digest: &[u8],
+) -> Result<Vec<u8>, InternalError> {
+    let suite_id =
+        u16::try_from(CS::Group::SUITE_ID).map_err(|_| InternalError::SerializationError)?;
+    let context_string = create_context_string(Mode::Verifiable, suite_id);
+
+    Ok([
+        STR_RECEIPT,
+        &context_string,
+        key_id,
+        &epoch.to_be_bytes(),
+        &timestamp.to_be_bytes(),
+        batch_digest,
+    ]
+    .concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tests::{
+            mac_signer::{MacKey, Tag},
+            Ristretto255Sha512,
+        },
+        VerifiableClient,
+    };
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_receipt() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let key = MacKey([7; 32]);
+        let server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng)?;
+        let pk = server.get_public_key();
+        let metadata = Metadata(b"metadata".to_vec());
+
+        let blinded_elements: Vec<_> = [&b"one"[..], b"two"]
+            .iter()
+            .map(|input| {
+                VerifiableClient::<Ristretto255Sha512>::blind(input, &mut rng)
+                    .map(|result| result.message)
+            })
+            .collect::<Result<_, _>>()?;
+        let evaluation = server.batch_evaluate(&mut rng, &blinded_elements, &metadata)?;
+
+        let receipt = EvaluationReceipt::sign(
+            &key,
+            &server,
+            &blinded_elements,
+            &evaluation,
+            &metadata,
+            3,
+            1_700_000_000,
+        )?;
+        let receipt =
+            EvaluationReceipt::<Ristretto255Sha512, Tag>::deserialize(&receipt.serialize())?;
+        assert_eq!(receipt.epoch(), 3);
+        assert_eq!(receipt.timestamp(), 1_700_000_000);
+        assert_eq!(
+            receipt.key_id(),
+            &KeyFingerprints::<Ristretto255Sha512>::fingerprint(&pk)
+        );
+
+        receipt.verify(
+            &key,
+            &pk,
+            &blinded_elements,
+            &evaluation.messages,
+            &evaluation.proof,
+            &metadata,
+        )?;
+
+        // A receipt doesn't cover a different batch, key or signer
+        assert_eq!(
+            receipt.verify(
+                &key,
+                &pk,
+                &blinded_elements[..1],
+                &evaluation.messages[..1],
+                &evaluation.proof,
+                &metadata,
+            ),
+            Err(InternalError::ReceiptError { source: None })
+        );
+        assert_eq!(
+            receipt.verify(
+                &key,
+                &VerifiableServer::<Ristretto255Sha512>::new(&mut rng)?.get_public_key(),
+                &blinded_elements,
+                &evaluation.messages,
+                &evaluation.proof,
+                &metadata,
+            ),
+            Err(InternalError::ReceiptError { source: None })
+        );
+        assert_eq!(
+            receipt.verify(
+                &MacKey([8; 32]),
+                &pk,
+                &blinded_elements,
+                &evaluation.messages,
+                &evaluation.proof,
+                &metadata,
+            ),
+            Err(InternalError::ReceiptError {
+                source: Some(ErrorSource::new(signature::Error::new()))
+            })
+        );
+
+        Ok(())
+    }
+}
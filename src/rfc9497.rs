@@ -0,0 +1,1167 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! The protocol of [RFC 9497](https://www.rfc-editor.org/rfc/rfc9497.html)
+//!
+//! The types at the root of the crate implement draft-07, whose context
+//! strings, domain separation tags, proofs and finalization differ from the
+//! final RFC. The types of this module implement the RFC, so they interoperate
+//! with other RFC-conformant libraries:
+//! - [OprfClient] and [OprfServer] for the OPRF mode
+//! - [VoprfClient] and [VoprfServer] for the VOPRF mode
+//! - [PoprfClient] and [PoprfServer] for the POPRF mode
+//!
+//! Unlike in draft-07, the OPRF and VOPRF modes take no [Metadata], only the
+//! POPRF mode binds its outputs to public metadata. The messages exchanged,
+//! [BlindedElement], [EvaluationElement] and [Proof], are the same types as at
+//! the root of the crate, as their encoding didn't change.
+//!
+//! The ciphersuite is identified in the context string by
+//! [Group::IDENTIFIER], so a [CipherSuite] is only conformant if its group and
+//! hash match the identifier, e.g. ristretto255 with SHA-512.
+//!
+//! ```
+//! # use voprf::CipherSuite;
+//! # struct Default;
+//! # impl CipherSuite for Default {
+//! #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+//! #     type Hash = sha2::Sha512;
+//! # }
+//! use rand::rngs::OsRng;
+//! use voprf::rfc9497::{VoprfClient, VoprfServer};
+//!
+//! let mut rng = OsRng;
+//! let server = VoprfServer::<Default>::new(&mut rng)?;
+//! let client_blind_result = VoprfClient::<Default>::blind(b"input", &mut rng)?;
+//! let server_result = server.blind_evaluate(&mut rng, &client_blind_result.message)?;
+//! let client_finalize_result = client_blind_result.state.finalize(
+//!     b"input",
+//!     &server_result.message,
+//!     &server_result.proof,
+//!     server.get_public_key(),
+//! )?;
+//! assert_eq!(*client_finalize_result.output, server.evaluate(b"input")?);
+//! # Ok::<(), voprf::errors::InternalError>(())
+//! ```
+
+use crate::{
+    ciphersuite::CipherSuite,
+    errors::InternalError,
+    evaluator::{AsyncBlindEvaluator, BlindEvaluator, ProofTranscript},
+    group::Group,
+    keys::{PrivateKey, PublicKey},
+    secret::Secret,
+    serialization::{i2osp_2, i2osp_2_len},
+    voprf::{
+        check_batch_lengths, check_blinded_elements, check_input_len, check_metadata_len,
+        BlindedElement, EvaluationElement, Metadata, Mode, Proof, ZeroizingElement,
+    },
+};
+use alloc::vec::Vec;
+use digest::Digest;
+use generic_array::{typenum::Unsigned, GenericArray};
+use rand::{CryptoRng, RngCore};
+use zeroize::{Zeroize, Zeroizing};
+
+///////////////
+// Constants //
+// ========= //
+///////////////
+
+static STR_OPRF: &[u8] = b"OPRFV1-";
+static STR_HASH_TO_GROUP: &[u8] = b"HashToGroup-";
+static STR_HASH_TO_SCALAR: &[u8] = b"HashToScalar-";
+static STR_DERIVE_KEYPAIR: &[u8] = b"DeriveKeyPair";
+static STR_SEED: &[u8] = b"Seed-";
+static STR_COMPOSITE: &[u8] = b"Composite";
+static STR_CHALLENGE: &[u8] = b"Challenge";
+static STR_INFO: &[u8] = b"Info";
+static STR_FINALIZE: &[u8] = b"Finalize";
+
+////////////////////////////
+// High-level API Structs //
+// ====================== //
+////////////////////////////
+
+/// A client of the OPRF mode, which engages with an [OprfServer]
+pub struct OprfClient<CS: CipherSuite> {
+    pub(crate) blind: <CS::Group as Group>::Scalar,
+}
+impl_secret_traits_for!(
+    struct OprfClient<CS: CipherSuite>,
+    [],
+    redacted [blind],
+    [<CS::Group as Group>::Scalar],
+);
+
+impl<CS: CipherSuite> PartialEq for OprfClient<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.blind, &other.blind)
+    }
+}
+
+impl<CS: CipherSuite> Eq for OprfClient<CS> {}
+
+/// A client of the VOPRF mode, which engages with a [VoprfServer] and checks
+/// its outputs against the server public key
+pub struct VoprfClient<CS: CipherSuite> {
+    pub(crate) blind: <CS::Group as Group>::Scalar,
+    pub(crate) blinded_element: CS::Group,
+}
+impl_secret_traits_for!(
+    struct VoprfClient<CS: CipherSuite>,
+    [blinded_element],
+    redacted [blind],
+    [<CS::Group as Group>::Scalar, CS::Group],
+);
+
+impl<CS: CipherSuite> PartialEq for VoprfClient<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.blind, &other.blind)
+            & self.blinded_element.ct_equal(&other.blinded_element)
+    }
+}
+
+impl<CS: CipherSuite> Eq for VoprfClient<CS> {}
+
+/// A client of the POPRF mode, which engages with a [PoprfServer] and checks
+/// its outputs against the server public key tweaked by the metadata
+pub struct PoprfClient<CS: CipherSuite> {
+    pub(crate) blind: <CS::Group as Group>::Scalar,
+    pub(crate) blinded_element: CS::Group,
+}
+impl_secret_traits_for!(
+    struct PoprfClient<CS: CipherSuite>,
+    [blinded_element],
+    redacted [blind],
+    [<CS::Group as Group>::Scalar, CS::Group],
+);
+
+impl<CS: CipherSuite> PartialEq for PoprfClient<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.blind, &other.blind)
+            & self.blinded_element.ct_equal(&other.blinded_element)
+    }
+}
+
+impl<CS: CipherSuite> Eq for PoprfClient<CS> {}
+
+/// A server of the OPRF mode, which engages with an [OprfClient]
+pub struct OprfServer<CS: CipherSuite> {
+    pub(crate) sk: Secret<<CS::Group as Group>::Scalar>,
+}
+impl_secret_traits_for!(
+    struct OprfServer<CS: CipherSuite>,
+    [sk],
+    [<CS::Group as Group>::Scalar],
+);
+
+impl<CS: CipherSuite> PartialEq for OprfServer<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.sk, &other.sk)
+    }
+}
+
+impl<CS: CipherSuite> Eq for OprfServer<CS> {}
+
+/// A server of the VOPRF mode, which engages with a [VoprfClient]
+pub struct VoprfServer<CS: CipherSuite> {
+    pub(crate) sk: Secret<<CS::Group as Group>::Scalar>,
+    pub(crate) pk: CS::Group,
+}
+impl_secret_traits_for!(
+    struct VoprfServer<CS: CipherSuite>,
+    [sk, pk],
+    [<CS::Group as Group>::Scalar, CS::Group],
+);
+
+impl<CS: CipherSuite> PartialEq for VoprfServer<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.sk, &other.sk) & self.pk.ct_equal(&other.pk)
+    }
+}
+
+impl<CS: CipherSuite> Eq for VoprfServer<CS> {}
+
+/// A server of the POPRF mode, which engages with a [PoprfClient]
+pub struct PoprfServer<CS: CipherSuite> {
+    pub(crate) sk: Secret<<CS::Group as Group>::Scalar>,
+    pub(crate) pk: CS::Group,
+}
+impl_secret_traits_for!(
+    struct PoprfServer<CS: CipherSuite>,
+    [sk, pk],
+    [<CS::Group as Group>::Scalar, CS::Group],
+);
+
+impl<CS: CipherSuite> PartialEq for PoprfServer<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.sk, &other.sk) & self.pk.ct_equal(&other.pk)
+    }
+}
+
+impl<CS: CipherSuite> Eq for PoprfServer<CS> {}
+
+/////////////////////////
+// API Implementations //
+// =================== //
+/////////////////////////
+
+impl<CS: CipherSuite> OprfClient<CS> {
+    /// Corresponds to Blind() of the OPRF mode
+    pub fn blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<OprfClientBlindResult<CS>, InternalError> {
+        let blind = CS::Group::random_nonzero_scalar(blinding_factor_rng);
+        let blinded_element = deterministic_blind::<CS>(input, &blind, Mode::Base)?;
+        Ok(OprfClientBlindResult {
+            state: Self { blind },
+            message: BlindedElement {
+                value: blinded_element,
+            },
+        })
+    }
+
+    /// Corresponds to Finalize() of the OPRF mode. The `input` has to be the
+    /// same as the one given to [OprfClient::blind].
+    pub fn finalize(
+        &self,
+        input: &[u8],
+        evaluation_element: &EvaluationElement<CS>,
+    ) -> Result<OprfClientFinalizeResult<CS>, InternalError> {
+        let unblinded_element = unblind::<CS>(&self.blind, evaluation_element);
+        let output = Zeroizing::new(finalize::<CS>(input, None, &unblinded_element)?);
+        Ok(OprfClientFinalizeResult { output })
+    }
+}
+
+impl<CS: CipherSuite> VoprfClient<CS> {
+    /// Corresponds to Blind() of the VOPRF mode
+    pub fn blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<VoprfClientBlindResult<CS>, InternalError> {
+        let blind = CS::Group::random_nonzero_scalar(blinding_factor_rng);
+        let blinded_element = deterministic_blind::<CS>(input, &blind, Mode::Verifiable)?;
+        Ok(VoprfClientBlindResult {
+            state: Self {
+                blind,
+                blinded_element,
+            },
+            message: BlindedElement {
+                value: blinded_element,
+            },
+        })
+    }
+
+    /// Corresponds to Finalize() of the VOPRF mode, which verifies the proof
+    /// against `pk` before unblinding. The `input` has to be the same as the
+    /// one given to [VoprfClient::blind].
+    pub fn finalize(
+        &self,
+        input: &[u8],
+        evaluation_element: &EvaluationElement<CS>,
+        proof: &Proof<CS>,
+        pk: CS::Group,
+    ) -> Result<VoprfClientFinalizeResult<CS>, InternalError> {
+        let output = Self::batch_finalize(
+            &[input],
+            core::slice::from_ref(self),
+            core::slice::from_ref(evaluation_element),
+            proof,
+            pk,
+        )?
+        .pop()
+        .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?;
+        Ok(VoprfClientFinalizeResult { output })
+    }
+
+    /// Finalizes a batch of evaluations, which were proven by a single proof
+    /// produced by [VoprfServer::batch_blind_evaluate]. The inputs have to be
+    /// given in the same order as the clients they were blinded with. The
+    /// outputs are zeroized when dropped.
+    #[allow(clippy::type_complexity)]
+    pub fn batch_finalize<I: AsRef<[u8]>>(
+        inputs: &[I],
+        clients: &[Self],
+        messages: &[EvaluationElement<CS>],
+        proof: &Proof<CS>,
+        pk: CS::Group,
+    ) -> Result<Vec<Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>>, InternalError>
+    {
+        check_batch_lengths(inputs.len(), clients.len())?;
+        check_batch_lengths(clients.len(), messages.len())?;
+        if pk.is_identity() {
+            return Err(InternalError::InvalidPublicKey);
+        }
+
+        verify_proof::<CS>(
+            CS::Group::base_point(),
+            pk,
+            clients.iter().map(|x| x.blinded_element),
+            messages.iter().map(|x| x.value),
+            proof,
+            Mode::Verifiable,
+        )?;
+
+        inputs
+            .iter()
+            .zip(clients)
+            .zip(messages)
+            .map(|((input, client), message)| {
+                let unblinded_element = unblind::<CS>(&client.blind, message);
+                finalize::<CS>(input.as_ref(), None, &unblinded_element).map(Zeroizing::new)
+            })
+            .collect()
+    }
+}
+
+impl<CS: CipherSuite> PoprfClient<CS> {
+    /// Corresponds to Blind() of the POPRF mode
+    pub fn blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<PoprfClientBlindResult<CS>, InternalError> {
+        let blind = CS::Group::random_nonzero_scalar(blinding_factor_rng);
+        let blinded_element = deterministic_blind::<CS>(input, &blind, Mode::Poprf)?;
+        Ok(PoprfClientBlindResult {
+            state: Self {
+                blind,
+                blinded_element,
+            },
+            message: BlindedElement {
+                value: blinded_element,
+            },
+        })
+    }
+
+    /// Corresponds to Finalize() of the POPRF mode, which verifies the proof
+    /// against `pk` tweaked by `metadata` before unblinding. The `input` has to
+    /// be the same as the one given to [PoprfClient::blind].
+    pub fn finalize(
+        &self,
+        input: &[u8],
+        evaluation_element: &EvaluationElement<CS>,
+        proof: &Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+    ) -> Result<PoprfClientFinalizeResult<CS>, InternalError> {
+        let output = Self::batch_finalize(
+            &[input],
+            core::slice::from_ref(self),
+            core::slice::from_ref(evaluation_element),
+            proof,
+            pk,
+            metadata,
+        )?
+        .pop()
+        .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?;
+        Ok(PoprfClientFinalizeResult { output })
+    }
+
+    /// Finalizes a batch of evaluations with the same metadata, which were
+    /// proven by a single proof produced by [PoprfServer::batch_blind_evaluate].
+    /// The inputs have to be given in the same order as the clients they were
+    /// blinded with. The outputs are zeroized when dropped.
+    #[allow(clippy::type_complexity)]
+    pub fn batch_finalize<I: AsRef<[u8]>>(
+        inputs: &[I],
+        clients: &[Self],
+        messages: &[EvaluationElement<CS>],
+        proof: &Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+    ) -> Result<Vec<Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>>, InternalError>
+    {
+        check_batch_lengths(inputs.len(), clients.len())?;
+        check_batch_lengths(clients.len(), messages.len())?;
+
+        let m = compute_tweak::<CS>(&metadata.0)?;
+        let tweaked_key = CS::Group::base_point() * &m + &pk;
+        if tweaked_key.is_identity() {
+            return Err(InternalError::InvalidPublicKey);
+        }
+
+        verify_proof::<CS>(
+            CS::Group::base_point(),
+            tweaked_key,
+            messages.iter().map(|x| x.value),
+            clients.iter().map(|x| x.blinded_element),
+            proof,
+            Mode::Poprf,
+        )?;
+
+        inputs
+            .iter()
+            .zip(clients)
+            .zip(messages)
+            .map(|((input, client), message)| {
+                let unblinded_element = unblind::<CS>(&client.blind, message);
+                finalize::<CS>(input.as_ref(), Some(&metadata.0), &unblinded_element)
+                    .map(Zeroizing::new)
+            })
+            .collect()
+    }
+}
+
+impl<CS: CipherSuite> OprfServer<CS> {
+    /// Produces a new instance of an [OprfServer] with a random private key,
+    /// corresponds to GenerateKeyPair()
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self, InternalError> {
+        Self::from_scalar(CS::Group::random_nonzero_scalar(rng))
+    }
+
+    /// Produces a new instance of an [OprfServer] from the bytes of a private
+    /// key
+    pub fn new_with_key(key: &[u8]) -> Result<Self, InternalError> {
+        Self::from_scalar(scalar_from_slice::<CS>(key)?)
+    }
+
+    /// Produces a new instance of an [OprfServer] with the given private key
+    pub fn new_with_private_key(sk: &PrivateKey<CS>) -> Result<Self, InternalError> {
+        Self::from_scalar(*sk.value)
+    }
+
+    /// Produces a new instance of an [OprfServer] with the private key derived
+    /// from `seed` and `info`, corresponds to DeriveKeyPair()
+    pub fn new_from_seed(seed: &[u8], info: &[u8]) -> Result<Self, InternalError> {
+        Self::from_scalar(derive_key_pair::<CS>(seed, info, Mode::Base)?)
+    }
+
+    fn from_scalar(sk: <CS::Group as Group>::Scalar) -> Result<Self, InternalError> {
+        Ok(Self {
+            sk: Secret::new(sk)?,
+        })
+    }
+
+    #[cfg(test)]
+    /// Only used for test functions
+    pub fn get_private_key(&self) -> <CS::Group as Group>::Scalar {
+        *self.sk
+    }
+
+    /// Corresponds to BlindEvaluate() of the OPRF mode
+    pub fn blind_evaluate(
+        &self,
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<EvaluationElement<CS>, InternalError> {
+        check_blinded_elements(core::slice::from_ref(blinded_element))?;
+
+        Ok(EvaluationElement {
+            value: blinded_element.value * &self.sk,
+        })
+    }
+
+    /// Computes the output for `input` directly with the private key,
+    /// corresponds to Evaluate() of the OPRF mode
+    pub fn evaluate(
+        &self,
+        input: &[u8],
+    ) -> Result<GenericArray<u8, <CS::Hash as Digest>::OutputSize>, InternalError> {
+        let evaluated_element =
+            ZeroizingElement(hash_to_group::<CS>(input, Mode::Base)? * &self.sk);
+        finalize::<CS>(input, None, &evaluated_element)
+    }
+}
+
+impl<CS: CipherSuite> VoprfServer<CS> {
+    /// Produces a new instance of a [VoprfServer] with a random private key,
+    /// corresponds to GenerateKeyPair()
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self, InternalError> {
+        Self::from_scalar(CS::Group::random_nonzero_scalar(rng))
+    }
+
+    /// Produces a new instance of a [VoprfServer] from the bytes of a private
+    /// key
+    pub fn new_with_key(key: &[u8]) -> Result<Self, InternalError> {
+        Self::from_scalar(scalar_from_slice::<CS>(key)?)
+    }
+
+    /// Produces a new instance of a [VoprfServer] with the given private key
+    pub fn new_with_private_key(sk: &PrivateKey<CS>) -> Result<Self, InternalError> {
+        Self::from_scalar(*sk.value)
+    }
+
+    /// Produces a new instance of a [VoprfServer] with the private key derived
+    /// from `seed` and `info`, corresponds to DeriveKeyPair()
+    pub fn new_from_seed(seed: &[u8], info: &[u8]) -> Result<Self, InternalError> {
+        Self::from_scalar(derive_key_pair::<CS>(seed, info, Mode::Verifiable)?)
+    }
+
+    fn from_scalar(sk: <CS::Group as Group>::Scalar) -> Result<Self, InternalError> {
+        Ok(Self {
+            sk: Secret::new(sk)?,
+            pk: CS::Group::base_point() * &sk,
+        })
+    }
+
+    #[cfg(test)]
+    /// Only used for test functions
+    pub fn get_private_key(&self) -> <CS::Group as Group>::Scalar {
+        *self.sk
+    }
+
+    /// Corresponds to BlindEvaluate() of the VOPRF mode
+    pub fn blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<VoprfServerEvaluateResult<CS>, InternalError> {
+        let VoprfServerBatchEvaluateResult {
+            mut messages,
+            proof,
+        } = self.batch_blind_evaluate(rng, core::slice::from_ref(blinded_element))?;
+        let message = messages
+            .pop()
+            .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?;
+        Ok(VoprfServerEvaluateResult { message, proof })
+    }
+
+    /// Evaluates a batch of [BlindedElement]s with a single proof
+    pub fn batch_blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+    ) -> Result<VoprfServerBatchEvaluateResult<CS>, InternalError> {
+        let r = Zeroizing::new(CS::Group::random_nonzero_scalar(rng));
+        self.batch_blind_evaluate_with_proof_nonce(blinded_elements, &r)
+    }
+
+    pub(crate) fn batch_blind_evaluate_with_proof_nonce(
+        &self,
+        blinded_elements: &[BlindedElement<CS>],
+        r: &<CS::Group as Group>::Scalar,
+    ) -> Result<VoprfServerBatchEvaluateResult<CS>, InternalError> {
+        check_blinded_elements(blinded_elements)?;
+
+        let messages: Vec<_> = blinded_elements
+            .iter()
+            .map(|x| EvaluationElement {
+                value: x.value * &self.sk,
+            })
+            .collect();
+
+        let proof = generate_proof::<CS>(
+            &self.sk,
+            CS::Group::base_point(),
+            self.pk,
+            blinded_elements.iter().map(|x| x.value),
+            messages.iter().map(|x| x.value),
+            r,
+            Mode::Verifiable,
+        )?;
+
+        Ok(VoprfServerBatchEvaluateResult { messages, proof })
+    }
+
+    /// Corresponds to BlindEvaluate() of the VOPRF mode, with the private key
+    /// held by `evaluator`, see [BlindEvaluator]
+    pub fn blind_evaluate_with<E: BlindEvaluator<CS>, R: RngCore + CryptoRng>(
+        evaluator: &E,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<VoprfServerEvaluateResult<CS>, InternalError> {
+        let VoprfServerBatchEvaluateResult {
+            mut messages,
+            proof,
+        } = Self::batch_blind_evaluate_with(
+            evaluator,
+            rng,
+            core::slice::from_ref(blinded_element),
+        )?;
+        let message = messages
+            .pop()
+            .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?;
+        Ok(VoprfServerEvaluateResult { message, proof })
+    }
+
+    /// Evaluates a batch of [BlindedElement]s with a single proof, with the
+    /// private key held by `evaluator`, see [BlindEvaluator]. Fails with
+    /// [InternalError::ProofVerificationError] if the evaluator returned a
+    /// wrong evaluation or proof.
+    pub fn batch_blind_evaluate_with<E: BlindEvaluator<CS>, R: RngCore + CryptoRng>(
+        evaluator: &E,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+    ) -> Result<VoprfServerBatchEvaluateResult<CS>, InternalError> {
+        check_blinded_elements(blinded_elements)?;
+
+        let pk = evaluator.public_key();
+        let blinded_elements: Vec<_> = blinded_elements.iter().map(|x| x.value).collect();
+        let evaluated_elements = evaluator.evaluate(&blinded_elements)?;
+        check_batch_lengths(blinded_elements.len(), evaluated_elements.len())?;
+        let transcript = ProofTranscript::new(pk, &blinded_elements, &evaluated_elements)?;
+        let proof = evaluator.prove(rng, &transcript)?;
+
+        finish_evaluation(pk, blinded_elements, evaluated_elements, proof)
+    }
+
+    /// Corresponds to BlindEvaluate() of the VOPRF mode, with the private key
+    /// held by `evaluator`, see [AsyncBlindEvaluator]
+    pub async fn blind_evaluate_async<E: AsyncBlindEvaluator<CS>, R: RngCore + CryptoRng + Send>(
+        evaluator: &E,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<VoprfServerEvaluateResult<CS>, InternalError> {
+        let VoprfServerBatchEvaluateResult {
+            mut messages,
+            proof,
+        } = Self::batch_blind_evaluate_async(
+            evaluator,
+            rng,
+            core::slice::from_ref(blinded_element),
+        )
+        .await?;
+        let message = messages
+            .pop()
+            .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?;
+        Ok(VoprfServerEvaluateResult { message, proof })
+    }
+
+    /// Evaluates a batch of [BlindedElement]s with a single proof, with the
+    /// private key held by `evaluator`, see [AsyncBlindEvaluator]. Fails with
+    /// [InternalError::ProofVerificationError] if the evaluator returned a
+    /// wrong evaluation or proof.
+    pub async fn batch_blind_evaluate_async<
+        E: AsyncBlindEvaluator<CS>,
+        R: RngCore + CryptoRng + Send,
+    >(
+        evaluator: &E,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+    ) -> Result<VoprfServerBatchEvaluateResult<CS>, InternalError> {
+        check_blinded_elements(blinded_elements)?;
+
+        let pk = evaluator.public_key();
+        let blinded_elements: Vec<_> = blinded_elements.iter().map(|x| x.value).collect();
+        let evaluated_elements = evaluator.evaluate(&blinded_elements).await?;
+        check_batch_lengths(blinded_elements.len(), evaluated_elements.len())?;
+        let transcript = ProofTranscript::new(pk, &blinded_elements, &evaluated_elements)?;
+        let proof = evaluator.prove(rng, &transcript).await?;
+
+        finish_evaluation(pk, blinded_elements, evaluated_elements, proof)
+    }
+
+    /// Computes the output for `input` directly with the private key,
+    /// corresponds to Evaluate() of the VOPRF mode
+    pub fn evaluate(
+        &self,
+        input: &[u8],
+    ) -> Result<GenericArray<u8, <CS::Hash as Digest>::OutputSize>, InternalError> {
+        let evaluated_element =
+            ZeroizingElement(hash_to_group::<CS>(input, Mode::Verifiable)? * &self.sk);
+        finalize::<CS>(input, None, &evaluated_element)
+    }
+
+    /// Retrieves the server's public key
+    pub fn get_public_key(&self) -> CS::Group {
+        self.pk
+    }
+
+    /// Retrieves the server's public key as a [PublicKey]
+    pub fn public_key(&self) -> PublicKey<CS> {
+        PublicKey { value: self.pk }
+    }
+}
+
+impl<CS: CipherSuite> PoprfServer<CS> {
+    /// Produces a new instance of a [PoprfServer] with a random private key,
+    /// corresponds to GenerateKeyPair()
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self, InternalError> {
+        Self::from_scalar(CS::Group::random_nonzero_scalar(rng))
+    }
+
+    /// Produces a new instance of a [PoprfServer] from the bytes of a private
+    /// key
+    pub fn new_with_key(key: &[u8]) -> Result<Self, InternalError> {
+        Self::from_scalar(scalar_from_slice::<CS>(key)?)
+    }
+
+    /// Produces a new instance of a [PoprfServer] with the given private key
+    pub fn new_with_private_key(sk: &PrivateKey<CS>) -> Result<Self, InternalError> {
+        Self::from_scalar(*sk.value)
+    }
+
+    /// Produces a new instance of a [PoprfServer] with the private key derived
+    /// from `seed` and `info`, corresponds to DeriveKeyPair()
+    pub fn new_from_seed(seed: &[u8], info: &[u8]) -> Result<Self, InternalError> {
+        Self::from_scalar(derive_key_pair::<CS>(seed, info, Mode::Poprf)?)
+    }
+
+    fn from_scalar(sk: <CS::Group as Group>::Scalar) -> Result<Self, InternalError> {
+        Ok(Self {
+            sk: Secret::new(sk)?,
+            pk: CS::Group::base_point() * &sk,
+        })
+    }
+
+    #[cfg(test)]
+    /// Only used for test functions
+    pub fn get_private_key(&self) -> <CS::Group as Group>::Scalar {
+        *self.sk
+    }
+
+    /// Corresponds to BlindEvaluate() of the POPRF mode, which evaluates with
+    /// the private key tweaked by `metadata`
+    pub fn blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+        metadata: &Metadata,
+    ) -> Result<PoprfServerEvaluateResult<CS>, InternalError> {
+        let PoprfServerBatchEvaluateResult {
+            mut messages,
+            proof,
+        } = self.batch_blind_evaluate(rng, core::slice::from_ref(blinded_element), metadata)?;
+        let message = messages
+            .pop()
+            .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?;
+        Ok(PoprfServerEvaluateResult { message, proof })
+    }
+
+    /// Evaluates a batch of [BlindedElement]s with the same metadata and a
+    /// single proof
+    pub fn batch_blind_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+        metadata: &Metadata,
+    ) -> Result<PoprfServerBatchEvaluateResult<CS>, InternalError> {
+        let r = Zeroizing::new(CS::Group::random_nonzero_scalar(rng));
+        self.batch_blind_evaluate_with_proof_nonce(blinded_elements, metadata, &r)
+    }
+
+    pub(crate) fn batch_blind_evaluate_with_proof_nonce(
+        &self,
+        blinded_elements: &[BlindedElement<CS>],
+        metadata: &Metadata,
+        r: &<CS::Group as Group>::Scalar,
+    ) -> Result<PoprfServerBatchEvaluateResult<CS>, InternalError> {
+        let t = self.tweaked_key(metadata)?;
+        let t_inverted = Zeroizing::new(CS::Group::scalar_invert(&t));
+        check_blinded_elements(blinded_elements)?;
+
+        let messages: Vec<_> = blinded_elements
+            .iter()
+            .map(|x| EvaluationElement {
+                value: x.value * &t_inverted,
+            })
+            .collect();
+
+        let g = CS::Group::base_point();
+        let proof = generate_proof::<CS>(
+            &t,
+            g,
+            g * &t,
+            messages.iter().map(|x| x.value),
+            blinded_elements.iter().map(|x| x.value),
+            r,
+            Mode::Poprf,
+        )?;
+
+        Ok(PoprfServerBatchEvaluateResult { messages, proof })
+    }
+
+    /// Computes the output for `input` directly with the private key,
+    /// corresponds to Evaluate() of the POPRF mode
+    pub fn evaluate(
+        &self,
+        input: &[u8],
+        metadata: &Metadata,
+    ) -> Result<GenericArray<u8, <CS::Hash as Digest>::OutputSize>, InternalError> {
+        let t = self.tweaked_key(metadata)?;
+        let t_inverted = Zeroizing::new(CS::Group::scalar_invert(&t));
+        let evaluated_element =
+            ZeroizingElement(hash_to_group::<CS>(input, Mode::Poprf)? * &t_inverted);
+        finalize::<CS>(input, Some(&metadata.0), &evaluated_element)
+    }
+
+    /// Retrieves the server's public key
+    pub fn get_public_key(&self) -> CS::Group {
+        self.pk
+    }
+
+    /// Retrieves the server's public key as a [PublicKey]
+    pub fn public_key(&self) -> PublicKey<CS> {
+        PublicKey { value: self.pk }
+    }
+
+    // The private key tweaked by the metadata, which can't be inverted if the
+    // metadata hashes to the negated private key
+    fn tweaked_key(
+        &self,
+        metadata: &Metadata,
+    ) -> Result<Zeroizing<<CS::Group as Group>::Scalar>, InternalError> {
+        let m = compute_tweak::<CS>(&metadata.0)?;
+        let t = Zeroizing::new(*self.sk + &m);
+        if CS::Group::ct_equal_scalar(&t, &CS::Group::scalar_zero()) {
+            return Err(InternalError::ZeroScalarError);
+        }
+        Ok(t)
+    }
+}
+
+/////////////////////////
+// Convenience Structs //
+//==================== //
+/////////////////////////
+
+/// Contains the fields that are returned by [OprfClient::blind]
+#[must_use]
+pub struct OprfClientBlindResult<CS: CipherSuite> {
+    /// The state to be persisted on the client
+    pub state: OprfClient<CS>,
+    /// The message to send to the server
+    pub message: BlindedElement<CS>,
+}
+
+/// Contains the fields that are returned by [OprfClient::finalize]
+#[must_use]
+pub struct OprfClientFinalizeResult<CS: CipherSuite> {
+    /// The output of the protocol
+    pub output: Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>,
+}
+
+/// Contains the fields that are returned by [VoprfClient::blind]
+#[must_use]
+pub struct VoprfClientBlindResult<CS: CipherSuite> {
+    /// The state to be persisted on the client
+    pub state: VoprfClient<CS>,
+    /// The message to send to the server
+    pub message: BlindedElement<CS>,
+}
+
+/// Contains the fields that are returned by [VoprfClient::finalize]
+#[must_use]
+pub struct VoprfClientFinalizeResult<CS: CipherSuite> {
+    /// The output of the protocol
+    pub output: Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>,
+}
+
+/// Contains the fields that are returned by [PoprfClient::blind]
+#[must_use]
+pub struct PoprfClientBlindResult<CS: CipherSuite> {
+    /// The state to be persisted on the client
+    pub state: PoprfClient<CS>,
+    /// The message to send to the server
+    pub message: BlindedElement<CS>,
+}
+
+/// Contains the fields that are returned by [PoprfClient::finalize]
+#[must_use]
+pub struct PoprfClientFinalizeResult<CS: CipherSuite> {
+    /// The output of the protocol
+    pub output: Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>,
+}
+
+/// Contains the fields that are returned by [VoprfServer::blind_evaluate]
+#[must_use]
+pub struct VoprfServerEvaluateResult<CS: CipherSuite> {
+    /// The message to send to the client
+    pub message: EvaluationElement<CS>,
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+}
+
+/// Contains the fields that are returned by [VoprfServer::batch_blind_evaluate]
+#[must_use]
+pub struct VoprfServerBatchEvaluateResult<CS: CipherSuite> {
+    /// The messages to send to the client
+    pub messages: Vec<EvaluationElement<CS>>,
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+}
+
+/// Contains the fields that are returned by [PoprfServer::blind_evaluate]
+#[must_use]
+pub struct PoprfServerEvaluateResult<CS: CipherSuite> {
+    /// The message to send to the client
+    pub message: EvaluationElement<CS>,
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+}
+
+/// Contains the fields that are returned by [PoprfServer::batch_blind_evaluate]
+#[must_use]
+pub struct PoprfServerBatchEvaluateResult<CS: CipherSuite> {
+    /// The messages to send to the client
+    pub messages: Vec<EvaluationElement<CS>>,
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+}
+
+/////////////////////
+// Inner functions //
+// =============== //
+/////////////////////
+
+// Corresponds to CreateContextString()
+fn create_context_string<CS: CipherSuite>(mode: Mode) -> Vec<u8> {
+    [
+        STR_OPRF,
+        &[mode as u8],
+        b"-",
+        CS::Group::IDENTIFIER.as_bytes(),
+    ]
+    .concat()
+}
+
+fn hash_to_group<CS: CipherSuite>(input: &[u8], mode: Mode) -> Result<CS::Group, InternalError> {
+    check_input_len::<CS>(input)?;
+
+    let context_string = create_context_string::<CS>(mode);
+    let input_element =
+        CS::Group::hash_to_curve::<CS>(&[input], &[STR_HASH_TO_GROUP, &context_string])?;
+    if input_element.is_identity() {
+        return Err(InternalError::IdentityElementError);
+    }
+
+    Ok(input_element)
+}
+
+fn hash_to_scalar<CS: CipherSuite>(
+    input: &[&[u8]],
+    mode: Mode,
+) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    let context_string = create_context_string::<CS>(mode);
+    CS::Group::hash_to_scalar::<CS>(input, &[STR_HASH_TO_SCALAR, &context_string])
+}
+
+pub(crate) fn deterministic_blind<CS: CipherSuite>(
+    input: &[u8],
+    blind: &<CS::Group as Group>::Scalar,
+    mode: Mode,
+) -> Result<CS::Group, InternalError> {
+    Ok(hash_to_group::<CS>(input, mode)? * blind)
+}
+
+fn unblind<CS: CipherSuite>(
+    blind: &<CS::Group as Group>::Scalar,
+    evaluation_element: &EvaluationElement<CS>,
+) -> ZeroizingElement<CS::Group> {
+    let blind_inverted = Zeroizing::new(CS::Group::scalar_invert(blind));
+    ZeroizingElement(evaluation_element.value * &blind_inverted)
+}
+
+fn scalar_from_slice<CS: CipherSuite>(
+    key: &[u8],
+) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    if key.len() != <CS::Group as Group>::ScalarLen::USIZE {
+        return Err(InternalError::SizeError {
+            expected: <CS::Group as Group>::ScalarLen::USIZE,
+            got: key.len(),
+        });
+    }
+
+    CS::Group::from_scalar_slice(GenericArray::from_slice(key))
+}
+
+// Corresponds to DeriveKeyPair(), returns the private key
+pub(crate) fn derive_key_pair<CS: CipherSuite>(
+    seed: &[u8],
+    info: &[u8],
+    mode: Mode,
+) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    let context_string = create_context_string::<CS>(mode);
+    let info_len = i2osp_2(info.len())?;
+
+    for counter in 0..=u8::MAX {
+        let sk = CS::Group::hash_to_scalar::<CS>(
+            &[seed, &info_len, info, &[counter]],
+            &[STR_DERIVE_KEYPAIR, &context_string],
+        )?;
+        if !CS::Group::ct_equal_scalar(&sk, &CS::Group::scalar_zero()) {
+            return Ok(sk);
+        }
+    }
+
+    Err(InternalError::DeriveKeyPairError)
+}
+
+// Computes the scalar `m` the private key is tweaked with in the POPRF mode
+fn compute_tweak<CS: CipherSuite>(
+    info: &[u8],
+) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    check_metadata_len::<CS>(info)?;
+
+    hash_to_scalar::<CS>(&[STR_INFO, &i2osp_2(info.len())?, info], Mode::Poprf)
+}
+
+// Corresponds to Finalize(), `info` is only given in the POPRF mode
+fn finalize<CS: CipherSuite>(
+    input: &[u8],
+    info: Option<&[u8]>,
+    unblinded_element: &CS::Group,
+) -> Result<GenericArray<u8, <CS::Hash as Digest>::OutputSize>, InternalError> {
+    check_input_len::<CS>(input)?;
+
+    let mut hash = CS::Hash::new().chain(i2osp_2(input.len())?).chain(input);
+    if let Some(info) = info {
+        hash = hash.chain(i2osp_2(info.len())?).chain(info);
+    }
+
+    let mut unblinded_element = unblinded_element.to_arr();
+    let output = hash
+        .chain(i2osp_2_len::<<CS::Group as Group>::ElemLen>())
+        .chain(&unblinded_element)
+        .chain(STR_FINALIZE)
+        .finalize();
+    unblinded_element.as_mut_slice().zeroize();
+
+    Ok(output)
+}
+
+// Corresponds to GenerateProof()
+#[allow(clippy::many_single_char_names)]
+fn generate_proof<CS: CipherSuite>(
+    k: &<CS::Group as Group>::Scalar,
+    a: CS::Group,
+    b: CS::Group,
+    cs: impl ExactSizeIterator<Item = CS::Group>,
+    ds: impl ExactSizeIterator<Item = CS::Group>,
+    r: &<CS::Group as Group>::Scalar,
+    mode: Mode,
+) -> Result<Proof<CS>, InternalError> {
+    let (m, z) = compute_composites::<CS>(Some(k), b, cs, ds, mode)?;
+
+    let t2 = a * r;
+    let t3 = m * r;
+
+    let c_scalar = compute_challenge::<CS>(b, m, z, t2, t3, mode)?;
+    let s_scalar = *r - &Zeroizing::new(c_scalar * k);
+
+    Ok(Proof { c_scalar, s_scalar })
+}
+
+// Verifies the proof of an evaluator before handing out its evaluation
+fn finish_evaluation<CS: CipherSuite>(
+    pk: CS::Group,
+    blinded_elements: Vec<CS::Group>,
+    evaluated_elements: Vec<CS::Group>,
+    proof: Proof<CS>,
+) -> Result<VoprfServerBatchEvaluateResult<CS>, InternalError> {
+    verify_proof::<CS>(
+        CS::Group::base_point(),
+        pk,
+        blinded_elements.into_iter(),
+        evaluated_elements.iter().copied(),
+        &proof,
+        Mode::Verifiable,
+    )?;
+
+    Ok(VoprfServerBatchEvaluateResult {
+        messages: evaluated_elements
+            .into_iter()
+            .map(|value| EvaluationElement { value })
+            .collect(),
+        proof,
+    })
+}
+
+// Corresponds to VerifyProof()
+#[allow(clippy::many_single_char_names)]
+fn verify_proof<CS: CipherSuite>(
+    a: CS::Group,
+    b: CS::Group,
+    cs: impl ExactSizeIterator<Item = CS::Group>,
+    ds: impl ExactSizeIterator<Item = CS::Group>,
+    proof: &Proof<CS>,
+    mode: Mode,
+) -> Result<(), InternalError> {
+    let (m, z) = compute_composites::<CS>(None, b, cs, ds, mode)?;
+
+    let t2 = a * &proof.s_scalar + &(b * &proof.c_scalar);
+    let t3 = m * &proof.s_scalar + &(z * &proof.c_scalar);
+
+    let c = compute_challenge::<CS>(b, m, z, t2, t3, mode)?;
+
+    match CS::Group::ct_equal_scalar(&c, &proof.c_scalar) {
+        true => Ok(()),
+        false => Err(InternalError::ProofVerificationError),
+    }
+}
+
+// Corresponds to ComputeCompositesFast() if `k` is given, and to
+// ComputeComposites() otherwise
+pub(crate) fn compute_composites<CS: CipherSuite>(
+    k: Option<&<CS::Group as Group>::Scalar>,
+    b: CS::Group,
+    c_iter: impl ExactSizeIterator<Item = CS::Group>,
+    d_iter: impl ExactSizeIterator<Item = CS::Group>,
+    mode: Mode,
+) -> Result<(CS::Group, CS::Group), InternalError> {
+    check_batch_lengths(c_iter.len(), d_iter.len())?;
+
+    let context_string = create_context_string::<CS>(mode);
+    let elem_len = i2osp_2_len::<<CS::Group as Group>::ElemLen>();
+    let seed = CS::Hash::new()
+        .chain(elem_len)
+        .chain(b.to_arr())
+        .chain(i2osp_2(STR_SEED.len() + context_string.len())?)
+        .chain(STR_SEED)
+        .chain(&context_string)
+        .finalize();
+    let seed_len = i2osp_2(seed.len())?;
+
+    let mut m = CS::Group::identity();
+    let mut z = CS::Group::identity();
+
+    for (i, (c, d)) in c_iter.zip(d_iter).enumerate() {
+        let di = hash_to_scalar::<CS>(
+            &[
+                &seed_len,
+                &seed,
+                &i2osp_2(i)?,
+                &elem_len,
+                &c.to_arr(),
+                &elem_len,
+                &d.to_arr(),
+                STR_COMPOSITE,
+            ],
+            mode,
+        )?;
+        m = c * &di + &m;
+        if k.is_none() {
+            z = d * &di + &z;
+        }
+    }
+
+    if let Some(k) = k {
+        z = m * k;
+    }
+
+    Ok((m, z))
+}
+
+pub(crate) fn compute_challenge<CS: CipherSuite>(
+    b: CS::Group,
+    m: CS::Group,
+    z: CS::Group,
+    t2: CS::Group,
+    t3: CS::Group,
+    mode: Mode,
+) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    let elem_len = i2osp_2_len::<<CS::Group as Group>::ElemLen>();
+
+    hash_to_scalar::<CS>(
+        &[
+            &elem_len,
+            &b.to_arr(),
+            &elem_len,
+            &m.to_arr(),
+            &elem_len,
+            &z.to_arr(),
+            &elem_len,
+            &t2.to_arr(),
+            &elem_len,
+            &t3.to_arr(),
+            STR_CHALLENGE,
+        ],
+        mode,
+    )
+}
@@ -0,0 +1,352 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Continuous health checks for the RNG passed in by the caller
+
+use crate::errors::{ErrorSource, InternalError};
+use core::num::NonZeroU32;
+use rand::{CryptoRng, Error, RngCore};
+
+// The number of consecutive identical bytes at which the repetition count
+// test fails. Following NIST SP 800-90B, section 4.4.1, with a false positive
+// probability of 2^-40 and a full-entropy source this is 1 + 40 / 8.
+const REPETITION_CUTOFF: usize = 6;
+
+// The number of bytes of each draw which are compared to the previous draw
+const FINGERPRINT_LEN: usize = 16;
+
+// Draws shorter than this are not compared to the previous draw, as they
+// might be equal by chance
+const MIN_FINGERPRINT_LEN: usize = 8;
+
+/// Wraps an RNG and checks its output for failures, like a stuck or repeating
+/// output of a flaky hardware RNG, instead of silently producing weak blinds
+/// and proof nonces. Two continuous tests are run on every byte drawn:
+///
+/// - A repetition count test, which fails if the same byte is produced six
+///   times in a row.
+/// - A stuck output test, which fails if a draw of at least 8 bytes starts with
+///   the same 16 bytes as the previous draw of the same length.
+///
+/// Once a test failed, the wrapper stays failed. As the protocol can't be
+/// interrupted in the middle of drawing randomness, wrap every call with
+/// [HealthCheckedRng::checked], which discards the result and returns
+/// [InternalError::RngHealthError] if a test failed:
+///
+/// ```
+/// # use voprf::CipherSuite;
+/// # struct Default;
+/// # impl CipherSuite for Default {
+/// #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+/// #     type Hash = sha2::Sha512;
+/// # }
+/// use rand::rngs::OsRng;
+/// use voprf::{HealthCheckedRng, VerifiableClient};
+///
+/// let mut rng = HealthCheckedRng::new(OsRng);
+/// let client_blind_result = rng.checked(|rng| VerifiableClient::<Default>::blind(b"input", rng))?;
+/// # Ok::<(), voprf::errors::InternalError>(())
+/// ```
+///
+/// These checks only detect catastrophic failures of the RNG, they don't
+/// estimate its entropy.
+#[derive(Clone, Debug)]
+pub struct HealthCheckedRng<R: RngCore + CryptoRng> {
+    rng: R,
+    last_byte: Option<u8>,
+    repetitions: usize,
+    fingerprint: [u8; FINGERPRINT_LEN],
+    fingerprint_len: usize,
+    failed: bool,
+}
+
+impl<R: RngCore + CryptoRng> HealthCheckedRng<R> {
+    /// Wraps `rng`
+    pub fn new(rng: R) -> Self {
+        Self {
+            rng,
+            last_byte: None,
+            repetitions: 0,
+            fingerprint: [0; FINGERPRINT_LEN],
+            fingerprint_len: 0,
+            failed: false,
+        }
+    }
+
+    /// Runs `f` with this RNG, and discards its result if a health check
+    /// failed before or while running it
+    pub fn checked<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, InternalError>,
+    ) -> Result<T, InternalError> {
+        self.health()?;
+        let result = f(self)?;
+        self.health()?;
+        Ok(result)
+    }
+
+    /// Returns [InternalError::RngHealthError] if a health check failed
+    pub fn health(&self) -> Result<(), InternalError> {
+        if self.failed {
+            return Err(InternalError::RngHealthError);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the wrapped RNG
+    pub fn into_inner(self) -> R {
+        self.rng
+    }
+
+    fn check(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if self.last_byte == Some(byte) {
+                self.repetitions += 1;
+            } else {
+                self.last_byte = Some(byte);
+                self.repetitions = 1;
+            }
+
+            if self.repetitions >= REPETITION_CUTOFF {
+                self.failed = true;
+            }
+        }
+
+        if bytes.len() >= MIN_FINGERPRINT_LEN {
+            let len = bytes.len().min(FINGERPRINT_LEN);
+
+            if self.fingerprint_len == bytes.len() && self.fingerprint[..len] == bytes[..len] {
+                self.failed = true;
+            }
+
+            self.fingerprint[..len].copy_from_slice(&bytes[..len]);
+            self.fingerprint_len = bytes.len();
+        }
+    }
+}
+
+impl<R: RngCore + CryptoRng> RngCore for HealthCheckedRng<R> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest);
+        self.check(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.rng.try_fill_bytes(dest)?;
+        self.check(dest);
+
+        if self.failed {
+            return Err(Error::from(
+                NonZeroU32::new(Error::CUSTOM_START).expect("constant is non-zero"),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: RngCore + CryptoRng> CryptoRng for HealthCheckedRng<R> {}
+
+// Draws from the wrapped RNG with `try_fill_bytes`, and records the first
+// error instead of panicking. The bytes of a failed draw are filled with a
+// constant, which is never a zero scalar, so sampling terminates and its
+// result can be discarded by `try_with_rng`.
+pub(crate) struct FallibleRng<'a, R> {
+    rng: &'a mut R,
+    error: Option<ErrorSource>,
+}
+
+impl<R: RngCore + CryptoRng> RngCore for FallibleRng<'_, R> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(error) = self.rng.try_fill_bytes(dest) {
+            self.error.get_or_insert_with(|| ErrorSource::new(error));
+        }
+        if self.error.is_some() {
+            for byte in dest.iter_mut() {
+                *byte = 1;
+            }
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl<R: RngCore + CryptoRng> CryptoRng for FallibleRng<'_, R> {}
+
+// Runs `f` with `rng`, drawing only with `try_fill_bytes`, and returns
+// [InternalError::RngError] if any draw failed
+pub(crate) fn try_with_rng<R: RngCore + CryptoRng, T>(
+    rng: &mut R,
+    f: impl FnOnce(&mut FallibleRng<'_, R>) -> Result<T, InternalError>,
+) -> Result<T, InternalError> {
+    let mut rng = FallibleRng { rng, error: None };
+    let result = f(&mut rng);
+    if let Some(source) = rng.error {
+        return Err(InternalError::RngError {
+            source: Some(source),
+        });
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::Ristretto255Sha512, Metadata, VerifiableClient, VerifiableServer};
+    use rand::rngs::OsRng;
+
+    // Produces the same bytes on every draw
+    struct StuckRng;
+
+    impl RngCore for StuckRng {
+        fn next_u32(&mut self) -> u32 {
+            unimplemented!()
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            unimplemented!()
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for (index, byte) in dest.iter_mut().enumerate() {
+                *byte = index as u8;
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    impl CryptoRng for StuckRng {}
+
+    // Fails on every draw
+    struct FailingRng;
+
+    impl RngCore for FailingRng {
+        fn next_u32(&mut self) -> u32 {
+            unimplemented!()
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            unimplemented!()
+        }
+
+        fn fill_bytes(&mut self, _: &mut [u8]) {
+            unimplemented!()
+        }
+
+        fn try_fill_bytes(&mut self, _: &mut [u8]) -> Result<(), Error> {
+            Err(Error::from(
+                NonZeroU32::new(Error::CUSTOM_START).expect("constant is non-zero"),
+            ))
+        }
+    }
+
+    impl CryptoRng for FailingRng {}
+
+    #[test]
+    fn test_fallible_rng() -> Result<(), InternalError> {
+        assert!(matches!(
+            VerifiableServer::<Ristretto255Sha512>::try_new(&mut FailingRng),
+            Err(InternalError::RngError { source: Some(_) })
+        ));
+        assert!(matches!(
+            VerifiableClient::<Ristretto255Sha512>::try_blind(b"input", &mut FailingRng),
+            Err(InternalError::RngError { source: Some(_) })
+        ));
+
+        let server = VerifiableServer::<Ristretto255Sha512>::try_new(&mut OsRng)?;
+        let client_blind_result =
+            VerifiableClient::<Ristretto255Sha512>::try_blind(b"input", &mut OsRng)?;
+        let server_result =
+            server.evaluate(&mut OsRng, client_blind_result.message, &Metadata::none())?;
+        let _ = client_blind_result.state.finalize_once(
+            b"input",
+            server_result.message,
+            server_result.proof,
+            server.get_public_key(),
+            &Metadata::none(),
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_healthy() -> Result<(), InternalError> {
+        let mut rng = HealthCheckedRng::new(OsRng);
+
+        for _ in 0..100 {
+            let server = rng.checked(VerifiableServer::<Ristretto255Sha512>::new)?;
+            let client_blind_result =
+                rng.checked(|rng| VerifiableClient::<Ristretto255Sha512>::blind(b"input", rng))?;
+            let _server_result = rng.checked(|rng| {
+                server.evaluate(rng, client_blind_result.message, &Metadata::none())
+            })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stuck_output() {
+        let mut rng = HealthCheckedRng::new(StuckRng);
+
+        // The first draw can't be distinguished from a healthy one
+        assert!(rng
+            .checked(|rng| VerifiableClient::<Ristretto255Sha512>::blind(b"input", rng))
+            .is_ok());
+        assert!(matches!(
+            rng.checked(|rng| VerifiableClient::<Ristretto255Sha512>::blind(b"input", rng)),
+            Err(InternalError::RngHealthError)
+        ));
+
+        // Failures are sticky
+        let mut bytes = [0; 4];
+        assert!(rng.try_fill_bytes(&mut bytes).is_err());
+        assert!(rng.health().is_err());
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut rng = HealthCheckedRng::new(OsRng);
+        rng.check(&[1, 2, 2, 2, 2, 2, 3]);
+        assert!(rng.health().is_ok());
+        rng.check(&[3, 3, 3, 3, 3]);
+        assert!(matches!(rng.health(), Err(InternalError::RngHealthError)));
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Storage for long-lived secrets, like the private key of a server.
+//!
+//! With the `mlock` feature, every secret is moved into its own page-aligned
+//! heap allocation, which is locked into memory and therefore never written to
+//! swap. Otherwise secrets are stored inline. Either way, they are zeroized on
+//! drop.
+
+use crate::errors::InternalError;
+use core::ops::Deref;
+use zeroize::Zeroize;
+
+/// A secret, see the [module documentation](self)
+pub(crate) struct Secret<T: Zeroize>(Inner<T>);
+
+#[cfg(not(feature = "mlock"))]
+struct Inner<T>(T);
+
+// The allocation is aligned to at least a page, and its size is a multiple of
+// its alignment, so no other secret shares its pages. Otherwise unlocking
+// one secret on drop would unlock the others too.
+#[cfg(feature = "mlock")]
+#[repr(align(4096))]
+struct Page<T>(T);
+
+// The value is zeroized by `Secret`'s `Drop` implementation, then the pages
+// are unlocked, and only then freed
+#[cfg(feature = "mlock")]
+struct Inner<T> {
+    _guard: region::LockGuard,
+    value: alloc::boxed::Box<Page<T>>,
+}
+
+impl<T: Zeroize> Secret<T> {
+    #[cfg(not(feature = "mlock"))]
+    pub(crate) fn new(value: T) -> Result<Self, InternalError> {
+        Ok(Self(Inner(value)))
+    }
+
+    #[cfg(feature = "mlock")]
+    pub(crate) fn new(value: T) -> Result<Self, InternalError> {
+        let mut value = alloc::boxed::Box::new(Page(value));
+
+        match region::lock(&*value, core::mem::size_of::<Page<T>>()) {
+            Ok(guard) => Ok(Self(Inner {
+                _guard: guard,
+                value,
+            })),
+            Err(_) => {
+                // Don't let the secret linger on the heap if it can't be protected
+                value.0.zeroize();
+                Err(InternalError::MemoryLockError)
+            }
+        }
+    }
+}
+
+impl<T: Zeroize> Deref for Secret<T> {
+    type Target = T;
+
+    #[cfg(not(feature = "mlock"))]
+    fn deref(&self) -> &T {
+        &(self.0).0
+    }
+
+    #[cfg(feature = "mlock")]
+    fn deref(&self) -> &T {
+        &self.0.value.0
+    }
+}
+
+impl<T: Zeroize> Zeroize for Secret<T> {
+    #[cfg(not(feature = "mlock"))]
+    fn zeroize(&mut self) {
+        (self.0).0.zeroize()
+    }
+
+    #[cfg(feature = "mlock")]
+    fn zeroize(&mut self) {
+        self.0.value.0.zeroize()
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.zeroize()
+    }
+}
+
+impl<T: Zeroize + Clone> Clone for Secret<T> {
+    /// # Panics
+    ///
+    /// With the `mlock` feature, if the memory of the copy can't be locked
+    fn clone(&self) -> Self {
+        Self::new(T::clone(self)).expect("failed to lock memory")
+    }
+}
+
+impl<T: Zeroize + core::fmt::Debug> core::fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&Redacted(&**self), f)
+    }
+}
+
+/// Formats a secret as `"<redacted>"`, so that it doesn't end up in logs.
+/// With the `danger` feature, the value is formatted instead, for debugging
+/// the library itself.
+pub(crate) struct Redacted<'a, T>(
+    #[cfg_attr(not(feature = "danger"), allow(dead_code))] pub(crate) &'a T,
+);
+
+impl<T: core::fmt::Debug> core::fmt::Debug for Redacted<'_, T> {
+    #[cfg(not(feature = "danger"))]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt("<redacted>", f)
+    }
+
+    #[cfg(feature = "danger")]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        T::fmt(self.0, f)
+    }
+}
+
+impl<T: Zeroize + PartialEq> PartialEq for Secret<T> {
+    fn eq(&self, other: &Self) -> bool {
+        T::eq(self, other)
+    }
+}
+
+impl<T: Zeroize + Eq> Eq for Secret<T> {}
+
+impl<T: Zeroize + core::hash::Hash> core::hash::Hash for Secret<T> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        T::hash(self, state)
+    }
+}
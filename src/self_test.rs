@@ -0,0 +1,224 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! A known-answer test of the whole protocol, to be run before serving traffic
+
+use crate::{
+    ciphersuite::CipherSuite,
+    errors::{ErrorKind, InternalError},
+    group::Group,
+    voprf::{deterministic_blind_unchecked, Mode},
+    BlindedElement, Metadata, VerifiableClient, VerifiableServer,
+};
+use alloc::vec::Vec;
+use generic_array::{typenum::Unsigned, GenericArray};
+
+// The first verifiable mode test vector of a ciphersuite, from the draft
+struct KnownAnswer {
+    suite_id: usize,
+    sk_sm: &'static str,
+    pk_sm: &'static str,
+    input: &'static str,
+    info: &'static str,
+    blind: &'static str,
+    blinded_element: &'static str,
+    evaluation_element: &'static str,
+    proof: &'static str,
+    proof_random_scalar: &'static str,
+    output: &'static str,
+}
+
+static KNOWN_ANSWERS: &[KnownAnswer] = &[
+    // ristretto255, SHA-512
+    KnownAnswer {
+        suite_id: 0x0001,
+        sk_sm: "ac37d5850510299406ea8eb8fa226a7bfc2467a4b070d6c7bf667948b9600b00",
+        pk_sm: "0c0254e22063cae3e1bae02fb6fa20882664a117c0278eda6bda3372c0dd9860",
+        input: "00",
+        info: "7465737420696e666f",
+        blind: "ed8366feb6b1d05d1f46acb727061e43aadfafe9c10e5a64e7518d63e3263503",
+        blinded_element: "3a0a53f2c57e5ee0d89e394087f8e5f95b24159db01c31933a07f0e6414c954d",
+        evaluation_element: "f8a50ed35a477b0cde91d926e1bc5ae59b97d5bd0dda51a728b0f036ec557d79",
+        proof: "7a5375eb1dbad259431f5c294e816a1c1483c279748da1a75d91f8a81438ea08355d4087d4d848b46878dcc8fb5849ac7a09133382c2c6129564a7f7b4b7bf01",
+        proof_random_scalar: "019cbd1d7420292528f8cdd62f339fdabb602f04a95dac9dbcec831b8c681a09",
+        output: "4b2ff4c984985829c3cd9d90c255cdc0d6b61c4c0aafa9215769d51cf7deb01472ba945928a8305e010f12b7dcc75a9dc2460439e6297d57dc2ce7ca0abaae1a",
+    },
+    // P-256, SHA-256
+    KnownAnswer {
+        suite_id: 0x0003,
+        sk_sm: "4e7804245a743c59d624457677294e04a8bc4bdcd94f0d3bd54f568067489d34",
+        pk_sm: "03b51a0af95c819b09ee80c2056cf0ab0551a5355266d3a0aaff90c3fe915ed892",
+        input: "00",
+        info: "7465737420696e666f",
+        blind: "cee64d86fd20ab4caa264a26c0e3d42fb773b3173ba76f9588c9b14779bd8d91",
+        blinded_element: "0222f5dba2da1ec7bd1086d0e04894ef1da1c11163daf376b2bc76cc51edb16815",
+        evaluation_element: "02f2767135f75f69b257675b38f2bcd50338a655a50921663c8942ca61ea7d3c29",
+        proof: "ffa082fc9f9a287e7edc50e3ad879ee13aebd24b69124792bdf047c643f70af2b50907b2fa188b90aff3b25e1d9abb02e9e2c8bfdc525c61ca008428940fca64",
+        proof_random_scalar: "70a5204b2b606f5a28328916e1e5ea5a17862d7a261fdd6d959759758d5e34ac",
+        output: "c74d46cc93e578f7048bc6b852cd9bc1d9ebb90c586308f9202b9deedc894448",
+    },
+];
+
+/// Runs a known-answer test of the protocol in verifiable mode with the given
+/// [CipherSuite]: blinds with a fixed scalar, evaluates with a fixed private
+/// key and proof nonce, finalizes, and compares every message, the proof and
+/// the output against a test vector of the draft. This is meant to be called
+/// at startup, as required by FIPS-style operational policies before a
+/// cryptographic module may serve traffic:
+///
+/// ```
+/// # use voprf::CipherSuite;
+/// # struct Default;
+/// # impl CipherSuite for Default {
+/// #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+/// #     type Hash = sha2::Sha512;
+/// # }
+/// voprf::self_test::<Default>()?;
+/// # Ok::<(), voprf::errors::InternalError>(())
+/// ```
+///
+/// Test vectors are built in for the ciphersuites of the draft, identified by
+/// [Group::SUITE_ID]. Fails with
+/// [InternalError::SelfTestError](crate::errors::InternalError::SelfTestError)
+/// if any value doesn't match, including when the ciphersuite uses a
+/// different hash function than the draft for its group, or if there is no
+/// test vector for the group. Errors caused by a lack of resources, like
+/// [InternalError::MemoryLockError](crate::errors::InternalError::MemoryLockError),
+/// are returned as is.
+pub fn self_test<CS: CipherSuite>() -> Result<(), InternalError> {
+    let known_answer = KNOWN_ANSWERS
+        .iter()
+        .find(|known_answer| known_answer.suite_id == CS::Group::SUITE_ID)
+        .ok_or(InternalError::SelfTestError)?;
+    let input = decode(known_answer.input)?;
+    let metadata = Metadata(decode(known_answer.info)?);
+
+    let server =
+        VerifiableServer::<CS>::new_with_key(&decode(known_answer.sk_sm)?).map_err(failed)?;
+    check(&server.get_public_key().to_arr(), known_answer.pk_sm)?;
+
+    let blind = decode_scalar::<CS>(known_answer.blind)?;
+    let blinded_element =
+        deterministic_blind_unchecked::<CS>(&[&input], &blind, Mode::Verifiable).map_err(failed)?;
+    check(&blinded_element.to_arr(), known_answer.blinded_element)?;
+
+    let server_result = server
+        .evaluate_with_proof_nonce(
+            BlindedElement {
+                value: blinded_element,
+            },
+            &metadata,
+            decode_scalar::<CS>(known_answer.proof_random_scalar)?,
+        )
+        .map_err(failed)?;
+    check(
+        &server_result.message.serialize(),
+        known_answer.evaluation_element,
+    )?;
+    check(&server_result.proof.serialize(), known_answer.proof)?;
+
+    let client = VerifiableClient::<CS> {
+        blind,
+        blinded_element,
+        input_binding: None,
+    };
+    let client_result = client
+        .finalize_once(
+            &input,
+            server_result.message,
+            server_result.proof,
+            server.get_public_key(),
+            &metadata,
+        )
+        .map_err(failed)?;
+    check(&client_result.output, known_answer.output)
+}
+
+// Reports every failure as a failed self-test, except running out of resources
+fn failed(error: InternalError) -> InternalError {
+    match error.kind() {
+        ErrorKind::Resource => error,
+        _ => InternalError::SelfTestError,
+    }
+}
+
+fn check(actual: &[u8], expected: &str) -> Result<(), InternalError> {
+    if actual == decode(expected)?.as_slice() {
+        Ok(())
+    } else {
+        Err(InternalError::SelfTestError)
+    }
+}
+
+fn decode_scalar<CS: CipherSuite>(
+    hex: &str,
+) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    let bytes = decode(hex)?;
+    if bytes.len() != <CS::Group as Group>::ScalarLen::USIZE {
+        return Err(InternalError::SelfTestError);
+    }
+
+    CS::Group::from_scalar_slice(GenericArray::from_slice(&bytes)).map_err(failed)
+}
+
+fn decode(hex: &str) -> Result<Vec<u8>, InternalError> {
+    let digit = |digit: u8| {
+        (digit as char)
+            .to_digit(16)
+            .ok_or(InternalError::SelfTestError)
+    };
+
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => Ok((digit(*high)? << 4 | digit(*low)?) as u8),
+            _ => Err(InternalError::SelfTestError),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test() -> Result<(), InternalError> {
+        use crate::tests::Ristretto255Sha512;
+
+        self_test::<Ristretto255Sha512>()?;
+
+        #[cfg(feature = "p256")]
+        {
+            use crate::tests::P256Sha256;
+
+            self_test::<P256Sha256>()?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_self_test_wrong_hash() {
+        struct Ristretto255Sha256;
+        impl CipherSuite for Ristretto255Sha256 {
+            type Group = curve25519_dalek::ristretto::RistrettoPoint;
+            type Hash = sha2::Sha256;
+        }
+
+        assert!(matches!(
+            self_test::<Ristretto255Sha256>(),
+            Err(InternalError::SelfTestError)
+        ));
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("00ff7a").unwrap(), [0x00, 0xff, 0x7a]);
+        assert!(decode("0").is_err());
+        assert!(decode("zz").is_err());
+    }
+}
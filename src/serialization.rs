@@ -8,29 +8,67 @@
 //! Handles the serialization of each of the components used
 //! in the VOPRF protocol
 
+#[cfg(feature = "privacypass")]
+use crate::privacypass::{
+    token_input, BatchTokenRequest, BatchTokenResponse, PendingToken, Token, TokenRequest,
+    TokenResponse, NK,
+};
 use crate::{
     ciphersuite::CipherSuite,
     errors::InternalError,
     group::Group,
+    key_rotation::KeyRotation,
+    keys::{PrivateKey, PublicKey},
+    modes::NoElement,
+    rfc9497::{
+        OprfClient, OprfServer, PoprfClient as RfcPoprfClient, PoprfServer as RfcPoprfServer,
+        VoprfClient, VoprfServer,
+    },
+    secret::Secret,
     voprf::{
-        BlindedElement, EvaluationElement, NonVerifiableClient, NonVerifiableServer, Proof,
-        VerifiableClient, VerifiableServer,
+        compute_input_binding, BlindedElement, EvaluationElement, Mode, NonVerifiableClient,
+        NonVerifiableServer, PoprfClient, PoprfServer, PreparedEvaluationElement, PreparedTscalar,
+        Proof, SplitKeyProof, SplitKeyServer, VerifiableClient, VerifiableServer,
     },
 };
 use alloc::vec::Vec;
-use generic_array::{typenum::Unsigned, GenericArray};
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+use core::ops::Add;
+#[cfg(feature = "privacypass")]
+use digest::Digest;
+use generic_array::sequence::Concat;
+use generic_array::typenum::{IsLess, Sum, True, Unsigned, U65536};
+use generic_array::{ArrayLength, GenericArray};
 
 //////////////////////////////////////////////////////////
 // Serialization and Deserialization for High-Level API //
 // ==================================================== //
 //////////////////////////////////////////////////////////
 
+/// The layout of a serialized client or server state, to read states
+/// persisted by earlier versions of this crate with the `deserialize_versioned`
+/// functions
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum StateVersion {
+    /// The layout of voprf 0.1.0, which is also the layout of the upstream
+    /// facebook/voprf release it was forked from. Client states end with the
+    /// input instead of the input binding; they are migrated into states bound
+    /// to that input, see [NonVerifiableClient::blind_with_input_binding].
+    /// Server states have the same layout as in the current version.
+    V0_1,
+    /// The layout written by the `serialize` functions of this version
+    Current,
+}
+
 impl<CS: CipherSuite> NonVerifiableClient<CS> {
-    /// Serialization into bytes
+    /// Serialization into bytes, followed by the input binding if the state
+    /// is bound to an input
     pub fn serialize(&self) -> Vec<u8> {
         [
             CS::Group::scalar_as_bytes(self.blind).to_vec(),
-            self.data.clone(),
+            serialize_input_binding::<CS>(&self.input_binding),
         ]
         .concat()
     }
@@ -39,23 +77,61 @@ impl<CS: CipherSuite> NonVerifiableClient<CS> {
     pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
         let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
         if input.len() < scalar_len {
-            return Err(InternalError::SizeError);
+            return Err(InternalError::SizeError {
+                expected: scalar_len,
+                got: input.len(),
+            });
         }
 
         let blind = CS::Group::from_scalar_slice(GenericArray::from_slice(&input[..scalar_len]))?;
-        let data = input[scalar_len..].to_vec();
+        let input_binding = deserialize_input_binding::<CS>(&input[scalar_len..])?;
+
+        Ok(Self {
+            blind,
+            blinded_element: NoElement,
+            input_binding,
+        })
+    }
+
+    /// Deserialization from bytes in the layout of `version`
+    pub fn deserialize_versioned(
+        input: &[u8],
+        version: StateVersion,
+    ) -> Result<Self, InternalError> {
+        match version {
+            StateVersion::V0_1 => {
+                let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
+                if input.len() < scalar_len {
+                    return Err(InternalError::SizeError {
+                        expected: scalar_len,
+                        got: input.len(),
+                    });
+                }
+
+                let blind =
+                    CS::Group::from_scalar_slice(GenericArray::from_slice(&input[..scalar_len]))?;
+                let input_binding =
+                    compute_input_binding::<CS>(&input[scalar_len..], &blind, Mode::Base)?;
 
-        Ok(Self { blind, data })
+                Ok(Self {
+                    blind,
+                    blinded_element: NoElement,
+                    input_binding: Some(input_binding),
+                })
+            }
+            StateVersion::Current => Self::deserialize(input),
+        }
     }
 }
 
 impl<CS: CipherSuite> VerifiableClient<CS> {
-    /// Serialization into bytes
+    /// Serialization into bytes, followed by the input binding if the state
+    /// is bound to an input
     pub fn serialize(&self) -> Vec<u8> {
         [
             CS::Group::scalar_as_bytes(self.blind).to_vec(),
             self.blinded_element.to_arr().to_vec(),
-            self.data.clone(),
+            serialize_input_binding::<CS>(&self.input_binding),
         ]
         .concat()
     }
@@ -65,39 +141,92 @@ impl<CS: CipherSuite> VerifiableClient<CS> {
         let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
         let elem_len = <CS::Group as Group>::ElemLen::USIZE;
         if input.len() < scalar_len + elem_len {
-            return Err(InternalError::SizeError);
+            return Err(InternalError::SizeError {
+                expected: scalar_len + elem_len,
+                got: input.len(),
+            });
         }
 
         let blind = CS::Group::from_scalar_slice(GenericArray::from_slice(&input[..scalar_len]))?;
         let blinded_element = CS::Group::from_element_slice(GenericArray::from_slice(
             &input[scalar_len..scalar_len + elem_len],
         ))?;
-        let data = input[scalar_len + elem_len..].to_vec();
+        let input_binding = deserialize_input_binding::<CS>(&input[scalar_len + elem_len..])?;
 
         Ok(Self {
             blind,
             blinded_element,
-            data,
+            input_binding,
         })
     }
+
+    /// Deserialization from bytes in the layout of `version`
+    pub fn deserialize_versioned(
+        input: &[u8],
+        version: StateVersion,
+    ) -> Result<Self, InternalError> {
+        match version {
+            StateVersion::V0_1 => {
+                let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
+                let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+                if input.len() < scalar_len + elem_len {
+                    return Err(InternalError::SizeError {
+                        expected: scalar_len + elem_len,
+                        got: input.len(),
+                    });
+                }
+
+                let blind =
+                    CS::Group::from_scalar_slice(GenericArray::from_slice(&input[..scalar_len]))?;
+                let blinded_element = CS::Group::from_element_slice(GenericArray::from_slice(
+                    &input[scalar_len..scalar_len + elem_len],
+                ))?;
+                let input_binding = compute_input_binding::<CS>(
+                    &input[scalar_len + elem_len..],
+                    &blind,
+                    Mode::Verifiable,
+                )?;
+
+                Ok(Self {
+                    blind,
+                    blinded_element,
+                    input_binding: Some(input_binding),
+                })
+            }
+            StateVersion::Current => Self::deserialize(input),
+        }
+    }
 }
 
 impl<CS: CipherSuite> NonVerifiableServer<CS> {
     /// Serialization into bytes
     pub fn serialize(&self) -> Vec<u8> {
-        CS::Group::scalar_as_bytes(self.sk).to_vec()
+        CS::Group::scalar_as_bytes(*self.sk).to_vec()
     }
 
     /// Deserialization from bytes
     pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
         let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
         if input.len() != scalar_len {
-            return Err(InternalError::SizeError);
+            return Err(InternalError::SizeError {
+                expected: scalar_len,
+                got: input.len(),
+            });
         }
 
         let sk = CS::Group::from_scalar_slice(GenericArray::from_slice(input))?;
 
-        Ok(Self { sk })
+        Self::from_private_key(sk)
+    }
+
+    /// Deserialization from bytes in the layout of `version`
+    pub fn deserialize_versioned(
+        input: &[u8],
+        version: StateVersion,
+    ) -> Result<Self, InternalError> {
+        match version {
+            StateVersion::V0_1 | StateVersion::Current => Self::deserialize(input),
+        }
     }
 }
 
@@ -105,7 +234,7 @@ impl<CS: CipherSuite> VerifiableServer<CS> {
     /// Serialization into bytes
     pub fn serialize(&self) -> Vec<u8> {
         [
-            CS::Group::scalar_as_bytes(self.sk).to_vec(),
+            CS::Group::scalar_as_bytes(*self.sk).to_vec(),
             self.pk.to_arr().to_vec(),
         ]
         .concat()
@@ -116,13 +245,263 @@ impl<CS: CipherSuite> VerifiableServer<CS> {
         let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
         let elem_len = <CS::Group as Group>::ElemLen::USIZE;
         if input.len() != scalar_len + elem_len {
-            return Err(InternalError::SizeError);
+            return Err(InternalError::SizeError {
+                expected: scalar_len + elem_len,
+                got: input.len(),
+            });
         }
 
         let sk = CS::Group::from_scalar_slice(GenericArray::from_slice(&input[..scalar_len]))?;
         let pk = CS::Group::from_element_slice(GenericArray::from_slice(&input[scalar_len..]))?;
 
-        Ok(Self { sk, pk })
+        if !pk.ct_equal(&(CS::Group::base_point() * &sk)) {
+            return Err(InternalError::InvalidPublicKey);
+        }
+
+        Ok(Self {
+            sk: Secret::new(sk)?,
+            pk,
+        })
+    }
+
+    /// Deserialization from bytes in the layout of `version`
+    pub fn deserialize_versioned(
+        input: &[u8],
+        version: StateVersion,
+    ) -> Result<Self, InternalError> {
+        match version {
+            StateVersion::V0_1 | StateVersion::Current => Self::deserialize(input),
+        }
+    }
+}
+
+impl<CS: CipherSuite> PoprfClient<CS> {
+    /// Serialization into bytes, followed by the input binding if the state
+    /// is bound to an input
+    pub fn serialize(&self) -> Vec<u8> {
+        [
+            CS::Group::scalar_as_bytes(self.blind).to_vec(),
+            self.blinded_element.to_arr().to_vec(),
+            serialize_input_binding::<CS>(&self.input_binding),
+        ]
+        .concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
+        let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+        if input.len() < scalar_len + elem_len {
+            return Err(InternalError::SizeError {
+                expected: scalar_len + elem_len,
+                got: input.len(),
+            });
+        }
+
+        let blind = CS::Group::from_scalar_slice(GenericArray::from_slice(&input[..scalar_len]))?;
+        let blinded_element = CS::Group::from_element_slice(GenericArray::from_slice(
+            &input[scalar_len..scalar_len + elem_len],
+        ))?;
+        let input_binding = deserialize_input_binding::<CS>(&input[scalar_len + elem_len..])?;
+
+        Ok(Self {
+            blind,
+            blinded_element,
+            input_binding,
+        })
+    }
+}
+
+impl<CS: CipherSuite> PoprfServer<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        [
+            CS::Group::scalar_as_bytes(*self.sk).to_vec(),
+            self.pk.to_arr().to_vec(),
+        ]
+        .concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
+        let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+        if input.len() != scalar_len + elem_len {
+            return Err(InternalError::SizeError {
+                expected: scalar_len + elem_len,
+                got: input.len(),
+            });
+        }
+
+        let sk = CS::Group::from_scalar_slice(GenericArray::from_slice(&input[..scalar_len]))?;
+        let pk = CS::Group::from_element_slice(GenericArray::from_slice(&input[scalar_len..]))?;
+
+        if !pk.ct_equal(&(CS::Group::base_point() * &sk)) {
+            return Err(InternalError::InvalidPublicKey);
+        }
+
+        Ok(Self {
+            sk: Secret::new(sk)?,
+            pk,
+        })
+    }
+}
+
+impl<CS: CipherSuite> KeyRotation<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        CS::Group::scalar_as_bytes(*self.delta).to_vec()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
+        if input.len() != scalar_len {
+            return Err(InternalError::SizeError {
+                expected: scalar_len,
+                got: input.len(),
+            });
+        }
+
+        let delta = CS::Group::from_scalar_slice(GenericArray::from_slice(input))?;
+
+        Ok(Self {
+            delta: Secret::new(delta)?,
+        })
+    }
+}
+
+impl<CS: CipherSuite> PrivateKey<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        CS::Group::scalar_as_bytes(*self.value).to_vec()
+    }
+
+    /// Deserialization from bytes, fails if the private key is zero
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
+        if input.len() != scalar_len {
+            return Err(InternalError::SizeError {
+                expected: scalar_len,
+                got: input.len(),
+            });
+        }
+
+        Self::from_scalar(CS::Group::from_scalar_slice(GenericArray::from_slice(
+            input,
+        ))?)
+    }
+}
+
+impl<CS: CipherSuite> PublicKey<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        self.value.to_arr().to_vec()
+    }
+
+    /// Deserialization from bytes, fails if the public key is the identity
+    /// element
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+        if input.len() != elem_len {
+            return Err(InternalError::SizeError {
+                expected: elem_len,
+                got: input.len(),
+            });
+        }
+
+        Ok(Self {
+            value: CS::Group::from_element_slice(GenericArray::from_slice(input))?,
+        })
+    }
+}
+
+impl<CS: CipherSuite> SplitKeyServer<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        [
+            CS::Group::scalar_as_bytes(*self.t).to_vec(),
+            self.base.to_arr().to_vec(),
+            self.pk.to_arr().to_vec(),
+            alloc::vec![self.mode as u8],
+        ]
+        .concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
+        let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+        if input.len() != scalar_len + 2 * elem_len + 1 {
+            return Err(InternalError::SizeError {
+                expected: scalar_len + 2 * elem_len + 1,
+                got: input.len(),
+            });
+        }
+
+        let t = CS::Group::from_scalar_slice(GenericArray::from_slice(&input[..scalar_len]))?;
+        let base = CS::Group::from_element_slice(GenericArray::from_slice(
+            &input[scalar_len..scalar_len + elem_len],
+        ))?;
+        let pk = CS::Group::from_element_slice(GenericArray::from_slice(
+            &input[scalar_len + elem_len..scalar_len + 2 * elem_len],
+        ))?;
+        let mode = match input[scalar_len + 2 * elem_len] {
+            0 => Mode::Base,
+            1 => Mode::Verifiable,
+            _ => return Err(InternalError::SerializationError),
+        };
+
+        if !pk.ct_equal(&(base * &t)) {
+            return Err(InternalError::InvalidPublicKey);
+        }
+
+        Ok(Self {
+            t: Secret::new(t)?,
+            base,
+            pk,
+            mode,
+        })
+    }
+}
+
+impl<CS: CipherSuite> SplitKeyProof<CS> {
+    /// Serialization into bytes, the proofs of both shares followed by the
+    /// partial elements
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut output = [self.first.serialize(), self.second.serialize()].concat();
+        for partial_element in &self.partial_elements {
+            output.extend_from_slice(&partial_element.value.to_arr());
+        }
+        output
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let proof_len = 2 * <CS::Group as Group>::ScalarLen::USIZE;
+        let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+        if input.len() < 2 * proof_len {
+            return Err(InternalError::SizeError {
+                expected: 2 * proof_len,
+                got: input.len(),
+            });
+        }
+
+        let partial_elements = input[2 * proof_len..].chunks_exact(elem_len);
+        if !partial_elements.remainder().is_empty() {
+            return Err(InternalError::SizeError {
+                expected: input.len() - partial_elements.remainder().len(),
+                got: input.len(),
+            });
+        }
+
+        Ok(Self {
+            first: Proof::deserialize(&input[..proof_len])?,
+            second: Proof::deserialize(&input[proof_len..2 * proof_len])?,
+            partial_elements: partial_elements
+                .map(EvaluationElement::deserialize)
+                .collect::<Result<_, _>>()?,
+        })
     }
 }
 
@@ -136,15 +515,22 @@ impl<CS: CipherSuite> Proof<CS> {
         .concat()
     }
 
-    /// Deserialization from bytes
+    /// Deserialization from bytes, fails with
+    /// [InternalError::InvalidProofError] if either scalar is not canonically
+    /// encoded. Unlike other scalars, zero is accepted: a valid proof can
+    /// contain it, and a forged one still fails verification, which
+    /// recomputes the challenge.
     pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
         let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
-        if input.len() < scalar_len + scalar_len {
-            return Err(InternalError::SizeError);
+        if input.len() != scalar_len + scalar_len {
+            return Err(InternalError::SizeError {
+                expected: scalar_len + scalar_len,
+                got: input.len(),
+            });
         }
         Ok(Proof {
-            c_scalar: CS::Group::from_scalar_slice(GenericArray::from_slice(&input[..scalar_len]))?,
-            s_scalar: CS::Group::from_scalar_slice(GenericArray::from_slice(&input[scalar_len..]))?,
+            c_scalar: deserialize_proof_scalar::<CS>(&input[..scalar_len])?,
+            s_scalar: deserialize_proof_scalar::<CS>(&input[scalar_len..])?,
         })
     }
 }
@@ -157,6 +543,14 @@ impl<CS: CipherSuite> BlindedElement<CS> {
 
     /// Deserialization from bytes
     pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+        if input.len() != elem_len {
+            return Err(InternalError::SizeError {
+                expected: elem_len,
+                got: input.len(),
+            });
+        }
+
         Ok(Self {
             value: CS::Group::from_element_slice(GenericArray::from_slice(input))?,
         })
@@ -171,58 +565,1198 @@ impl<CS: CipherSuite> EvaluationElement<CS> {
 
     /// Deserialization from bytes
     pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+        if input.len() != elem_len {
+            return Err(InternalError::SizeError {
+                expected: elem_len,
+                got: input.len(),
+            });
+        }
+
+        Ok(Self {
+            value: CS::Group::from_element_slice(GenericArray::from_slice(input))?,
+        })
+    }
+}
+
+impl<CS: CipherSuite> BlindedElement<CS> {
+    /// Serialization of a batch into a TLS `opaque<0..2^16-1>` vector: the
+    /// concatenated elements prefixed by their total length in two bytes, as
+    /// carried by Privacy Pass. Fails with [InternalError::SerializationError]
+    /// if the batch is longer than that.
+    pub fn serialize_batch(elements: &[Self]) -> Result<Vec<u8>, InternalError> {
+        check_batch_fits::<CS>(elements.len())?;
+        Ok(serialize_batch(elements.iter().map(Self::serialize)))
+    }
+
+    /// Deserialization of a batch from bytes written by
+    /// [BlindedElement::serialize_batch]
+    pub fn deserialize_batch(input: &[u8]) -> Result<Vec<Self>, InternalError> {
+        let (elements, rest) = deserialize_batch::<CS, _, _>(input, Self::deserialize)?;
+        if !rest.is_empty() {
+            return Err(InternalError::SizeError {
+                expected: input.len() - rest.len(),
+                got: input.len(),
+            });
+        }
+
+        Ok(elements)
+    }
+}
+
+/// A view of concatenated [BlindedElement]s, e.g. of a large issuance
+/// request, which decodes them lazily instead of collecting them into a
+/// [Vec]. The length of the buffer is checked once when the view is created,
+/// each element is decoded when it is visited.
+///
+/// ```
+/// # use voprf::CipherSuite;
+/// # struct Default;
+/// # impl CipherSuite for Default {
+/// #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+/// #     type Hash = sha2::Sha512;
+/// # }
+/// use rand::rngs::OsRng;
+/// use voprf::{BlindedElement, BlindedElementSlice, VerifiableClient};
+///
+/// let messages = [
+///     VerifiableClient::<Default>::blind(b"input 1", &mut OsRng)?.message,
+///     VerifiableClient::<Default>::blind(b"input 2", &mut OsRng)?.message,
+/// ];
+/// let request = BlindedElement::serialize_batch(&messages)?;
+///
+/// let view = BlindedElementSlice::<Default>::from_batch(&request)?;
+/// assert_eq!(view.len(), 2);
+/// for (message, expected) in view.iter().zip(&messages) {
+///     assert_eq!(&message?, expected);
+/// }
+/// # Ok::<(), voprf::errors::InternalError>(())
+/// ```
+pub struct BlindedElementSlice<'a, CS: CipherSuite> {
+    bytes: &'a [u8],
+    marker: PhantomData<CS>,
+}
+
+impl<CS: CipherSuite> Clone for BlindedElementSlice<'_, CS> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<CS: CipherSuite> Copy for BlindedElementSlice<'_, CS> {}
+
+impl<CS: CipherSuite> core::fmt::Debug for BlindedElementSlice<'_, CS> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BlindedElementSlice")
+            .field("bytes", &self.bytes)
+            .finish()
+    }
+}
+
+impl<'a, CS: CipherSuite> BlindedElementSlice<'a, CS> {
+    /// Views `bytes` as concatenated elements, fails with
+    /// [InternalError::SizeError] if its length isn't a multiple of the
+    /// length of an element
+    pub fn new(bytes: &'a [u8]) -> Result<Self, InternalError> {
+        let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+        let remainder = bytes.len() % elem_len;
+        if remainder != 0 {
+            return Err(InternalError::SizeError {
+                expected: bytes.len() - remainder,
+                got: bytes.len(),
+            });
+        }
+
+        Ok(Self {
+            bytes,
+            marker: PhantomData,
+        })
+    }
+
+    /// Views the elements of a batch written by
+    /// [BlindedElement::serialize_batch]
+    pub fn from_batch(input: &'a [u8]) -> Result<Self, InternalError> {
+        if input.len() < 2 {
+            return Err(InternalError::SizeError {
+                expected: 2,
+                got: input.len(),
+            });
+        }
+        let len = usize::from(u16::from_be_bytes([input[0], input[1]]));
+        if input.len() != 2 + len {
+            return Err(InternalError::SizeError {
+                expected: 2 + len,
+                got: input.len(),
+            });
+        }
+
+        Self::new(&input[2..])
+    }
+
+    /// The number of elements
+    pub fn len(&self) -> usize {
+        self.bytes.len() / <CS::Group as Group>::ElemLen::USIZE
+    }
+
+    /// Returns `true` if there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Decodes the element at `index`, if there is one
+    pub fn get(&self, index: usize) -> Option<Result<BlindedElement<CS>, InternalError>> {
+        self.bytes
+            .chunks(<CS::Group as Group>::ElemLen::USIZE)
+            .nth(index)
+            .map(BlindedElement::deserialize)
+    }
+
+    /// Decodes the elements in order, failing for each element that isn't
+    /// valid
+    pub fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = Result<BlindedElement<CS>, InternalError>>
+           + ExactSizeIterator
+           + 'a
+    where
+        CS: 'a,
+    {
+        self.bytes
+            .chunks(<CS::Group as Group>::ElemLen::USIZE)
+            .map(BlindedElement::deserialize)
+    }
+
+    /// The viewed bytes
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+impl<CS: CipherSuite> EvaluationElement<CS> {
+    /// Serialization of a batch and its proof: the elements as in
+    /// [BlindedElement::serialize_batch], followed by the proof
+    pub fn serialize_batch(elements: &[Self], proof: &Proof<CS>) -> Result<Vec<u8>, InternalError> {
+        check_batch_fits::<CS>(elements.len())?;
+        Ok([
+            serialize_batch(elements.iter().map(Self::serialize)),
+            proof.serialize(),
+        ]
+        .concat())
+    }
+
+    /// Deserialization of a batch and its proof from bytes written by
+    /// [EvaluationElement::serialize_batch]
+    pub fn deserialize_batch(input: &[u8]) -> Result<(Vec<Self>, Proof<CS>), InternalError> {
+        let (elements, rest) = deserialize_batch::<CS, _, _>(input, Self::deserialize)?;
+
+        Ok((elements, Proof::deserialize(rest)?))
+    }
+}
+
+impl<CS: CipherSuite> PreparedEvaluationElement<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        self.value.to_arr().to_vec()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+        if input.len() != elem_len {
+            return Err(InternalError::SizeError {
+                expected: elem_len,
+                got: input.len(),
+            });
+        }
+
         Ok(Self {
             value: CS::Group::from_element_slice(GenericArray::from_slice(input))?,
         })
     }
 }
 
+impl<CS: CipherSuite> PreparedTscalar<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        CS::Group::scalar_as_bytes(self.t).to_vec()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
+        if input.len() != scalar_len {
+            return Err(InternalError::SizeError {
+                expected: scalar_len,
+                got: input.len(),
+            });
+        }
+
+        Ok(Self {
+            t: CS::Group::from_scalar_slice(GenericArray::from_slice(input))?,
+        })
+    }
+}
+
+// Conversions mirroring `deserialize`, so the types compose with generic
+// codecs
+macro_rules! impl_try_from_bytes_for {
+    ($($t:ident),+$(,)?) => {
+        $(
+            impl<CS: CipherSuite> TryFrom<&[u8]> for $t<CS> {
+                type Error = InternalError;
+
+                fn try_from(input: &[u8]) -> Result<Self, Self::Error> {
+                    Self::deserialize(input)
+                }
+            }
+        )+
+    };
+}
+
+impl_try_from_bytes_for!(
+    BlindedElement,
+    EvaluationElement,
+    Proof,
+    NonVerifiableClient,
+    VerifiableClient,
+    PoprfClient,
+    NonVerifiableServer,
+    VerifiableServer,
+    PoprfServer,
+);
+
+// Conversions mirroring `serialize`, for the messages of a fixed length
+macro_rules! impl_from_element_for_array {
+    ($($t:ident),+$(,)?) => {
+        $(
+            impl<CS: CipherSuite> From<&$t<CS>>
+                for GenericArray<u8, <CS::Group as Group>::ElemLen>
+            {
+                fn from(message: &$t<CS>) -> Self {
+                    message.value.to_arr()
+                }
+            }
+        )+
+    };
+}
+
+impl_from_element_for_array!(BlindedElement, EvaluationElement);
+
+impl<CS: CipherSuite> From<&Proof<CS>>
+    for GenericArray<u8, Sum<<CS::Group as Group>::ScalarLen, <CS::Group as Group>::ScalarLen>>
+where
+    <CS::Group as Group>::ScalarLen: Add<<CS::Group as Group>::ScalarLen>,
+    Sum<<CS::Group as Group>::ScalarLen, <CS::Group as Group>::ScalarLen>: ArrayLength<u8>,
+{
+    fn from(proof: &Proof<CS>) -> Self {
+        CS::Group::scalar_as_bytes(proof.c_scalar)
+            .concat(CS::Group::scalar_as_bytes(proof.s_scalar))
+    }
+}
+
+// Streaming into an `io::Write` and from an `io::Read`. The components are
+// written one by one instead of being collected by `serialize` first. Types of
+// a fixed length read exactly their length, client states are read to the end
+// of the reader, as they may or may not end with an input binding.
+macro_rules! impl_io_for {
+    ($t:ident, |$value:ident| [$($part:expr),+$(,)?], $($len:tt)+) => {
+        #[cfg(feature = "std")]
+        impl<CS: CipherSuite> $t<CS> {
+            /// Serialization into `writer`, writing the same bytes as
+            /// `serialize`
+            pub fn write_to<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                let $value = self;
+                $(writer.write_all(&$part)?;)+
+                Ok(())
+            }
+
+            /// Deserialization from `reader`, see `write_to`. Errors of
+            /// `deserialize` are returned as [std::io::ErrorKind::InvalidData]
+            pub fn read_from<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+                let input = impl_io_for!(@read reader, $($len)+);
+                Self::deserialize(&input)
+                    .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+            }
+        }
+    };
+    (@read $reader:ident, to_end) => {{
+        let mut input = zeroize::Zeroizing::new(Vec::new());
+        $reader.read_to_end(&mut input)?;
+        input
+    }};
+    (@read $reader:ident, $len:expr) => {{
+        let mut input = zeroize::Zeroizing::new(alloc::vec![0; $len]);
+        $reader.read_exact(&mut input)?;
+        input
+    }};
+}
+
+impl_io_for!(
+    BlindedElement,
+    |message| [message.value.to_arr()],
+    <CS::Group as Group>::ElemLen::USIZE
+);
+impl_io_for!(
+    EvaluationElement,
+    |message| [message.value.to_arr()],
+    <CS::Group as Group>::ElemLen::USIZE
+);
+impl_io_for!(
+    Proof,
+    |proof| [
+        CS::Group::scalar_as_bytes(proof.c_scalar),
+        CS::Group::scalar_as_bytes(proof.s_scalar),
+    ],
+    2 * <CS::Group as Group>::ScalarLen::USIZE
+);
+impl_io_for!(
+    PrivateKey,
+    |sk| [CS::Group::scalar_as_bytes(*sk.value)],
+    <CS::Group as Group>::ScalarLen::USIZE
+);
+impl_io_for!(
+    PublicKey,
+    |pk| [pk.value.to_arr()],
+    <CS::Group as Group>::ElemLen::USIZE
+);
+impl_io_for!(
+    NonVerifiableServer,
+    |server| [CS::Group::scalar_as_bytes(*server.sk)],
+    <CS::Group as Group>::ScalarLen::USIZE
+);
+impl_io_for!(
+    VerifiableServer,
+    |server| [CS::Group::scalar_as_bytes(*server.sk), server.pk.to_arr()],
+    <CS::Group as Group>::ScalarLen::USIZE + <CS::Group as Group>::ElemLen::USIZE
+);
+impl_io_for!(
+    PoprfServer,
+    |server| [CS::Group::scalar_as_bytes(*server.sk), server.pk.to_arr()],
+    <CS::Group as Group>::ScalarLen::USIZE + <CS::Group as Group>::ElemLen::USIZE
+);
+impl_io_for!(
+    NonVerifiableClient,
+    |client| [
+        CS::Group::scalar_as_bytes(client.blind),
+        serialize_input_binding::<CS>(&client.input_binding),
+    ],
+    to_end
+);
+impl_io_for!(
+    VerifiableClient,
+    |client| [
+        CS::Group::scalar_as_bytes(client.blind),
+        client.blinded_element.to_arr(),
+        serialize_input_binding::<CS>(&client.input_binding),
+    ],
+    to_end
+);
+impl_io_for!(
+    PoprfClient,
+    |client| [
+        CS::Group::scalar_as_bytes(client.blind),
+        client.blinded_element.to_arr(),
+        serialize_input_binding::<CS>(&client.input_binding),
+    ],
+    to_end
+);
+
+//////////////////////////////////////////////////////////
+// Serialization and Deserialization for the RFC 9497 API //
+// ====================================================== //
+//////////////////////////////////////////////////////////
+
+impl<CS: CipherSuite> OprfClient<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        CS::Group::scalar_as_bytes(self.blind).to_vec()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
+        if input.len() != scalar_len {
+            return Err(InternalError::SizeError {
+                expected: scalar_len,
+                got: input.len(),
+            });
+        }
+
+        let blind = CS::Group::from_scalar_slice(GenericArray::from_slice(input))?;
+
+        Ok(Self { blind })
+    }
+}
+
+macro_rules! impl_serialization_for_blinded_client {
+    ($name:ident) => {
+        impl<CS: CipherSuite> $name<CS> {
+            /// Serialization into bytes
+            pub fn serialize(&self) -> Vec<u8> {
+                [
+                    CS::Group::scalar_as_bytes(self.blind).to_vec(),
+                    self.blinded_element.to_arr().to_vec(),
+                ]
+                .concat()
+            }
+
+            /// Deserialization from bytes
+            pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+                let (blind, blinded_element) = deserialize_scalar_and_element::<CS>(input)?;
+
+                Ok(Self {
+                    blind,
+                    blinded_element,
+                })
+            }
+        }
+    };
+}
+
+impl_serialization_for_blinded_client!(VoprfClient);
+impl_serialization_for_blinded_client!(RfcPoprfClient);
+
+impl<CS: CipherSuite> OprfServer<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        CS::Group::scalar_as_bytes(*self.sk).to_vec()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
+        if input.len() != scalar_len {
+            return Err(InternalError::SizeError {
+                expected: scalar_len,
+                got: input.len(),
+            });
+        }
+
+        let sk = CS::Group::from_scalar_slice(GenericArray::from_slice(input))?;
+
+        Ok(Self {
+            sk: Secret::new(sk)?,
+        })
+    }
+}
+
+macro_rules! impl_serialization_for_keyed_server {
+    ($name:ident) => {
+        impl<CS: CipherSuite> $name<CS> {
+            /// Serialization into bytes
+            pub fn serialize(&self) -> Vec<u8> {
+                [
+                    CS::Group::scalar_as_bytes(*self.sk).to_vec(),
+                    self.pk.to_arr().to_vec(),
+                ]
+                .concat()
+            }
+
+            /// Deserialization from bytes
+            pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+                let (sk, pk) = deserialize_scalar_and_element::<CS>(input)?;
+
+                if !pk.ct_equal(&(CS::Group::base_point() * &sk)) {
+                    return Err(InternalError::InvalidPublicKey);
+                }
+
+                Ok(Self {
+                    sk: Secret::new(sk)?,
+                    pk,
+                })
+            }
+        }
+    };
+}
+
+impl_serialization_for_keyed_server!(VoprfServer);
+impl_serialization_for_keyed_server!(RfcPoprfServer);
+
+////////////////////////////////////////////////////////
+// Serialization and Deserialization for Privacy Pass //
+// ================================================== //
+////////////////////////////////////////////////////////
+
+#[cfg(feature = "privacypass")]
+impl<CS: CipherSuite> TokenRequest<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        [
+            &self.token_type.to_be_bytes()[..],
+            &[self.truncated_token_key_id],
+            &self.blinded_msg.serialize(),
+        ]
+        .concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        if input.len() < 3 {
+            return Err(InternalError::SizeError {
+                expected: 3,
+                got: input.len(),
+            });
+        }
+
+        Ok(Self {
+            token_type: u16::from_be_bytes([input[0], input[1]]),
+            truncated_token_key_id: input[2],
+            blinded_msg: BlindedElement::deserialize(&input[3..])?,
+        })
+    }
+}
+
+#[cfg(feature = "privacypass")]
+impl<CS: CipherSuite> TokenResponse<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        [
+            self.evaluate_msg.serialize(),
+            self.evaluate_proof.serialize(),
+        ]
+        .concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+        if input.len() < elem_len {
+            return Err(InternalError::SizeError {
+                expected: elem_len,
+                got: input.len(),
+            });
+        }
+
+        Ok(Self {
+            evaluate_msg: EvaluationElement::deserialize(&input[..elem_len])?,
+            evaluate_proof: Proof::deserialize(&input[elem_len..])?,
+        })
+    }
+}
+
+#[cfg(feature = "privacypass")]
+impl<CS: CipherSuite> BatchTokenRequest<CS> {
+    /// Serialization into bytes, with the blinded elements prefixed by their
+    /// length
+    pub fn serialize(&self) -> Vec<u8> {
+        [
+            &self.token_type.to_be_bytes()[..],
+            &[self.truncated_token_key_id],
+            &serialize_batch(self.blinded_elements.iter().map(BlindedElement::serialize)),
+        ]
+        .concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        if input.len() < 3 {
+            return Err(InternalError::SizeError {
+                expected: 3,
+                got: input.len(),
+            });
+        }
+        let (blinded_elements, rest) =
+            deserialize_batch::<CS, _, _>(&input[3..], BlindedElement::deserialize)?;
+        if !rest.is_empty() {
+            return Err(InternalError::SizeError {
+                expected: input.len() - rest.len(),
+                got: input.len(),
+            });
+        }
+
+        Ok(Self {
+            token_type: u16::from_be_bytes([input[0], input[1]]),
+            truncated_token_key_id: input[2],
+            blinded_elements,
+        })
+    }
+}
+
+#[cfg(feature = "privacypass")]
+impl<CS: CipherSuite> BatchTokenResponse<CS> {
+    /// Serialization into bytes, with the evaluated elements prefixed by their
+    /// length
+    pub fn serialize(&self) -> Vec<u8> {
+        [
+            serialize_batch(
+                self.evaluated_elements
+                    .iter()
+                    .map(EvaluationElement::serialize),
+            ),
+            self.evaluated_proof.serialize(),
+        ]
+        .concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let (evaluated_elements, rest) =
+            deserialize_batch::<CS, _, _>(input, EvaluationElement::deserialize)?;
+
+        Ok(Self {
+            evaluated_elements,
+            evaluated_proof: Proof::deserialize(rest)?,
+        })
+    }
+}
+
+#[cfg(feature = "privacypass")]
+impl<CS: CipherSuite> Token<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        [
+            &token_input(
+                self.token_type,
+                &self.nonce,
+                &self.challenge_digest,
+                &self.token_key_id,
+            ),
+            &self.authenticator[..],
+        ]
+        .concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        let hash_len = <CS::Hash as Digest>::OutputSize::USIZE;
+        if input.len() != TOKEN_INPUT_LEN + hash_len {
+            return Err(InternalError::SizeError {
+                expected: TOKEN_INPUT_LEN + hash_len,
+                got: input.len(),
+            });
+        }
+        let (token_type, nonce, challenge_digest, token_key_id) =
+            deserialize_token_input_fields(&input[..TOKEN_INPUT_LEN]);
+
+        Ok(Self {
+            token_type,
+            nonce,
+            challenge_digest,
+            token_key_id,
+            authenticator: GenericArray::clone_from_slice(&input[TOKEN_INPUT_LEN..]),
+        })
+    }
+}
+
+#[cfg(feature = "privacypass")]
+impl<CS: CipherSuite> PendingToken<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        [
+            token_input(
+                self.token_type,
+                &self.nonce,
+                &self.challenge_digest,
+                &self.token_key_id,
+            ),
+            self.client.serialize(),
+        ]
+        .concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        if input.len() < TOKEN_INPUT_LEN {
+            return Err(InternalError::SizeError {
+                expected: TOKEN_INPUT_LEN,
+                got: input.len(),
+            });
+        }
+        let (token_type, nonce, challenge_digest, token_key_id) =
+            deserialize_token_input_fields(&input[..TOKEN_INPUT_LEN]);
+
+        Ok(Self {
+            token_type,
+            nonce,
+            challenge_digest,
+            token_key_id,
+            client: VoprfClient::deserialize(&input[TOKEN_INPUT_LEN..])?,
+        })
+    }
+}
+
+///////////////////////////////////////////
+// Suite-Tagged Serialization of Messages //
+// ====================================== //
+///////////////////////////////////////////
+
+/// The version of the envelope written by the `serialize_tagged` functions
+pub const TAGGED_FORMAT_VERSION: u8 = 1;
+
+macro_rules! impl_tagged_serialization_for {
+    ($t:ident) => {
+        impl<CS: CipherSuite> $t<CS> {
+            /// Serialization into bytes, prefixed with
+            /// [TAGGED_FORMAT_VERSION] and the 2-byte big-endian
+            /// [SUITE_ID](CipherSuite::SUITE_ID) of the ciphersuite, so that a
+            /// peer using another group or hash rejects it instead of
+            /// misreading it
+            pub fn serialize_tagged(&self) -> Result<Vec<u8>, InternalError> {
+                Ok([
+                    &[TAGGED_FORMAT_VERSION][..],
+                    &i2osp_2(CS::SUITE_ID)?,
+                    &self.serialize(),
+                ]
+                .concat())
+            }
+
+            /// Deserialization from bytes written by `serialize_tagged`,
+            /// fails with [InternalError::SuiteMismatch] if they were written
+            /// with another ciphersuite, and with
+            /// [InternalError::SerializationError] for an unknown format
+            /// version
+            pub fn deserialize_tagged(input: &[u8]) -> Result<Self, InternalError> {
+                Self::deserialize(check_tag::<CS>(input)?)
+            }
+        }
+    };
+}
+
+impl_tagged_serialization_for!(BlindedElement);
+impl_tagged_serialization_for!(EvaluationElement);
+impl_tagged_serialization_for!(Proof);
+impl_tagged_serialization_for!(PublicKey);
+
+// Returns the bytes following the envelope
+fn check_tag<CS: CipherSuite>(input: &[u8]) -> Result<&[u8], InternalError> {
+    if input.len() < 3 {
+        return Err(InternalError::SizeError {
+            expected: 3,
+            got: input.len(),
+        });
+    }
+    if input[0] != TAGGED_FORMAT_VERSION {
+        return Err(InternalError::SerializationError);
+    }
+    if input[1..3] != i2osp_2(CS::SUITE_ID)? {
+        return Err(InternalError::SuiteMismatch);
+    }
+
+    Ok(&input[3..])
+}
+
 //////////////////////
 // Helper Functions //
 // ================ //
 //////////////////////
 
-// Corresponds to the I2OSP() function from RFC8017
-pub(crate) fn i2osp(input: usize, length: usize) -> Result<alloc::vec::Vec<u8>, InternalError> {
-    let sizeof_usize = core::mem::size_of::<usize>();
+// Deserializes a nonzero scalar followed by an element other than the identity
+fn deserialize_scalar_and_element<CS: CipherSuite>(
+    input: &[u8],
+) -> Result<(<CS::Group as Group>::Scalar, CS::Group), InternalError> {
+    let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
+    let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+    if input.len() != scalar_len + elem_len {
+        return Err(InternalError::SizeError {
+            expected: scalar_len + elem_len,
+            got: input.len(),
+        });
+    }
 
-    // Check if input >= 256^length
-    if (sizeof_usize as u32 - input.leading_zeros() / 8) > length as u32 {
-        return Err(InternalError::SerializationError);
+    let scalar = CS::Group::from_scalar_slice(GenericArray::from_slice(&input[..scalar_len]))?;
+    let element = CS::Group::from_element_slice(GenericArray::from_slice(&input[scalar_len..]))?;
+
+    Ok((scalar, element))
+}
+
+// The scalars of a proof are a hash output and a value derived from a random
+// nonce, both of which can be zero, so unlike keys and blinds they are only
+// checked for a canonical encoding
+fn deserialize_proof_scalar<CS: CipherSuite>(
+    input: &[u8],
+) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    let input = GenericArray::from_slice(input);
+    let scalar = CS::Group::from_scalar_slice_unchecked(input)
+        .map_err(|_| InternalError::InvalidProofError)?;
+
+    // Reject any encoding which doesn't round-trip, e.g. values larger than
+    // the group order, like `Group::from_scalar_slice`
+    if &CS::Group::scalar_as_bytes(scalar) != input {
+        return Err(InternalError::InvalidProofError);
+    }
+
+    Ok(scalar)
+}
+
+// The input binding of a client state is either absent, or a scalar
+fn serialize_input_binding<CS: CipherSuite>(
+    input_binding: &Option<<CS::Group as Group>::Scalar>,
+) -> Vec<u8> {
+    match input_binding {
+        Some(input_binding) => CS::Group::scalar_as_bytes(*input_binding).to_vec(),
+        None => Vec::new(),
+    }
+}
+
+fn deserialize_input_binding<CS: CipherSuite>(
+    input: &[u8],
+) -> Result<Option<<CS::Group as Group>::Scalar>, InternalError> {
+    if input.is_empty() {
+        return Ok(None);
     }
+    if input.len() != <CS::Group as Group>::ScalarLen::USIZE {
+        return Err(InternalError::SizeError {
+            expected: <CS::Group as Group>::ScalarLen::USIZE,
+            got: input.len(),
+        });
+    }
+
+    CS::Group::from_scalar_slice(GenericArray::from_slice(input)).map(Some)
+}
+
+// The length of the token type, nonce, challenge digest and key ID of a
+// Privacy Pass token
+#[cfg(feature = "privacypass")]
+const TOKEN_INPUT_LEN: usize = 2 + 3 * NK;
+
+// The input has to be exactly `TOKEN_INPUT_LEN` long
+#[cfg(feature = "privacypass")]
+fn deserialize_token_input_fields(input: &[u8]) -> (u16, [u8; NK], [u8; NK], [u8; NK]) {
+    let field = |offset: usize| {
+        let mut field = [0; NK];
+        field.copy_from_slice(&input[offset..offset + NK]);
+        field
+    };
+
+    (
+        u16::from_be_bytes([input[0], input[1]]),
+        field(2),
+        field(2 + NK),
+        field(2 + 2 * NK),
+    )
+}
+
+// The elements of a batch are prefixed by their total length in two bytes, as
+// a TLS `opaque<0..2^16-1>` vector. Privacy Pass batches are checked to fit
+// when they are created, see `check_batch_len`.
+fn serialize_batch(elements: impl Iterator<Item = Vec<u8>>) -> Vec<u8> {
+    let elements: Vec<u8> = elements.flatten().collect();
+    [&i2osp_2(elements.len()).unwrap_or_default()[..], &elements].concat()
+}
+
+fn check_batch_fits<CS: CipherSuite>(len: usize) -> Result<(), InternalError> {
+    len.checked_mul(<CS::Group as Group>::ElemLen::USIZE)
+        .map_or(Err(InternalError::SerializationError), i2osp_2)
+        .map(|_| ())
+}
 
-    if length <= sizeof_usize {
-        return Ok((&input.to_be_bytes()[sizeof_usize - length..]).to_vec());
+// Returns the elements and the bytes following them
+fn deserialize_batch<CS: CipherSuite, T, F: Fn(&[u8]) -> Result<T, InternalError>>(
+    input: &[u8],
+    deserialize: F,
+) -> Result<(Vec<T>, &[u8]), InternalError> {
+    let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+    if input.len() < 2 {
+        return Err(InternalError::SizeError {
+            expected: 2,
+            got: input.len(),
+        });
+    }
+    let len = usize::from(u16::from_be_bytes([input[0], input[1]]));
+    if len % elem_len != 0 {
+        return Err(InternalError::SizeError {
+            expected: 2 + len - len % elem_len,
+            got: 2 + len,
+        });
     }
+    if input.len() < 2 + len {
+        return Err(InternalError::SizeError {
+            expected: 2 + len,
+            got: input.len(),
+        });
+    }
+
+    let elements = input[2..2 + len]
+        .chunks(elem_len)
+        .map(deserialize)
+        .collect::<Result<_, _>>()?;
+    Ok((elements, &input[2 + len..]))
+}
 
-    let mut output = alloc::vec![0u8; length];
-    output.splice(
-        length - sizeof_usize..length,
-        input.to_be_bytes().iter().cloned(),
-    );
-    Ok(output)
+// Corresponds to the I2OSP() function from RFC8017, with an output length of 1
+pub(crate) fn i2osp_1(input: usize) -> Result<[u8; 1], InternalError> {
+    u8::try_from(input)
+        .map(|input| input.to_be_bytes())
+        .map_err(|_| InternalError::SerializationError)
 }
 
-// Computes I2OSP(len(input), max_bytes) || input
-pub(crate) fn serialize(input: &[u8], max_bytes: usize) -> Result<Vec<u8>, InternalError> {
-    Ok([&i2osp(input.len(), max_bytes)?, input].concat())
+// Corresponds to the I2OSP() function from RFC8017, with an output length of 2
+pub(crate) fn i2osp_2(input: usize) -> Result<[u8; 2], InternalError> {
+    u16::try_from(input)
+        .map(|input| input.to_be_bytes())
+        .map_err(|_| InternalError::SerializationError)
+}
+
+// Corresponds to the I2OSP() function from RFC8017, with an output length of 2,
+// for a length which is guaranteed to fit at compile time
+pub(crate) fn i2osp_2_len<L: Unsigned + IsLess<U65536, Output = True>>() -> [u8; 2] {
+    L::U16.to_be_bytes()
 }
 
 #[cfg(test)]
 mod unit_tests {
     use super::*;
+    use generic_array::typenum::{U258, U65535};
 
     // Test the error condition for I2OSP
     #[test]
     fn test_i2osp_err_check() {
-        assert!(i2osp(0, 1).is_ok());
+        assert!(i2osp_1(0).is_ok());
+
+        assert!(i2osp_1(255).is_ok());
+        assert!(i2osp_1(256).is_err());
+        assert!(i2osp_1(257).is_err());
+
+        assert!(i2osp_2(256 * 256 - 1).is_ok());
+        assert!(i2osp_2(256 * 256).is_err());
+        assert!(i2osp_2(256 * 256 + 1).is_err());
+
+        assert_eq!(i2osp_2(258).unwrap(), [1, 2]);
+        assert_eq!(i2osp_2_len::<U258>(), [1, 2]);
+        assert_eq!(i2osp_2_len::<U65535>(), [255, 255]);
+    }
+
+    #[test]
+    fn test_batch_serialization() -> Result<(), InternalError> {
+        use crate::{tests::Ristretto255Sha512, Metadata, VerifiableClient, VerifiableServer};
+        use rand::rngs::OsRng;
+
+        type CS = Ristretto255Sha512;
+
+        let mut rng = OsRng;
+        let server = VerifiableServer::<CS>::new(&mut rng)?;
+        let messages = [&b"input"[..], b"other input"]
+            .iter()
+            .map(|input| VerifiableClient::<CS>::blind(input, &mut rng).map(|r| r.message))
+            .collect::<Result<Vec<_>, _>>()?;
+        let result = server.batch_evaluate(&mut rng, &messages, &Metadata::none())?;
+
+        let bytes = BlindedElement::serialize_batch(&messages)?;
+        assert_eq!(bytes[..2], [0, 64]);
+        assert_eq!(bytes.len(), 2 + 64);
+        assert_eq!(BlindedElement::deserialize_batch(&bytes)?, messages);
+        let bytes = EvaluationElement::serialize_batch(&result.messages, &result.proof)?;
+        assert_eq!(bytes.len(), 2 + 64 + 64);
+        let (evaluation_elements, proof) = EvaluationElement::<CS>::deserialize_batch(&bytes)?;
+        assert_eq!(evaluation_elements, result.messages);
+        assert_eq!(proof, result.proof);
+
+        // Trailing bytes, truncated elements and a wrong length are rejected
+        let bytes = BlindedElement::serialize_batch(&messages[..1])?;
+        assert!(BlindedElement::<CS>::deserialize_batch(&[&bytes[..], &[0]].concat()).is_err());
+        assert!(BlindedElement::<CS>::deserialize_batch(&bytes[..bytes.len() - 1]).is_err());
+        assert!(
+            BlindedElement::<CS>::deserialize_batch(&[&[0, 31][..], &bytes[2..33]].concat())
+                .is_err()
+        );
+        assert_eq!(BlindedElement::<CS>::deserialize_batch(&[0, 0])?, vec![]);
+
+        // Batches longer than the length prefix can't be serialized
+        assert!(check_batch_fits::<CS>(65535 / 32).is_ok());
+        assert!(matches!(
+            check_batch_fits::<CS>(65535 / 32 + 1),
+            Err(InternalError::SerializationError)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_blinded_element_slice() -> Result<(), InternalError> {
+        use crate::{tests::Ristretto255Sha512, VerifiableClient};
+        use rand::rngs::OsRng;
+
+        type CS = Ristretto255Sha512;
+
+        let messages = [&b"input"[..], b"other input"]
+            .iter()
+            .map(|input| VerifiableClient::<CS>::blind(input, &mut OsRng).map(|r| r.message))
+            .collect::<Result<Vec<_>, _>>()?;
+        let bytes = [messages[0].serialize(), messages[1].serialize()].concat();
+        let view = BlindedElementSlice::<CS>::new(&bytes)?;
+        assert_eq!(view.len(), 2);
+        assert!(!view.is_empty());
+        assert_eq!(view.get(1).unwrap()?, messages[1]);
+        assert!(view.get(2).is_none());
+        assert_eq!(view.iter().collect::<Result<Vec<_>, _>>()?, messages);
+        assert_eq!(view.iter().next_back().unwrap()?, messages[1]);
+        assert_eq!(view.as_bytes(), &bytes[..]);
+
+        let batch = BlindedElement::serialize_batch(&messages)?;
+        let view = BlindedElementSlice::<CS>::from_batch(&batch)?;
+        assert_eq!(view.as_bytes(), &bytes[..]);
+        assert!(BlindedElementSlice::<CS>::from_batch(&batch[..batch.len() - 1]).is_err());
+        assert!(BlindedElementSlice::<CS>::from_batch(&[&batch[..], &[0]].concat()).is_err());
+        assert!(BlindedElementSlice::<CS>::from_batch(&[0, 0])?.is_empty());
+
+        // The length is checked up front, the elements when they are decoded
+        assert!(matches!(
+            BlindedElementSlice::<CS>::new(&bytes[1..]),
+            Err(InternalError::SizeError { expected, got })
+                if expected == bytes.len() - 32 && got == bytes.len() - 1
+        ));
+        let invalid = [&[0xFF; 32][..], &bytes[32..]].concat();
+        let view = BlindedElementSlice::<CS>::new(&invalid)?;
+        assert!(view.get(0).unwrap().is_err());
+        assert_eq!(view.get(1).unwrap()?, messages[1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_conversions() -> Result<(), InternalError> {
+        use crate::{tests::Ristretto255Sha512, Metadata, VerifiableClient, VerifiableServer};
+        use generic_array::typenum::{U32, U64};
+        use rand::rngs::OsRng;
+
+        type CS = Ristretto255Sha512;
+
+        let mut rng = OsRng;
+        let server = VerifiableServer::<CS>::new(&mut rng)?;
+        assert_eq!(
+            VerifiableServer::<CS>::try_from(&server.serialize()[..])?,
+            server
+        );
+        let client_blind_result = VerifiableClient::<CS>::blind(b"input", &mut rng)?;
+        let state = client_blind_result.state;
+        assert_eq!(
+            VerifiableClient::<CS>::try_from(&state.serialize()[..])?,
+            state
+        );
+        assert!(NonVerifiableClient::<CS>::try_from(&[][..]).is_err());
+
+        let message = client_blind_result.message;
+        let bytes = GenericArray::<u8, U32>::from(&message);
+        assert_eq!(bytes[..], message.serialize()[..]);
+        assert_eq!(BlindedElement::try_from(&bytes[..])?, message);
+        let result = server.evaluate(&mut rng, message, &Metadata::none())?;
+        let bytes = GenericArray::<u8, U32>::from(&result.message);
+        assert_eq!(EvaluationElement::try_from(&bytes[..])?, result.message);
+        let bytes = GenericArray::<u8, U64>::from(&result.proof);
+        assert_eq!(bytes[..], result.proof.serialize()[..]);
+        assert_eq!(Proof::try_from(&bytes[..])?, result.proof);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_io() -> Result<(), InternalError> {
+        use crate::{tests::Ristretto255Sha512, Metadata, VerifiableClient, VerifiableServer};
+        use rand::rngs::OsRng;
+        use std::io::{Cursor, ErrorKind};
 
-        assert!(i2osp(255, 1).is_ok());
-        assert!(i2osp(256, 1).is_err());
-        assert!(i2osp(257, 1).is_err());
+        type CS = Ristretto255Sha512;
+
+        let mut rng = OsRng;
+        let server = VerifiableServer::<CS>::new(&mut rng)?;
+        let messages = [&b"input"[..], b"other input"]
+            .iter()
+            .map(|input| VerifiableClient::<CS>::blind(input, &mut rng).map(|r| r.message))
+            .collect::<Result<Vec<_>, _>>()?;
+        let result = server.batch_evaluate(&mut rng, &messages, &Metadata::none())?;
+
+        // Messages are streamed back to back, and read one at a time
+        let mut stream = Vec::new();
+        for message in &result.messages {
+            message.write_to(&mut stream).unwrap();
+        }
+        result.proof.write_to(&mut stream).unwrap();
+        assert_eq!(
+            stream,
+            [
+                result.messages[0].serialize(),
+                result.messages[1].serialize(),
+                result.proof.serialize(),
+            ]
+            .concat()
+        );
+        let mut reader = Cursor::new(&stream);
+        for message in &result.messages {
+            assert_eq!(&EvaluationElement::read_from(&mut reader).unwrap(), message);
+        }
+        assert_eq!(Proof::read_from(&mut reader).unwrap(), result.proof);
+
+        let mut bytes = Vec::new();
+        server.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes, server.serialize());
+        let deserialized = VerifiableServer::<CS>::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(deserialized, server);
+
+        // Client states are read to the end
+        let state = VerifiableClient::<CS>::blind_with_input_binding(b"input", &mut rng)?.state;
+        let mut bytes = Vec::new();
+        state.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes, state.serialize());
+        let deserialized = VerifiableClient::<CS>::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(deserialized, state);
+
+        // Truncated input fails as an unexpected EOF, invalid bytes as invalid
+        // data
+        let error = Proof::<CS>::read_from(&mut &stream[..10]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+        let error = BlindedElement::<CS>::read_from(&mut &[0xFF; 32][..]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tagged_serialization() -> Result<(), InternalError> {
+        use crate::tests::Ristretto255Sha512;
+        use rand::rngs::OsRng;
+
+        let pk = PrivateKey::<Ristretto255Sha512>::new(&mut OsRng)?.public_key();
+        let bytes = pk.serialize_tagged()?;
+        assert_eq!(bytes[..3], [TAGGED_FORMAT_VERSION, 0x00, 0x01]);
+        assert_eq!(bytes[3..], pk.serialize()[..]);
+        assert_eq!(PublicKey::deserialize_tagged(&bytes)?, pk);
+
+        // Messages of another suite or format version are rejected, even if
+        // the element would decode
+        let mut other_suite = bytes.clone();
+        other_suite[1..3].copy_from_slice(&[0xFF, 0xFF]);
+        assert!(matches!(
+            PublicKey::<Ristretto255Sha512>::deserialize_tagged(&other_suite),
+            Err(InternalError::SuiteMismatch)
+        ));
+        #[cfg(feature = "mock")]
+        assert!(matches!(
+            BlindedElement::<crate::tests::MockSha256>::deserialize_tagged(&bytes),
+            Err(InternalError::SuiteMismatch)
+        ));
+        // The ristretto255 suites only differ in their hash
+        #[cfg(feature = "shake256")]
+        {
+            use crate::Ristretto255Shake256;
+
+            assert!(matches!(
+                PublicKey::<Ristretto255Shake256>::deserialize_tagged(&bytes),
+                Err(InternalError::SuiteMismatch)
+            ));
+            let shake_pk = PrivateKey::<Ristretto255Shake256>::new(&mut OsRng)?.public_key();
+            let shake_bytes = shake_pk.serialize_tagged()?;
+            assert_eq!(shake_bytes[..3], [TAGGED_FORMAT_VERSION, 0x80, 0x01]);
+            assert_eq!(PublicKey::deserialize_tagged(&shake_bytes)?, shake_pk);
+            assert!(matches!(
+                PublicKey::<Ristretto255Sha512>::deserialize_tagged(&shake_bytes),
+                Err(InternalError::SuiteMismatch)
+            ));
+        }
+        let mut other_version = bytes;
+        other_version[0] = TAGGED_FORMAT_VERSION + 1;
+        assert!(matches!(
+            PublicKey::<Ristretto255Sha512>::deserialize_tagged(&other_version),
+            Err(InternalError::SerializationError)
+        ));
+        assert!(matches!(
+            Proof::<Ristretto255Sha512>::deserialize_tagged(&[TAGGED_FORMAT_VERSION]),
+            Err(InternalError::SizeError {
+                expected: 3,
+                got: 1
+            })
+        ));
 
-        assert!(i2osp(256 * 256 - 1, 2).is_ok());
-        assert!(i2osp(256 * 256, 2).is_err());
-        assert!(i2osp(256 * 256 + 1, 2).is_err());
+        Ok(())
     }
 }
@@ -0,0 +1,93 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! A ready-made ciphersuite pairing ristretto255 with SHAKE-256
+
+use crate::errors::InternalError;
+use crate::group::expand_message_xof;
+use crate::CipherSuite;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use digest::{BlockInput, ExtendableOutputDirty, FixedOutputDirty, Reset, Update, XofReader};
+use generic_array::typenum::{U136, U64};
+use generic_array::{ArrayLength, GenericArray};
+
+/// SHAKE-256 with a fixed output length of 64 bytes, as the draft uses it for
+/// the `Hash` of its SHAKE-256 ciphersuites
+#[derive(Clone, Debug, Default)]
+pub struct Shake256Hash(sha3::Shake256);
+
+impl Update for Shake256Hash {
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        self.0.update(data)
+    }
+}
+
+impl BlockInput for Shake256Hash {
+    // The rate of SHAKE-256
+    type BlockSize = U136;
+}
+
+impl FixedOutputDirty for Shake256Hash {
+    type OutputSize = U64;
+
+    fn finalize_into_dirty(&mut self, out: &mut GenericArray<u8, Self::OutputSize>) {
+        self.0.finalize_xof_dirty().read(out)
+    }
+}
+
+impl Reset for Shake256Hash {
+    fn reset(&mut self) {
+        self.0.reset()
+    }
+}
+
+/// The ciphersuite pairing ristretto255 with SHAKE-256, which hashes to the
+/// group with `expand_message_xof` and uses [Shake256Hash] everywhere else
+///
+/// This is the alternate ristretto255 suite discussed in the CFRG drafts, not
+/// one registered by draft-07. Its context string shares the suite identifier
+/// of ristretto255-SHA512, so both sides of an exchange have to agree on it out
+/// of band, and its outputs differ from those of ristretto255-SHA512.
+/// Suite-tagged serializations use [CipherSuite::SUITE_ID] `0x8001` instead,
+/// which lies outside of the identifiers registered by the draft.
+#[derive(Clone, Copy, Debug)]
+pub struct Ristretto255Shake256;
+
+impl CipherSuite for Ristretto255Shake256 {
+    type Group = RistrettoPoint;
+    type Hash = Shake256Hash;
+
+    const SUITE_ID: usize = 0x8001;
+
+    fn expand_message<L: ArrayLength<u8>>(
+        msg: &[&[u8]],
+        dst: &[&[u8]],
+    ) -> Result<GenericArray<u8, L>, InternalError> {
+        expand_message_xof::<sha3::Shake256, L>(msg, dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use digest::Digest;
+
+    #[test]
+    fn test_shake256_hash() {
+        // SHAKE-256 of "abc" from FIPS 202, truncated to 64 bytes
+        let expected = "483366601360a8771c6863080cc4114d8db44530f8f1e1ee4f94ea37e78b5739\
+                        d5a15bef186a5386c75744c0527e1faa9f8726e462a12a4feb06bd8801e751e4";
+
+        let mut hash = Shake256Hash::new();
+        Digest::update(&mut hash, b"abc");
+        assert_eq!(hex::encode(hash.finalize_reset()), expected);
+
+        // Finalizing resets the state
+        Digest::update(&mut hash, b"abc");
+        assert_eq!(hex::encode(hash.finalize()), expected);
+    }
+}
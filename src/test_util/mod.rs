@@ -0,0 +1,18 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Utilities for testing this crate and integrations built on top of it,
+//! enabled by the `test-util` feature. None of these are meant to be used in
+//! production.
+
+pub mod negative;
+pub mod rng;
+#[cfg(feature = "serialize")]
+pub mod round_trip;
+pub mod strategy;
+pub mod tamper;
+pub mod timing;
@@ -0,0 +1,392 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! A corpus of malformed and edge-case inputs in the style of
+//! [Wycheproof](https://github.com/google/wycheproof), together with a runner
+//! checking that all of them are rejected.
+//!
+//! The corpus is generated for a given [CipherSuite], so it can be run against
+//! custom [Group] implementations as well:
+//!
+//! ```
+//! # use voprf::CipherSuite;
+//! # struct Default;
+//! # impl CipherSuite for Default {
+//! #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+//! #     type Hash = sha2::Sha512;
+//! # }
+//! use rand::rngs::OsRng;
+//! use voprf::test_util::negative;
+//!
+//! let failures = negative::run::<Default, _>(&mut OsRng).expect("Unable to run corpus");
+//! assert!(failures.is_empty(), "accepted invalid inputs: {:?}", failures);
+//! ```
+
+use crate::{
+    ciphersuite::CipherSuite, errors::InternalError, group::Group, BlindedElement,
+    EvaluationElement, Metadata, NonVerifiableClient, NonVerifiableServer, Proof, VerifiableClient,
+    VerifiableServer,
+};
+use alloc::vec;
+use alloc::vec::Vec;
+use generic_array::typenum::Unsigned;
+use rand::{CryptoRng, RngCore};
+use std::panic::{self, AssertUnwindSafe};
+
+static INPUT: &[u8] = b"voprf negative input";
+
+/// What a [NegativeVector] is given to
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Target {
+    /// [BlindedElement::deserialize]
+    BlindedElement,
+    /// [EvaluationElement::deserialize]
+    EvaluationElement,
+    /// [Proof::deserialize], and if successful, [VerifiableClient::finalize]
+    /// with a valid evaluation
+    Proof,
+    /// [NonVerifiableClient::deserialize]
+    NonVerifiableClient,
+    /// [VerifiableClient::deserialize]
+    VerifiableClient,
+    /// [NonVerifiableServer::deserialize]
+    NonVerifiableServer,
+    /// [VerifiableServer::deserialize]
+    VerifiableServer,
+    /// [NonVerifiableServer::new_with_key] and
+    /// [VerifiableServer::new_with_key]
+    PrivateKey,
+    /// The [Metadata] given to both server evaluations and client
+    /// finalizations
+    Metadata,
+    /// The input given to the client finalizations
+    Input,
+}
+
+/// An input which has to be rejected
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NegativeVector {
+    /// Identifies the vector in the corpus
+    pub id: usize,
+    /// Describes what is wrong with the input
+    pub comment: &'static str,
+    /// What the input is given to
+    pub target: Target,
+    /// The raw input
+    pub input: Vec<u8>,
+}
+
+/// Returns the corpus of negative vectors for the given [CipherSuite]
+pub fn vectors<CS: CipherSuite>() -> Vec<NegativeVector> {
+    let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
+    let elem_len = <CS::Group as Group>::ElemLen::USIZE;
+
+    let identity = CS::Group::identity().to_arr().to_vec();
+    let element = CS::Group::base_point().to_arr().to_vec();
+    let zero_scalar = CS::Group::scalar_as_bytes(CS::Group::scalar_zero()).to_vec();
+    let scalar = {
+        let mut scalar = zero_scalar.clone();
+        scalar[0] = 1;
+        scalar
+    };
+    // Larger than the order of any supported group, regardless of endianness
+    let non_canonical_scalar = vec![0xff; scalar_len];
+    let oversized = vec![0; usize::from(u16::MAX) + 1];
+
+    let truncated = |bytes: &[u8]| bytes[..bytes.len() - 1].to_vec();
+    let extended = |bytes: &[u8]| [bytes, &[0]].concat();
+
+    let mut vectors = Vec::new();
+    let mut push = |comment, target, input| {
+        vectors.push(NegativeVector {
+            id: vectors.len(),
+            comment,
+            target,
+            input,
+        })
+    };
+
+    for &target in &[Target::BlindedElement, Target::EvaluationElement] {
+        push("identity element", target, identity.clone());
+        push("empty", target, Vec::new());
+        push("truncated element", target, truncated(&element));
+        push("extended element", target, extended(&element));
+        push("all bytes set", target, vec![0xff; elem_len]);
+    }
+
+    let proof = [scalar.clone(), scalar.clone()].concat();
+    push("empty", Target::Proof, Vec::new());
+    push("truncated proof", Target::Proof, truncated(&proof));
+    push("extended proof", Target::Proof, extended(&proof));
+    push(
+        "zero challenge",
+        Target::Proof,
+        [zero_scalar.clone(), scalar.clone()].concat(),
+    );
+    push(
+        "zero response",
+        Target::Proof,
+        [scalar.clone(), zero_scalar.clone()].concat(),
+    );
+    push(
+        "non-canonical challenge",
+        Target::Proof,
+        [non_canonical_scalar.clone(), scalar.clone()].concat(),
+    );
+    push(
+        "non-canonical response",
+        Target::Proof,
+        [scalar.clone(), non_canonical_scalar.clone()].concat(),
+    );
+    push("proof for a different statement", Target::Proof, proof);
+
+    for &target in &[
+        Target::NonVerifiableClient,
+        Target::NonVerifiableServer,
+        Target::PrivateKey,
+    ] {
+        push("empty", target, Vec::new());
+        push("zero scalar", target, zero_scalar.clone());
+        push("non-canonical scalar", target, non_canonical_scalar.clone());
+        push("truncated scalar", target, truncated(&scalar));
+        push("extended scalar", target, extended(&scalar));
+    }
+
+    for &target in &[Target::VerifiableClient, Target::VerifiableServer] {
+        push("empty", target, Vec::new());
+        push(
+            "zero scalar",
+            target,
+            [zero_scalar.clone(), element.clone()].concat(),
+        );
+        push(
+            "non-canonical scalar",
+            target,
+            [non_canonical_scalar.clone(), element.clone()].concat(),
+        );
+        push(
+            "identity element",
+            target,
+            [scalar.clone(), identity.clone()].concat(),
+        );
+        push(
+            "truncated element",
+            target,
+            [scalar.clone(), truncated(&element)].concat(),
+        );
+        push(
+            "extended element",
+            target,
+            [scalar.clone(), extended(&element)].concat(),
+        );
+    }
+
+    // Twice the base point, which is not the public key of `scalar`
+    let other_element = (CS::Group::base_point() + &CS::Group::base_point())
+        .to_arr()
+        .to_vec();
+    push(
+        "public key not matching the private key",
+        Target::VerifiableServer,
+        [scalar.clone(), other_element].concat(),
+    );
+
+    for (target, state) in &[
+        (Target::NonVerifiableClient, scalar.clone()),
+        (
+            Target::VerifiableClient,
+            [scalar.clone(), element.clone()].concat(),
+        ),
+    ] {
+        push(
+            "zero input binding",
+            *target,
+            [state.clone(), zero_scalar.clone()].concat(),
+        );
+        push(
+            "truncated input binding",
+            *target,
+            [state.clone(), truncated(&scalar)].concat(),
+        );
+    }
+
+    push(
+        "metadata longer than 2^16 - 1 bytes",
+        Target::Metadata,
+        oversized.clone(),
+    );
+    push("input longer than 2^16 - 1 bytes", Target::Input, oversized);
+
+    vectors
+}
+
+/// Runs all [vectors] for the given [CipherSuite] and returns the ones which
+/// were accepted, or caused a panic, instead of being rejected. The RNG is only
+/// used to set up valid protocol runs the vectors are injected into.
+pub fn run<CS: CipherSuite, R: RngCore + CryptoRng>(
+    rng: &mut R,
+) -> Result<Vec<NegativeVector>, InternalError> {
+    let context = Context::<CS>::new(rng)?;
+
+    Ok(vectors::<CS>()
+        .into_iter()
+        .filter(|vector| {
+            !panic::catch_unwind(AssertUnwindSafe(|| context.is_rejected(vector))).unwrap_or(false)
+        })
+        .collect())
+}
+
+// A valid protocol run, which the vectors are injected into
+struct Context<CS: CipherSuite> {
+    non_verifiable_server: NonVerifiableServer<CS>,
+    non_verifiable_client: NonVerifiableClient<CS>,
+    non_verifiable_blinded_element: BlindedElement<CS>,
+    non_verifiable_evaluation_element: EvaluationElement<CS>,
+    verifiable_server: VerifiableServer<CS>,
+    verifiable_client: VerifiableClient<CS>,
+    verifiable_blinded_element: BlindedElement<CS>,
+    verifiable_evaluation_element: EvaluationElement<CS>,
+    proof: Proof<CS>,
+}
+
+impl<CS: CipherSuite> Context<CS> {
+    fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self, InternalError> {
+        let metadata = Metadata::none();
+
+        let non_verifiable_server = NonVerifiableServer::new(rng)?;
+        let non_verifiable_blind_result = NonVerifiableClient::blind(INPUT, rng)?;
+        let non_verifiable_evaluation_element = non_verifiable_server
+            .evaluate(non_verifiable_blind_result.message.clone(), &metadata)?
+            .message;
+
+        let verifiable_server = VerifiableServer::new(rng)?;
+        let verifiable_blind_result = VerifiableClient::blind(INPUT, rng)?;
+        let verifiable_server_result =
+            verifiable_server.evaluate(rng, verifiable_blind_result.message.clone(), &metadata)?;
+
+        Ok(Self {
+            non_verifiable_server,
+            non_verifiable_client: non_verifiable_blind_result.state,
+            non_verifiable_blinded_element: non_verifiable_blind_result.message,
+            non_verifiable_evaluation_element,
+            verifiable_server,
+            verifiable_client: verifiable_blind_result.state,
+            verifiable_blinded_element: verifiable_blind_result.message,
+            verifiable_evaluation_element: verifiable_server_result.message,
+            proof: verifiable_server_result.proof,
+        })
+    }
+
+    fn is_rejected(&self, vector: &NegativeVector) -> bool {
+        let input = vector.input.as_slice();
+
+        match vector.target {
+            Target::BlindedElement => BlindedElement::<CS>::deserialize(input).is_err(),
+            Target::EvaluationElement => EvaluationElement::<CS>::deserialize(input).is_err(),
+            Target::Proof => match Proof::<CS>::deserialize(input) {
+                Ok(proof) => self
+                    .verifiable_client
+                    .clone()
+                    .finalize_once(
+                        INPUT,
+                        self.verifiable_evaluation_element.clone(),
+                        proof,
+                        self.verifiable_server.get_public_key(),
+                        &Metadata::none(),
+                    )
+                    .is_err(),
+                Err(_) => true,
+            },
+            Target::NonVerifiableClient => NonVerifiableClient::<CS>::deserialize(input).is_err(),
+            Target::VerifiableClient => VerifiableClient::<CS>::deserialize(input).is_err(),
+            Target::NonVerifiableServer => NonVerifiableServer::<CS>::deserialize(input).is_err(),
+            Target::VerifiableServer => VerifiableServer::<CS>::deserialize(input).is_err(),
+            Target::PrivateKey => {
+                NonVerifiableServer::<CS>::new_with_key(input).is_err()
+                    && VerifiableServer::<CS>::new_with_key(input).is_err()
+            }
+            Target::Metadata => {
+                let metadata = Metadata(input.to_vec());
+
+                self.non_verifiable_server
+                    .evaluate(self.non_verifiable_blinded_element.clone(), &metadata)
+                    .is_err()
+                    && self
+                        .verifiable_server
+                        .evaluate_derandomized(
+                            self.verifiable_blinded_element.clone(),
+                            &metadata,
+                            &[],
+                        )
+                        .is_err()
+                    && self
+                        .non_verifiable_client
+                        .clone()
+                        .finalize_once(
+                            INPUT,
+                            self.non_verifiable_evaluation_element.clone(),
+                            &metadata,
+                        )
+                        .is_err()
+                    && self
+                        .verifiable_client
+                        .clone()
+                        .finalize_once(
+                            INPUT,
+                            self.verifiable_evaluation_element.clone(),
+                            self.proof.clone(),
+                            self.verifiable_server.get_public_key(),
+                            &metadata,
+                        )
+                        .is_err()
+            }
+            Target::Input => {
+                self.non_verifiable_client
+                    .clone()
+                    .finalize_once(
+                        input,
+                        self.non_verifiable_evaluation_element.clone(),
+                        &Metadata::none(),
+                    )
+                    .is_err()
+                    && self
+                        .verifiable_client
+                        .clone()
+                        .finalize_once(
+                            input,
+                            self.verifiable_evaluation_element.clone(),
+                            self.proof.clone(),
+                            self.verifiable_server.get_public_key(),
+                            &Metadata::none(),
+                        )
+                        .is_err()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_negative_vectors() -> Result<(), InternalError> {
+        use crate::tests::Ristretto255Sha512;
+
+        assert_eq!(run::<Ristretto255Sha512, _>(&mut OsRng)?, []);
+
+        #[cfg(feature = "p256")]
+        {
+            use crate::tests::P256Sha256;
+
+            assert_eq!(run::<P256Sha256, _>(&mut OsRng)?, []);
+        }
+
+        Ok(())
+    }
+}
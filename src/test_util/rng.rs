@@ -0,0 +1,108 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! A seedable, deterministic RNG to make blinding, key generation and proofs
+//! reproducible.
+//!
+//! [TestRng] produces the ChaCha20 keystream of its seed. Unlike
+//! [StdRng](https://docs.rs/rand/0.8/rand/rngs/struct.StdRng.html), whose
+//! algorithm may change between releases, the output for a given seed is
+//! stable, so it can be used to generate snapshot tests and fixtures to be
+//! checked against other implementations:
+//!
+//! ```
+//! # use voprf::CipherSuite;
+//! # struct Default;
+//! # impl CipherSuite for Default {
+//! #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+//! #     type Hash = sha2::Sha512;
+//! # }
+//! use rand::SeedableRng;
+//! use voprf::{test_util::rng::TestRng, Metadata, VerifiableClient, VerifiableServer};
+//!
+//! let run = || {
+//!     let mut rng = TestRng::seed_from_u64(42);
+//!     let server = VerifiableServer::<Default>::new(&mut rng)?;
+//!     let client_blind_result = VerifiableClient::<Default>::blind(b"input", &mut rng)?;
+//!     let server_result = server.evaluate(&mut rng, client_blind_result.message, &Metadata::none())?;
+//!     Ok::<_, voprf::errors::InternalError>((server_result.message, server_result.proof.serialize()))
+//! };
+//!
+//! assert_eq!(run()?, run()?);
+//! # Ok::<(), voprf::errors::InternalError>(())
+//! ```
+//!
+//! **Never** use [TestRng] outside of tests: anyone who knows the seed can
+//! reproduce every secret generated with it.
+
+use rand::{CryptoRng, Error, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+/// A deterministic RNG, see the [module documentation](self)
+#[derive(Clone, Debug)]
+pub struct TestRng(ChaCha20Rng);
+
+impl Default for TestRng {
+    /// Creates a [TestRng] with an all-zero seed
+    fn default() -> Self {
+        Self::from_seed([0; 32])
+    }
+}
+
+impl SeedableRng for TestRng {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self(ChaCha20Rng::from_seed(seed))
+    }
+}
+
+impl RngCore for TestRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+// Only so it is accepted by the protocol, it is not secure in any way
+impl CryptoRng for TestRng {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystream() {
+        // The ChaCha20 block function test vector with an all-zero key and
+        // nonce from RFC 8439, appendix A.1
+        let mut bytes = [0; 16];
+        TestRng::default().fill_bytes(&mut bytes);
+        assert_eq!(hex::encode(bytes), "76b8e0ada0f13d90405d6ae55386bd28");
+    }
+
+    #[test]
+    fn test_reproducible() {
+        let mut rng1 = TestRng::seed_from_u64(0);
+        let mut rng2 = rng1.clone();
+        assert_eq!(rng1.next_u64(), rng2.next_u64());
+        assert_ne!(
+            TestRng::seed_from_u64(0).next_u64(),
+            TestRng::seed_from_u64(1).next_u64()
+        );
+    }
+}
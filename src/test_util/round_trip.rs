@@ -0,0 +1,182 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Round-trips every type implementing `serde`'s `Serialize` and `Deserialize`
+//! through a caller-supplied [Format], to validate that a storage or transport
+//! layer built on top of it is compatible with this crate:
+//!
+//! ```
+//! # use voprf::CipherSuite;
+//! # struct Default;
+//! # impl CipherSuite for Default {
+//! #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+//! #     type Hash = sha2::Sha512;
+//! # }
+//! use serde::{de::DeserializeOwned, Serialize};
+//! use voprf::test_util::{
+//!     rng::TestRng,
+//!     round_trip::{self, Format},
+//! };
+//!
+//! struct Json;
+//!
+//! impl Format for Json {
+//!     type Error = serde_json::Error;
+//!
+//!     fn serialize<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, Self::Error> {
+//!         serde_json::to_vec(value)
+//!     }
+//!
+//!     fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Self::Error> {
+//!         serde_json::from_slice(bytes)
+//!     }
+//! }
+//!
+//! round_trip::assert_round_trip::<Default, _, _>(&Json, &mut TestRng::default())?;
+//! # Ok::<(), voprf::errors::InternalError>(())
+//! ```
+
+use crate::{
+    ciphersuite::CipherSuite, errors::InternalError, BlindedElement, EvaluationElement, Metadata,
+    NonVerifiableClient, NonVerifiableServer, PreparedEvaluationElement, PreparedTscalar, Proof,
+    VerifiableClient, VerifiableServer,
+};
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use rand::{CryptoRng, RngCore};
+use serde::{de::DeserializeOwned, Serialize};
+
+static INPUT: &[u8] = b"voprf round trip input";
+
+/// A `serde` data format, like JSON, CBOR or bincode
+pub trait Format {
+    /// The error returned by the format
+    type Error: Debug;
+
+    /// Serializes `value` into bytes
+    fn serialize<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, Self::Error>;
+
+    /// Deserializes a value from `bytes`
+    fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Self::Error>;
+}
+
+/// Runs the protocol with the given [CipherSuite], round-trips every
+/// message and state through `format`, and panics if any of them fail to
+/// serialize, fail to deserialize, or don't come back unchanged. The RNG is
+/// only used to run the protocol.
+pub fn assert_round_trip<CS: CipherSuite, F: Format, R: RngCore + CryptoRng>(
+    format: &F,
+    rng: &mut R,
+) -> Result<(), InternalError> {
+    let metadata = Metadata::none();
+
+    let non_verifiable_server = NonVerifiableServer::<CS>::new(rng)?;
+    let non_verifiable_blind_result = NonVerifiableClient::<CS>::blind(INPUT, rng)?;
+    let non_verifiable_evaluation_element = non_verifiable_server
+        .evaluate(non_verifiable_blind_result.message.clone(), &metadata)?
+        .message;
+
+    let verifiable_server = VerifiableServer::<CS>::new(rng)?;
+    let verifiable_blind_result = VerifiableClient::<CS>::blind(INPUT, rng)?;
+    let verifiable_server_result =
+        verifiable_server.evaluate(rng, verifiable_blind_result.message.clone(), &metadata)?;
+    let mut verifiable_prepare_result = verifiable_server.batch_evaluate_prepare(
+        core::iter::once(&verifiable_blind_result.message),
+        &metadata,
+    )?;
+    let prepared_evaluation_element = verifiable_prepare_result
+        .prepared_evaluation_elements
+        .next()
+        .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?;
+
+    macro_rules! check {
+        ($($type:ident: $value:expr),+$(,)?) => {
+            $(
+                let value: &$type<CS> = &$value;
+                let bytes = format
+                    .serialize(value)
+                    .unwrap_or_else(|e| panic!("failed to serialize {}: {:?}", stringify!($type), e));
+                let result: $type<CS> = format
+                    .deserialize(&bytes)
+                    .unwrap_or_else(|e| panic!("failed to deserialize {}: {:?}", stringify!($type), e));
+                assert!(
+                    result.serialize() == value.serialize(),
+                    "{} changed after a round trip",
+                    stringify!($type)
+                );
+            )+
+        };
+    }
+
+    check!(
+        NonVerifiableServer: non_verifiable_server,
+        NonVerifiableClient: non_verifiable_blind_result.state,
+        BlindedElement: non_verifiable_blind_result.message,
+        EvaluationElement: non_verifiable_evaluation_element,
+        VerifiableServer: verifiable_server,
+        VerifiableClient: verifiable_blind_result.state,
+        BlindedElement: verifiable_blind_result.message,
+        EvaluationElement: verifiable_server_result.message,
+        Proof: verifiable_server_result.proof,
+        PreparedEvaluationElement: prepared_evaluation_element,
+        PreparedTscalar: verifiable_prepare_result.t,
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rng::TestRng;
+
+    struct Json;
+
+    impl Format for Json {
+        type Error = serde_json::Error;
+
+        fn serialize<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, Self::Error> {
+            serde_json::to_vec(value)
+        }
+
+        fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Self::Error> {
+            serde_json::from_slice(bytes)
+        }
+    }
+
+    struct Bincode;
+
+    impl Format for Bincode {
+        type Error = bincode::Error;
+
+        fn serialize<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, Self::Error> {
+            bincode::serialize(value)
+        }
+
+        fn deserialize<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Self::Error> {
+            bincode::deserialize(bytes)
+        }
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), InternalError> {
+        use crate::tests::Ristretto255Sha512;
+
+        assert_round_trip::<Ristretto255Sha512, _, _>(&Json, &mut TestRng::default())?;
+        assert_round_trip::<Ristretto255Sha512, _, _>(&Bincode, &mut TestRng::default())?;
+
+        #[cfg(feature = "p256")]
+        {
+            use crate::tests::P256Sha256;
+
+            assert_round_trip::<P256Sha256, _, _>(&Json, &mut TestRng::default())?;
+            assert_round_trip::<P256Sha256, _, _>(&Bincode, &mut TestRng::default())?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,364 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! [proptest](mod@proptest) strategies for scalars, elements, messages, client and server
+//! states, and full protocol transcripts.
+//!
+//! All messages and states additionally implement [Arbitrary], so they can be
+//! used with [any] directly:
+//!
+//! ```
+//! # use voprf::CipherSuite;
+//! # #[derive(Debug)]
+//! # struct Default;
+//! # impl CipherSuite for Default {
+//! #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+//! #     type Hash = sha2::Sha512;
+//! # }
+//! use proptest::prelude::*;
+//! use voprf::{test_util::strategy, BlindedElement};
+//!
+//! proptest! {
+//!     fn blinded_element_round_trip(message in any::<BlindedElement<Default>>()) {
+//!         let bytes = message.serialize();
+//!         prop_assert_eq!(BlindedElement::deserialize(&bytes).unwrap(), message);
+//!     }
+//!
+//!     fn transcript_is_valid(transcript in strategy::verifiable_transcript::<Default>()) {
+//!         let result = transcript.client.finalize(
+//!             &transcript.input,
+//!             transcript.evaluation_element,
+//!             transcript.proof,
+//!             transcript.server.get_public_key(),
+//!             &transcript.metadata,
+//!         ).unwrap();
+//!         prop_assert_eq!(*result.output, transcript.output);
+//!     }
+//! }
+//! # blinded_element_round_trip();
+//! # transcript_is_valid();
+//! ```
+//!
+//! Values are derived from a seed drawn by proptest, so shrinking a failing case
+//! doesn't produce a "smaller" value, but it is still reproducible.
+
+use crate::{
+    ciphersuite::CipherSuite, group::Group, modes::NoElement, test_util::rng::TestRng,
+    BlindedElement, EvaluationElement, Metadata, NonVerifiableClient, NonVerifiableServer, Proof,
+    VerifiableClient, VerifiableServer,
+};
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use digest::Digest;
+use generic_array::GenericArray;
+use proptest::arbitrary::Arbitrary;
+use proptest::collection;
+use proptest::prelude::*;
+use rand::SeedableRng;
+
+// The maximum length of generated inputs and metadata
+const MAX_LEN: usize = 64;
+
+/// Generates non-zero scalars of the given [Group]
+pub fn scalar<G: Group>() -> impl Strategy<Value = G::Scalar>
+where
+    G::Scalar: Debug,
+{
+    any::<[u8; 32]>().prop_map(|seed| G::random_nonzero_scalar(&mut TestRng::from_seed(seed)))
+}
+
+/// Generates elements of the given [Group], excluding the identity
+pub fn element<G: Group + Debug>() -> impl Strategy<Value = G>
+where
+    G::Scalar: Debug,
+{
+    scalar::<G>().prop_map(|scalar| G::base_point() * &scalar)
+}
+
+/// Generates client inputs
+pub fn input() -> impl Strategy<Value = Vec<u8>> {
+    collection::vec(any::<u8>(), 0..=MAX_LEN)
+}
+
+impl Arbitrary for Metadata {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        input().prop_map(Metadata).boxed()
+    }
+}
+
+macro_rules! impl_arbitrary_for {
+    ($name:ident, $($field:ident => $strategy:expr),+$(,)?) => {
+        impl<CS: 'static + CipherSuite> Arbitrary for $name<CS>
+        where
+            <CS::Group as Group>::Scalar: Debug,
+            CS::Group: Debug,
+        {
+            type Parameters = ();
+            type Strategy = BoxedStrategy<Self>;
+
+            fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+                ($($strategy,)+)
+                    .prop_map(|($($field,)+)| Self { $($field),+ })
+                    .boxed()
+            }
+        }
+    };
+}
+
+impl_arbitrary_for!(BlindedElement, value => element::<CS::Group>());
+impl_arbitrary_for!(EvaluationElement, value => element::<CS::Group>());
+impl_arbitrary_for!(
+    Proof,
+    c_scalar => scalar::<CS::Group>(),
+    s_scalar => scalar::<CS::Group>(),
+);
+impl_arbitrary_for!(
+    NonVerifiableClient,
+    blind => scalar::<CS::Group>(),
+    blinded_element => Just(NoElement),
+    input_binding => proptest::option::of(scalar::<CS::Group>()),
+);
+impl_arbitrary_for!(
+    VerifiableClient,
+    blind => scalar::<CS::Group>(),
+    blinded_element => element::<CS::Group>(),
+    input_binding => proptest::option::of(scalar::<CS::Group>()),
+);
+
+impl<CS: 'static + CipherSuite> Arbitrary for NonVerifiableServer<CS>
+where
+    <CS::Group as Group>::Scalar: Debug,
+    CS::Group: Debug,
+{
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        scalar::<CS::Group>()
+            .prop_filter_map("failed to create server", |sk| {
+                Self::new_with_key(&CS::Group::scalar_as_bytes(sk)).ok()
+            })
+            .boxed()
+    }
+}
+
+impl<CS: 'static + CipherSuite> Arbitrary for VerifiableServer<CS>
+where
+    <CS::Group as Group>::Scalar: Debug,
+    CS::Group: Debug,
+{
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        // Derives the public key from the private key
+        scalar::<CS::Group>()
+            .prop_filter_map("failed to create server", |sk| {
+                Self::new_with_key(&CS::Group::scalar_as_bytes(sk)).ok()
+            })
+            .boxed()
+    }
+}
+
+/// A complete and valid run of the protocol in base mode
+pub struct NonVerifiableTranscript<CS: CipherSuite> {
+    /// The input given to [NonVerifiableClient::blind]
+    pub input: Vec<u8>,
+    /// The metadata given to the server and the client
+    pub metadata: Metadata,
+    /// The client state after blinding
+    pub client: NonVerifiableClient<CS>,
+    /// The message sent from the client to the server
+    pub blinded_element: BlindedElement<CS>,
+    /// The server
+    pub server: NonVerifiableServer<CS>,
+    /// The message sent from the server to the client
+    pub evaluation_element: EvaluationElement<CS>,
+    /// The output of the protocol
+    pub output: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+}
+impl_debug_eq_hash_for!(
+    struct NonVerifiableTranscript<CS: CipherSuite>,
+    [
+        input,
+        metadata,
+        client,
+        blinded_element,
+        server,
+        evaluation_element,
+        output,
+    ],
+    [<CS::Group as Group>::Scalar, CS::Group],
+);
+
+/// A complete and valid run of the protocol in verifiable mode
+pub struct VerifiableTranscript<CS: CipherSuite> {
+    /// The input given to [VerifiableClient::blind]
+    pub input: Vec<u8>,
+    /// The metadata given to the server and the client
+    pub metadata: Metadata,
+    /// The client state after blinding
+    pub client: VerifiableClient<CS>,
+    /// The message sent from the client to the server
+    pub blinded_element: BlindedElement<CS>,
+    /// The server
+    pub server: VerifiableServer<CS>,
+    /// The message sent from the server to the client
+    pub evaluation_element: EvaluationElement<CS>,
+    /// The proof sent from the server to the client
+    pub proof: Proof<CS>,
+    /// The output of the protocol
+    pub output: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+}
+impl_debug_eq_hash_for!(
+    struct VerifiableTranscript<CS: CipherSuite>,
+    [
+        input,
+        metadata,
+        client,
+        blinded_element,
+        server,
+        evaluation_element,
+        proof,
+        output,
+    ],
+    [<CS::Group as Group>::Scalar, CS::Group],
+);
+
+/// Generates complete runs of the protocol in base mode
+pub fn non_verifiable_transcript<CS: CipherSuite>(
+) -> impl Strategy<Value = NonVerifiableTranscript<CS>>
+where
+    <CS::Group as Group>::Scalar: Debug,
+    CS::Group: Debug,
+{
+    (any::<[u8; 32]>(), input(), any::<Metadata>()).prop_filter_map(
+        "protocol run failed",
+        |(seed, input, metadata)| {
+            let mut rng = TestRng::from_seed(seed);
+            let server = NonVerifiableServer::new(&mut rng).ok()?;
+            let client_blind_result = NonVerifiableClient::blind(&input, &mut rng).ok()?;
+            let evaluation_element = server
+                .evaluate(client_blind_result.message.clone(), &metadata)
+                .ok()?
+                .message;
+            let finalize_result = client_blind_result
+                .state
+                .clone()
+                .finalize_once(&input, evaluation_element.clone(), &metadata)
+                .ok()?;
+
+            Some(NonVerifiableTranscript {
+                input,
+                metadata,
+                client: client_blind_result.state,
+                blinded_element: client_blind_result.message,
+                server,
+                evaluation_element,
+                output: GenericArray::clone(&finalize_result.output),
+            })
+        },
+    )
+}
+
+/// Generates complete runs of the protocol in verifiable mode
+pub fn verifiable_transcript<CS: CipherSuite>() -> impl Strategy<Value = VerifiableTranscript<CS>>
+where
+    <CS::Group as Group>::Scalar: Debug,
+    CS::Group: Debug,
+{
+    (any::<[u8; 32]>(), input(), any::<Metadata>()).prop_filter_map(
+        "protocol run failed",
+        |(seed, input, metadata)| {
+            let mut rng = TestRng::from_seed(seed);
+            let server = VerifiableServer::new(&mut rng).ok()?;
+            let client_blind_result = VerifiableClient::blind(&input, &mut rng).ok()?;
+            let server_result = server
+                .evaluate(&mut rng, client_blind_result.message.clone(), &metadata)
+                .ok()?;
+            let finalize_result = client_blind_result
+                .state
+                .clone()
+                .finalize_once(
+                    &input,
+                    server_result.message.clone(),
+                    server_result.proof.clone(),
+                    server.get_public_key(),
+                    &metadata,
+                )
+                .ok()?;
+
+            Some(VerifiableTranscript {
+                input,
+                metadata,
+                client: client_blind_result.state,
+                blinded_element: client_blind_result.message,
+                server,
+                evaluation_element: server_result.message,
+                proof: server_result.proof,
+                output: GenericArray::clone(&finalize_result.output),
+            })
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Ristretto255Sha512;
+
+    macro_rules! test_round_trip {
+        ($($name:ident),+$(,)?) => {
+            proptest! {
+                $(
+                    #[test]
+                    #[allow(non_snake_case)]
+                    fn $name(value in any::<$name<Ristretto255Sha512>>()) {
+                        let bytes = value.serialize();
+                        prop_assert_eq!($name::deserialize(&bytes).unwrap(), value);
+                    }
+                )+
+            }
+        };
+    }
+
+    test_round_trip!(
+        BlindedElement,
+        EvaluationElement,
+        Proof,
+        NonVerifiableClient,
+        VerifiableClient,
+        NonVerifiableServer,
+        VerifiableServer,
+    );
+
+    proptest! {
+        #[test]
+        fn test_non_verifiable_transcript(
+            transcript in non_verifiable_transcript::<Ristretto255Sha512>(),
+        ) {
+            let evaluation_element = transcript
+                .server
+                .evaluate(transcript.blinded_element, &transcript.metadata)
+                .unwrap()
+                .message;
+            prop_assert_eq!(evaluation_element, transcript.evaluation_element);
+        }
+
+        #[test]
+        fn test_verifiable_server(server in any::<VerifiableServer<Ristretto255Sha512>>()) {
+            let expected = VerifiableServer::<Ristretto255Sha512>::new_with_key(
+                &<Ristretto255Sha512 as CipherSuite>::Group::scalar_as_bytes(*server.sk),
+            )
+            .unwrap();
+            prop_assert_eq!(server, expected);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Systematically corrupts valid messages, to check that every corruption is
+//! reported as an error, and that no layer in between, like a transport or a
+//! storage, masks verification failures:
+//!
+//! ```
+//! # use voprf::CipherSuite;
+//! # struct Default;
+//! # impl CipherSuite for Default {
+//! #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+//! #     type Hash = sha2::Sha512;
+//! # }
+//! use voprf::{test_util::{rng::TestRng, tamper}, Metadata, VerifiableClient, VerifiableServer};
+//!
+//! let mut rng = TestRng::default();
+//! let server = VerifiableServer::<Default>::new(&mut rng)?;
+//! let client_blind_result = VerifiableClient::<Default>::blind(b"input", &mut rng)?;
+//! let server_result = server.evaluate(&mut rng, client_blind_result.message, &Metadata::none())?;
+//!
+//! for tampered in tamper::proofs(&server_result.proof) {
+//!     // Send the tampered proof through the transport under test here
+//!     let result = client_blind_result.state.finalize(
+//!         b"input",
+//!         server_result.message.clone(),
+//!         tampered.value,
+//!         server.get_public_key(),
+//!         &Metadata::none(),
+//!     );
+//!     assert!(result.is_err(), "accepted {}", tampered.description);
+//! }
+//! # Ok::<(), voprf::errors::InternalError>(())
+//! ```
+
+use crate::{ciphersuite::CipherSuite, EvaluationElement, Proof};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A corrupted value
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Tampered<T> {
+    /// Describes the corruption
+    pub description: String,
+    /// The corrupted value
+    pub value: T,
+}
+
+impl<T> Tampered<T> {
+    fn new(description: String, value: T) -> Self {
+        Self { description, value }
+    }
+}
+
+/// Flips every bit of `bytes`, one at a time
+pub fn bit_flips(bytes: &[u8]) -> Vec<Tampered<Vec<u8>>> {
+    (0..bytes.len() * 8)
+        .map(|bit| {
+            let mut tampered = bytes.to_vec();
+            tampered[bit / 8] ^= 1 << (bit % 8);
+            Tampered::new(format!("bit {} flipped", bit), tampered)
+        })
+        .collect()
+}
+
+/// Truncates `bytes` to every shorter length, and extends it by a byte
+pub fn truncations(bytes: &[u8]) -> Vec<Tampered<Vec<u8>>> {
+    (0..bytes.len())
+        .map(|len| Tampered::new(format!("truncated to {} bytes", len), bytes[..len].to_vec()))
+        .chain(core::iter::once(Tampered::new(
+            String::from("extended by a byte"),
+            [bytes, &[0]].concat(),
+        )))
+        .collect()
+}
+
+/// Reorders `batch` by swapping every pair of neighbours, reversing it, and
+/// rotating it, and resizes it by dropping the last and duplicating the first
+/// entry. Reorderings that don't change the batch are skipped.
+pub fn reorderings<T: Clone + PartialEq>(batch: &[T]) -> Vec<Tampered<Vec<T>>> {
+    let mut tampered = Vec::new();
+
+    for index in 1..batch.len() {
+        let mut swapped = batch.to_vec();
+        swapped.swap(index - 1, index);
+        tampered.push(Tampered::new(
+            format!("entries {} and {} swapped", index - 1, index),
+            swapped,
+        ));
+    }
+
+    let mut reversed = batch.to_vec();
+    reversed.reverse();
+    tampered.push(Tampered::new(String::from("reversed"), reversed));
+
+    let mut rotated = batch.to_vec();
+    rotated.rotate_left(1);
+    tampered.push(Tampered::new(String::from("rotated by one"), rotated));
+
+    tampered.retain(|tampered| tampered.value != batch);
+
+    if let Some((first, rest)) = batch.split_first() {
+        tampered.push(Tampered::new(
+            String::from("last entry dropped"),
+            batch[..batch.len() - 1].to_vec(),
+        ));
+
+        let mut duplicated = alloc::vec![first.clone(), first.clone()];
+        duplicated.extend_from_slice(rest);
+        tampered.push(Tampered::new(
+            String::from("first entry duplicated"),
+            duplicated,
+        ));
+    }
+
+    tampered
+}
+
+/// Applies [bit_flips] to the encoding of `element`, and keeps the ones which
+/// still decode to a valid [EvaluationElement], so they reach verification
+pub fn evaluation_elements<CS: CipherSuite>(
+    element: &EvaluationElement<CS>,
+) -> Vec<Tampered<EvaluationElement<CS>>> {
+    bit_flips(&element.serialize())
+        .into_iter()
+        .filter_map(|tampered| {
+            EvaluationElement::deserialize(&tampered.value)
+                .ok()
+                .map(|value| Tampered::new(tampered.description, value))
+        })
+        .collect()
+}
+
+/// Applies [bit_flips] to the encoding of `proof`, and keeps the ones which
+/// still decode to a valid [Proof], so they reach verification. Additionally
+/// swaps both scalars of the proof.
+pub fn proofs<CS: CipherSuite>(proof: &Proof<CS>) -> Vec<Tampered<Proof<CS>>> {
+    let mut tampered: Vec<_> = bit_flips(&proof.serialize())
+        .into_iter()
+        .filter_map(|tampered| {
+            Proof::deserialize(&tampered.value)
+                .ok()
+                .map(|value| Tampered::new(tampered.description, value))
+        })
+        .collect();
+
+    tampered.push(Tampered::new(
+        String::from("scalars swapped"),
+        Proof {
+            c_scalar: proof.s_scalar,
+            s_scalar: proof.c_scalar,
+        },
+    ));
+
+    tampered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        errors::InternalError, test_util::rng::TestRng, tests::Ristretto255Sha512, Metadata,
+        VerifiableClient, VerifiableServer,
+    };
+
+    #[test]
+    fn test_batch_tampering() -> Result<(), InternalError> {
+        let mut rng = TestRng::default();
+        let metadata = Metadata::none();
+        let inputs = [&b"input 1"[..], b"input 2", b"input 3"];
+
+        let server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng)?;
+        let mut clients = Vec::new();
+        let mut blinded_elements = Vec::new();
+        for input in &inputs {
+            let client_blind_result = VerifiableClient::blind(input, &mut rng)?;
+            clients.push(client_blind_result.state);
+            blinded_elements.push(client_blind_result.message);
+        }
+        let server_result = server.batch_evaluate(&mut rng, &blinded_elements, &metadata)?;
+
+        let finalize = |messages: &[EvaluationElement<Ristretto255Sha512>], proof| {
+            VerifiableClient::batch_finalize(
+                &inputs,
+                &clients,
+                messages,
+                proof,
+                server.get_public_key(),
+                &metadata,
+            )
+            .and_then(|outputs| outputs.collect::<Result<Vec<_>, _>>())
+        };
+
+        finalize(&server_result.messages, server_result.proof.clone())?;
+
+        for tampered in reorderings(&server_result.messages) {
+            assert!(
+                finalize(&tampered.value, server_result.proof.clone()).is_err(),
+                "accepted messages with {}",
+                tampered.description
+            );
+        }
+
+        for tampered in evaluation_elements(&server_result.messages[1]) {
+            let mut messages = server_result.messages.clone();
+            messages[1] = tampered.value;
+            assert!(
+                finalize(&messages, server_result.proof.clone()).is_err(),
+                "accepted a message with {}",
+                tampered.description
+            );
+        }
+
+        for tampered in proofs(&server_result.proof) {
+            assert!(
+                finalize(&server_result.messages, tampered.value).is_err(),
+                "accepted a proof with {}",
+                tampered.description
+            );
+        }
+
+        for tampered in truncations(&server_result.proof.serialize()) {
+            assert!(Proof::<Ristretto255Sha512>::deserialize(&tampered.value).is_err());
+        }
+
+        Ok(())
+    }
+}
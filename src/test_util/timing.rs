@@ -0,0 +1,323 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! A statistical timing-leak test in the style of
+//! [dudect](https://eprint.iacr.org/2016/1123.pdf).
+//!
+//! Every operation is measured for two classes of secret inputs: a fixed
+//! secret and freshly sampled random secrets, in random order. A Welch's
+//! t-test is then computed between the timings of both classes, once on all
+//! measurements and once for each of several upper percentiles cropped away.
+//! A large t-statistic indicates that the running time depends on the secret.
+//!
+//! Timing measurements are noisy, so a [TimingReport] should be evaluated with
+//! a sufficiently large number of samples, on a quiet machine, and in release
+//! mode.
+
+use crate::{
+    ciphersuite::CipherSuite, errors::InternalError, group::Group, EvaluationElement, Metadata,
+    NonVerifiableClient, NonVerifiableServer, VerifiableClient, VerifiableServer,
+};
+use alloc::vec::Vec;
+use core::iter;
+use rand::{CryptoRng, RngCore};
+use std::time::Instant;
+
+/// The t-statistic above which dudect considers an operation to be
+/// definitely not constant-time
+pub const DEFAULT_THRESHOLD: f64 = 10.0;
+
+// The number of cropped percentiles the t-test is additionally computed for
+const CROPS: usize = 10;
+
+static FIXED_SEED: &[u8] = b"voprf timing fixed seed";
+static INPUT: &[u8] = b"voprf timing input";
+
+/// The class of a secret input
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Class {
+    /// The same secret for every measurement
+    Fixed,
+    /// A freshly sampled secret for every measurement
+    Random,
+}
+
+/// The result of a timing-leak test
+#[derive(Clone, Copy, Debug)]
+pub struct TimingReport {
+    /// The number of measurements taken
+    pub samples: usize,
+    /// The largest absolute t-statistic over all percentile crops
+    pub max_t: f64,
+}
+
+impl TimingReport {
+    /// Returns `true` if no timing difference between the classes was
+    /// detected, meaning that [TimingReport::max_t] stayed below the given
+    /// `threshold`, for example [DEFAULT_THRESHOLD]
+    pub fn is_constant_time(&self, threshold: f64) -> bool {
+        self.max_t < threshold
+    }
+}
+
+/// Measures `operation` for `samples` inputs produced by `prepare`, each of
+/// which is randomly assigned to a [Class]. All inputs are prepared before any
+/// measurement is taken, so that only `operation` is timed.
+pub fn measure<R: RngCore + CryptoRng, I, O>(
+    rng: &mut R,
+    samples: usize,
+    mut prepare: impl FnMut(&mut R, Class) -> Result<I, InternalError>,
+    mut operation: impl FnMut(I) -> O,
+) -> Result<TimingReport, InternalError> {
+    let mut classes = Vec::with_capacity(samples);
+    let mut inputs = Vec::with_capacity(samples);
+
+    for _ in 0..samples {
+        let class = match rng.next_u32() & 1 {
+            0 => Class::Fixed,
+            _ => Class::Random,
+        };
+        inputs.push(prepare(rng, class)?);
+        classes.push(class);
+    }
+
+    // Outputs are kept around, so that the operation can't be optimized away
+    let mut outputs = Vec::with_capacity(samples);
+    let mut timings = Vec::with_capacity(samples);
+
+    for input in inputs {
+        let start = Instant::now();
+        outputs.push(operation(input));
+        timings.push(start.elapsed().as_nanos() as f64);
+    }
+
+    drop(outputs);
+
+    let mut sorted = timings.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).expect("timings are never NaN"));
+
+    let max_t = iter::once(f64::INFINITY)
+        .chain((0..CROPS).map(|i| {
+            let fraction = 1.0 - 0.5_f64.powf(10.0 * (i + 1) as f64 / CROPS as f64);
+            sorted[((sorted.len() as f64 * fraction) as usize).min(sorted.len() - 1)]
+        }))
+        .map(|crop| {
+            let mut fixed = Welford::default();
+            let mut random = Welford::default();
+
+            for (class, &timing) in classes.iter().zip(&timings) {
+                if timing <= crop {
+                    match class {
+                        Class::Fixed => fixed.push(timing),
+                        Class::Random => random.push(timing),
+                    }
+                }
+            }
+
+            welch_t(&fixed, &random).abs()
+        })
+        .fold(0.0, f64::max);
+
+    Ok(TimingReport { samples, max_t })
+}
+
+/// Tests [NonVerifiableServer::evaluate] with a fixed against random private
+/// keys
+pub fn test_evaluate<CS: CipherSuite, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    samples: usize,
+) -> Result<TimingReport, InternalError> {
+    let metadata = Metadata::none();
+    let blinded_element = NonVerifiableClient::<CS>::blind(INPUT, rng)?.message;
+
+    measure(
+        rng,
+        samples,
+        |rng, class| {
+            let server = match class {
+                Class::Fixed => NonVerifiableServer::<CS>::new_from_seed(FIXED_SEED)?,
+                Class::Random => NonVerifiableServer::<CS>::new(rng)?,
+            };
+            Ok((server, blinded_element.clone()))
+        },
+        |(server, blinded_element)| server.evaluate(blinded_element, &metadata),
+    )
+}
+
+/// Tests [NonVerifiableClient::finalize] with a fixed against random blinds
+pub fn test_finalize<CS: CipherSuite, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    samples: usize,
+) -> Result<TimingReport, InternalError> {
+    let metadata = Metadata::none();
+    let fixed_blind = fixed_scalar::<CS>()?;
+    let server = NonVerifiableServer::<CS>::new(rng)?;
+    let evaluation_element = server
+        .evaluate(
+            NonVerifiableClient::<CS>::blind(INPUT, rng)?.message,
+            &metadata,
+        )?
+        .message;
+
+    measure(
+        rng,
+        samples,
+        |rng, class| {
+            let blind = match class {
+                Class::Fixed => fixed_blind,
+                Class::Random => CS::Group::random_nonzero_scalar(rng),
+            };
+            let client =
+                NonVerifiableClient::<CS>::deserialize(&CS::Group::scalar_as_bytes(blind))?;
+            Ok((client, evaluation_element.clone()))
+        },
+        |(client, evaluation_element)| client.finalize_once(INPUT, evaluation_element, &metadata),
+    )
+}
+
+/// Tests [Group::from_scalar_slice] with a fixed against random scalars
+pub fn test_scalar_deserialization<CS: CipherSuite, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    samples: usize,
+) -> Result<TimingReport, InternalError> {
+    let fixed_scalar = CS::Group::scalar_as_bytes(fixed_scalar::<CS>()?);
+
+    measure(
+        rng,
+        samples,
+        |rng, class| {
+            Ok(match class {
+                Class::Fixed => fixed_scalar.clone(),
+                Class::Random => CS::Group::scalar_as_bytes(CS::Group::random_nonzero_scalar(rng)),
+            })
+        },
+        |scalar| CS::Group::from_scalar_slice(&scalar),
+    )
+}
+
+/// Tests the proof verification in [VerifiableClient::finalize] with a fixed
+/// against random private keys of the server
+pub fn test_proof_verification<CS: CipherSuite, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    samples: usize,
+) -> Result<TimingReport, InternalError> {
+    let metadata = Metadata::none();
+
+    measure(
+        rng,
+        samples,
+        |rng, class| {
+            let server = match class {
+                Class::Fixed => VerifiableServer::<CS>::new_from_seed(FIXED_SEED)?,
+                Class::Random => VerifiableServer::<CS>::new(rng)?,
+            };
+            let client_blind_result = VerifiableClient::<CS>::blind(INPUT, rng)?;
+            let server_result = server.evaluate(rng, client_blind_result.message, &metadata)?;
+            Ok((
+                client_blind_result.state,
+                server_result.message,
+                server_result.proof,
+                server.get_public_key(),
+            ))
+        },
+        |(client, evaluation_element, proof, pk): (_, EvaluationElement<CS>, _, _)| {
+            client.finalize_once(INPUT, evaluation_element, proof, pk, &metadata)
+        },
+    )
+}
+
+// A fixed, non-zero scalar
+fn fixed_scalar<CS: CipherSuite>() -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    CS::Group::hash_to_scalar::<CS>(&[FIXED_SEED], &[FIXED_SEED])
+}
+
+// Online mean and variance, see Welford's algorithm
+#[derive(Default)]
+struct Welford {
+    n: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Welford {
+    fn push(&mut self, x: f64) {
+        self.n += 1.0;
+        let delta = x - self.mean;
+        self.mean += delta / self.n;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn variance(&self) -> f64 {
+        self.m2 / (self.n - 1.0)
+    }
+}
+
+fn welch_t(a: &Welford, b: &Welford) -> f64 {
+    if a.n < 2.0 || b.n < 2.0 {
+        return 0.0;
+    }
+
+    let denominator = (a.variance() / a.n + b.variance() / b.n).sqrt();
+
+    if denominator == 0.0 {
+        return 0.0;
+    }
+
+    (a.mean - b.mean) / denominator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Ristretto255Sha512;
+    use rand::rngs::OsRng;
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn test_detects_leak() -> Result<(), InternalError> {
+        let report = measure(
+            &mut OsRng,
+            200,
+            |_, class| {
+                Ok(match class {
+                    Class::Fixed => Duration::from_micros(0),
+                    Class::Random => Duration::from_micros(200),
+                })
+            },
+            thread::sleep,
+        )?;
+
+        assert_eq!(report.samples, 200);
+        assert!(!report.is_constant_time(DEFAULT_THRESHOLD));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_harnesses() -> Result<(), InternalError> {
+        // Too few samples to be meaningful, this only checks that the harnesses run
+        let mut rng = OsRng;
+        assert_eq!(
+            test_evaluate::<Ristretto255Sha512, _>(&mut rng, 10)?.samples,
+            10
+        );
+        assert_eq!(
+            test_finalize::<Ristretto255Sha512, _>(&mut rng, 10)?.samples,
+            10
+        );
+        assert_eq!(
+            test_scalar_deserialization::<Ristretto255Sha512, _>(&mut rng, 10)?.samples,
+            10
+        );
+        assert_eq!(
+            test_proof_verification::<Ristretto255Sha512, _>(&mut rng, 10)?.samples,
+            10
+        );
+
+        Ok(())
+    }
+}
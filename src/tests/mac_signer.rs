@@ -0,0 +1,50 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! A MAC standing in for a signature scheme in tests of the `receipts` and
+//! `transparency` features
+
+use alloc::vec::Vec;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+use signature::{Signature, Signer, Verifier};
+
+#[derive(Clone, Debug)]
+pub(crate) struct Tag(Vec<u8>);
+
+impl AsRef<[u8]> for Tag {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Signature for Tag {
+    fn from_bytes(bytes: &[u8]) -> Result<Self, signature::Error> {
+        Ok(Self(bytes.to_vec()))
+    }
+}
+
+pub(crate) struct MacKey(pub(crate) [u8; 32]);
+
+impl Signer<Tag> for MacKey {
+    fn try_sign(&self, msg: &[u8]) -> Result<Tag, signature::Error> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.0).map_err(|_| signature::Error::new())?;
+        mac.update(msg);
+        Ok(Tag(mac.finalize().into_bytes().to_vec()))
+    }
+}
+
+impl Verifier<Tag> for MacKey {
+    fn verify(&self, msg: &[u8], signature: &Tag) -> Result<(), signature::Error> {
+        if self.try_sign(msg)?.0 == signature.0 {
+            Ok(())
+        } else {
+            Err(signature::Error::new())
+        }
+    }
+}
@@ -5,8 +5,13 @@
 // License, Version 2.0 found in the LICENSE-APACHE file in the root directory
 // of this source tree.
 
+#[cfg(any(feature = "receipts", feature = "transparency"))]
+pub(crate) mod mac_signer;
 mod mock_rng;
+mod no_alloc;
 mod parser;
+mod rfc9497_test_vectors;
+mod stack;
 mod voprf_test_vectors;
 mod voprf_vectors;
 
@@ -17,6 +22,26 @@ impl crate::CipherSuite for Ristretto255Sha512 {
     type Hash = sha2::Sha512;
 }
 
+pub(crate) struct Ristretto255Sha3_512;
+impl crate::CipherSuite for Ristretto255Sha3_512 {
+    type Group = curve25519_dalek::ristretto::RistrettoPoint;
+    type Hash = sha3::Sha3_512;
+}
+
+pub(crate) struct Ristretto255Blake2b;
+impl crate::CipherSuite for Ristretto255Blake2b {
+    type Group = curve25519_dalek::ristretto::RistrettoPoint;
+    type Hash = blake2::Blake2b;
+}
+
+#[cfg(feature = "mock")]
+pub(crate) struct MockSha256;
+#[cfg(feature = "mock")]
+impl crate::CipherSuite for MockSha256 {
+    type Group = crate::group::MockGroup;
+    type Hash = sha2::Sha256;
+}
+
 #[cfg(feature = "p256")]
 pub(crate) struct P256Sha256;
 #[cfg(feature = "p256")]
@@ -0,0 +1,260 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Checks that the base protocol and the fixed-size batch paths don't perform
+//! any heap allocations. A global allocator counts the allocations made by the
+//! current thread, so that tests running in parallel don't interfere.
+
+// Implementing `GlobalAlloc` is inherently unsafe
+#![allow(unsafe_code)]
+
+use crate::{
+    tests::Ristretto255Sha512, Metadata, NonVerifiableClient, NonVerifiableClientBlindResult,
+    NonVerifiableServer, PreparedEvaluationElement, VerifiableClient, VerifiableClientBlindResult,
+    VerifiableServer, VerifiableServerBatchEvaluatePrepareResult,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count_allocation() {
+    // Ignore allocations made while the thread-local is being torn down
+    let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// Runs `f` and asserts that it didn't allocate on the current thread
+fn assert_no_alloc<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    let after = ALLOCATIONS.with(Cell::get);
+    assert_eq!(before, after, "`{}` performed a heap allocation", name);
+    result
+}
+
+// Like `assert_no_alloc`, but for creating private keys, which are stored in a
+// dedicated allocation with the `mlock` feature
+fn assert_key_no_alloc<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    if cfg!(feature = "mlock") {
+        f()
+    } else {
+        assert_no_alloc(name, f)
+    }
+}
+
+#[test]
+fn test_base_no_alloc() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let metadata = Metadata::none();
+    let input = b"input";
+
+    let server = assert_key_no_alloc("NonVerifiableServer::new", || {
+        NonVerifiableServer::<Ristretto255Sha512>::new(&mut rng).unwrap()
+    });
+    let NonVerifiableClientBlindResult { state, message } =
+        assert_no_alloc("NonVerifiableClient::blind", || {
+            NonVerifiableClient::<Ristretto255Sha512>::blind(input, &mut rng).unwrap()
+        });
+    let server_result = assert_no_alloc("NonVerifiableServer::evaluate", || {
+        server.evaluate(message, &metadata).unwrap()
+    });
+    let _client_finalize_result = assert_no_alloc("NonVerifiableClient::finalize", || {
+        state
+            .finalize_once(input, server_result.message, &metadata)
+            .unwrap()
+    });
+}
+
+#[test]
+fn test_verifiable_no_alloc() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let metadata = Metadata::none();
+    let input = b"input";
+
+    let server = assert_key_no_alloc("VerifiableServer::new", || {
+        VerifiableServer::<Ristretto255Sha512>::new(&mut rng).unwrap()
+    });
+    let VerifiableClientBlindResult { state, message } =
+        assert_no_alloc("VerifiableClient::blind", || {
+            VerifiableClient::<Ristretto255Sha512>::blind(input, &mut rng).unwrap()
+        });
+    let server_result = assert_no_alloc("VerifiableServer::evaluate", || {
+        server.evaluate(&mut rng, message, &metadata).unwrap()
+    });
+    let _client_finalize_result = assert_no_alloc("VerifiableClient::finalize", || {
+        state
+            .finalize_once(
+                input,
+                server_result.message,
+                server_result.proof,
+                server.get_public_key(),
+                &metadata,
+            )
+            .unwrap()
+    });
+}
+
+#[test]
+fn test_fixed_size_batch_no_alloc() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let metadata = Metadata::none();
+    let inputs = [&b"input 1"[..], b"input 2", b"input 3"];
+
+    let server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng).unwrap();
+    let client_blind_results = [
+        VerifiableClient::<Ristretto255Sha512>::blind(inputs[0], &mut rng).unwrap(),
+        VerifiableClient::<Ristretto255Sha512>::blind(inputs[1], &mut rng).unwrap(),
+        VerifiableClient::<Ristretto255Sha512>::blind(inputs[2], &mut rng).unwrap(),
+    ];
+    let clients = [
+        client_blind_results[0].state.clone(),
+        client_blind_results[1].state.clone(),
+        client_blind_results[2].state.clone(),
+    ];
+    let messages = [
+        client_blind_results[0].message.clone(),
+        client_blind_results[1].message.clone(),
+        client_blind_results[2].message.clone(),
+    ];
+
+    let (messages, proof) = assert_no_alloc("VerifiableServer::batch_evaluate_*", || {
+        let VerifiableServerBatchEvaluatePrepareResult {
+            mut prepared_evaluation_elements,
+            t,
+        } = server
+            .batch_evaluate_prepare(messages.iter(), &metadata)
+            .unwrap();
+        let prepared_evaluation_elements: [PreparedEvaluationElement<_>; 3] = [
+            prepared_evaluation_elements.next().unwrap(),
+            prepared_evaluation_elements.next().unwrap(),
+            prepared_evaluation_elements.next().unwrap(),
+        ];
+        let mut finish_result = VerifiableServer::batch_evaluate_finish(
+            &mut rng,
+            &messages,
+            &prepared_evaluation_elements,
+            &t,
+        )
+        .unwrap();
+        let messages = [
+            finish_result.messages.next().unwrap(),
+            finish_result.messages.next().unwrap(),
+            finish_result.messages.next().unwrap(),
+        ];
+        (messages, finish_result.proof)
+    });
+
+    assert_no_alloc("VerifiableClient::batch_finalize", || {
+        for output in VerifiableClient::batch_finalize(
+            &inputs,
+            &clients,
+            &messages,
+            proof,
+            server.get_public_key(),
+            &metadata,
+        )
+        .unwrap()
+        {
+            output.unwrap();
+        }
+    });
+}
+
+#[test]
+fn test_array_batch_no_alloc() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let metadata = Metadata::none();
+    let inputs = [&b"input 1"[..], b"input 2", b"input 3"];
+
+    let server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng).unwrap();
+    let client_blind_results = [
+        VerifiableClient::<Ristretto255Sha512>::blind(inputs[0], &mut rng).unwrap(),
+        VerifiableClient::<Ristretto255Sha512>::blind(inputs[1], &mut rng).unwrap(),
+        VerifiableClient::<Ristretto255Sha512>::blind(inputs[2], &mut rng).unwrap(),
+    ];
+    let clients = [
+        client_blind_results[0].state.clone(),
+        client_blind_results[1].state.clone(),
+        client_blind_results[2].state.clone(),
+    ];
+    let messages = [
+        client_blind_results[0].message.clone(),
+        client_blind_results[1].message.clone(),
+        client_blind_results[2].message.clone(),
+    ];
+
+    let server_result = assert_no_alloc("VerifiableServer::batch_evaluate_array", || {
+        server
+            .batch_evaluate_array(&mut rng, &messages, &metadata)
+            .unwrap()
+    });
+    assert_no_alloc("VerifiableClient::batch_finalize_array", || {
+        VerifiableClient::batch_finalize_array(
+            &inputs,
+            &clients,
+            &server_result.messages,
+            server_result.proof,
+            server.get_public_key(),
+            &metadata,
+        )
+        .unwrap()
+    });
+}
+
+#[test]
+fn test_input_parts_no_alloc() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let metadata = Metadata::none();
+    let input = [&b"first field"[..], b"second field"];
+
+    let server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng).unwrap();
+    let VerifiableClientBlindResult { state, message } =
+        assert_no_alloc("VerifiableClient::blind_parts", || {
+            VerifiableClient::<Ristretto255Sha512>::blind_parts(&input, &mut rng).unwrap()
+        });
+    let server_result = server.evaluate(&mut rng, message, &metadata).unwrap();
+    let _client_finalize_result = assert_no_alloc("VerifiableClient::finalize_parts_once", || {
+        state
+            .finalize_parts_once(
+                &input,
+                server_result.message,
+                server_result.proof,
+                server.get_public_key(),
+                &metadata,
+            )
+            .unwrap()
+    });
+}
@@ -0,0 +1,289 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! The ristretto255-SHA512 test vectors of RFC 9497, Appendix A.1
+
+use crate::{
+    ciphersuite::CipherSuite,
+    errors::InternalError,
+    group::Group,
+    rfc9497::{
+        deterministic_blind, OprfClient, OprfServer, PoprfClient, PoprfServer, VoprfClient,
+        VoprfServer,
+    },
+    tests::Ristretto255Sha512,
+    voprf::{
+        BlindedElement, EvaluationElement, Metadata, Mode, NonVerifiableServer,
+        PoprfServer as Draft07PoprfServer, Proof, VerifiableServer,
+    },
+};
+use generic_array::GenericArray;
+
+type CS = Ristretto255Sha512;
+
+const SEED: &str = "a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3";
+const KEY_INFO: &str = "74657374206b6579";
+const INFO: &str = "7465737420696e666f";
+const INPUT: &str = "00";
+const BLIND: &str = "64d37aed22a27f5191de1c1d69fadb899d8862b58eb4220029e036ec4c1f6706";
+const PROOF_RANDOM_SCALAR: &str =
+    "222a5e897cf59db8145db8d16e597e8facb80ae7d4e26d9881aa6f61d645fc0e";
+
+struct TestVector {
+    sksm: &'static str,
+    blinded_element: &'static str,
+    evaluation_element: &'static str,
+    proof: &'static str,
+    output: &'static str,
+}
+
+const OPRF: TestVector = TestVector {
+    sksm: "5ebcea5ee37023ccb9fc2d2019f9d7737be85591ae8652ffa9ef0f4d37063b0e",
+    blinded_element: "609a0ae68c15a3cf6903766461307e5c8bb2f95e7e6550e1ffa2dc99e412803c",
+    evaluation_element: "7ec6578ae5120958eb2db1745758ff379e77cb64fe77b0b2d8cc917ea0869c7e",
+    proof: "",
+    output: "527759c3d9366f277d8c6020418d96bb393ba2afb20ff90df23fb7708264e2f3\
+             ab9135e3bd69955851de4b1f9fe8a0973396719b7912ba9ee8aa7d0b5e24bcf6",
+};
+
+const VOPRF: TestVector = TestVector {
+    sksm: "e6f73f344b79b379f1a0dd37e07ff62e38d9f71345ce62ae3a9bc60b04ccd909",
+    blinded_element: "863f330cc1a1259ed5a5998a23acfd37fb4351a793a5b3c090b642ddc439b945",
+    evaluation_element: "aa8fa048764d5623868679402ff6108d2521884fa138cd7f9c7669a9a014267e",
+    proof: "ddef93772692e535d1a53903db24367355cc2cc78de93b3be5a8ffcc6985dd06\
+            6d4346421d17bf5117a2a1ff0fcb2a759f58a539dfbe857a40bce4cf49ec600d",
+    output: "b58cfbe118e0cb94d79b5fd6a6dafb98764dff49c14e1770b566e42402da1a7d\
+             a4d8527693914139caee5bd03903af43a491351d23b430948dd50cde10d32b3c",
+};
+
+const POPRF: TestVector = TestVector {
+    sksm: "145c79c108538421ac164ecbe131942136d5570b16d8bf41a24d4337da981e07",
+    blinded_element: "c8713aa89241d6989ac142f22dba30596db635c772cbf25021fdd8f3d461f715",
+    evaluation_element: "1a4b860d808ff19624731e67b5eff20ceb2df3c3c03b906f5693e2078450d874",
+    proof: "41ad1a291aa02c80b0915fbfbb0c0afa15a57e2970067a602ddb9e8fd6b7100d\
+            e32e1ecff943a36f0b10e3dae6bd266cdeb8adf825d86ef27dbc6c0e30c52206",
+    output: "ca688351e88afb1d841fde4401c79efebb2eb75e7998fa9737bd5a82a152406d\
+             38bd29f680504e54fd4587eddcf2f37a2617ac2fbd2993f7bdf45442ace7d221",
+};
+
+fn decode(hex: &str) -> alloc::vec::Vec<u8> {
+    hex::decode(hex).unwrap()
+}
+
+fn scalar(hex: &str) -> <<CS as CipherSuite>::Group as Group>::Scalar {
+    <CS as CipherSuite>::Group::from_scalar_slice(GenericArray::from_slice(&decode(hex))).unwrap()
+}
+
+#[test]
+fn test_oprf_vector() -> Result<(), InternalError> {
+    let server = OprfServer::<CS>::new_from_seed(&decode(SEED), &decode(KEY_INFO))?;
+    assert_eq!(server.serialize(), decode(OPRF.sksm));
+
+    let blind = scalar(BLIND);
+    let input = decode(INPUT);
+    let blinded_element = deterministic_blind::<CS>(&input, &blind, Mode::Base)?;
+    assert_eq!(
+        blinded_element.to_arr().to_vec(),
+        decode(OPRF.blinded_element)
+    );
+
+    let evaluation_element =
+        server.blind_evaluate(&BlindedElement::deserialize(&decode(OPRF.blinded_element))?)?;
+    assert_eq!(
+        evaluation_element.serialize(),
+        decode(OPRF.evaluation_element)
+    );
+
+    let client = OprfClient::<CS> { blind };
+    let output = client.finalize(&input, &evaluation_element)?.output;
+    assert_eq!(output.to_vec(), decode(OPRF.output));
+    assert_eq!(server.evaluate(&input)?.to_vec(), decode(OPRF.output));
+
+    Ok(())
+}
+
+#[test]
+fn test_voprf_vector() -> Result<(), InternalError> {
+    let server = VoprfServer::<CS>::new_from_seed(&decode(SEED), &decode(KEY_INFO))?;
+    assert_eq!(&server.serialize()[..32], &decode(VOPRF.sksm)[..]);
+
+    let blind = scalar(BLIND);
+    let input = decode(INPUT);
+    let blinded_element = deterministic_blind::<CS>(&input, &blind, Mode::Verifiable)?;
+    assert_eq!(
+        blinded_element.to_arr().to_vec(),
+        decode(VOPRF.blinded_element)
+    );
+
+    let result = server.batch_blind_evaluate_with_proof_nonce(
+        &[BlindedElement::deserialize(&decode(VOPRF.blinded_element))?],
+        &scalar(PROOF_RANDOM_SCALAR),
+    )?;
+    assert_eq!(
+        result.messages[0].serialize(),
+        decode(VOPRF.evaluation_element)
+    );
+    assert_eq!(result.proof.serialize(), decode(VOPRF.proof));
+
+    let client = VoprfClient::<CS> {
+        blind,
+        blinded_element,
+    };
+    let output = client
+        .finalize(
+            &input,
+            &EvaluationElement::deserialize(&decode(VOPRF.evaluation_element))?,
+            &Proof::deserialize(&decode(VOPRF.proof))?,
+            server.get_public_key(),
+        )?
+        .output;
+    assert_eq!(output.to_vec(), decode(VOPRF.output));
+    assert_eq!(server.evaluate(&input)?.to_vec(), decode(VOPRF.output));
+
+    Ok(())
+}
+
+#[test]
+fn test_poprf_vector() -> Result<(), InternalError> {
+    let server = PoprfServer::<CS>::new_from_seed(&decode(SEED), &decode(KEY_INFO))?;
+    assert_eq!(&server.serialize()[..32], &decode(POPRF.sksm)[..]);
+
+    let blind = scalar(BLIND);
+    let input = decode(INPUT);
+    let info = Metadata(decode(INFO));
+    let blinded_element = deterministic_blind::<CS>(&input, &blind, Mode::Poprf)?;
+    assert_eq!(
+        blinded_element.to_arr().to_vec(),
+        decode(POPRF.blinded_element)
+    );
+
+    let result = server.batch_blind_evaluate_with_proof_nonce(
+        &[BlindedElement::deserialize(&decode(POPRF.blinded_element))?],
+        &info,
+        &scalar(PROOF_RANDOM_SCALAR),
+    )?;
+    assert_eq!(
+        result.messages[0].serialize(),
+        decode(POPRF.evaluation_element)
+    );
+    assert_eq!(result.proof.serialize(), decode(POPRF.proof));
+
+    let client = PoprfClient::<CS> {
+        blind,
+        blinded_element,
+    };
+    let output = client
+        .finalize(
+            &input,
+            &EvaluationElement::deserialize(&decode(POPRF.evaluation_element))?,
+            &Proof::deserialize(&decode(POPRF.proof))?,
+            server.get_public_key(),
+            &info,
+        )?
+        .output;
+    assert_eq!(output.to_vec(), decode(POPRF.output));
+    assert_eq!(
+        server.evaluate(&input, &info)?.to_vec(),
+        decode(POPRF.output)
+    );
+
+    // The proof doesn't verify with other metadata
+    assert!(client
+        .finalize(
+            &input,
+            &EvaluationElement::deserialize(&decode(POPRF.evaluation_element))?,
+            &Proof::deserialize(&decode(POPRF.proof))?,
+            server.get_public_key(),
+            &Metadata::none(),
+        )
+        .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_batch_and_serialization() -> Result<(), InternalError> {
+    let mut rng = rand::rngs::OsRng;
+    let inputs = [&b"input"[..], b"other input"];
+    let info = Metadata(decode(INFO));
+
+    let server = VoprfServer::<CS>::new(&mut rng)?;
+    let server = VoprfServer::<CS>::deserialize(&server.serialize())?;
+    let (clients, messages): (alloc::vec::Vec<_>, alloc::vec::Vec<_>) = inputs
+        .iter()
+        .map(|input| {
+            let result = VoprfClient::<CS>::blind(input, &mut rng).unwrap();
+            let state = VoprfClient::deserialize(&result.state.serialize()).unwrap();
+            (state, result.message)
+        })
+        .unzip();
+    let result = server.batch_blind_evaluate(&mut rng, &messages)?;
+    let outputs = VoprfClient::batch_finalize(
+        &inputs,
+        &clients,
+        &result.messages,
+        &result.proof,
+        server.get_public_key(),
+    )?;
+    for (input, output) in inputs.iter().zip(outputs) {
+        assert_eq!(*output, server.evaluate(input)?);
+    }
+
+    let server = PoprfServer::<CS>::new(&mut rng)?;
+    let server = PoprfServer::<CS>::deserialize(&server.serialize())?;
+    let (clients, messages): (alloc::vec::Vec<_>, alloc::vec::Vec<_>) = inputs
+        .iter()
+        .map(|input| {
+            let result = PoprfClient::<CS>::blind(input, &mut rng).unwrap();
+            let state = PoprfClient::deserialize(&result.state.serialize()).unwrap();
+            (state, result.message)
+        })
+        .unzip();
+    let result = server.batch_blind_evaluate(&mut rng, &messages, &info)?;
+    let outputs = PoprfClient::batch_finalize(
+        &inputs,
+        &clients,
+        &result.messages,
+        &result.proof,
+        server.get_public_key(),
+        &info,
+    )?;
+    for (input, output) in inputs.iter().zip(outputs) {
+        assert_eq!(*output, server.evaluate(input, &info)?);
+    }
+
+    // A batch proof doesn't verify for a subset of the batch
+    assert!(PoprfClient::batch_finalize(
+        &inputs[..1],
+        &clients[..1],
+        &result.messages[..1],
+        &result.proof,
+        server.get_public_key(),
+        &info,
+    )
+    .is_err());
+
+    Ok(())
+}
+
+// Servers of the draft API derive the same private keys as the RFC 9497 API
+#[test]
+fn test_derive_key_pair_with_info() -> Result<(), InternalError> {
+    let (seed, key_info) = (decode(SEED), decode(KEY_INFO));
+    let server = NonVerifiableServer::<CS>::new_from_seed_and_info(&seed, &key_info)?;
+    assert_eq!(server.serialize(), decode(OPRF.sksm));
+    let server = VerifiableServer::<CS>::new_from_seed_and_info(&seed, &key_info)?;
+    assert_eq!(&server.serialize()[..32], &decode(VOPRF.sksm)[..]);
+    let server = Draft07PoprfServer::<CS>::new_from_seed_and_info(&seed, &key_info)?;
+    assert_eq!(&server.serialize()[..32], &decode(POPRF.sksm)[..]);
+
+    // The info is part of the derivation
+    let server = NonVerifiableServer::<CS>::new_from_seed_and_info(&seed, b"")?;
+    assert_ne!(server.serialize(), decode(OPRF.sksm));
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Checks that the protocol stays within the stack bound documented in the
+//! crate root, by running it on a thread with a stack of that size. Exceeding
+//! it aborts the whole test run with a stack overflow.
+
+use crate::{
+    ciphersuite::CipherSuite, errors::InternalError, tests::Ristretto255Sha512, Metadata,
+    NonVerifiableClient, NonVerifiableServer, VerifiableClient, VerifiableServer,
+};
+use alloc::vec::Vec;
+use rand::{rngs::StdRng, SeedableRng};
+use std::thread;
+
+// Unoptimized builds use considerably more stack
+#[cfg(debug_assertions)]
+const STACK_BOUND: usize = 64 * 1024;
+#[cfg(not(debug_assertions))]
+const STACK_BOUND: usize = 16 * 1024;
+
+fn protocol<CS: CipherSuite>() -> Result<(), InternalError> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let metadata = Metadata(b"metadata".to_vec());
+
+    let server = NonVerifiableServer::<CS>::new(&mut rng)?;
+    let client_blind_result = NonVerifiableClient::<CS>::blind(b"input", &mut rng)?;
+    let server_result = server.evaluate(client_blind_result.message, &metadata)?;
+    let _client_finalize_result =
+        client_blind_result
+            .state
+            .finalize_once(b"input", server_result.message, &metadata)?;
+
+    let server = VerifiableServer::<CS>::new(&mut rng)?;
+    let client_blind_result = VerifiableClient::<CS>::blind(b"input", &mut rng)?;
+    let server_result = server.evaluate(&mut rng, client_blind_result.message, &metadata)?;
+    let _client_finalize_result = client_blind_result.state.finalize_once(
+        b"input",
+        server_result.message,
+        server_result.proof,
+        server.get_public_key(),
+        &metadata,
+    )?;
+
+    let inputs = [b"input 1", b"input 2", b"input 3"];
+    let mut clients = Vec::new();
+    let mut messages = Vec::new();
+    for input in &inputs {
+        let client_blind_result = VerifiableClient::<CS>::blind(*input, &mut rng)?;
+        clients.push(client_blind_result.state);
+        messages.push(client_blind_result.message);
+    }
+    let server_result = server.batch_evaluate(&mut rng, &messages, &metadata)?;
+    VerifiableClient::batch_finalize(
+        &inputs,
+        &clients,
+        &server_result.messages,
+        server_result.proof,
+        server.get_public_key(),
+        &metadata,
+    )?
+    .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(())
+}
+
+fn assert_stack_bound<CS: 'static + CipherSuite>() {
+    thread::Builder::new()
+        .stack_size(STACK_BOUND)
+        .spawn(protocol::<CS>)
+        .unwrap()
+        .join()
+        .unwrap()
+        .unwrap();
+}
+
+#[test]
+fn test_stack_bound() {
+    assert_stack_bound::<Ristretto255Sha512>();
+
+    #[cfg(feature = "p256")]
+    {
+        use crate::tests::P256Sha256;
+
+        assert_stack_bound::<P256Sha256>();
+    }
+}
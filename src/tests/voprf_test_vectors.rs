@@ -11,11 +11,10 @@ use crate::{
     group::Group,
     tests::{mock_rng::CycleRng, parser::*},
     voprf::{
-        BatchFinalizeInput, BlindedElement, EvaluationElement, Metadata, NonVerifiableClient,
-        NonVerifiableServer, Proof, VerifiableClient, VerifiableServer,
+        BlindedElement, EvaluationElement, Metadata, NonVerifiableClient, NonVerifiableServer,
+        Proof, VerifiableClient, VerifiableServer,
     },
 };
-use alloc::string::ToString;
 use alloc::vec::Vec;
 use generic_array::GenericArray;
 use json::JsonValue;
@@ -40,33 +39,29 @@ fn populate_test_vectors(values: &JsonValue) -> VOPRFTestVectorParameters {
         seed: decode(values, "seed"),
         sksm: decode(values, "skSm"),
         pksm: decode(values, "pkSm"),
-        input: decode_vec(&values, "Input"),
+        input: decode_vec(values, "Input"),
         info: decode(values, "Info"),
-        blind: decode_vec(&values, "Blind"),
-        blinded_element: decode_vec(&values, "BlindedElement"),
-        evaluation_element: decode_vec(&values, "EvaluationElement"),
+        blind: decode_vec(values, "Blind"),
+        blinded_element: decode_vec(values, "BlindedElement"),
+        evaluation_element: decode_vec(values, "EvaluationElement"),
         proof: decode(values, "Proof"),
         proof_random_scalar: decode(values, "ProofRandomScalar"),
-        output: decode_vec(&values, "Output"),
+        output: decode_vec(values, "Output"),
     }
 }
 
 fn decode(values: &JsonValue, key: &str) -> Vec<u8> {
     values[key]
         .as_str()
-        .and_then(|s| hex::decode(&s.to_string()).ok())
+        .and_then(|s| hex::decode(s).ok())
         .unwrap_or(vec![])
 }
 
 fn decode_vec(values: &JsonValue, key: &str) -> Vec<Vec<u8>> {
     let s = values[key].as_str().unwrap();
     let res = match s.contains(',') {
-        true => Some(
-            s.split(',')
-                .map(|x| hex::decode(&x.to_string()).unwrap())
-                .collect(),
-        ),
-        false => Some(vec![hex::decode(&s.to_string()).unwrap()]),
+        true => Some(s.split(',').map(|x| hex::decode(x).unwrap()).collect()),
+        false => Some(vec![hex::decode(s).unwrap()]),
     };
     res.unwrap()
 }
@@ -240,7 +235,7 @@ fn test_verifiable_evaluate<CS: CipherSuite>(
 
         let mut blinded_elements = vec![];
         for blinded_element_bytes in &parameters.blinded_element {
-            blinded_elements.push(BlindedElement::deserialize(&blinded_element_bytes)?);
+            blinded_elements.push(BlindedElement::deserialize(blinded_element_bytes)?);
         }
 
         let batch_evaluate_result = server.batch_evaluate(
@@ -267,14 +262,13 @@ fn test_base_finalize<CS: CipherSuite>(
 ) -> Result<(), InternalError> {
     for parameters in tvs {
         for i in 0..parameters.input.len() {
-            let client = NonVerifiableClient::<CS>::from_data_and_blind(
-                &parameters.input[i],
-                &<CS::Group as Group>::from_scalar_slice(&GenericArray::clone_from_slice(
-                    &parameters.blind[i],
-                ))?,
-            );
+            let client =
+                NonVerifiableClient::<CS>::from_blind(&<CS::Group as Group>::from_scalar_slice(
+                    &GenericArray::clone_from_slice(&parameters.blind[i]),
+                )?);
 
-            let client_finalize_result = client.finalize(
+            let client_finalize_result = client.finalize_once(
+                &parameters.input[i],
                 EvaluationElement::deserialize(&parameters.evaluation_element[i])?,
                 &Metadata(parameters.info.clone()),
             )?;
@@ -294,8 +288,7 @@ fn test_verifiable_finalize<CS: CipherSuite>(
     for parameters in tvs {
         let mut clients = vec![];
         for i in 0..parameters.input.len() {
-            let client = VerifiableClient::<CS>::from_data_and_blind(
-                &parameters.input[i],
+            let client = VerifiableClient::<CS>::from_blind_and_element(
                 &<CS::Group as Group>::from_scalar_slice(&GenericArray::clone_from_slice(
                     &parameters.blind[i],
                 ))?,
@@ -303,32 +296,30 @@ fn test_verifiable_finalize<CS: CipherSuite>(
                     &parameters.blinded_element[i],
                 ))?,
             );
-            clients.push(client.clone());
+            clients.push(client);
         }
 
-        let batch_finalize_input = BatchFinalizeInput::new(
-            clients,
-            parameters
-                .evaluation_element
-                .iter()
-                .map(|x| EvaluationElement::deserialize(x).unwrap())
-                .collect(),
-        );
+        let messages: Vec<_> = parameters
+            .evaluation_element
+            .iter()
+            .map(|x| EvaluationElement::deserialize(x).unwrap())
+            .collect();
 
+        let metadata = Metadata(parameters.info.clone());
         let batch_result = VerifiableClient::batch_finalize(
-            batch_finalize_input,
+            &parameters.input,
+            &clients,
+            &messages,
             Proof::deserialize(&parameters.proof)?,
             CS::Group::from_element_slice(GenericArray::from_slice(&parameters.pksm))?,
-            &Metadata(parameters.info.clone()),
+            &metadata,
         )?;
 
         assert_eq!(
             parameters.output,
             batch_result
-                .outputs
-                .iter()
-                .map(|arr| arr.to_vec())
-                .collect::<Vec<Vec<u8>>>()
+                .map(|arr| arr.map(|arr| arr.to_vec()))
+                .collect::<Result<Vec<_>, _>>()?
         );
     }
     Ok(())
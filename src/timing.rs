@@ -0,0 +1,131 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Padding of operations to a constant wall-clock duration
+
+use std::time::{Duration, Instant};
+
+/// How [ConstantDuration] waits for the remaining time
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PaddingStrategy {
+    /// Spins until the deadline, which is precise but keeps the thread busy
+    BusyWait,
+    /// Sleeps until the deadline, which frees the thread but is only as
+    /// precise as the timer of the operating system
+    Sleep,
+}
+
+/// Pads operations, like [VerifiableServer::evaluate](crate::VerifiableServer::evaluate)
+/// or [VerifiableServer::batch_evaluate](crate::VerifiableServer::batch_evaluate),
+/// to a constant wall-clock duration, for deployments which treat the time
+/// taken per request as a side channel, e.g. across metadata or key IDs:
+///
+/// ```
+/// # use voprf::CipherSuite;
+/// # struct Default;
+/// # impl CipherSuite for Default {
+/// #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+/// #     type Hash = sha2::Sha512;
+/// # }
+/// use rand::rngs::OsRng;
+/// use std::time::Duration;
+/// use voprf::{ConstantDuration, Metadata, VerifiableClient, VerifiableServer};
+///
+/// let mut rng = OsRng;
+/// let server = VerifiableServer::<Default>::new(&mut rng)?;
+/// let client_blind_result = VerifiableClient::<Default>::blind(b"input", &mut rng)?;
+///
+/// let padding = ConstantDuration::new(Duration::from_millis(50));
+/// let (server_result, within_duration) = padding.run(|| {
+///     server.evaluate(&mut rng, client_blind_result.message, &Metadata::none())
+/// });
+/// if !within_duration {
+///     // The duration is too short to hide the timing, log it
+/// }
+/// let server_result = server_result?;
+/// # Ok::<(), voprf::errors::InternalError>(())
+/// ```
+///
+/// Operations taking longer than the duration aren't interrupted, so it has
+/// to be chosen above the slowest operation measured on the deployment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ConstantDuration {
+    duration: Duration,
+    strategy: PaddingStrategy,
+}
+
+impl ConstantDuration {
+    /// Pads to `duration` with [PaddingStrategy::BusyWait]
+    pub fn new(duration: Duration) -> Self {
+        Self::with_strategy(duration, PaddingStrategy::BusyWait)
+    }
+
+    /// Pads to `duration` with the given strategy
+    pub fn with_strategy(duration: Duration, strategy: PaddingStrategy) -> Self {
+        Self { duration, strategy }
+    }
+
+    /// The duration operations are padded to
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Runs `f` and waits until the duration has passed since it was started.
+    /// Also returns `false` if `f` took longer than the duration, in which
+    /// case its timing isn't hidden.
+    pub fn run<T>(&self, f: impl FnOnce() -> T) -> (T, bool) {
+        let deadline = Instant::now() + self.duration;
+        let result = f();
+        let within_duration = Instant::now() <= deadline;
+
+        match self.strategy {
+            PaddingStrategy::BusyWait => {
+                while Instant::now() < deadline {
+                    core::hint::spin_loop();
+                }
+            }
+            PaddingStrategy::Sleep => {
+                // Sleeping can wake up early on some platforms
+                loop {
+                    let now = Instant::now();
+
+                    if now >= deadline {
+                        break;
+                    }
+
+                    std::thread::sleep(deadline - now);
+                }
+            }
+        }
+
+        (result, within_duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_duration() {
+        let duration = Duration::from_millis(20);
+
+        for strategy in [PaddingStrategy::BusyWait, PaddingStrategy::Sleep].iter() {
+            let padding = ConstantDuration::with_strategy(duration, *strategy);
+
+            let start = Instant::now();
+            let (result, within_duration) = padding.run(|| 42);
+            assert_eq!(result, 42);
+            assert!(within_duration);
+            assert!(start.elapsed() >= duration);
+
+            let (_, within_duration) =
+                padding.run(|| std::thread::sleep(duration + Duration::from_millis(5)));
+            assert!(!within_duration);
+        }
+    }
+}
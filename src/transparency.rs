@@ -0,0 +1,438 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Signed lists of server public keys for a key transparency log, enabled by
+//! the `transparency` feature
+
+use crate::{
+    ciphersuite::CipherSuite,
+    errors::{ErrorSource, InternalError},
+    group::Group,
+    key_policy::{KeyFingerprints, PinnedKeys},
+    voprf::{create_context_string, Mode},
+};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use digest::Digest;
+use generic_array::{typenum::Unsigned, GenericArray};
+use signature::{Signature, Signer, Verifier};
+
+static STR_KEY_LOG: &[u8] = b"KeyLog-";
+
+/// An entry of a key transparency log, publishing the public key a server
+/// evaluates with from the given epoch on, and the time window in which
+/// clients accept it. The times aren't interpreted by this crate beyond
+/// comparing them; they would usually be in seconds since the Unix epoch.
+pub struct KeyLogEntry<CS: CipherSuite> {
+    pk: CS::Group,
+    epoch: u64,
+    not_before: u64,
+    not_after: u64,
+}
+impl_debug_eq_hash_for!(
+    struct KeyLogEntry<CS: CipherSuite>,
+    [pk, epoch, not_before, not_after],
+    [CS::Group],
+);
+impl_clone_for!(
+    struct KeyLogEntry<CS: CipherSuite>,
+    [pk, epoch, not_before, not_after],
+    [CS::Group],
+);
+impl_serialize_and_deserialize_for!(KeyLogEntry);
+
+/// A list of [KeyLogEntry]s signed by the operator of a key transparency
+/// log. Clients verify the signature with [SignedKeyList::verify], check
+/// that the list only appends to the one they saw before with
+/// [SignedKeyList::check_extends], and pin the keys valid at the current time
+/// with [SignedKeyList::pinned_keys]. A server substituting the key for some
+/// clients then has to get the substituted key into the log, where every
+/// client and auditor sees it, or to fork the log, which clients detect once
+/// they see both branches.
+///
+/// The signature scheme is chosen by the log, through any
+/// [Signer](signature::Signer) of the [signature] crate. The signed message
+/// is the concatenation of `"KeyLog-"`, the context string, the number of
+/// entries as an 8-byte big-endian integer, and the serialized entries.
+pub struct SignedKeyList<CS: CipherSuite, S: Signature> {
+    entries: Vec<KeyLogEntry<CS>>,
+    signature: S,
+}
+
+impl<CS: CipherSuite> KeyLogEntry<CS> {
+    /// Creates an entry for `pk`, valid from `not_before` to `not_after`
+    /// inclusively
+    pub fn new(
+        pk: CS::Group,
+        epoch: u64,
+        not_before: u64,
+        not_after: u64,
+    ) -> Result<Self, InternalError> {
+        if pk.is_identity() {
+            return Err(InternalError::InvalidPublicKey);
+        }
+
+        if not_before > not_after {
+            return Err(InternalError::KeyLogError { source: None });
+        }
+
+        Ok(Self {
+            pk,
+            epoch,
+            not_before,
+            not_after,
+        })
+    }
+
+    /// The ID of the public key, its [fingerprint](KeyFingerprints::fingerprint)
+    pub fn key_id(&self) -> GenericArray<u8, <CS::Hash as Digest>::OutputSize> {
+        KeyFingerprints::<CS>::fingerprint(&self.pk)
+    }
+
+    /// The public key of the server
+    pub fn public_key(&self) -> CS::Group {
+        self.pk
+    }
+
+    /// The epoch from which on the server evaluates with the public key
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The start of the validity window
+    pub fn not_before(&self) -> u64 {
+        self.not_before
+    }
+
+    /// The end of the validity window
+    pub fn not_after(&self) -> u64 {
+        self.not_after
+    }
+
+    /// Returns `true` if `time` is within the validity window
+    pub fn is_valid_at(&self, time: u64) -> bool {
+        self.not_before <= time && time <= self.not_after
+    }
+
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        [
+            &self.epoch.to_be_bytes()[..],
+            &self.not_before.to_be_bytes(),
+            &self.not_after.to_be_bytes(),
+            &self.pk.to_arr(),
+        ]
+        .concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        if input.len() != Self::len() {
+            return Err(InternalError::SizeError {
+                expected: Self::len(),
+                got: input.len(),
+            });
+        }
+
+        let (epoch, input) = input.split_at(8);
+        let (not_before, input) = input.split_at(8);
+        let (not_after, pk) = input.split_at(8);
+
+        Self::new(
+            CS::Group::from_element_slice(GenericArray::from_slice(pk))?,
+            read_u64(epoch)?,
+            read_u64(not_before)?,
+            read_u64(not_after)?,
+        )
+    }
+
+    // The length of a serialized entry
+    fn len() -> usize {
+        24 + <CS::Group as Group>::ElemLen::USIZE
+    }
+}
+
+impl<CS: CipherSuite, S: Signature> SignedKeyList<CS, S> {
+    /// Signs `entries`, which have to be ordered by strictly increasing epoch
+    /// and publish every public key only once
+    pub fn sign(
+        signer: &impl Signer<S>,
+        entries: Vec<KeyLogEntry<CS>>,
+    ) -> Result<Self, InternalError> {
+        check_entries(&entries)?;
+
+        let message = signed_message(&entries)?;
+        let signature = signer
+            .try_sign(&message)
+            .map_err(|error| InternalError::KeyLogError {
+                source: Some(ErrorSource::new(error)),
+            })?;
+
+        Ok(Self { entries, signature })
+    }
+
+    /// Checks the signature of the log and the order of the entries
+    pub fn verify(&self, verifier: &impl Verifier<S>) -> Result<(), InternalError> {
+        check_entries(&self.entries)?;
+
+        let message = signed_message(&self.entries)?;
+        verifier
+            .verify(&message, &self.signature)
+            .map_err(|error| InternalError::KeyLogError {
+                source: Some(ErrorSource::new(error)),
+            })
+    }
+
+    /// Checks that the list starts with the entries of `previous`, a list the
+    /// client saw before, so the log was only appended to. Fails with
+    /// [InternalError::KeyLogError] if an entry was changed or removed.
+    pub fn check_extends(&self, previous: &Self) -> Result<(), InternalError> {
+        if previous.entries.len() > self.entries.len()
+            || previous
+                .entries
+                .iter()
+                .zip(&self.entries)
+                .any(|(previous, entry)| !entries_equal(previous, entry))
+        {
+            return Err(InternalError::KeyLogError { source: None });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the entry of `pk` if it is valid at `time`, and fails with
+    /// [InternalError::UntrustedPublicKey] otherwise
+    pub fn entry_for(&self, pk: &CS::Group, time: u64) -> Result<&KeyLogEntry<CS>, InternalError> {
+        self.entries
+            .iter()
+            .find(|entry| entry.pk.ct_equal(pk))
+            .filter(|entry| entry.is_valid_at(time))
+            .ok_or(InternalError::UntrustedPublicKey)
+    }
+
+    /// The public keys which are valid at `time`, as a
+    /// [KeyPolicy](crate::KeyPolicy) for
+    /// [VerifiableClient::finalize_with_key_policy_once](crate::VerifiableClient::finalize_with_key_policy_once)
+    pub fn pinned_keys(&self, time: u64) -> PinnedKeys<CS> {
+        PinnedKeys::new(
+            self.entries
+                .iter()
+                .filter(|entry| entry.is_valid_at(time))
+                .map(|entry| entry.pk)
+                .collect(),
+        )
+    }
+
+    /// The entries of the log
+    pub fn entries(&self) -> &[KeyLogEntry<CS>] {
+        &self.entries
+    }
+
+    /// The signature of the log
+    pub fn signature(&self) -> &S {
+        &self.signature
+    }
+
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut output = (self.entries.len() as u64).to_be_bytes().to_vec();
+        for entry in &self.entries {
+            output.extend_from_slice(&entry.serialize());
+        }
+        output.extend_from_slice(self.signature.as_bytes());
+        output
+    }
+
+    /// Deserialization from bytes. The signature isn't verified, see
+    /// [SignedKeyList::verify].
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalError> {
+        if input.len() < 8 {
+            return Err(InternalError::SizeError {
+                expected: 8,
+                got: input.len(),
+            });
+        }
+
+        let (count, input) = input.split_at(8);
+        let entries_len = usize::try_from(read_u64(count)?)
+            .ok()
+            .and_then(|count| count.checked_mul(KeyLogEntry::<CS>::len()))
+            .unwrap_or(usize::MAX);
+        if entries_len > input.len() {
+            return Err(InternalError::SizeError {
+                expected: entries_len.saturating_add(8),
+                got: 8 + input.len(),
+            });
+        }
+        let (entries, signature) = input.split_at(entries_len);
+
+        Ok(Self {
+            entries: entries
+                .chunks_exact(KeyLogEntry::<CS>::len())
+                .map(KeyLogEntry::deserialize)
+                .collect::<Result<_, _>>()?,
+            signature: S::from_bytes(signature).map_err(|_| InternalError::SerializationError)?,
+        })
+    }
+}
+
+impl<CS: CipherSuite, S: Signature + Clone> Clone for SignedKeyList<CS, S> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+            signature: self.signature.clone(),
+        }
+    }
+}
+
+impl<CS: CipherSuite, S: Signature> core::fmt::Debug for SignedKeyList<CS, S>
+where
+    CS::Group: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SignedKeyList")
+            .field("entries", &self.entries)
+            .field("signature", &self.signature)
+            .finish()
+    }
+}
+
+// Compares entries without requiring `PartialEq` of the group
+fn entries_equal<CS: CipherSuite>(a: &KeyLogEntry<CS>, b: &KeyLogEntry<CS>) -> bool {
+    a.pk.ct_equal(&b.pk)
+        && a.epoch == b.epoch
+        && a.not_before == b.not_before
+        && a.not_after == b.not_after
+}
+
+// Checks that the epochs are strictly increasing and that every public key
+// is published once
+fn check_entries<CS: CipherSuite>(entries: &[KeyLogEntry<CS>]) -> Result<(), InternalError> {
+    let ordered = entries.windows(2).all(|pair| pair[0].epoch < pair[1].epoch);
+    let unique = entries.iter().enumerate().all(|(index, entry)| {
+        !entries[..index]
+            .iter()
+            .any(|previous| previous.pk.ct_equal(&entry.pk))
+    });
+
+    if ordered && unique {
+        Ok(())
+    } else {
+        Err(InternalError::KeyLogError { source: None })
+    }
+}
+
+fn signed_message<CS: CipherSuite>(entries: &[KeyLogEntry<CS>]) -> Result<Vec<u8>, InternalError> {
+    let suite_id =
+        u16::try_from(CS::Group::SUITE_ID).map_err(|_| InternalError::SerializationError)?;
+    let context_string = create_context_string(Mode::Verifiable, suite_id);
+    let count = u64::try_from(entries.len()).map_err(|_| InternalError::SerializationError)?;
+
+    let mut message = [STR_KEY_LOG, &context_string, &count.to_be_bytes()].concat();
+    for entry in entries {
+        message.extend_from_slice(&entry.serialize());
+    }
+
+    Ok(message)
+}
+
+fn read_u64(bytes: &[u8]) -> Result<u64, InternalError> {
+    Ok(u64::from_be_bytes(<[u8; 8]>::try_from(bytes).map_err(
+        |_| InternalError::SizeError {
+            expected: 8,
+            got: bytes.len(),
+        },
+    )?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tests::{
+            mac_signer::{MacKey, Tag},
+            Ristretto255Sha512,
+        },
+        KeyPolicy, VerifiableServer,
+    };
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_signed_key_list() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let key = MacKey([7; 32]);
+        let old_pk = VerifiableServer::<Ristretto255Sha512>::new(&mut rng)?.get_public_key();
+        let pk = VerifiableServer::<Ristretto255Sha512>::new(&mut rng)?.get_public_key();
+        let other_pk = VerifiableServer::<Ristretto255Sha512>::new(&mut rng)?.get_public_key();
+
+        let old_entry = KeyLogEntry::new(old_pk, 1, 100, 200)?;
+        let entry = KeyLogEntry::new(pk, 2, 150, 300)?;
+        assert!(matches!(
+            KeyLogEntry::<Ristretto255Sha512>::new(pk, 3, 300, 299),
+            Err(InternalError::KeyLogError { .. })
+        ));
+        assert_eq!(KeyLogEntry::deserialize(&entry.serialize())?, entry);
+        assert_eq!(
+            entry.key_id(),
+            KeyFingerprints::<Ristretto255Sha512>::fingerprint(&pk)
+        );
+
+        let previous = SignedKeyList::<_, Tag>::sign(&key, alloc::vec![old_entry.clone()])?;
+        let list = SignedKeyList::sign(&key, alloc::vec![old_entry.clone(), entry.clone()])?;
+        let list = SignedKeyList::<Ristretto255Sha512, Tag>::deserialize(&list.serialize())?;
+        list.verify(&key)?;
+        assert!(matches!(
+            list.verify(&MacKey([8; 32])),
+            Err(InternalError::KeyLogError { .. })
+        ));
+
+        // The log only grows
+        list.check_extends(&previous)?;
+        assert!(matches!(
+            previous.check_extends(&list),
+            Err(InternalError::KeyLogError { .. })
+        ));
+        let forked = SignedKeyList::sign(
+            &key,
+            alloc::vec![KeyLogEntry::new(other_pk, 1, 100, 200)?, entry.clone()],
+        )?;
+        assert!(matches!(
+            forked.check_extends(&previous),
+            Err(InternalError::KeyLogError { .. })
+        ));
+
+        // Entries have to be ordered by epoch and publish each key once
+        assert!(matches!(
+            SignedKeyList::<_, Tag>::sign(&key, alloc::vec![entry.clone(), old_entry]),
+            Err(InternalError::KeyLogError { .. })
+        ));
+        assert!(matches!(
+            SignedKeyList::<_, Tag>::sign(
+                &key,
+                alloc::vec![entry.clone(), KeyLogEntry::new(pk, 3, 300, 400)?]
+            ),
+            Err(InternalError::KeyLogError { .. })
+        ));
+
+        // Only the keys valid at the given time are trusted
+        assert_eq!(list.entry_for(&pk, 200)?, &entry);
+        assert!(matches!(
+            list.entry_for(&pk, 301),
+            Err(InternalError::UntrustedPublicKey)
+        ));
+        assert!(matches!(
+            list.entry_for(&other_pk, 200),
+            Err(InternalError::UntrustedPublicKey)
+        ));
+        assert!(list.pinned_keys(175).is_trusted(&old_pk));
+        assert!(list.pinned_keys(175).is_trusted(&pk));
+        assert!(!list.pinned_keys(250).is_trusted(&old_pk));
+        assert!(!list.pinned_keys(250).is_trusted(&other_pk));
+
+        Ok(())
+    }
+}
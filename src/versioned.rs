@@ -0,0 +1,442 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under both the MIT license found in the
+// LICENSE-MIT file in the root directory of this source tree and the Apache
+// License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+// of this source tree.
+
+//! Clients and servers whose protocol version is selected at runtime
+
+use crate::{
+    ciphersuite::CipherSuite,
+    errors::InternalError,
+    rfc9497::{OprfClient, OprfServer, VoprfClient, VoprfServer},
+    voprf::{
+        BlindedElement, EvaluationElement, Metadata, NonVerifiableClient,
+        NonVerifiableClientFinalizeResult, NonVerifiableServer, NonVerifiableServerEvaluateResult,
+        Proof, VerifiableClient, VerifiableClientFinalizeResult, VerifiableServer,
+        VerifiableServerEvaluateResult,
+    },
+};
+use rand::{CryptoRng, RngCore};
+
+/// The version of the protocol spoken with a peer
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Version {
+    /// [draft-irtf-cfrg-voprf-07](https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-07.html),
+    /// implemented by the types at the root of the crate
+    Draft07,
+    /// [RFC 9497](https://www.rfc-editor.org/rfc/rfc9497.html), implemented
+    /// by the types of [rfc9497](crate::rfc9497)
+    Rfc9497,
+}
+
+// The metadata of draft-07 was removed from the OPRF and VOPRF modes of the RFC
+fn check_rfc9497_metadata(metadata: &Metadata) -> Result<(), InternalError> {
+    match metadata.0.is_empty() {
+        true => Ok(()),
+        false => Err(InternalError::IncompatibleModeError),
+    }
+}
+
+/// A client of the base mode of either [Version], see
+/// [NonVerifiableClient] and [OprfClient]
+///
+/// The metadata of draft-07 doesn't exist in the OPRF mode of RFC 9497, so
+/// [Version::Rfc9497] only accepts empty [Metadata] and fails with
+/// [InternalError::IncompatibleModeError] otherwise.
+pub enum VersionedNonVerifiableClient<CS: CipherSuite> {
+    /// A client of [Version::Draft07]
+    Draft07(NonVerifiableClient<CS>),
+    /// A client of [Version::Rfc9497]
+    Rfc9497(OprfClient<CS>),
+}
+
+/// A server of the base mode of either [Version], see
+/// [NonVerifiableServer] and [OprfServer]
+///
+/// Each server speaks a single version, a server migrating between versions
+/// runs one server of each with separate keys. The security analysis of
+/// either version doesn't cover a key which is also evaluated with the other.
+pub enum VersionedNonVerifiableServer<CS: CipherSuite> {
+    /// A server of [Version::Draft07]
+    Draft07(NonVerifiableServer<CS>),
+    /// A server of [Version::Rfc9497]
+    Rfc9497(OprfServer<CS>),
+}
+
+/// A client of the verifiable mode of either [Version], see
+/// [VerifiableClient] and [VoprfClient]
+///
+/// Like [VersionedNonVerifiableClient], [Version::Rfc9497] only accepts empty
+/// [Metadata].
+pub enum VersionedVerifiableClient<CS: CipherSuite> {
+    /// A client of [Version::Draft07]
+    Draft07(VerifiableClient<CS>),
+    /// A client of [Version::Rfc9497]
+    Rfc9497(VoprfClient<CS>),
+}
+
+/// A server of the verifiable mode of either [Version], see
+/// [VerifiableServer] and [VoprfServer]
+///
+/// Like [VersionedNonVerifiableServer], each server speaks a single version.
+pub enum VersionedVerifiableServer<CS: CipherSuite> {
+    /// A server of [Version::Draft07]
+    Draft07(VerifiableServer<CS>),
+    /// A server of [Version::Rfc9497]
+    Rfc9497(VoprfServer<CS>),
+}
+
+impl<CS: CipherSuite> VersionedNonVerifiableClient<CS> {
+    /// Blinds `input` with the protocol of `version`
+    pub fn blind<R: RngCore + CryptoRng>(
+        version: Version,
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<VersionedNonVerifiableClientBlindResult<CS>, InternalError> {
+        Ok(match version {
+            Version::Draft07 => {
+                let result = NonVerifiableClient::blind(input, blinding_factor_rng)?;
+                VersionedNonVerifiableClientBlindResult {
+                    state: Self::Draft07(result.state),
+                    message: result.message,
+                }
+            }
+            Version::Rfc9497 => {
+                let result = OprfClient::blind(input, blinding_factor_rng)?;
+                VersionedNonVerifiableClientBlindResult {
+                    state: Self::Rfc9497(result.state),
+                    message: result.message,
+                }
+            }
+        })
+    }
+
+    /// The version of the protocol this client speaks
+    pub fn version(&self) -> Version {
+        match self {
+            Self::Draft07(_) => Version::Draft07,
+            Self::Rfc9497(_) => Version::Rfc9497,
+        }
+    }
+
+    /// Unblinds the server's message and computes the output. The `input` has
+    /// to be the same as the one given to [VersionedNonVerifiableClient::blind].
+    /// Consumes the client, so that its blind can't be reused.
+    pub fn finalize(
+        self,
+        input: &[u8],
+        evaluation_element: EvaluationElement<CS>,
+        metadata: &Metadata,
+    ) -> Result<NonVerifiableClientFinalizeResult<CS>, InternalError> {
+        match self {
+            Self::Draft07(client) => client.finalize_once(input, evaluation_element, metadata),
+            Self::Rfc9497(client) => {
+                check_rfc9497_metadata(metadata)?;
+                let output = client.finalize(input, &evaluation_element)?.output;
+                Ok(NonVerifiableClientFinalizeResult { output })
+            }
+        }
+    }
+}
+
+impl<CS: CipherSuite> VersionedNonVerifiableServer<CS> {
+    /// Produces a new server of `version` using a supplied RNG
+    pub fn new<R: RngCore + CryptoRng>(
+        version: Version,
+        rng: &mut R,
+    ) -> Result<Self, InternalError> {
+        Ok(match version {
+            Version::Draft07 => Self::Draft07(NonVerifiableServer::new(rng)?),
+            Version::Rfc9497 => Self::Rfc9497(OprfServer::new(rng)?),
+        })
+    }
+
+    /// Produces a new server of `version` using a supplied set of bytes to
+    /// represent the server's private key
+    pub fn new_with_key(version: Version, key: &[u8]) -> Result<Self, InternalError> {
+        Ok(match version {
+            Version::Draft07 => Self::Draft07(NonVerifiableServer::new_with_key(key)?),
+            Version::Rfc9497 => Self::Rfc9497(OprfServer::new_with_key(key)?),
+        })
+    }
+
+    /// The version of the protocol this server speaks
+    pub fn version(&self) -> Version {
+        match self {
+            Self::Draft07(_) => Version::Draft07,
+            Self::Rfc9497(_) => Version::Rfc9497,
+        }
+    }
+
+    /// Evaluates the client's message
+    pub fn evaluate(
+        &self,
+        blinded_element: BlindedElement<CS>,
+        metadata: &Metadata,
+    ) -> Result<NonVerifiableServerEvaluateResult<CS>, InternalError> {
+        match self {
+            Self::Draft07(server) => server.evaluate(blinded_element, metadata),
+            Self::Rfc9497(server) => {
+                check_rfc9497_metadata(metadata)?;
+                Ok(NonVerifiableServerEvaluateResult {
+                    message: server.blind_evaluate(&blinded_element)?,
+                })
+            }
+        }
+    }
+}
+
+impl<CS: CipherSuite> VersionedVerifiableClient<CS> {
+    /// Blinds `input` with the protocol of `version`
+    pub fn blind<R: RngCore + CryptoRng>(
+        version: Version,
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<VersionedVerifiableClientBlindResult<CS>, InternalError> {
+        Ok(match version {
+            Version::Draft07 => {
+                let result = VerifiableClient::blind(input, blinding_factor_rng)?;
+                VersionedVerifiableClientBlindResult {
+                    state: Self::Draft07(result.state),
+                    message: result.message,
+                }
+            }
+            Version::Rfc9497 => {
+                let result = VoprfClient::blind(input, blinding_factor_rng)?;
+                VersionedVerifiableClientBlindResult {
+                    state: Self::Rfc9497(result.state),
+                    message: result.message,
+                }
+            }
+        })
+    }
+
+    /// The version of the protocol this client speaks
+    pub fn version(&self) -> Version {
+        match self {
+            Self::Draft07(_) => Version::Draft07,
+            Self::Rfc9497(_) => Version::Rfc9497,
+        }
+    }
+
+    /// Verifies the proof against `pk`, unblinds the server's message and
+    /// computes the output. The `input` has to be the same as the one given to
+    /// [VersionedVerifiableClient::blind]. Consumes the client, so that its
+    /// blind can't be reused.
+    pub fn finalize(
+        self,
+        input: &[u8],
+        evaluation_element: EvaluationElement<CS>,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+    ) -> Result<VerifiableClientFinalizeResult<CS>, InternalError> {
+        match self {
+            Self::Draft07(client) => {
+                client.finalize_once(input, evaluation_element, proof, pk, metadata)
+            }
+            Self::Rfc9497(client) => {
+                check_rfc9497_metadata(metadata)?;
+                let output = client
+                    .finalize(input, &evaluation_element, &proof, pk)?
+                    .output;
+                Ok(VerifiableClientFinalizeResult { output })
+            }
+        }
+    }
+}
+
+impl<CS: CipherSuite> VersionedVerifiableServer<CS> {
+    /// Produces a new server of `version` using a supplied RNG
+    pub fn new<R: RngCore + CryptoRng>(
+        version: Version,
+        rng: &mut R,
+    ) -> Result<Self, InternalError> {
+        Ok(match version {
+            Version::Draft07 => Self::Draft07(VerifiableServer::new(rng)?),
+            Version::Rfc9497 => Self::Rfc9497(VoprfServer::new(rng)?),
+        })
+    }
+
+    /// Produces a new server of `version` using a supplied set of bytes to
+    /// represent the server's private key
+    pub fn new_with_key(version: Version, key: &[u8]) -> Result<Self, InternalError> {
+        Ok(match version {
+            Version::Draft07 => Self::Draft07(VerifiableServer::new_with_key(key)?),
+            Version::Rfc9497 => Self::Rfc9497(VoprfServer::new_with_key(key)?),
+        })
+    }
+
+    /// The version of the protocol this server speaks
+    pub fn version(&self) -> Version {
+        match self {
+            Self::Draft07(_) => Version::Draft07,
+            Self::Rfc9497(_) => Version::Rfc9497,
+        }
+    }
+
+    /// Evaluates the client's message and proves the evaluation
+    pub fn evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_element: BlindedElement<CS>,
+        metadata: &Metadata,
+    ) -> Result<VerifiableServerEvaluateResult<CS>, InternalError> {
+        match self {
+            Self::Draft07(server) => server.evaluate(rng, blinded_element, metadata),
+            Self::Rfc9497(server) => {
+                check_rfc9497_metadata(metadata)?;
+                let result = server.blind_evaluate(rng, &blinded_element)?;
+                Ok(VerifiableServerEvaluateResult {
+                    message: result.message,
+                    proof: result.proof,
+                })
+            }
+        }
+    }
+
+    /// Retrieves the server's public key
+    pub fn get_public_key(&self) -> CS::Group {
+        match self {
+            Self::Draft07(server) => server.get_public_key(),
+            Self::Rfc9497(server) => server.get_public_key(),
+        }
+    }
+}
+
+/// Contains the fields that are returned by
+/// [VersionedNonVerifiableClient::blind]
+#[must_use]
+pub struct VersionedNonVerifiableClientBlindResult<CS: CipherSuite> {
+    /// The state to be persisted on the client
+    pub state: VersionedNonVerifiableClient<CS>,
+    /// The message to send to the server
+    pub message: BlindedElement<CS>,
+}
+
+/// Contains the fields that are returned by [VersionedVerifiableClient::blind]
+#[must_use]
+pub struct VersionedVerifiableClientBlindResult<CS: CipherSuite> {
+    /// The state to be persisted on the client
+    pub state: VersionedVerifiableClient<CS>,
+    /// The message to send to the server
+    pub message: BlindedElement<CS>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::Ristretto255Sha512;
+    use alloc::vec::Vec;
+    use rand::rngs::OsRng;
+
+    const VERSIONS: [Version; 2] = [Version::Draft07, Version::Rfc9497];
+
+    #[test]
+    fn test_non_verifiable_versions() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let key = [1; 32];
+
+        let outputs = VERSIONS
+            .iter()
+            .map(|&version| {
+                let server = VersionedNonVerifiableServer::<Ristretto255Sha512>::new_with_key(
+                    version, &key,
+                )?;
+                assert_eq!(server.version(), version);
+                let blind_result =
+                    VersionedNonVerifiableClient::blind(version, b"input", &mut rng)?;
+                assert_eq!(blind_result.state.version(), version);
+                let message = server
+                    .evaluate(blind_result.message, &Metadata::none())?
+                    .message;
+                Ok(blind_result
+                    .state
+                    .finalize(b"input", message, &Metadata::none())?
+                    .output)
+            })
+            .collect::<Result<Vec<_>, InternalError>>()?;
+
+        // The output of each version is the one of its implementation
+        assert_eq!(
+            *outputs[0],
+            NonVerifiableServer::<Ristretto255Sha512>::new_with_key(&key)?
+                .full_evaluate(b"input", &Metadata::none())?
+        );
+        assert_eq!(
+            *outputs[1],
+            OprfServer::<Ristretto255Sha512>::new_with_key(&key)?.evaluate(b"input")?
+        );
+        assert_ne!(outputs[0], outputs[1]);
+
+        // Only draft-07 has metadata in the base mode
+        let server =
+            VersionedNonVerifiableServer::<Ristretto255Sha512>::new(Version::Rfc9497, &mut rng)?;
+        let blind_result =
+            VersionedNonVerifiableClient::blind(Version::Rfc9497, b"input", &mut rng)?;
+        assert!(matches!(
+            server.evaluate(blind_result.message, &Metadata(b"info".to_vec())),
+            Err(InternalError::IncompatibleModeError)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_verifiable_versions() -> Result<(), InternalError> {
+        let mut rng = OsRng;
+        let key = [1; 32];
+
+        let outputs = VERSIONS
+            .iter()
+            .map(|&version| {
+                let server =
+                    VersionedVerifiableServer::<Ristretto255Sha512>::new_with_key(version, &key)?;
+                assert_eq!(server.version(), version);
+                let blind_result = VersionedVerifiableClient::blind(version, b"input", &mut rng)?;
+                assert_eq!(blind_result.state.version(), version);
+                let result = server.evaluate(&mut rng, blind_result.message, &Metadata::none())?;
+                Ok(blind_result
+                    .state
+                    .finalize(
+                        b"input",
+                        result.message,
+                        result.proof,
+                        server.get_public_key(),
+                        &Metadata::none(),
+                    )?
+                    .output)
+            })
+            .collect::<Result<Vec<_>, InternalError>>()?;
+
+        assert_eq!(
+            *outputs[0],
+            VerifiableServer::<Ristretto255Sha512>::new_with_key(&key)?
+                .full_evaluate(b"input", &Metadata::none())?
+        );
+        assert_eq!(
+            *outputs[1],
+            VoprfServer::<Ristretto255Sha512>::new_with_key(&key)?.evaluate(b"input")?
+        );
+
+        // A client of one version can't verify the proof of the other
+        let server =
+            VersionedVerifiableServer::<Ristretto255Sha512>::new(Version::Draft07, &mut rng)?;
+        let blind_result = VersionedVerifiableClient::blind(Version::Rfc9497, b"input", &mut rng)?;
+        let result = server.evaluate(&mut rng, blind_result.message, &Metadata::none())?;
+        assert!(blind_result
+            .state
+            .finalize(
+                b"input",
+                result.message,
+                result.proof,
+                server.get_public_key(),
+                &Metadata::none(),
+            )
+            .is_err());
+
+        Ok(())
+    }
+}
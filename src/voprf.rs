@@ -9,15 +9,34 @@
 
 use crate::{
     ciphersuite::CipherSuite,
-    errors::InternalError,
+    errors::{ErrorSource, InternalError},
     group::Group,
-    serialization::{i2osp, serialize},
+    key_policy::KeyPolicy,
+    keys::{PrivateKey, PublicKey},
+    modes::{Base, Poprf, ProtocolMode, Verifiable},
+    pool::ScalarPool,
+    secret::{Redacted, Secret},
+    serialization::{i2osp_2, i2osp_2_len},
 };
-use alloc::vec;
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
+use arrayvec::ArrayVec;
+use core::borrow::Borrow;
+use core::convert::TryFrom;
+use core::iter::{self, Map, Repeat, Zip};
+use core::marker::PhantomData;
 use digest::Digest;
-use generic_array::{typenum::Unsigned, GenericArray};
+use generic_array::{
+    sequence::Concat,
+    typenum::{Unsigned, U11},
+    GenericArray,
+};
+use hkdf::Hkdf;
 use rand::{CryptoRng, RngCore};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, Zeroizing};
 
 ///////////////
 // Constants //
@@ -27,18 +46,27 @@ use rand::{CryptoRng, RngCore};
 static STR_HASH_TO_SCALAR: &[u8] = b"HashToScalar-";
 static STR_HASH_TO_GROUP: &[u8] = b"HashToGroup-";
 static STR_FINALIZE: &[u8] = b"Finalize-";
+static STR_FINALIZE_CONTEXT: &[u8] = b"FinalizeContext-";
 static STR_SEED: &[u8] = b"Seed-";
 static STR_CONTEXT: &[u8] = b"Context-";
 static STR_COMPOSITE: &[u8] = b"Composite-";
 static STR_CHALLENGE: &[u8] = b"Challenge-";
-static STR_VOPRF: &[u8] = b"VOPRF07-";
+static STR_NONCE: &[u8] = b"Nonce-";
+static STR_INPUT_BINDING: &[u8] = b"InputBinding-";
+static STR_OUTPUT_TO_SCALAR: &[u8] = b"OutputToScalar-";
+static STR_OUTPUT_TO_GROUP: &[u8] = b"OutputToGroup-";
+static STR_VOPRF: [u8; 8] = *b"VOPRF07-";
 
-/// Determines the mode of operation (either base mode or
-/// verifiable mode)
-#[derive(Clone, Copy)]
-enum Mode {
+/// Determines the mode of operation (base mode, verifiable mode or partially
+/// oblivious mode)
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Mode {
+    /// Base mode, used by [NonVerifiableClient] and [NonVerifiableServer]
     Base = 0,
+    /// Verifiable mode, used by [VerifiableClient] and [VerifiableServer]
     Verifiable = 1,
+    /// Partially oblivious mode, used by [PoprfClient] and [PoprfServer]
+    Poprf = 2,
 }
 
 ////////////////////////////
@@ -46,70 +74,232 @@ enum Mode {
 // ====================== //
 ////////////////////////////
 
+/// A client of the protocol in the mode `M`, which is one of the markers of
+/// [modes](crate::modes). It is used through the aliases
+/// [NonVerifiableClient], [VerifiableClient] and [PoprfClient].
+pub struct Client<CS: CipherSuite, M: ProtocolMode<CS>> {
+    pub(crate) blind: <CS::Group as Group>::Scalar,
+    pub(crate) blinded_element: M::Element,
+    pub(crate) input_binding: Option<<CS::Group as Group>::Scalar>,
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> core::fmt::Debug for Client<CS, M>
+where
+    <CS::Group as Group>::Scalar: core::fmt::Debug,
+    M::Element: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Client")
+            .field("blind", &Redacted(&self.blind))
+            .field("blinded_element", &self.blinded_element)
+            .field("input_binding", &Redacted(&self.input_binding))
+            .finish()
+    }
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> core::hash::Hash for Client<CS, M>
+where
+    <CS::Group as Group>::Scalar: core::hash::Hash,
+    M::Element: core::hash::Hash,
+{
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        core::hash::Hash::hash(&self.blind, state);
+        core::hash::Hash::hash(&self.blinded_element, state);
+        core::hash::Hash::hash(&self.input_binding, state);
+    }
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> Clone for Client<CS, M> {
+    fn clone(&self) -> Self {
+        Self {
+            blind: self.blind,
+            blinded_element: self.blinded_element,
+            input_binding: self.input_binding,
+        }
+    }
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> Zeroize for Client<CS, M> {
+    fn zeroize(&mut self) {
+        self.blind.zeroize();
+        M::zeroize_element(&mut self.blinded_element);
+        self.input_binding.zeroize();
+    }
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> Drop for Client<CS, M> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> PartialEq for Client<CS, M> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.blind, &other.blind)
+            & M::ct_equal_element(&self.blinded_element, &other.blinded_element)
+            & ct_equal_input_binding::<CS>(&self.input_binding, &other.input_binding)
+    }
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> Eq for Client<CS, M> {}
+
 /// A client which engages with a [NonVerifiableServer]
 /// in base mode, meaning that the OPRF outputs are not
 /// verifiable.
-pub struct NonVerifiableClient<CS: CipherSuite> {
-    pub(crate) blind: <CS::Group as Group>::Scalar,
-    pub(crate) data: Vec<u8>,
-}
-impl_traits_for!(
-    struct NonVerifiableClient<CS: CipherSuite>,
-    [blind, data],
-    [<CS::Group as Group>::Scalar],
-);
+pub type NonVerifiableClient<CS> = Client<CS, Base>;
+impl_serialize_and_deserialize_for!(NonVerifiableClient);
 
 /// A client which engages with a [VerifiableServer]
 /// in verifiable mode, meaning that the OPRF outputs
 /// can be checked against a server public key.
-pub struct VerifiableClient<CS: CipherSuite> {
-    pub(crate) blind: <CS::Group as Group>::Scalar,
-    pub(crate) blinded_element: CS::Group,
-    pub(crate) data: alloc::vec::Vec<u8>,
+pub type VerifiableClient<CS> = Client<CS, Verifiable>;
+impl_serialize_and_deserialize_for!(VerifiableClient);
+
+/// A client which engages with a [PoprfServer]
+/// in partially oblivious mode, meaning that the server
+/// learns the metadata but not the input, and that the
+/// OPRF outputs can be checked against a server public key.
+pub type PoprfClient<CS> = Client<CS, Poprf>;
+impl_serialize_and_deserialize_for!(PoprfClient);
+
+/// A server of the protocol in the mode `M`, which is one of the markers of
+/// [modes](crate::modes). It is used through the aliases
+/// [NonVerifiableServer], [VerifiableServer] and [PoprfServer].
+pub struct Server<CS: CipherSuite, M: ProtocolMode<CS>> {
+    pub(crate) sk: Secret<<CS::Group as Group>::Scalar>,
+    pub(crate) pk: M::Element,
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> core::fmt::Debug for Server<CS, M>
+where
+    <CS::Group as Group>::Scalar: core::fmt::Debug,
+    M::Element: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Server")
+            .field("sk", &self.sk)
+            .field("pk", &self.pk)
+            .finish()
+    }
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> core::hash::Hash for Server<CS, M>
+where
+    <CS::Group as Group>::Scalar: core::hash::Hash,
+    M::Element: core::hash::Hash,
+{
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        core::hash::Hash::hash(&self.sk, state);
+        core::hash::Hash::hash(&self.pk, state);
+    }
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> Clone for Server<CS, M> {
+    fn clone(&self) -> Self {
+        Self {
+            sk: self.sk.clone(),
+            pk: self.pk,
+        }
+    }
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> Zeroize for Server<CS, M> {
+    fn zeroize(&mut self) {
+        self.sk.zeroize();
+        M::zeroize_element(&mut self.pk);
+    }
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> Drop for Server<CS, M> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> PartialEq for Server<CS, M> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.sk, &other.sk) & M::ct_equal_element(&self.pk, &other.pk)
+    }
 }
-impl_traits_for!(
-    struct VerifiableClient<CS: CipherSuite>,
-    [blind, blinded_element, data],
-    [<CS::Group as Group>::Scalar, CS::Group],
-);
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> Eq for Server<CS, M> {}
 
 /// A server which engages with a [NonVerifiableClient]
 /// in base mode, meaning that the OPRF outputs are not
 /// verifiable.
-pub struct NonVerifiableServer<CS: CipherSuite> {
-    pub(crate) sk: <CS::Group as Group>::Scalar,
-}
-impl_traits_for!(
-    struct NonVerifiableServer<CS: CipherSuite>,
-    [sk],
-    [<CS::Group as Group>::Scalar],
-);
+pub type NonVerifiableServer<CS> = Server<CS, Base>;
+impl_serialize_and_deserialize_for!(NonVerifiableServer);
 
 /// A server which engages with a [VerifiableClient]
 /// in verifiable mode, meaning that the OPRF outputs
 /// can be checked against a server public key.
-pub struct VerifiableServer<CS: CipherSuite> {
-    pub(crate) sk: <CS::Group as Group>::Scalar,
-    pub(crate) pk: CS::Group,
+pub type VerifiableServer<CS> = Server<CS, Verifiable>;
+impl_serialize_and_deserialize_for!(VerifiableServer);
+
+/// A server which engages with a [PoprfClient]
+/// in partially oblivious mode, evaluating with its
+/// key tweaked by the public metadata.
+pub type PoprfServer<CS> = Server<CS, Poprf>;
+impl_serialize_and_deserialize_for!(PoprfServer);
+
+/// A [Server] whose key was tweaked by fixed [Metadata] in advance, created by
+/// [Server::with_metadata]. Deployments which always evaluate with the same
+/// metadata skip hashing it and inverting the tweaked key on every
+/// evaluation.
+pub struct PreparedServer<CS: CipherSuite, M: ProtocolMode<CS>> {
+    t: Secret<<CS::Group as Group>::Scalar>,
+    t_inverted: Secret<<CS::Group as Group>::Scalar>,
+    u: CS::Group,
+    mode: PhantomData<M>,
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> core::fmt::Debug for PreparedServer<CS, M>
+where
+    <CS::Group as Group>::Scalar: core::fmt::Debug,
+    CS::Group: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PreparedServer")
+            .field("t", &self.t)
+            .field("t_inverted", &self.t_inverted)
+            .field("u", &self.u)
+            .finish()
+    }
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> Clone for PreparedServer<CS, M> {
+    fn clone(&self) -> Self {
+        Self {
+            t: self.t.clone(),
+            t_inverted: self.t_inverted.clone(),
+            u: self.u,
+            mode: PhantomData,
+        }
+    }
 }
-impl_traits_for!(
-    struct VerifiableServer<CS: CipherSuite>,
-    [sk, pk],
-    [<CS::Group as Group>::Scalar, CS::Group],
-);
 
 /// A proof produced by a [VerifiableServer] that
 /// the OPRF output matches against a server public key.
+#[must_use = "the proof has to be sent to the client"]
 pub struct Proof<CS: CipherSuite> {
     pub(crate) c_scalar: <CS::Group as Group>::Scalar,
     pub(crate) s_scalar: <CS::Group as Group>::Scalar,
 }
-impl_traits_for!(
+impl_secret_traits_for!(
     struct Proof<CS: CipherSuite>,
     [c_scalar, s_scalar],
     [<CS::Group as Group>::Scalar],
 );
 
+impl<CS: CipherSuite> PartialEq for Proof<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.c_scalar, &other.c_scalar)
+            & CS::Group::ct_equal_scalar(&self.s_scalar, &other.s_scalar)
+    }
+}
+
+impl<CS: CipherSuite> Eq for Proof<CS> {}
+
 /// The first client message sent from a client (either verifiable or not)
 /// to a server (either verifiable or not).
 pub struct BlindedElement<CS: CipherSuite> {
@@ -133,799 +323,5282 @@ impl_traits_for!(
     [CS::Group],
 );
 
+/// An [EvaluationElement] produced by
+/// [VerifiableServer::batch_evaluate_prepare] which can't be sent to the
+/// client until the proof was generated by
+/// [VerifiableServer::batch_evaluate_finish].
+pub struct PreparedEvaluationElement<CS: CipherSuite> {
+    pub(crate) value: CS::Group,
+}
+impl_debug_eq_hash_for!(
+    struct PreparedEvaluationElement<CS: CipherSuite>,
+    [value],
+    [CS::Group],
+);
+impl_clone_for!(
+    struct PreparedEvaluationElement<CS: CipherSuite>,
+    [value],
+    [CS::Group],
+);
+impl_serialize_and_deserialize_for!(PreparedEvaluationElement);
+
+/// The scalar `t` produced by [VerifiableServer::batch_evaluate_prepare],
+/// which is required by [VerifiableServer::batch_evaluate_finish] to generate
+/// the proof.
+pub struct PreparedTscalar<CS: CipherSuite> {
+    pub(crate) t: <CS::Group as Group>::Scalar,
+}
+impl_redacted_debug_hash_for!(
+    struct PreparedTscalar<CS: CipherSuite>,
+    [],
+    redacted [t],
+    [<CS::Group as Group>::Scalar],
+);
+
+impl<CS: CipherSuite> PartialEq for PreparedTscalar<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.t, &other.t)
+    }
+}
+
+impl<CS: CipherSuite> Eq for PreparedTscalar<CS> {}
+impl_clone_for!(
+    struct PreparedTscalar<CS: CipherSuite>,
+    [t],
+    [<CS::Group as Group>::Scalar],
+);
+impl_zeroize_on_drop_for!(
+    struct PreparedTscalar<CS: CipherSuite>,
+    [t],
+    [<CS::Group as Group>::Scalar],
+);
+impl_serialize_and_deserialize_for!(PreparedTscalar);
+
+/// [Metadata] which was hashed in advance by
+/// [NonVerifiableServer::prepare_metadata] or
+/// [VerifiableServer::prepare_metadata], so that evaluating with it takes the
+/// same time regardless of the length of the metadata.
+pub struct PreparedMetadata<CS: CipherSuite> {
+    pub(crate) m: <CS::Group as Group>::Scalar,
+    pub(crate) mode: Mode,
+}
+impl<CS: CipherSuite> core::fmt::Debug for PreparedMetadata<CS>
+where
+    <CS::Group as Group>::Scalar: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PreparedMetadata")
+            .field("m", &Redacted(&self.m))
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+impl<CS: CipherSuite> PartialEq for PreparedMetadata<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.m, &other.m) & (self.mode == other.mode)
+    }
+}
+
+impl<CS: CipherSuite> Eq for PreparedMetadata<CS> {}
+impl_clone_for!(
+    struct PreparedMetadata<CS: CipherSuite>,
+    [m, mode],
+    [<CS::Group as Group>::Scalar],
+);
+impl_zeroize_on_drop_for!(
+    struct PreparedMetadata<CS: CipherSuite>,
+    [m],
+    [<CS::Group as Group>::Scalar],
+);
+
+/// Verifies the proof of a batch evaluation while the [EvaluationElement]s
+/// arrive, e.g. over a chunked response, instead of after receiving all of
+/// them. Each pair absorbed with [StreamingVerifier::absorb] updates the
+/// composites of the proof and is finalized right away, but the outputs are
+/// only released by [StreamingVerifier::finish] once the proof is verified.
+pub struct StreamingVerifier<CS: CipherSuite> {
+    u: CS::Group,
+    metadata: Metadata,
+    composites: Composites<CS>,
+    outputs: Vec<Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>>,
+}
+
+/// Builds the proof of a batch evaluation while the [BlindedElement]s arrive,
+/// e.g. as requests come in within a time window, instead of requiring all of
+/// them up front. Each pair added with [ProofBuilder::evaluate] or
+/// [ProofBuilder::push] updates the composites of the proof, and
+/// [ProofBuilder::finish] produces a single [Proof] covering all of them.
+pub struct ProofBuilder<CS: CipherSuite> {
+    t: Secret<<CS::Group as Group>::Scalar>,
+    t_inverted: Secret<<CS::Group as Group>::Scalar>,
+    u: CS::Group,
+    composites: Composites<CS>,
+}
+
+/// Outputs precomputed by [NonVerifiableServer::precompute_evaluations] or
+/// [VerifiableServer::precompute_evaluations] for a fixed, small input domain,
+/// e.g. a known dictionary for set membership. Lookups take time depending on
+/// the input, so the domain should be public.
+pub struct PrecomputedEvaluations<CS: CipherSuite> {
+    evaluations: BTreeMap<Vec<u8>, GenericArray<u8, <CS::Hash as Digest>::OutputSize>>,
+    metadata: Metadata,
+    mode: Mode,
+}
+
+/// One of the two shares of a key split by [NonVerifiableServer::split_key]
+/// or [VerifiableServer::split_key], so that no single operator holds the
+/// full key. The first share evaluates the [BlindedElement]s of the clients
+/// with [SplitKeyServer::evaluate_first], and the second share evaluates its
+/// result with [SplitKeyServer::evaluate_second], which produces the same
+/// [EvaluationElement]s as the unsplit server.
+///
+/// The key is split together with the [Metadata], so a pair of shares only
+/// evaluates with the metadata it was split for.
+pub struct SplitKeyServer<CS: CipherSuite> {
+    pub(crate) t: Secret<<CS::Group as Group>::Scalar>,
+    pub(crate) base: CS::Group,
+    pub(crate) pk: CS::Group,
+    pub(crate) mode: Mode,
+}
+impl_debug_hash_for!(
+    struct SplitKeyServer<CS: CipherSuite>,
+    [t, base, pk, mode],
+    [<CS::Group as Group>::Scalar, CS::Group],
+);
+
+impl<CS: CipherSuite> PartialEq for SplitKeyServer<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        CS::Group::ct_equal_scalar(&self.t, &other.t)
+            & self.base.ct_equal(&other.base)
+            & self.pk.ct_equal(&other.pk)
+            & (self.mode == other.mode)
+    }
+}
+
+impl<CS: CipherSuite> Eq for SplitKeyServer<CS> {}
+impl_clone_for!(
+    struct SplitKeyServer<CS: CipherSuite>,
+    [t, base, pk, mode],
+    [<CS::Group as Group>::Scalar, CS::Group],
+);
+impl_zeroize_on_drop_for!(
+    struct SplitKeyServer<CS: CipherSuite>,
+    [t],
+    [<CS::Group as Group>::Scalar],
+);
+impl_serialize_and_deserialize_for!(SplitKeyServer);
+
+/// The proofs of both shares of a key split by [VerifiableServer::split_key],
+/// produced by [SplitKeyServer::evaluate_second] and checked by
+/// [VerifiableClient::batch_finalize_split]
+pub struct SplitKeyProof<CS: CipherSuite> {
+    pub(crate) partial_elements: Vec<EvaluationElement<CS>>,
+    pub(crate) first: Proof<CS>,
+    pub(crate) second: Proof<CS>,
+}
+impl_debug_hash_for!(
+    struct SplitKeyProof<CS: CipherSuite>,
+    [partial_elements, first, second],
+    [Vec<EvaluationElement<CS>>, Proof<CS>],
+);
+
+impl<CS: CipherSuite> PartialEq for SplitKeyProof<CS> {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_elements.len() == other.partial_elements.len()
+            && self
+                .partial_elements
+                .iter()
+                .zip(&other.partial_elements)
+                .all(|(a, b)| a.value.ct_equal(&b.value))
+            && self.first == other.first
+            && self.second == other.second
+    }
+}
+
+impl<CS: CipherSuite> Eq for SplitKeyProof<CS> {}
+impl_clone_for!(
+    struct SplitKeyProof<CS: CipherSuite>,
+    [partial_elements, first, second],
+    [Vec<EvaluationElement<CS>>, Proof<CS>],
+);
+impl_serialize_and_deserialize_for!(SplitKeyProof);
+
 /////////////////////////
 // API Implementations //
 // =================== //
 /////////////////////////
 
-impl<CS: CipherSuite> NonVerifiableClient<CS> {
+impl<CS: CipherSuite, M: ProtocolMode<CS>> Client<CS, M> {
     /// Computes the first step for the multiplicative blinding version of DH-OPRF.
     pub fn blind<R: RngCore + CryptoRng>(
         input: &[u8],
         blinding_factor_rng: &mut R,
-    ) -> Result<NonVerifiableClientBlindResult<CS>, InternalError> {
-        let (blind, blinded_element) = blind::<CS, _>(input, blinding_factor_rng, Mode::Base)?;
-        Ok(NonVerifiableClientBlindResult {
+    ) -> Result<ClientBlindResult<CS, M>, InternalError> {
+        Self::blind_parts(&[input], blinding_factor_rng)
+    }
+
+    /// Alternative version of [Client::blind] for an input made of several
+    /// parts, e.g. the fields of a record, which are hashed as if they were
+    /// concatenated, without concatenating them in memory. The parts aren't
+    /// separated from each other, so they have to be encoded unambiguously,
+    /// e.g. with length prefixes.
+    pub fn blind_parts<R: RngCore + CryptoRng>(
+        input: &[&[u8]],
+        blinding_factor_rng: &mut R,
+    ) -> Result<ClientBlindResult<CS, M>, InternalError> {
+        let (blind, blinded_element) = blind::<CS, _>(input, blinding_factor_rng, M::MODE)?;
+        Ok(Self::blind_result(blind, blinded_element))
+    }
+
+    /// Like [Client::blind], but fails with [InternalError::RngError] instead
+    /// of panicking if the RNG fails to produce random bytes
+    pub fn try_blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<ClientBlindResult<CS, M>, InternalError> {
+        crate::rng::try_with_rng(blinding_factor_rng, |rng| Self::blind(input, rng))
+    }
+
+    /// Like [Client::blind], but the returned state is bound to `input`:
+    /// finalizing it fails with [InternalError::InputMismatchError] if it's
+    /// given a different input. Use this when the state is persisted between
+    /// requests, e.g. in a session store, so the input can't be swapped before
+    /// finalizing.
+    ///
+    /// The state holds a hash of the input and the blind, so anyone who
+    /// obtains the state can check guesses of the input against it.
+    pub fn blind_with_input_binding<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<ClientBlindResult<CS, M>, InternalError> {
+        let mut result = Self::blind(input, blinding_factor_rng)?;
+        result.state.input_binding = Some(compute_input_binding::<CS>(
+            input,
+            &result.state.blind,
+            M::MODE,
+        )?);
+        Ok(result)
+    }
+
+    /// Alternative version of [Client::blind] which takes the blind from a
+    /// [ScalarPool] instead of sampling it
+    pub fn blind_with_pool<R: RngCore + CryptoRng>(
+        input: &[u8],
+        pool: &mut ScalarPool<CS, R>,
+    ) -> Result<ClientBlindResult<CS, M>, InternalError> {
+        let (blind, blinded_element) = pooled_blind::<CS, _>(input, pool, M::MODE)?;
+        Ok(Self::blind_result(blind, blinded_element))
+    }
+
+    fn blind_result(
+        blind: <CS::Group as Group>::Scalar,
+        blinded_element: CS::Group,
+    ) -> ClientBlindResult<CS, M> {
+        ClientBlindResult {
             state: Self {
-                data: input.to_vec(),
                 blind,
+                blinded_element: M::element(blinded_element),
+                input_binding: None,
             },
             message: BlindedElement {
                 value: blinded_element,
             },
-        })
+        }
+    }
+
+    #[cfg(test)]
+    /// Only used for test functions
+    pub fn get_blind(&self) -> <CS::Group as Group>::Scalar {
+        self.blind
+    }
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS, Element = CS::Group>> Client<CS, M> {
+    /// Reconstructs the message returned by [Client::blind] from the state,
+    /// e.g. to send it again after the original [BlindedElement] was dropped.
+    /// Only clients in the verifiable modes store it.
+    pub fn message(&self) -> BlindedElement<CS> {
+        BlindedElement {
+            value: self.blinded_element,
+        }
     }
+}
 
+impl<CS: CipherSuite> NonVerifiableClient<CS> {
     /// Computes the third step for the multiplicative blinding version of DH-OPRF, in which
-    /// the client unblinds the server's message.
+    /// the client unblinds the server's message. The `input` has to be the same
+    /// as the one given to [NonVerifiableClient::blind].
+    ///
+    /// Borrows the client, so its blind can be reused for another evaluation
+    /// by mistake, which links the evaluations.
+    #[deprecated(note = "use finalize_once")]
     pub fn finalize(
         &self,
+        input: &[u8],
+        evaluation_element: EvaluationElement<CS>,
+        metadata: &Metadata,
+    ) -> Result<NonVerifiableClientFinalizeResult<CS>, InternalError> {
+        self.finalize_inner(&[input], evaluation_element, metadata, None)
+    }
+
+    /// Alternative version of [NonVerifiableClient::finalize] for an input
+    /// made of several parts, see [Client::blind_parts]
+    #[deprecated(note = "use finalize_parts_once")]
+    pub fn finalize_parts(
+        &self,
+        input: &[&[u8]],
+        evaluation_element: EvaluationElement<CS>,
+        metadata: &Metadata,
+    ) -> Result<NonVerifiableClientFinalizeResult<CS>, InternalError> {
+        self.finalize_inner(input, evaluation_element, metadata, None)
+    }
+
+    /// Like [NonVerifiableClient::finalize_parts], but consumes the client
+    pub fn finalize_parts_once(
+        self,
+        input: &[&[u8]],
+        evaluation_element: EvaluationElement<CS>,
+        metadata: &Metadata,
+    ) -> Result<NonVerifiableClientFinalizeResult<CS>, InternalError> {
+        self.finalize_inner(input, evaluation_element, metadata, None)
+    }
+
+    /// Like [NonVerifiableClient::finalize], but consumes the client, so that
+    /// its blind can't be reused for another evaluation
+    pub fn finalize_once(
+        self,
+        input: &[u8],
+        evaluation_element: EvaluationElement<CS>,
+        metadata: &Metadata,
+    ) -> Result<NonVerifiableClientFinalizeResult<CS>, InternalError> {
+        self.finalize_inner(&[input], evaluation_element, metadata, None)
+    }
+
+    /// Alternative version of [NonVerifiableClient::finalize] which binds the
+    /// output to an application `context`, e.g. a session ID, an origin or a
+    /// nonce. The context is hashed into the output alongside the input and
+    /// the metadata, with a different domain separation tag, so applications
+    /// sharing a server key can't produce the same output for the same input.
+    ///
+    /// The server is unchanged, but the output differs from the output of
+    /// [NonVerifiableClient::finalize] and of
+    /// [NonVerifiableServer::full_evaluate], also for an empty context.
+    #[deprecated(note = "use finalize_with_context_once")]
+    pub fn finalize_with_context(
+        &self,
+        input: &[u8],
+        evaluation_element: EvaluationElement<CS>,
+        metadata: &Metadata,
+        context: &[u8],
+    ) -> Result<NonVerifiableClientFinalizeResult<CS>, InternalError> {
+        self.finalize_inner(&[input], evaluation_element, metadata, Some(context))
+    }
+
+    /// Like [NonVerifiableClient::finalize_with_context], but consumes the
+    /// client
+    pub fn finalize_with_context_once(
+        self,
+        input: &[u8],
+        evaluation_element: EvaluationElement<CS>,
+        metadata: &Metadata,
+        context: &[u8],
+    ) -> Result<NonVerifiableClientFinalizeResult<CS>, InternalError> {
+        self.finalize_inner(&[input], evaluation_element, metadata, Some(context))
+    }
+
+    fn finalize_inner(
+        &self,
+        input: &[&[u8]],
         evaluation_element: EvaluationElement<CS>,
         metadata: &Metadata,
+        context: Option<&[u8]>,
     ) -> Result<NonVerifiableClientFinalizeResult<CS>, InternalError> {
-        let unblinded_element =
-            evaluation_element.value * &<CS::Group as Group>::scalar_invert(&self.blind);
-        let outputs = finalize_after_unblind::<CS>(
-            &[(self.data.clone(), unblinded_element)],
+        check_input_parts_len::<CS>(input)?;
+        check_metadata_len::<CS>(&metadata.0)?;
+        check_input_binding_parts::<CS>(&self.input_binding, input, &self.blind, Mode::Base)?;
+
+        let blind_inverted = Zeroizing::new(<CS::Group as Group>::scalar_invert(&self.blind));
+        let unblinded_element = ZeroizingElement(evaluation_element.value * &blind_inverted);
+        let output = Zeroizing::new(finalize_after_unblind_with_context::<CS>(
+            input,
+            &unblinded_element,
             &metadata.0,
+            context,
             Mode::Base,
-        )?;
-        Ok(NonVerifiableClientFinalizeResult {
-            output: outputs[0].clone(),
-        })
+        )?);
+        Ok(NonVerifiableClientFinalizeResult { output })
     }
 
     #[cfg(test)]
     /// Only used for test functions
-    pub fn from_data_and_blind(data: &[u8], blind: &<CS::Group as Group>::Scalar) -> Self {
+    pub fn from_blind(blind: &<CS::Group as Group>::Scalar) -> Self {
         Self {
-            data: data.to_vec(),
-            blind: blind.clone(),
+            blind: *blind,
+            blinded_element: crate::modes::NoElement,
+            input_binding: None,
         }
     }
-
-    #[cfg(test)]
-    /// Only used for test functions
-    pub fn get_blind(&self) -> <CS::Group as Group>::Scalar {
-        self.blind
-    }
 }
 
 impl<CS: CipherSuite> VerifiableClient<CS> {
-    /// Computes the first step for the multiplicative blinding version of DH-OPRF.
-    pub fn blind<R: RngCore + CryptoRng>(
-        input: &[u8],
-        blinding_factor_rng: &mut R,
-    ) -> Result<VerifiableClientBlindResult<CS>, InternalError> {
-        let (blind, blinded_element) =
-            blind::<CS, _>(input, blinding_factor_rng, Mode::Verifiable)?;
-        Ok(VerifiableClientBlindResult {
-            state: Self {
-                data: input.to_vec(),
-                blind,
-                blinded_element,
-            },
-            message: BlindedElement {
-                value: blinded_element,
-            },
-        })
-    }
-
     /// Computes the third step for the multiplicative blinding version of DH-OPRF, in which
-    /// the client unblinds the server's message.
+    /// the client unblinds the server's message. The `input` has to be the same
+    /// as the one given to [VerifiableClient::blind].
+    ///
+    /// Borrows the client, so its blind can be reused for another evaluation
+    /// by mistake, which links the evaluations.
+    #[deprecated(note = "use finalize_once")]
     pub fn finalize(
         &self,
+        input: &[u8],
         evaluation_element: EvaluationElement<CS>,
         proof: Proof<CS>,
         pk: CS::Group,
         metadata: &Metadata,
     ) -> Result<VerifiableClientFinalizeResult<CS>, InternalError> {
-        let batch_finalize_input =
-            BatchFinalizeInput::new(vec![self.clone()], vec![evaluation_element]);
-        let batch_result = Self::batch_finalize(batch_finalize_input, proof, pk, metadata)?;
-        Ok(VerifiableClientFinalizeResult {
-            output: batch_result.outputs[0].clone(),
-        })
+        let inputs = [input];
+        let clients = core::slice::from_ref(self);
+        let messages = core::slice::from_ref(&evaluation_element);
+
+        let mut batch_result =
+            Self::batch_finalize(&inputs, clients, messages, proof, pk, metadata)?;
+        let output = batch_result
+            .next()
+            .ok_or(InternalError::MismatchedLengthsForCompositeInputs)??;
+        Ok(VerifiableClientFinalizeResult { output })
     }
 
-    /// Allows for batching of the finalization of multiple [VerifiableClient] and [EvaluationElement] pairs
-    #[allow(clippy::type_complexity)]
-    pub fn batch_finalize(
-        batch_finalize_input: BatchFinalizeInput<CS>,
+    /// Like [VerifiableClient::finalize], but consumes the client, so that
+    /// its blind can't be reused for another evaluation
+    pub fn finalize_once(
+        self,
+        input: &[u8],
+        evaluation_element: EvaluationElement<CS>,
         proof: Proof<CS>,
         pk: CS::Group,
         metadata: &Metadata,
-    ) -> Result<VerifiableClientBatchFinalizeResult<CS>, InternalError> {
-        let batch_items: Vec<BatchItems<CS>> = batch_finalize_input
-            .clients
-            .iter()
-            .zip(batch_finalize_input.messages.iter())
-            .map(|(client, evaluation_element)| BatchItems {
-                blind: client.blind,
-                evaluation_element: evaluation_element.clone(),
-                blinded_element: BlindedElement {
-                    value: client.blinded_element,
-                },
-            })
-            .collect();
-
-        let unblinded_elements = verifiable_unblind(&batch_items, pk, proof, &metadata.0)?;
-
-        let inputs_and_unblinded_elements: Vec<(Vec<u8>, CS::Group)> = batch_finalize_input
-            .clients
-            .iter()
-            .zip(unblinded_elements.iter())
-            .map(|(client, &unblinded_element)| (client.data.clone(), unblinded_element))
-            .collect();
-
-        Ok(VerifiableClientBatchFinalizeResult {
-            outputs: finalize_after_unblind::<CS>(
-                &inputs_and_unblinded_elements,
-                &metadata.0,
-                Mode::Verifiable,
-            )?,
-        })
+    ) -> Result<VerifiableClientFinalizeResult<CS>, InternalError> {
+        self.finalize_inner(&[input], evaluation_element, proof, pk, metadata, None)
     }
 
-    #[cfg(test)]
-    /// Only used for test functions
-    pub fn from_data_and_blind(
-        data: &[u8],
-        blind: &<CS::Group as Group>::Scalar,
-        blinded_element: &CS::Group,
-    ) -> Self {
-        Self {
-            data: data.to_vec(),
-            blind: blind.clone(),
-            blinded_element: blinded_element.clone(),
-        }
+    /// Like [VerifiableClient::finalize], but first fails with
+    /// [InternalError::UntrustedPublicKey] if `policy` doesn't trust `pk`
+    #[deprecated(note = "use finalize_with_key_policy_once")]
+    pub fn finalize_with_key_policy(
+        &self,
+        input: &[u8],
+        evaluation_element: EvaluationElement<CS>,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+        policy: &impl KeyPolicy<CS>,
+    ) -> Result<VerifiableClientFinalizeResult<CS>, InternalError> {
+        check_key_policy(policy, &pk)?;
+        self.finalize_inner(&[input], evaluation_element, proof, pk, metadata, None)
     }
 
-    #[cfg(test)]
-    /// Only used for test functions
-    pub fn get_blind(&self) -> <CS::Group as Group>::Scalar {
-        self.blind
+    /// Like [VerifiableClient::finalize_with_key_policy], but consumes the
+    /// client
+    pub fn finalize_with_key_policy_once(
+        self,
+        input: &[u8],
+        evaluation_element: EvaluationElement<CS>,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+        policy: &impl KeyPolicy<CS>,
+    ) -> Result<VerifiableClientFinalizeResult<CS>, InternalError> {
+        check_key_policy(policy, &pk)?;
+        self.finalize_inner(&[input], evaluation_element, proof, pk, metadata, None)
     }
-}
 
-impl<CS: CipherSuite> NonVerifiableServer<CS> {
-    /// Produces a new instance of a [NonVerifiableServer] using a supplied RNG
-    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self, InternalError> {
-        let mut seed = vec![0u8; <CS::Hash as Digest>::OutputSize::USIZE];
-        rng.fill_bytes(&mut seed);
-        Self::new_from_seed(&seed)
+    /// Alternative version of [VerifiableClient::finalize] which binds the
+    /// output to an application `context`, see
+    /// [NonVerifiableClient::finalize_with_context]
+    #[deprecated(note = "use finalize_with_context_once")]
+    pub fn finalize_with_context(
+        &self,
+        input: &[u8],
+        evaluation_element: EvaluationElement<CS>,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+        context: &[u8],
+    ) -> Result<VerifiableClientFinalizeResult<CS>, InternalError> {
+        self.finalize_inner(
+            &[input],
+            evaluation_element,
+            proof,
+            pk,
+            metadata,
+            Some(context),
+        )
     }
 
-    /// Produces a new instance of a [NonVerifiableServer] using a supplied set of bytes to
-    /// represent the server's private key
-    pub fn new_with_key(private_key_bytes: &[u8]) -> Result<Self, InternalError> {
-        let sk = CS::Group::from_scalar_slice(&GenericArray::clone_from_slice(private_key_bytes))?;
-        Ok(Self { sk })
+    /// Like [VerifiableClient::finalize_with_context], but consumes the client
+    pub fn finalize_with_context_once(
+        self,
+        input: &[u8],
+        evaluation_element: EvaluationElement<CS>,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+        context: &[u8],
+    ) -> Result<VerifiableClientFinalizeResult<CS>, InternalError> {
+        self.finalize_inner(
+            &[input],
+            evaluation_element,
+            proof,
+            pk,
+            metadata,
+            Some(context),
+        )
     }
 
-    /// Produces a new instance of a [NonVerifiableServer] using a supplied set of bytes which
-    /// are used as a seed to derive the server's private key.
-    ///
-    /// Corresponds to DeriveKeyPair() function from the VOPRF specification.
-    pub fn new_from_seed(seed: &[u8]) -> Result<Self, InternalError> {
-        let dst = [STR_HASH_TO_SCALAR, &get_context_string::<CS>(Mode::Base)?].concat();
-        let sk = CS::Group::hash_to_scalar::<CS::Hash>(seed, &dst)?;
-        Ok(Self { sk })
+    /// Alternative version of [VerifiableClient::finalize] for an input made
+    /// of several parts, see [Client::blind_parts]
+    #[deprecated(note = "use finalize_parts_once")]
+    pub fn finalize_parts(
+        &self,
+        input: &[&[u8]],
+        evaluation_element: EvaluationElement<CS>,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+    ) -> Result<VerifiableClientFinalizeResult<CS>, InternalError> {
+        self.finalize_inner(input, evaluation_element, proof, pk, metadata, None)
     }
 
-    // Only used for tests
-    #[cfg(test)]
-    pub fn get_private_key(&self) -> <CS::Group as Group>::Scalar {
-        self.sk
+    /// Like [VerifiableClient::finalize_parts], but consumes the client
+    pub fn finalize_parts_once(
+        self,
+        input: &[&[u8]],
+        evaluation_element: EvaluationElement<CS>,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+    ) -> Result<VerifiableClientFinalizeResult<CS>, InternalError> {
+        self.finalize_inner(input, evaluation_element, proof, pk, metadata, None)
     }
 
-    /// Computes the second step for the multiplicative blinding version of DH-OPRF. This
-    /// message is sent from the server (who holds the OPRF key) to the client.
-    pub fn evaluate(
+    fn finalize_inner(
         &self,
-        blinded_element: BlindedElement<CS>,
+        input: &[&[u8]],
+        evaluation_element: EvaluationElement<CS>,
+        proof: Proof<CS>,
+        pk: CS::Group,
         metadata: &Metadata,
-    ) -> Result<NonVerifiableServerEvaluateResult<CS>, InternalError> {
-        let context = [
-            STR_CONTEXT,
-            &get_context_string::<CS>(Mode::Base)?,
-            &serialize(&metadata.0, 2)?,
-        ]
-        .concat();
-        let dst = [STR_HASH_TO_SCALAR, &get_context_string::<CS>(Mode::Base)?].concat();
-        let m = CS::Group::hash_to_scalar::<CS::Hash>(&context, &dst)?;
-        let t = self.sk + &m;
-        let evaluation_element = blinded_element.value * &CS::Group::scalar_invert(&t);
-        Ok(NonVerifiableServerEvaluateResult {
-            message: EvaluationElement {
-                value: evaluation_element,
-            },
-        })
-    }
-}
-
-impl<CS: CipherSuite> VerifiableServer<CS> {
-    /// Produces a new instance of a [VerifiableServer] using a supplied RNG
-    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self, InternalError> {
-        let mut seed = vec![0u8; <CS::Hash as Digest>::OutputSize::USIZE];
-        rng.fill_bytes(&mut seed);
-        Self::new_from_seed(&seed)
-    }
+        context: Option<&[u8]>,
+    ) -> Result<VerifiableClientFinalizeResult<CS>, InternalError> {
+        check_input_binding_parts::<CS>(&self.input_binding, input, &self.blind, Mode::Verifiable)?;
 
-    /// Produces a new instance of a [VerifiableServer] using a supplied set of bytes to
-    /// represent the server's private key
-    pub fn new_with_key(key: &[u8]) -> Result<Self, InternalError> {
-        let sk = CS::Group::from_scalar_slice(&GenericArray::clone_from_slice(key))?;
-        let pk = CS::Group::base_point() * &sk;
-        Ok(Self { sk, pk })
+        let clients = core::slice::from_ref(self);
+        let messages = core::slice::from_ref(&evaluation_element);
+        let unblinded_element = verifiable_unblind(clients, messages, pk, proof, &metadata.0)?
+            .next()
+            .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?;
+        let output = Zeroizing::new(finalize_after_unblind_with_context::<CS>(
+            input,
+            &unblinded_element,
+            &metadata.0,
+            context,
+            Mode::Verifiable,
+        )?);
+        Ok(VerifiableClientFinalizeResult { output })
     }
 
-    /// Produces a new instance of a [VerifiableServer] using a supplied set of bytes which
-    /// are used as a seed to derive the server's private key.
+    /// Allows for batching of the finalization of multiple [VerifiableClient] and
+    /// [EvaluationElement] pairs. The inputs have to be given in the same order
+    /// as the clients they were blinded with.
     ///
-    /// Corresponds to DeriveKeyPair() function from the VOPRF specification.
-    pub fn new_from_seed(seed: &[u8]) -> Result<Self, InternalError> {
-        let dst = [
-            STR_HASH_TO_SCALAR,
-            &get_context_string::<CS>(Mode::Verifiable)?,
-        ]
-        .concat();
-        let sk = CS::Group::hash_to_scalar::<CS::Hash>(seed, &dst)?;
-        let pk = CS::Group::base_point() * &sk;
-        Ok(Self { sk, pk })
+    /// The proof and the inputs of clients bound to them are verified before
+    /// this function returns, but the outputs are computed lazily by the
+    /// returned iterator, so that no allocation is necessary. Each blind is
+    /// inverted on its own; [VerifiableClient::batch_finalize_owned] inverts
+    /// them all at once, which is faster for large batches.
+    #[allow(clippy::type_complexity)]
+    pub fn batch_finalize<'a, I, II: ?Sized, IC: ?Sized, IM: ?Sized>(
+        inputs: &'a II,
+        clients: &'a IC,
+        messages: &'a IM,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &'a Metadata,
+    ) -> Result<
+        impl Iterator<
+                Item = Result<
+                    Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>,
+                    InternalError,
+                >,
+            > + 'a,
+        InternalError,
+    >
+    where
+        CS: 'a,
+        I: 'a + AsRef<[u8]>,
+        &'a II: 'a + IntoIterator<Item = I>,
+        &'a IC: 'a + IntoIterator<Item = &'a VerifiableClient<CS>>,
+        &'a IM: 'a + IntoIterator<Item = &'a EvaluationElement<CS>>,
+    {
+        check_input_bindings(inputs, clients, Mode::Verifiable)?;
+
+        let unblinded_elements = verifiable_unblind(clients, messages, pk, proof, &metadata.0)?;
+
+        Ok(inputs
+            .into_iter()
+            .zip(unblinded_elements)
+            .map(move |(input, unblinded_element)| {
+                finalize_after_unblind::<CS>(
+                    input.as_ref(),
+                    &unblinded_element,
+                    &metadata.0,
+                    Mode::Verifiable,
+                )
+                .map(Zeroizing::new)
+            }))
     }
 
-    // Only used for tests
-    #[cfg(test)]
-    pub fn get_private_key(&self) -> <CS::Group as Group>::Scalar {
-        self.sk
+    /// Like [VerifiableClient::batch_finalize], but first fails with
+    /// [InternalError::UntrustedPublicKey] if `policy` doesn't trust `pk`
+    #[allow(clippy::type_complexity)]
+    pub fn batch_finalize_with_key_policy<'a, I, II: ?Sized, IC: ?Sized, IM: ?Sized>(
+        inputs: &'a II,
+        clients: &'a IC,
+        messages: &'a IM,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &'a Metadata,
+        policy: &impl KeyPolicy<CS>,
+    ) -> Result<
+        impl Iterator<
+                Item = Result<
+                    Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>,
+                    InternalError,
+                >,
+            > + 'a,
+        InternalError,
+    >
+    where
+        CS: 'a,
+        I: 'a + AsRef<[u8]>,
+        &'a II: 'a + IntoIterator<Item = I>,
+        &'a IC: 'a + IntoIterator<Item = &'a VerifiableClient<CS>>,
+        &'a IM: 'a + IntoIterator<Item = &'a EvaluationElement<CS>>,
+    {
+        check_key_policy(policy, &pk)?;
+        Self::batch_finalize(inputs, clients, messages, proof, pk, metadata)
     }
 
-    /// Computes the second step for the multiplicative blinding version of DH-OPRF. This
-    /// message is sent from the server (who holds the OPRF key) to the client.
-    pub fn evaluate<R: RngCore + CryptoRng>(
-        &self,
-        rng: &mut R,
-        blinded_element: BlindedElement<CS>,
+    /// Alternative version of [VerifiableClient::batch_finalize] for a fixed
+    /// number of clients, which returns the outputs in an array and doesn't
+    /// allocate
+    #[allow(clippy::type_complexity)]
+    pub fn batch_finalize_array<I: AsRef<[u8]>, const N: usize>(
+        inputs: &[I; N],
+        clients: &[Self; N],
+        messages: &[EvaluationElement<CS>; N],
+        proof: Proof<CS>,
+        pk: CS::Group,
         metadata: &Metadata,
-    ) -> Result<VerifiableServerEvaluateResult<CS>, InternalError> {
-        let batch_result = self.batch_evaluate(rng, &[blinded_element], metadata)?;
-        Ok(VerifiableServerEvaluateResult {
-            message: batch_result.messages[0].clone(),
-            proof: batch_result.proof,
-        })
+    ) -> Result<[Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>; N], InternalError>
+    {
+        collect_array(Self::batch_finalize(
+            inputs, clients, messages, proof, pk, metadata,
+        )?)
     }
 
-    /// Allows for batching of the evaluation of multiple [BlindedElement] messages from a [VerifiableClient]
-    pub fn batch_evaluate<R: RngCore + CryptoRng>(
-        &self,
-        rng: &mut R,
-        blinded_elements: &[BlindedElement<CS>],
+    /// Like [VerifiableClient::batch_finalize], but takes the inputs, clients
+    /// and messages by value, e.g. as owned [Vec]s or iterator adapters,
+    /// consuming the client states. The clients and messages are collected
+    /// before the proof is verified, and the blinds are inverted all at once
+    /// with [Group::invert_scalars_batch]. The outputs are still computed
+    /// lazily.
+    #[allow(clippy::type_complexity)]
+    pub fn batch_finalize_owned<'a, I, II, IC, IM>(
+        inputs: II,
+        clients: IC,
+        messages: IM,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &'a Metadata,
+    ) -> Result<
+        impl Iterator<
+                Item = Result<
+                    Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>,
+                    InternalError,
+                >,
+            > + 'a,
+        InternalError,
+    >
+    where
+        CS: 'a,
+        I: 'a + AsRef<[u8]>,
+        II: IntoIterator<Item = I>,
+        IC: IntoIterator<Item = VerifiableClient<CS>>,
+        IM: IntoIterator<Item = EvaluationElement<CS>>,
+    {
+        let inputs: Vec<I> = inputs.into_iter().collect();
+        let clients: Vec<VerifiableClient<CS>> = clients.into_iter().collect();
+        let messages: Vec<EvaluationElement<CS>> = messages.into_iter().collect();
+        check_input_bindings(&inputs, &clients, Mode::Verifiable)?;
+
+        let verified = verify_batch(&clients, &messages, pk, proof, &metadata.0)?;
+        let unblinded_elements = unblind_verified_batch(clients, messages, verified);
+
+        Ok(inputs
+            .into_iter()
+            .zip(unblinded_elements)
+            .map(move |(input, unblinded_element)| {
+                finalize_after_unblind::<CS>(
+                    input.as_ref(),
+                    &unblinded_element,
+                    &metadata.0,
+                    Mode::Verifiable,
+                )
+                .map(Zeroizing::new)
+            }))
+    }
+
+    /// Like [VerifiableClient::batch_finalize], but verifies a proof which was
+    /// generated over a larger batch than the client's own, e.g. over every
+    /// element a Privacy Pass issuer evaluated in an issuance window. The
+    /// server has to send the whole batch, `batch_blinded_elements` and the
+    /// matching `batch_messages`, and each client is matched to the evaluation
+    /// of its blinded element in it.
+    ///
+    /// The outputs are returned in the order of `clients`.
+    #[allow(clippy::type_complexity)]
+    pub fn batch_finalize_superset<I: AsRef<[u8]>>(
+        inputs: &[I],
+        clients: &[VerifiableClient<CS>],
+        batch_blinded_elements: &[BlindedElement<CS>],
+        batch_messages: &[EvaluationElement<CS>],
+        proof: Proof<CS>,
+        pk: CS::Group,
         metadata: &Metadata,
-    ) -> Result<VerifiableServerBatchEvaluateResult<CS>, InternalError> {
-        let context = [
-            STR_CONTEXT,
-            &get_context_string::<CS>(Mode::Verifiable)?,
-            &serialize(&metadata.0, 2)?,
-        ]
-        .concat();
-        let dst = [
-            STR_HASH_TO_SCALAR,
-            &get_context_string::<CS>(Mode::Verifiable)?,
-        ]
-        .concat();
-        let m = CS::Group::hash_to_scalar::<CS::Hash>(&context, &dst)?;
-        let t = self.sk + &m;
-        let evaluation_elements: Vec<EvaluationElement<CS>> = blinded_elements
-            .iter()
-            .map(|x| EvaluationElement {
-                value: x.value * &CS::Group::scalar_invert(&t),
-            })
-            .collect();
+    ) -> Result<Vec<Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>>, InternalError>
+    {
+        check_batch_lengths(inputs.len(), clients.len())?;
+        check_batch_lengths(batch_blinded_elements.len(), batch_messages.len())?;
 
-        let g = CS::Group::base_point();
-        let u = g * &t;
+        for (input, client) in inputs.iter().zip(clients) {
+            check_input_binding::<CS>(
+                &client.input_binding,
+                input.as_ref(),
+                &client.blind,
+                Mode::Verifiable,
+            )?;
+        }
 
-        let proof = generate_proof(rng, t, g, u, &evaluation_elements, blinded_elements)?;
+        // The blinded elements are public, so they can be looked up in
+        // variable time
+        let messages = clients
+            .iter()
+            .map(|client| {
+                batch_blinded_elements
+                    .iter()
+                    .position(|blinded_element| {
+                        blinded_element.value.ct_equal(&client.blinded_element)
+                    })
+                    .map(|index| &batch_messages[index])
+                    .ok_or(InternalError::MissingFromBatchError)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(VerifiableServerBatchEvaluateResult {
-            messages: evaluation_elements,
+        let verified = verify_batch_elements(
+            batch_blinded_elements.iter().map(|x| x.value),
+            batch_messages.iter().map(|x| x.value),
+            pk,
             proof,
-        })
-    }
+            &metadata.0,
+            Mode::Verifiable,
+        )?;
 
-    /// Retrieves the server's public key
-    pub fn get_public_key(&self) -> CS::Group {
-        self.pk
+        inputs
+            .iter()
+            .zip(unblind_verified_batch(clients, messages, verified))
+            .map(|(input, unblinded_element)| {
+                finalize_after_unblind::<CS>(
+                    input.as_ref(),
+                    &unblinded_element,
+                    &metadata.0,
+                    Mode::Verifiable,
+                )
+                .map(Zeroizing::new)
+            })
+            .collect()
     }
-}
 
-/////////////////////////
-// Optional Parameters //
-//==================== //
-/////////////////////////
+    /// Alternative version of [VerifiableClient::batch_finalize] for an
+    /// evaluation by the two shares of a key split by
+    /// [VerifiableServer::split_key]. Verifies the proofs of both shares
+    /// against `first_pk`, the public key of the first share, and `pk`, the
+    /// public key of the unsplit server. Like `pk`, `first_pk` has to be
+    /// obtained from a trusted source.
+    #[allow(clippy::type_complexity)]
+    pub fn batch_finalize_split<I: AsRef<[u8]>>(
+        inputs: &[I],
+        clients: &[VerifiableClient<CS>],
+        messages: &[EvaluationElement<CS>],
+        proof: SplitKeyProof<CS>,
+        first_pk: CS::Group,
+        pk: CS::Group,
+        metadata: &Metadata,
+    ) -> Result<Vec<Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>>, InternalError>
+    {
+        check_batch_lengths(inputs.len(), clients.len())?;
 
-/// Allows for implementations to specify an optional sequence of
-/// public bytes that must be agreed-upon by the client and server
-pub struct Metadata(pub Vec<u8>);
+        for (input, client) in inputs.iter().zip(clients) {
+            check_input_binding::<CS>(
+                &client.input_binding,
+                input.as_ref(),
+                &client.blind,
+                Mode::Verifiable,
+            )?;
+        }
 
-impl Default for Metadata {
-    fn default() -> Self {
-        Self(vec![])
-    }
-}
+        if first_pk.is_identity() || pk.is_identity() {
+            return Err(InternalError::InvalidPublicKey);
+        }
 
-impl Metadata {
-    /// Specifies no metadata (the default option)
-    pub fn none() -> Self {
-        Self::default()
-    }
-}
+        let m = compute_metadata_scalar::<CS>(&metadata.0, Mode::Verifiable)?;
 
-/////////////////////////
-// Convenience Structs //
-//==================== //
-/////////////////////////
+        let g = CS::Group::base_point();
+        let u = g * &m + &pk;
 
-/// Contains the fields that are returned by a non-verifiable client blind
-pub struct NonVerifiableClientBlindResult<CS: CipherSuite> {
-    /// The state to be persisted on the client
-    pub state: NonVerifiableClient<CS>,
-    /// The message to send to the server
-    pub message: BlindedElement<CS>,
-}
+        let SplitKeyProof {
+            partial_elements,
+            first,
+            second,
+        } = proof;
 
-/// Contains the fields that are returned by a non-verifiable server evaluate
-pub struct NonVerifiableServerEvaluateResult<CS: CipherSuite> {
-    /// The message to send to the client
-    pub message: EvaluationElement<CS>,
-}
+        // Both proofs have to verify, but only the second one covers the
+        // messages which are unblinded
+        let _: VerifiedProof = verify_proof(
+            g,
+            first_pk,
+            partial_elements.iter().map(|x| x.value),
+            clients.iter().map(|x| x.blinded_element),
+            first,
+            Mode::Verifiable,
+        )?;
+        let verified = verify_proof(
+            first_pk,
+            u,
+            messages.iter().map(|x| x.value),
+            partial_elements.iter().map(|x| x.value),
+            second,
+            Mode::Verifiable,
+        )?;
 
-/// Contains the fields that are returned by a non-verifiable client finalize
-pub struct NonVerifiableClientFinalizeResult<CS: CipherSuite> {
-    /// The output of the protocol
-    pub output: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
-}
+        inputs
+            .iter()
+            .zip(unblind_verified_batch(clients, messages, verified))
+            .map(|(input, unblinded_element)| {
+                finalize_after_unblind::<CS>(
+                    input.as_ref(),
+                    &unblinded_element,
+                    &metadata.0,
+                    Mode::Verifiable,
+                )
+                .map(Zeroizing::new)
+            })
+            .collect()
+    }
 
-/// Contains the fields that are returned by a verifiable client blind
-pub struct VerifiableClientBlindResult<CS: CipherSuite> {
-    /// The state to be persisted on the client
-    pub state: VerifiableClient<CS>,
-    /// The message to send to the server
-    pub message: BlindedElement<CS>,
+    #[cfg(test)]
+    /// Only used for test functions
+    pub fn from_blind_and_element(
+        blind: &<CS::Group as Group>::Scalar,
+        blinded_element: &CS::Group,
+    ) -> Self {
+        Self {
+            blind: *blind,
+            blinded_element: *blinded_element,
+            input_binding: None,
+        }
+    }
 }
 
-/// Contains the fields that are returned by a verifiable server evaluate
-pub struct VerifiableServerEvaluateResult<CS: CipherSuite> {
-    /// The message to send to the client
-    pub message: EvaluationElement<CS>,
-    /// The proof for the client to verify
-    pub proof: Proof<CS>,
-}
+#[cfg(feature = "parallel")]
+impl<CS: CipherSuite> VerifiableClient<CS>
+where
+    CS::Group: Send + Sync,
+    <CS::Group as Group>::Scalar: Send + Sync,
+{
+    /// Alternative version of [VerifiableClient::batch_finalize] which spreads
+    /// the work across the threads of the global rayon pool. The input
+    /// bindings, the hashes weighting the composite elements of the proof, the
+    /// unblinding and the outputs are computed in parallel, so unlike
+    /// [VerifiableClient::batch_finalize] the outputs are returned collected.
+    #[allow(clippy::type_complexity)]
+    pub fn par_batch_finalize<I: AsRef<[u8]> + Sync>(
+        inputs: &[I],
+        clients: &[Self],
+        messages: &[EvaluationElement<CS>],
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+    ) -> Result<Vec<Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>>, InternalError>
+    {
+        check_batch_lengths(inputs.len(), clients.len())?;
 
-/// Contains the fields that are returned by a verifiable server batch evaluate
-pub struct VerifiableServerBatchEvaluateResult<CS: CipherSuite> {
-    /// The messages to send to the client
-    pub messages: Vec<EvaluationElement<CS>>,
-    /// The proof for the client to verify
-    pub proof: Proof<CS>,
-}
+        inputs
+            .par_iter()
+            .zip(clients)
+            .try_for_each(|(input, client)| {
+                check_input_binding::<CS>(
+                    &client.input_binding,
+                    input.as_ref(),
+                    &client.blind,
+                    Mode::Verifiable,
+                )
+            })?;
 
-/// Contains the fields that are returned by a verifiable client finalize
-pub struct VerifiableClientFinalizeResult<CS: CipherSuite> {
-    /// The output of the protocol
-    pub output: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
-}
+        if pk.is_identity() {
+            return Err(InternalError::InvalidPublicKey);
+        }
 
-/// Contains the fields that are returned by a verifiable client batch finalize
-pub struct VerifiableClientBatchFinalizeResult<CS: CipherSuite> {
-    /// The output of the protocol
-    pub outputs: Vec<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>,
-}
+        let m = compute_metadata_scalar::<CS>(&metadata.0, Mode::Verifiable)?;
+        let g = CS::Group::base_point();
+        let u = g * &m + &pk;
 
-/// An input to the verifiable client batch finalize function, constructed
-/// by aggregating clients and server messages
-pub struct BatchFinalizeInput<CS: CipherSuite> {
-    clients: Vec<VerifiableClient<CS>>,
-    messages: Vec<EvaluationElement<CS>>,
-}
+        let (composite_m, composite_z) = par_compute_composites::<CS>(
+            None,
+            u,
+            messages.par_iter().map(|x| x.value),
+            clients.par_iter().map(|x| x.blinded_element),
+            Mode::Verifiable,
+        )?;
+        let VerifiedProof(()) =
+            verify_challenge::<CS>(g, u, composite_m, composite_z, proof, Mode::Verifiable)?;
 
-impl<CS: CipherSuite> BatchFinalizeInput<CS> {
-    /// Create a new instance from a vector of clients and a vector of messages
-    pub fn new(clients: Vec<VerifiableClient<CS>>, messages: Vec<EvaluationElement<CS>>) -> Self {
-        Self { clients, messages }
+        inputs
+            .par_iter()
+            .zip(clients)
+            .zip(messages)
+            .map(|((input, client), message)| {
+                let blind_inverted = Zeroizing::new(CS::Group::scalar_invert(&client.blind));
+                let unblinded_element = ZeroizingElement(message.value * &blind_inverted);
+                finalize_after_unblind::<CS>(
+                    input.as_ref(),
+                    &unblinded_element,
+                    &metadata.0,
+                    Mode::Verifiable,
+                )
+                .map(Zeroizing::new)
+            })
+            .collect()
     }
 }
 
-///////////////////////////////////////////////
-// Inner functions and Trait Implementations //
-// ========================================= //
-///////////////////////////////////////////////
+impl<CS: CipherSuite> StreamingVerifier<CS> {
+    /// Starts verifying a batch evaluated with the public key `pk` and
+    /// `metadata`
+    pub fn new(pk: CS::Group, metadata: &Metadata) -> Result<Self, InternalError> {
+        // Both supported groups have prime order, so the identity is the only
+        // element of low order
+        if pk.is_identity() {
+            return Err(InternalError::InvalidPublicKey);
+        }
 
-/// Convenience struct only used in batching APIs
-struct BatchItems<CS: CipherSuite> {
-    blind: <CS::Group as Group>::Scalar,
-    evaluation_element: EvaluationElement<CS>,
-    blinded_element: BlindedElement<CS>,
-}
+        let m = compute_metadata_scalar::<CS>(&metadata.0, Mode::Verifiable)?;
+        let u = CS::Group::base_point() * &m + &pk;
 
-// Inner function for blind. Returns the blind scalar and the blinded element
-fn blind<CS: CipherSuite, R: RngCore + CryptoRng>(
-    input: &[u8],
-    blinding_factor_rng: &mut R,
-    mode: Mode,
-) -> Result<(<CS::Group as Group>::Scalar, CS::Group), InternalError> {
-    // Choose a random scalar that must be non-zero
-    let blind = <CS::Group as Group>::random_nonzero_scalar(blinding_factor_rng);
-    let dst = [STR_HASH_TO_GROUP, &get_context_string::<CS>(mode)?].concat();
-    let hashed_point = <CS::Group as Group>::hash_to_curve::<CS::Hash>(input, &dst)?;
-    let blinded_element = hashed_point * &blind;
-    Ok((blind, blinded_element))
-}
+        Ok(Self {
+            u,
+            metadata: metadata.clone(),
+            composites: Composites::new(u, Mode::Verifiable)?,
+            outputs: Vec::new(),
+        })
+    }
 
-fn verifiable_unblind<CS: CipherSuite>(
-    batch_items: &[BatchItems<CS>],
-    pk: CS::Group,
-    proof: Proof<CS>,
-    info: &[u8],
-) -> Result<Vec<CS::Group>, InternalError> {
-    let context = [
-        STR_CONTEXT,
-        &get_context_string::<CS>(Mode::Verifiable)?,
-        &serialize(info, 2)?,
-    ]
-    .concat();
-
-    let dst = [
-        STR_HASH_TO_SCALAR,
-        &get_context_string::<CS>(Mode::Verifiable)?,
-    ]
-    .concat();
-    let m = CS::Group::hash_to_scalar::<CS::Hash>(&context, &dst)?;
+    /// Like [StreamingVerifier::new], but first fails with
+    /// [InternalError::UntrustedPublicKey] if `policy` doesn't trust `pk`
+    pub fn new_with_key_policy(
+        pk: CS::Group,
+        metadata: &Metadata,
+        policy: &impl KeyPolicy<CS>,
+    ) -> Result<Self, InternalError> {
+        check_key_policy(policy, &pk)?;
+        Self::new(pk, metadata)
+    }
 
-    let g = CS::Group::base_point();
-    let t = g * &m;
-    let u = t + &pk;
+    /// Absorbs the next [EvaluationElement] of the batch, together with the
+    /// [VerifiableClient] and the input it was blinded from. They have to be
+    /// absorbed in the same order as the server evaluated them in.
+    pub fn absorb(
+        &mut self,
+        input: &[u8],
+        client: &VerifiableClient<CS>,
+        evaluation_element: &EvaluationElement<CS>,
+    ) -> Result<(), InternalError> {
+        check_input_len::<CS>(input)?;
+        check_input_binding::<CS>(
+            &client.input_binding,
+            input,
+            &client.blind,
+            Mode::Verifiable,
+        )?;
 
-    let blinds: Vec<<CS::Group as Group>::Scalar> = batch_items.iter().map(|x| x.blind).collect();
-    let evaluation_elements: Vec<EvaluationElement<CS>> = batch_items
-        .iter()
-        .map(|x| x.evaluation_element.clone())
-        .collect();
-    let blinded_elements: Vec<BlindedElement<CS>> = batch_items
-        .iter()
-        .map(|x| x.blinded_element.clone())
-        .collect();
+        self.composites
+            .absorb(evaluation_element.value, client.blinded_element, true)?;
 
-    verify_proof(g, u, &evaluation_elements, &blinded_elements, proof)?;
+        let blind_inverted = Zeroizing::new(CS::Group::scalar_invert(&client.blind));
+        let unblinded_element = ZeroizingElement(evaluation_element.value * &blind_inverted);
+        let output = Zeroizing::new(finalize_after_unblind::<CS>(
+            input,
+            &unblinded_element,
+            &self.metadata.0,
+            Mode::Verifiable,
+        )?);
+        self.outputs.push(output);
 
-    let unblinded_elements = blinds
-        .iter()
-        .zip(evaluation_elements.iter())
-        .map(|(&blind, x)| x.value * &CS::Group::scalar_invert(&blind))
-        .collect();
-    Ok(unblinded_elements)
+        Ok(())
+    }
+
+    /// Verifies `proof` against all absorbed pairs and returns their outputs,
+    /// in the order they were absorbed in
+    #[allow(clippy::type_complexity)]
+    pub fn finish(
+        self,
+        proof: Proof<CS>,
+    ) -> Result<Vec<Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>>, InternalError>
+    {
+        let _: VerifiedProof = verify_challenge::<CS>(
+            CS::Group::base_point(),
+            self.u,
+            self.composites.m,
+            self.composites.z,
+            proof,
+            Mode::Verifiable,
+        )?;
+
+        Ok(self.outputs)
+    }
 }
 
-#[allow(clippy::many_single_char_names)]
-fn generate_proof<CS: CipherSuite, R: RngCore + CryptoRng>(
-    rng: &mut R,
-    k: <CS::Group as Group>::Scalar,
-    a: CS::Group,
-    b: CS::Group,
-    cs: &[EvaluationElement<CS>],
-    ds: &[BlindedElement<CS>],
-) -> Result<Proof<CS>, InternalError> {
-    let (m, z) = compute_composites::<CS>(Some(k), b, cs, ds)?;
+impl<CS: CipherSuite> ProofBuilder<CS> {
+    /// Starts building the proof of a batch evaluated by `server` with
+    /// `metadata`
+    pub fn new(server: &VerifiableServer<CS>, metadata: &Metadata) -> Result<Self, InternalError> {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(
+            &metadata.0,
+            Mode::Verifiable,
+        )?);
+        let t = Zeroizing::new(*server.sk + &m);
+        let u = CS::Group::base_point() * &t;
 
-    let r = CS::Group::random_nonzero_scalar(rng);
-    let t2 = a * &r;
-    let t3 = m * &r;
+        Ok(Self {
+            t: Secret::new(*t)?,
+            t_inverted: Secret::new(CS::Group::scalar_invert(&t))?,
+            u,
+            composites: Composites::new(u, Mode::Verifiable)?,
+        })
+    }
 
-    let challenge_dst = [STR_CHALLENGE, &get_context_string::<CS>(Mode::Verifiable)?].concat();
-    let h2_input = [
-        serialize(&b.to_arr().to_vec(), 2)?,
-        serialize(&m.to_arr().to_vec(), 2)?,
-        serialize(&z.to_arr().to_vec(), 2)?,
-        serialize(&t2.to_arr().to_vec(), 2)?,
-        serialize(&t3.to_arr().to_vec(), 2)?,
-        serialize(&challenge_dst, 2)?,
-    ]
-    .concat();
-
-    let hash_to_scalar_dst = [
-        STR_HASH_TO_SCALAR,
-        &get_context_string::<CS>(Mode::Verifiable)?,
-    ]
-    .concat();
-
-    let c_scalar = CS::Group::hash_to_scalar::<CS::Hash>(&h2_input, &hash_to_scalar_dst)?;
-    let s_scalar = r - &(c_scalar * &k);
+    /// Evaluates the next [BlindedElement] of the batch and adds it to the
+    /// proof. The returned [EvaluationElement] can be sent to the client right
+    /// away, the proof follows once the batch is finished.
+    pub fn evaluate(
+        &mut self,
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<EvaluationElement<CS>, InternalError> {
+        check_blinded_elements(iter::once(blinded_element))?;
 
-    Ok(Proof { c_scalar, s_scalar })
-}
+        let value = blinded_element.value * &self.t_inverted;
+        trace!("evaluation_element", value.to_arr());
+        let evaluation_element = EvaluationElement { value };
+        self.push(blinded_element, &evaluation_element)?;
 
-#[allow(clippy::many_single_char_names)]
-fn verify_proof<CS: CipherSuite>(
-    a: CS::Group,
-    b: CS::Group,
-    cs: &[EvaluationElement<CS>],
-    ds: &[BlindedElement<CS>],
-    proof: Proof<CS>,
-) -> Result<(), InternalError> {
-    let (m, z) = compute_composites::<CS>(None, b, cs, ds)?;
-    let t2 = (a * &proof.s_scalar) + &(b * &proof.c_scalar);
-    let t3 = (m * &proof.s_scalar) + &(z * &proof.c_scalar);
+        Ok(evaluation_element)
+    }
 
-    let challenge_dst = [STR_CHALLENGE, &get_context_string::<CS>(Mode::Verifiable)?].concat();
-    let h2_input = [
-        serialize(&b.to_arr().to_vec(), 2)?,
-        serialize(&m.to_arr().to_vec(), 2)?,
-        serialize(&z.to_arr().to_vec(), 2)?,
-        serialize(&t2.to_arr().to_vec(), 2)?,
-        serialize(&t3.to_arr().to_vec(), 2)?,
-        serialize(&challenge_dst, 2)?,
-    ]
-    .concat();
-
-    let hash_to_scalar_dst = [
-        STR_HASH_TO_SCALAR,
-        &get_context_string::<CS>(Mode::Verifiable)?,
-    ]
-    .concat();
-    let c = CS::Group::hash_to_scalar::<CS::Hash>(&h2_input, &hash_to_scalar_dst)?;
+    /// Adds the next pair of the batch to the proof, for an [EvaluationElement]
+    /// the server already evaluated with the same metadata. The pairs have to
+    /// be added in the same order as the client finalizes them in.
+    pub fn push(
+        &mut self,
+        blinded_element: &BlindedElement<CS>,
+        evaluation_element: &EvaluationElement<CS>,
+    ) -> Result<(), InternalError> {
+        check_blinded_elements(iter::once(blinded_element))?;
 
-    match CS::Group::ct_equal_scalar(&c, &proof.c_scalar) {
-        true => Ok(()),
-        false => Err(InternalError::ProofVerificationError),
+        self.composites
+            .absorb(evaluation_element.value, blinded_element.value, false)
+    }
+
+    /// Produces the proof for all pairs added so far
+    pub fn finish<R: RngCore + CryptoRng>(self, rng: &mut R) -> Result<Proof<CS>, InternalError> {
+        let m = self.composites.m;
+        let z = m * &self.t;
+        trace!("composite_m", m.to_arr());
+        trace!("composite_z", z.to_arr());
+
+        prove::<CS>(
+            random_proof_nonce::<CS, _>(rng),
+            &self.t,
+            CS::Group::base_point(),
+            self.u,
+            m,
+            z,
+            Mode::Verifiable,
+        )
     }
 }
 
-#[allow(clippy::type_complexity)]
-fn finalize_after_unblind<CS: CipherSuite>(
-    inputs_and_unblinded_elements: &[(Vec<u8>, CS::Group)],
-    info: &[u8],
-    mode: Mode,
-) -> Result<Vec<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>, InternalError> {
-    let finalize_dst = [STR_FINALIZE, &get_context_string::<CS>(mode)?].concat();
+impl<CS: CipherSuite> PrecomputedEvaluations<CS> {
+    fn new<I: AsRef<[u8]>>(
+        domain: impl IntoIterator<Item = I>,
+        metadata: &Metadata,
+        mode: Mode,
+        mut full_evaluate: impl FnMut(
+            &[u8],
+        ) -> Result<
+            GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+            InternalError,
+        >,
+    ) -> Result<Self, InternalError> {
+        let mut evaluations = BTreeMap::new();
 
-    let mut outputs = vec![];
+        for input in domain {
+            let input = input.as_ref();
+            evaluations.insert(input.to_vec(), full_evaluate(input)?);
+        }
 
-    for (input, unblinded_element) in inputs_and_unblinded_elements {
-        outputs.push(<CS::Hash as Digest>::digest(
-            &[
-                serialize(input, 2)?,
-                serialize(info, 2)?,
-                serialize(&unblinded_element.to_arr().to_vec(), 2)?,
-                serialize(&finalize_dst, 2)?,
-            ]
-            .concat(),
-        ));
+        Ok(Self {
+            evaluations,
+            metadata: metadata.clone(),
+            mode,
+        })
     }
 
-    Ok(outputs)
-}
+    /// Returns the precomputed output for `input`, if it's in the domain
+    pub fn get(&self, input: &[u8]) -> Option<&GenericArray<u8, <CS::Hash as Digest>::OutputSize>> {
+        self.evaluations.get(input)
+    }
 
-fn compute_composites<CS: CipherSuite>(
-    k_option: Option<<CS::Group as Group>::Scalar>,
-    b: CS::Group,
-    c_slice: &[EvaluationElement<CS>],
-    d_slice: &[BlindedElement<CS>],
-) -> Result<(CS::Group, CS::Group), InternalError> {
-    if c_slice.len() != d_slice.len() {
-        return Err(InternalError::MismatchedLengthsForCompositeInputs);
+    /// Returns `true` if `output` is the output of any input in the domain,
+    /// e.g. to check the output a client obtained for set membership. The
+    /// outputs are compared in constant time.
+    pub fn contains_output(&self, output: &[u8]) -> bool {
+        self.evaluations.values().fold(false, |found, evaluation| {
+            found | bool::from(evaluation.as_slice().ct_eq(output))
+        })
     }
 
-    let seed_dst = [STR_SEED, &get_context_string::<CS>(Mode::Verifiable)?].concat();
-    let composite_dst = [STR_COMPOSITE, &get_context_string::<CS>(Mode::Verifiable)?].concat();
+    /// The [Metadata] the outputs were computed with
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
 
-    let h1_input = [
-        serialize(&b.to_arr().to_vec(), 2)?,
-        serialize(&seed_dst, 2)?,
-    ]
-    .concat();
-    let seed = <CS::Hash as Digest>::digest(&h1_input);
+    /// The number of inputs in the domain
+    pub fn len(&self) -> usize {
+        self.evaluations.len()
+    }
 
-    let mut m = CS::Group::identity();
-    let mut z = CS::Group::identity();
+    /// Returns `true` if the domain is empty
+    pub fn is_empty(&self) -> bool {
+        self.evaluations.is_empty()
+    }
+}
 
-    for i in 0..c_slice.len() {
-        let h2_input = [
-            serialize(&seed, 2)?,
-            i2osp(i, 2)?,
-            serialize(&c_slice[i].value.to_arr().to_vec(), 2)?,
-            serialize(&d_slice[i].value.to_arr().to_vec(), 2)?,
-            serialize(&composite_dst, 2)?,
-        ]
-        .concat();
-        let dst = [
-            STR_HASH_TO_SCALAR,
-            &get_context_string::<CS>(Mode::Verifiable)?,
-        ]
-        .concat();
-        let di = CS::Group::hash_to_scalar::<CS::Hash>(&h2_input, &dst)?;
-        m = c_slice[i].value * &di + &m;
-        z = match k_option {
-            Some(_) => z,
-            None => d_slice[i].value * &di + &z,
-        };
+impl<CS: CipherSuite> Drop for PrecomputedEvaluations<CS> {
+    fn drop(&mut self) {
+        for output in self.evaluations.values_mut() {
+            output.as_mut_slice().zeroize();
+        }
     }
+}
 
-    z = match k_option {
-        Some(k) => m * &k,
-        None => z,
-    };
+impl<CS: CipherSuite, M: ProtocolMode<CS>> Server<CS, M> {
+    /// Produces a new instance of a [Server] using a supplied RNG
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self, InternalError> {
+        let mut seed = GenericArray::<u8, <CS::Hash as Digest>::OutputSize>::default();
+        rng.fill_bytes(&mut seed);
+        Self::new_from_seed(&seed)
+    }
 
-    Ok((m, z))
-}
+    /// Like [Server::new], but fails with [InternalError::RngError] instead of
+    /// panicking if the RNG fails to produce random bytes
+    pub fn try_new<R: RngCore + CryptoRng>(rng: &mut R) -> Result<Self, InternalError> {
+        let mut seed = GenericArray::<u8, <CS::Hash as Digest>::OutputSize>::default();
+        rng.try_fill_bytes(&mut seed)
+            .map_err(|error| InternalError::RngError {
+                source: Some(ErrorSource::new(error)),
+            })?;
+        Self::new_from_seed(&seed)
+    }
 
-/// Generates the contextString parameter as defined in
-/// <https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-07.html>
-fn get_context_string<CS: CipherSuite>(mode: Mode) -> Result<alloc::vec::Vec<u8>, InternalError> {
-    Ok([
-        STR_VOPRF,
-        &i2osp(mode as usize, 1)?,
-        &i2osp(CS::Group::SUITE_ID, 2)?,
-    ]
-    .concat())
-}
+    /// Produces a new instance of a [Server] using a supplied set of bytes to
+    /// represent the server's private key
+    pub fn new_with_key(key: &[u8]) -> Result<Self, InternalError> {
+        if key.len() != <CS::Group as Group>::ScalarLen::USIZE {
+            return Err(InternalError::SizeError {
+                expected: <CS::Group as Group>::ScalarLen::USIZE,
+                got: key.len(),
+            });
+        }
 
-///////////
-// Tests //
-// ===== //
-///////////
+        let sk = CS::Group::from_scalar_slice(GenericArray::from_slice(key))?;
+        Self::from_private_key(sk)
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::group::Group;
-    use generic_array::GenericArray;
-    use rand::rngs::OsRng;
+    /// Produces a new instance of a [Server] using a supplied set of bytes which
+    /// are used as a seed to derive the server's private key.
+    ///
+    /// Corresponds to DeriveKeyPair() function from the VOPRF specification.
+    pub fn new_from_seed(seed: &[u8]) -> Result<Self, InternalError> {
+        let sk = derive_private_key::<CS>(seed, M::MODE)?;
+        Self::from_private_key(sk)
+    }
 
-    fn prf<CS: CipherSuite>(
-        input: &[u8],
-        key: <CS::Group as Group>::Scalar,
-        info: &[u8],
-        mode: Mode,
-    ) -> GenericArray<u8, <CS::Hash as Digest>::OutputSize> {
-        let dst = [STR_HASH_TO_GROUP, &get_context_string::<CS>(mode).unwrap()].concat();
-        let point = CS::Group::hash_to_curve::<CS::Hash>(input, &dst).unwrap();
+    /// Produces a new instance of a [Server] with the given private key
+    pub fn new_with_private_key(sk: &PrivateKey<CS>) -> Result<Self, InternalError> {
+        Self::from_private_key(*sk.value)
+    }
 
-        let context = [
-            STR_CONTEXT,
-            &get_context_string::<CS>(mode).unwrap(),
-            &serialize(info, 2).unwrap(),
-        ]
-        .concat();
-        let dst = [STR_HASH_TO_SCALAR, &get_context_string::<CS>(mode).unwrap()].concat();
-        let m = <CS::Group as Group>::hash_to_scalar::<CS::Hash>(&context, &dst).unwrap();
+    /// Produces a new instance of a [Server] with the private key derived from
+    /// `seed` and `info`, which corresponds to DeriveKeyPair() of
+    /// [RFC 9497](https://www.rfc-editor.org/rfc/rfc9497.html#section-3.2.1).
+    /// The private key is the one other implementations of RFC 9497 derive for
+    /// the ciphersuite and mode, unlike the one of [Server::new_from_seed],
+    /// which follows the older VOPRF specification.
+    ///
+    /// Fails with [InternalError::DeriveKeyPairError] in the unlikely case
+    /// that every attempt derives a zero private key.
+    pub fn new_from_seed_and_info(seed: &[u8], info: &[u8]) -> Result<Self, InternalError> {
+        let sk = crate::rfc9497::derive_key_pair::<CS>(seed, info, M::MODE)?;
+        Self::from_private_key(sk)
+    }
 
-        let res = point * &<CS::Group as Group>::scalar_invert(&(key + &m));
+    /// Tweaks the key by `metadata` in advance, for a [PreparedServer] which
+    /// evaluates with it. Fails with [InternalError::ZeroScalarError] if the
+    /// tweaked key is zero.
+    pub fn with_metadata(
+        &self,
+        metadata: &Metadata,
+    ) -> Result<PreparedServer<CS, M>, InternalError> {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(&metadata.0, M::MODE)?);
+        let t = Zeroizing::new(*self.sk + &m);
+        if CS::Group::ct_equal_scalar(&t, &CS::Group::scalar_zero()) {
+            return Err(InternalError::ZeroScalarError);
+        }
 
-        finalize_after_unblind::<CS>(&[(input.to_vec(), res)], info, mode).unwrap()[0].clone()
+        Ok(PreparedServer {
+            t: Secret::new(*t)?,
+            t_inverted: Secret::new(CS::Group::scalar_invert(&t))?,
+            u: CS::Group::base_point() * &t,
+            mode: PhantomData,
+        })
     }
 
-    fn base_retrieval<CS: CipherSuite>() {
-        let input = b"input";
-        let info = b"info";
-        let mut rng = OsRng;
-        let client_blind_result = NonVerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
-        let server = NonVerifiableServer::<CS>::new(&mut rng).unwrap();
-        let server_result = server
-            .evaluate(client_blind_result.message, &Metadata(info.to_vec()))
-            .unwrap();
-        let client_finalize_result = client_blind_result
-            .state
-            .finalize(server_result.message, &Metadata(info.to_vec()))
-            .unwrap();
-        let res2 = prf::<CS>(&input[..], server.get_private_key(), info, Mode::Base);
-        assert_eq!(client_finalize_result.output, res2);
+    pub(crate) fn from_private_key(
+        sk: <CS::Group as Group>::Scalar,
+    ) -> Result<Self, InternalError> {
+        let pk = M::element(CS::Group::base_point() * &sk);
+        Ok(Self {
+            sk: Secret::new(sk)?,
+            pk,
+        })
     }
 
-    fn verifiable_retrieval<CS: CipherSuite>() {
-        let input = b"input";
-        let info = b"info";
-        let mut rng = OsRng;
-        let client_blind_result = VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
-        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
-        let server_result = server
-            .evaluate(
-                &mut rng,
-                client_blind_result.message,
-                &Metadata(info.to_vec()),
-            )
-            .unwrap();
-        let client_finalize_result = client_blind_result
-            .state
-            .finalize(
-                server_result.message,
-                server_result.proof,
-                server.get_public_key(),
-                &Metadata(info.to_vec()),
-            )
-            .unwrap();
-        let res2 = prf::<CS>(&input[..], server.get_private_key(), info, Mode::Verifiable);
-        assert_eq!(client_finalize_result.output, res2);
+    #[cfg(test)]
+    /// Only used for test functions
+    pub fn get_private_key(&self) -> <CS::Group as Group>::Scalar {
+        *self.sk
     }
 
-    fn verifiable_bad_public_key<CS: CipherSuite>() {
-        let input = b"input";
-        let info = b"info";
-        let mut rng = OsRng;
-        let client_blind_result = VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
-        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
-        let server_result = server
-            .evaluate(
-                &mut rng,
-                client_blind_result.message,
-                &Metadata(info.to_vec()),
-            )
-            .unwrap();
-        let wrong_pk = {
-            // Choose a group element that is unlikely to be the right public key
-            CS::Group::hash_to_curve::<CS::Hash>(b"msg", b"dst").unwrap()
-        };
-        let client_finalize_result = client_blind_result.state.finalize(
-            server_result.message,
-            server_result.proof,
-            wrong_pk,
-            &Metadata(info.to_vec()),
-        );
-        assert!(client_finalize_result.is_err());
+    /// Computes the output for `input` directly with the private key, which is
+    /// the output a client obtains by blinding `input` and finalizing the
+    /// evaluation, e.g. to populate a database of outputs for known inputs.
+    ///
+    /// Corresponds to Evaluate() function from the VOPRF specification.
+    pub fn full_evaluate(
+        &self,
+        input: &[u8],
+        metadata: &Metadata,
+    ) -> Result<GenericArray<u8, <CS::Hash as Digest>::OutputSize>, InternalError> {
+        full_evaluate::<CS>(&self.sk, input, &metadata.0, M::MODE)
     }
 
-    fn verifiable_batch_retrieval<CS: CipherSuite>() {
-        let info = b"info";
-        let mut rng = OsRng;
-        let mut inputs = vec![];
-        let mut client_states = vec![];
-        let mut client_messages = vec![];
-        let num_iterations = 10;
-        for _ in 0..num_iterations {
-            let mut input = vec![0u8; 32];
-            rng.fill_bytes(&mut input);
-            let client_blind_result = VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
-            inputs.push(input);
-            client_states.push(client_blind_result.state);
-            client_messages.push(client_blind_result.message);
+    /// Checks in constant time if `output` is the output for `input` and
+    /// `metadata`, e.g. when a client reveals its input after obtaining the
+    /// output
+    ///
+    /// Corresponds to VerifyFinalize() function from the VOPRF specification.
+    pub fn verify_finalize(
+        &self,
+        input: &[u8],
+        metadata: &Metadata,
+        output: &[u8],
+    ) -> Result<bool, InternalError> {
+        let expected = self.full_evaluate(input, metadata)?;
+        Ok(expected.as_slice().ct_eq(output).into())
+    }
+
+    /// Computes the outputs of every input in `domain` in advance, to be
+    /// answered by [Server::full_evaluate_precomputed] from a lookup table.
+    /// Blinded evaluation is unaffected.
+    pub fn precompute_evaluations<I: AsRef<[u8]>>(
+        &self,
+        domain: impl IntoIterator<Item = I>,
+        metadata: &Metadata,
+    ) -> Result<PrecomputedEvaluations<CS>, InternalError> {
+        PrecomputedEvaluations::new(domain, metadata, M::MODE, |input| {
+            self.full_evaluate(input, metadata)
+        })
+    }
+
+    /// Alternative version of [Server::full_evaluate], which looks `input` up
+    /// in `precomputed` and only computes the output of inputs outside of its
+    /// domain, with the metadata of `precomputed`
+    pub fn full_evaluate_precomputed(
+        &self,
+        precomputed: &PrecomputedEvaluations<CS>,
+        input: &[u8],
+    ) -> Result<GenericArray<u8, <CS::Hash as Digest>::OutputSize>, InternalError> {
+        if precomputed.mode != M::MODE {
+            return Err(InternalError::IncompatibleModeError);
         }
-        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
-        let server_result = server
-            .batch_evaluate(&mut rng, &client_messages, &Metadata(info.to_vec()))
-            .unwrap();
-        let batch_finalize_input = BatchFinalizeInput::new(client_states, server_result.messages);
-        let client_finalize_result = VerifiableClient::batch_finalize(
-            batch_finalize_input,
-            server_result.proof,
-            server.get_public_key(),
-            &Metadata(info.to_vec()),
-        )
-        .unwrap();
-        let mut res2 = vec![];
-        for i in 0..num_iterations {
-            let output = prf::<CS>(
-                &inputs[i][..],
-                server.get_private_key(),
-                info,
-                Mode::Verifiable,
-            );
-            res2.push(output);
+
+        match precomputed.get(input) {
+            Some(output) => Ok(output.clone()),
+            None => self.full_evaluate(input, &precomputed.metadata),
         }
-        assert_eq!(client_finalize_result.outputs, res2);
     }
+}
 
-    fn verifiable_batch_bad_public_key<CS: CipherSuite>() {
-        let info = b"info";
-        let mut rng = OsRng;
-        let mut inputs = vec![];
-        let mut client_states = vec![];
-        let mut client_messages = vec![];
-        let num_iterations = 10;
-        for _ in 0..num_iterations {
-            let mut input = vec![0u8; 32];
-            rng.fill_bytes(&mut input);
-            let client_blind_result = VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
-            inputs.push(input);
+impl<CS: CipherSuite, M: ProtocolMode<CS, Element = CS::Group>> Server<CS, M> {
+    /// Retrieves the server's public key, against which clients verify the
+    /// proofs. In partially oblivious mode, they tweak it by the metadata
+    /// first.
+    pub fn get_public_key(&self) -> CS::Group {
+        self.pk
+    }
+
+    /// Retrieves the server's public key as a [PublicKey]
+    pub fn public_key(&self) -> PublicKey<CS> {
+        PublicKey { value: self.pk }
+    }
+}
+
+impl<CS: CipherSuite, M: ProtocolMode<CS>> PreparedServer<CS, M> {
+    fn evaluate_elements(
+        &self,
+        blinded_elements: &[BlindedElement<CS>],
+    ) -> Result<Vec<EvaluationElement<CS>>, InternalError> {
+        check_blinded_elements(blinded_elements)?;
+
+        Ok(blinded_elements
+            .iter()
+            .map(|x| EvaluationElement {
+                value: x.value * &self.t_inverted,
+            })
+            .collect())
+    }
+
+    fn batch_evaluate_with_proof<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+    ) -> Result<(Vec<EvaluationElement<CS>>, Proof<CS>), InternalError> {
+        let messages = self.evaluate_elements(blinded_elements)?;
+        let proof = generate_proof(
+            random_proof_nonce::<CS, _>(rng),
+            &self.t,
+            CS::Group::base_point(),
+            self.u,
+            messages.iter().map(|x| x.value),
+            blinded_elements.iter().map(|x| x.value),
+            M::MODE,
+        )?;
+
+        Ok((messages, proof))
+    }
+}
+
+impl<CS: CipherSuite> PreparedServer<CS, Base> {
+    /// Like [NonVerifiableServer::evaluate], with the metadata the server was
+    /// prepared with
+    pub fn evaluate(
+        &self,
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<NonVerifiableServerEvaluateResult<CS>, InternalError> {
+        let mut messages = self.evaluate_elements(core::slice::from_ref(blinded_element))?;
+        let message = messages
+            .pop()
+            .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?;
+        Ok(NonVerifiableServerEvaluateResult { message })
+    }
+
+    /// Evaluates several [BlindedElement]s, returning the messages in the
+    /// same order
+    pub fn batch_evaluate(
+        &self,
+        blinded_elements: &[BlindedElement<CS>],
+    ) -> Result<Vec<EvaluationElement<CS>>, InternalError> {
+        self.evaluate_elements(blinded_elements)
+    }
+}
+
+impl<CS: CipherSuite> PreparedServer<CS, Verifiable> {
+    /// Like [VerifiableServer::evaluate], with the metadata the server was
+    /// prepared with
+    pub fn evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<VerifiableServerEvaluateResult<CS>, InternalError> {
+        let (mut messages, proof) =
+            self.batch_evaluate_with_proof(rng, core::slice::from_ref(blinded_element))?;
+        let message = messages
+            .pop()
+            .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?;
+        Ok(VerifiableServerEvaluateResult { message, proof })
+    }
+
+    /// Like [VerifiableServer::batch_evaluate], with the metadata the server
+    /// was prepared with
+    pub fn batch_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+    ) -> Result<VerifiableServerBatchEvaluateResult<CS>, InternalError> {
+        let (messages, proof) = self.batch_evaluate_with_proof(rng, blinded_elements)?;
+        Ok(VerifiableServerBatchEvaluateResult { messages, proof })
+    }
+}
+
+impl<CS: CipherSuite> PreparedServer<CS, Poprf> {
+    /// Like [PoprfServer::evaluate], with the metadata the server was prepared
+    /// with
+    pub fn evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_element: &BlindedElement<CS>,
+    ) -> Result<PoprfServerEvaluateResult<CS>, InternalError> {
+        let (mut messages, proof) =
+            self.batch_evaluate_with_proof(rng, core::slice::from_ref(blinded_element))?;
+        let message = messages
+            .pop()
+            .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?;
+        Ok(PoprfServerEvaluateResult { message, proof })
+    }
+
+    /// Like [PoprfServer::batch_evaluate], with the metadata the server was
+    /// prepared with
+    pub fn batch_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+    ) -> Result<PoprfServerBatchEvaluateResult<CS>, InternalError> {
+        let (messages, proof) = self.batch_evaluate_with_proof(rng, blinded_elements)?;
+        Ok(PoprfServerBatchEvaluateResult { messages, proof })
+    }
+}
+
+impl<CS: CipherSuite> TryFrom<NonVerifiableServer<CS>> for VerifiableServer<CS> {
+    type Error = InternalError;
+
+    fn try_from(server: NonVerifiableServer<CS>) -> Result<Self, Self::Error> {
+        server.into_verifiable()
+    }
+}
+
+impl<CS: CipherSuite> TryFrom<VerifiableServer<CS>> for NonVerifiableServer<CS> {
+    type Error = InternalError;
+
+    fn try_from(server: VerifiableServer<CS>) -> Result<Self, Self::Error> {
+        server.into_non_verifiable()
+    }
+}
+
+impl<CS: CipherSuite> NonVerifiableServer<CS> {
+    /// Converts into a [VerifiableServer] with the same private key, computing
+    /// its public key. The modes are domain separated, so the outputs of both
+    /// servers for the same input are unrelated.
+    pub fn into_verifiable(self) -> Result<VerifiableServer<CS>, InternalError> {
+        VerifiableServer::from_private_key(*self.sk)
+    }
+
+    /// Computes the second step for the multiplicative blinding version of DH-OPRF. This
+    /// message is sent from the server (who holds the OPRF key) to the client.
+    pub fn evaluate(
+        &self,
+        blinded_element: BlindedElement<CS>,
+        metadata: &Metadata,
+    ) -> Result<NonVerifiableServerEvaluateResult<CS>, InternalError> {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(&metadata.0, Mode::Base)?);
+        self.evaluate_inner(blinded_element, &m)
+    }
+
+    /// Evaluates `blinded_element` with the key of each of `servers`, e.g. the
+    /// keys of the epochs of a key rotation, hashing `metadata` only once. The
+    /// messages are returned in the order of `servers`.
+    pub fn evaluate_with_keys(
+        servers: &[Self],
+        blinded_element: &BlindedElement<CS>,
+        metadata: &Metadata,
+    ) -> Result<Vec<NonVerifiableServerEvaluateResult<CS>>, InternalError> {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(&metadata.0, Mode::Base)?);
+        servers
+            .iter()
+            .map(|server| server.evaluate_inner(blinded_element.clone(), &m))
+            .collect()
+    }
+
+    /// Hashes `metadata` in advance, to be used with
+    /// [NonVerifiableServer::evaluate_with_prepared_metadata]. Servers which
+    /// treat metadata as sensitive can prepare all of it at startup, so that
+    /// the time taken per request doesn't depend on which metadata was used.
+    pub fn prepare_metadata(metadata: &Metadata) -> Result<PreparedMetadata<CS>, InternalError> {
+        Ok(PreparedMetadata {
+            m: compute_metadata_scalar::<CS>(&metadata.0, Mode::Base)?,
+            mode: Mode::Base,
+        })
+    }
+
+    /// Alternative version of [NonVerifiableServer::evaluate] with metadata
+    /// prepared by [NonVerifiableServer::prepare_metadata], whose running time
+    /// doesn't depend on the metadata.
+    pub fn evaluate_with_prepared_metadata(
+        &self,
+        blinded_element: BlindedElement<CS>,
+        metadata: &PreparedMetadata<CS>,
+    ) -> Result<NonVerifiableServerEvaluateResult<CS>, InternalError> {
+        if metadata.mode != Mode::Base {
+            return Err(InternalError::IncompatibleModeError);
+        }
+
+        self.evaluate_inner(blinded_element, &metadata.m)
+    }
+
+    /// Splits the key into the two shares of a [SplitKeyServer] for
+    /// `metadata`, to be handed to two independent operators. The shares
+    /// evaluate in base mode, without proofs.
+    pub fn split_key<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        metadata: &Metadata,
+    ) -> Result<(SplitKeyServer<CS>, SplitKeyServer<CS>), InternalError> {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(&metadata.0, Mode::Base)?);
+        split_key::<CS, _>(rng, &self.sk, &m, Mode::Base)
+    }
+
+    fn evaluate_inner(
+        &self,
+        blinded_element: BlindedElement<CS>,
+        m: &<CS::Group as Group>::Scalar,
+    ) -> Result<NonVerifiableServerEvaluateResult<CS>, InternalError> {
+        check_blinded_elements(iter::once(&blinded_element))?;
+
+        let t = Zeroizing::new(*self.sk + m);
+        let t_inverted = Zeroizing::new(CS::Group::scalar_invert(&t));
+        let evaluation_element = blinded_element.value * &t_inverted;
+        trace!("evaluation_element", evaluation_element.to_arr());
+        Ok(NonVerifiableServerEvaluateResult {
+            message: EvaluationElement {
+                value: evaluation_element,
+            },
+        })
+    }
+}
+
+impl<CS: CipherSuite> VerifiableServer<CS> {
+    /// Converts into a [NonVerifiableServer] with the same private key, see
+    /// [NonVerifiableServer::into_verifiable]
+    pub fn into_non_verifiable(self) -> Result<NonVerifiableServer<CS>, InternalError> {
+        NonVerifiableServer::from_private_key(*self.sk)
+    }
+
+    /// Computes the second step for the multiplicative blinding version of DH-OPRF. This
+    /// message is sent from the server (who holds the OPRF key) to the client.
+    pub fn evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_element: BlindedElement<CS>,
+        metadata: &Metadata,
+    ) -> Result<VerifiableServerEvaluateResult<CS>, InternalError> {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(
+            &metadata.0,
+            Mode::Verifiable,
+        )?);
+        self.evaluate_inner(blinded_element, &m, random_proof_nonce::<CS, _>(rng))
+    }
+
+    /// Evaluates `blinded_element` with the key of each of `servers`, e.g. the
+    /// keys of the epochs of a key rotation, hashing `metadata` only once. The
+    /// results are returned in the order of `servers`, each with a proof for
+    /// the public key of its server.
+    pub fn evaluate_with_keys<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        servers: &[Self],
+        blinded_element: &BlindedElement<CS>,
+        metadata: &Metadata,
+    ) -> Result<Vec<VerifiableServerEvaluateResult<CS>>, InternalError> {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(
+            &metadata.0,
+            Mode::Verifiable,
+        )?);
+        servers
+            .iter()
+            .map(|server| {
+                server.evaluate_inner(
+                    blinded_element.clone(),
+                    &m,
+                    random_proof_nonce::<CS, _>(rng),
+                )
+            })
+            .collect()
+    }
+
+    /// Hashes `metadata` in advance, to be used with
+    /// [VerifiableServer::evaluate_with_prepared_metadata]. Servers which
+    /// treat metadata as sensitive can prepare all of it at startup, so that
+    /// the time taken per request doesn't depend on which metadata was used.
+    pub fn prepare_metadata(metadata: &Metadata) -> Result<PreparedMetadata<CS>, InternalError> {
+        Ok(PreparedMetadata {
+            m: compute_metadata_scalar::<CS>(&metadata.0, Mode::Verifiable)?,
+            mode: Mode::Verifiable,
+        })
+    }
+
+    /// Alternative version of [VerifiableServer::evaluate] with metadata
+    /// prepared by [VerifiableServer::prepare_metadata], whose running time
+    /// doesn't depend on the metadata.
+    pub fn evaluate_with_prepared_metadata<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_element: BlindedElement<CS>,
+        metadata: &PreparedMetadata<CS>,
+    ) -> Result<VerifiableServerEvaluateResult<CS>, InternalError> {
+        if metadata.mode != Mode::Verifiable {
+            return Err(InternalError::IncompatibleModeError);
+        }
+
+        self.evaluate_inner(
+            blinded_element,
+            &metadata.m,
+            random_proof_nonce::<CS, _>(rng),
+        )
+    }
+
+    /// Alternative version of [VerifiableServer::evaluate] which takes the
+    /// nonce of the proof from a [ScalarPool] instead of sampling it
+    pub fn evaluate_with_pool<R: RngCore + CryptoRng>(
+        &self,
+        pool: &mut ScalarPool<CS, R>,
+        blinded_element: BlindedElement<CS>,
+        metadata: &Metadata,
+    ) -> Result<VerifiableServerEvaluateResult<CS>, InternalError> {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(
+            &metadata.0,
+            Mode::Verifiable,
+        )?);
+        self.evaluate_inner(blinded_element, &m, pooled_proof_nonce::<CS, _>(pool))
+    }
+
+    /// Alternative version of [VerifiableServer::evaluate] which derives the
+    /// nonce of the proof deterministically, see
+    /// [VerifiableServer::batch_evaluate_finish_derandomized].
+    pub fn evaluate_derandomized(
+        &self,
+        blinded_element: BlindedElement<CS>,
+        metadata: &Metadata,
+        personalization: &[u8],
+    ) -> Result<VerifiableServerEvaluateResult<CS>, InternalError> {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(
+            &metadata.0,
+            Mode::Verifiable,
+        )?);
+        self.evaluate_inner(
+            blinded_element,
+            &m,
+            derandomized_proof_nonce::<CS>(personalization),
+        )
+    }
+
+    // Evaluates with a fixed nonce for the proof, only used by the self-test
+    pub(crate) fn evaluate_with_proof_nonce(
+        &self,
+        blinded_element: BlindedElement<CS>,
+        metadata: &Metadata,
+        r: <CS::Group as Group>::Scalar,
+    ) -> Result<VerifiableServerEvaluateResult<CS>, InternalError> {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(
+            &metadata.0,
+            Mode::Verifiable,
+        )?);
+        self.evaluate_inner(blinded_element, &m, move |_: &_, _, _| Ok(r))
+    }
+
+    fn evaluate_inner(
+        &self,
+        blinded_element: BlindedElement<CS>,
+        m: &<CS::Group as Group>::Scalar,
+        nonce: impl ProofNonce<CS>,
+    ) -> Result<VerifiableServerEvaluateResult<CS>, InternalError> {
+        let VerifiableServerBatchEvaluatePrepareResult {
+            mut prepared_evaluation_elements,
+            t,
+        } = self.batch_evaluate_prepare_inner(iter::once(&blinded_element), m)?;
+        let prepared_evaluation_elements = [prepared_evaluation_elements
+            .next()
+            .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?];
+        let blinded_elements = [blinded_element];
+
+        let VerifiableServerBatchEvaluateFinishResult {
+            mut messages,
+            proof,
+        } = Self::batch_evaluate_finish_inner(
+            nonce,
+            &blinded_elements,
+            &prepared_evaluation_elements,
+            &t,
+        )?;
+        let message = messages
+            .next()
+            .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?;
+
+        Ok(VerifiableServerEvaluateResult { message, proof })
+    }
+
+    /// Allows for batching of the evaluation of multiple [BlindedElement] messages from a
+    /// [VerifiableClient]
+    pub fn batch_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+        metadata: &Metadata,
+    ) -> Result<VerifiableServerBatchEvaluateResult<CS>, InternalError> {
+        self.batch_evaluate_inner(blinded_elements, metadata, random_proof_nonce::<CS, _>(rng))
+    }
+
+    /// Alternative version of [VerifiableServer::batch_evaluate] for a fixed
+    /// number of [BlindedElement]s, which returns the messages in an array
+    /// instead of a [Vec] and doesn't allocate
+    pub fn batch_evaluate_array<R: RngCore + CryptoRng, const N: usize>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>; N],
+        metadata: &Metadata,
+    ) -> Result<VerifiableServerBatchEvaluateArrayResult<CS, N>, InternalError> {
+        let VerifiableServerBatchEvaluatePrepareResult {
+            prepared_evaluation_elements,
+            t,
+        } = self.batch_evaluate_prepare(blinded_elements.iter(), metadata)?;
+        let prepared_evaluation_elements: [_; N] =
+            collect_array(prepared_evaluation_elements.map(Ok))?;
+
+        let VerifiableServerBatchEvaluateFinishResult { messages, proof } =
+            Self::batch_evaluate_finish_inner(
+                random_proof_nonce::<CS, _>(rng),
+                blinded_elements,
+                &prepared_evaluation_elements,
+                &t,
+            )?;
+
+        Ok(VerifiableServerBatchEvaluateArrayResult {
+            messages: collect_array(messages.map(Ok))?,
+            proof,
+        })
+    }
+
+    /// Alternative version of [VerifiableServer::batch_evaluate] which takes
+    /// the nonce of the proof from a [ScalarPool] instead of sampling it
+    pub fn batch_evaluate_with_pool<R: RngCore + CryptoRng>(
+        &self,
+        pool: &mut ScalarPool<CS, R>,
+        blinded_elements: &[BlindedElement<CS>],
+        metadata: &Metadata,
+    ) -> Result<VerifiableServerBatchEvaluateResult<CS>, InternalError> {
+        self.batch_evaluate_inner(
+            blinded_elements,
+            metadata,
+            pooled_proof_nonce::<CS, _>(pool),
+        )
+    }
+
+    /// Alternative version of [VerifiableServer::batch_evaluate] which derives
+    /// the nonce of the proof deterministically, see
+    /// [VerifiableServer::batch_evaluate_finish_derandomized].
+    pub fn batch_evaluate_derandomized(
+        &self,
+        blinded_elements: &[BlindedElement<CS>],
+        metadata: &Metadata,
+        personalization: &[u8],
+    ) -> Result<VerifiableServerBatchEvaluateResult<CS>, InternalError> {
+        self.batch_evaluate_inner(
+            blinded_elements,
+            metadata,
+            derandomized_proof_nonce::<CS>(personalization),
+        )
+    }
+
+    fn batch_evaluate_inner(
+        &self,
+        blinded_elements: &[BlindedElement<CS>],
+        metadata: &Metadata,
+        nonce: impl ProofNonce<CS>,
+    ) -> Result<VerifiableServerBatchEvaluateResult<CS>, InternalError> {
+        let VerifiableServerBatchEvaluatePrepareResult {
+            prepared_evaluation_elements,
+            t,
+        } = self.batch_evaluate_prepare(blinded_elements.iter(), metadata)?;
+        let prepared_evaluation_elements: Vec<_> = prepared_evaluation_elements.collect();
+
+        let VerifiableServerBatchEvaluateFinishResult { messages, proof } =
+            Self::batch_evaluate_finish_inner(
+                nonce,
+                blinded_elements,
+                &prepared_evaluation_elements,
+                &t,
+            )?;
+
+        Ok(VerifiableServerBatchEvaluateResult {
+            messages: messages.collect(),
+            proof,
+        })
+    }
+
+    /// Alternative version of [VerifiableServer::batch_evaluate] which doesn't
+    /// require an allocation. The returned [PreparedEvaluationElement]s and
+    /// [PreparedTscalar] have to be passed to
+    /// [VerifiableServer::batch_evaluate_finish] to produce the messages
+    /// for the client and the proof.
+    ///
+    /// Both can be serialized, so a long batch job can be checkpointed and
+    /// resumed: store the [PreparedEvaluationElement]s produced so far, and
+    /// after an interruption prepare only the remaining [BlindedElement]s with
+    /// the same metadata. The [PreparedTscalar] is derived from the private
+    /// key, so it must be stored as securely as the key itself.
+    ///
+    /// The [BlindedElement]s are checked before any of them is evaluated, so
+    /// that no evaluation of the identity is ever checkpointed.
+    pub fn batch_evaluate_prepare<'a, I: Clone + Iterator<Item = &'a BlindedElement<CS>>>(
+        &self,
+        blinded_elements: I,
+        metadata: &Metadata,
+    ) -> Result<VerifiableServerBatchEvaluatePrepareResult<'a, CS, I>, InternalError> {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(
+            &metadata.0,
+            Mode::Verifiable,
+        )?);
+        self.batch_evaluate_prepare_inner(blinded_elements, &m)
+    }
+
+    fn batch_evaluate_prepare_inner<'a, I: Clone + Iterator<Item = &'a BlindedElement<CS>>>(
+        &self,
+        blinded_elements: I,
+        m: &<CS::Group as Group>::Scalar,
+    ) -> Result<VerifiableServerBatchEvaluatePrepareResult<'a, CS, I>, InternalError> {
+        check_blinded_elements(blinded_elements.clone())?;
+
+        let t = PreparedTscalar { t: *self.sk + m };
+        let t_inverted = Zeroizing::new(CS::Group::scalar_invert(&t.t));
+
+        #[allow(clippy::type_complexity)]
+        let prepare: fn(
+            (&BlindedElement<CS>, Zeroizing<<CS::Group as Group>::Scalar>),
+        ) -> PreparedEvaluationElement<CS> = |(blinded_element, t_inverted)| {
+            let value = blinded_element.value * &t_inverted;
+            trace!("evaluation_element", value.to_arr());
+            PreparedEvaluationElement { value }
+        };
+
+        Ok(VerifiableServerBatchEvaluatePrepareResult {
+            prepared_evaluation_elements: blinded_elements
+                .zip(iter::repeat(t_inverted))
+                .map(prepare),
+            t,
+        })
+    }
+
+    /// Finishes a batch evaluation started by
+    /// [VerifiableServer::batch_evaluate_prepare] by generating the proof. The
+    /// [BlindedElement]s have to be given in the same order as they were
+    /// prepared in.
+    pub fn batch_evaluate_finish<'a, 'b, R: RngCore + CryptoRng, IB: ?Sized, IE: ?Sized>(
+        rng: &mut R,
+        blinded_elements: &'a IB,
+        prepared_evaluation_elements: &'b IE,
+        prepared_tscalar: &PreparedTscalar<CS>,
+    ) -> Result<VerifiableServerBatchEvaluateFinishResult<'b, CS, IE>, InternalError>
+    where
+        CS: 'a + 'b,
+        &'a IB: IntoIterator<Item = &'a BlindedElement<CS>>,
+        &'b IE: IntoIterator<Item = &'b PreparedEvaluationElement<CS>>,
+    {
+        Self::batch_evaluate_finish_inner(
+            random_proof_nonce::<CS, _>(rng),
+            blinded_elements,
+            prepared_evaluation_elements,
+            prepared_tscalar,
+        )
+    }
+
+    /// Alternative version of [VerifiableServer::batch_evaluate_finish] which
+    /// doesn't take an RNG. Instead, the nonce of the proof is derived
+    /// deterministically from the private key, the composite elements and the
+    /// given `personalization` string, in the spirit of RFC 6979.
+    ///
+    /// This protects the private key against a weak or broken RNG at proof
+    /// time, and makes proofs reproducible for a given input. The
+    /// `personalization` string can be used to separate different
+    /// applications of the same key, and may be empty.
+    pub fn batch_evaluate_finish_derandomized<'a, 'b, IB: ?Sized, IE: ?Sized>(
+        blinded_elements: &'a IB,
+        prepared_evaluation_elements: &'b IE,
+        prepared_tscalar: &PreparedTscalar<CS>,
+        personalization: &[u8],
+    ) -> Result<VerifiableServerBatchEvaluateFinishResult<'b, CS, IE>, InternalError>
+    where
+        CS: 'a + 'b,
+        &'a IB: IntoIterator<Item = &'a BlindedElement<CS>>,
+        &'b IE: IntoIterator<Item = &'b PreparedEvaluationElement<CS>>,
+    {
+        Self::batch_evaluate_finish_inner(
+            derandomized_proof_nonce::<CS>(personalization),
+            blinded_elements,
+            prepared_evaluation_elements,
+            prepared_tscalar,
+        )
+    }
+
+    fn batch_evaluate_finish_inner<'a, 'b, IB: ?Sized, IE: ?Sized>(
+        nonce: impl ProofNonce<CS>,
+        blinded_elements: &'a IB,
+        prepared_evaluation_elements: &'b IE,
+        prepared_tscalar: &PreparedTscalar<CS>,
+    ) -> Result<VerifiableServerBatchEvaluateFinishResult<'b, CS, IE>, InternalError>
+    where
+        CS: 'a + 'b,
+        &'a IB: IntoIterator<Item = &'a BlindedElement<CS>>,
+        &'b IE: IntoIterator<Item = &'b PreparedEvaluationElement<CS>>,
+    {
+        // The prepared elements may come from a checkpoint, so the blinded
+        // elements they were prepared from are checked again
+        check_blinded_elements(blinded_elements)?;
+
+        let g = CS::Group::base_point();
+        let u = g * &prepared_tscalar.t;
+
+        let proof = generate_proof(
+            nonce,
+            &prepared_tscalar.t,
+            g,
+            u,
+            prepared_evaluation_elements.into_iter().map(|x| x.value),
+            blinded_elements.into_iter().map(|x| x.value),
+            Mode::Verifiable,
+        )?;
+
+        let finish: fn(&PreparedEvaluationElement<CS>) -> EvaluationElement<CS> =
+            |x| EvaluationElement { value: x.value };
+
+        Ok(VerifiableServerBatchEvaluateFinishResult {
+            messages: prepared_evaluation_elements.into_iter().map(finish),
+            proof,
+        })
+    }
+
+    /// Splits the key into the two shares of a [SplitKeyServer] for
+    /// `metadata`, to be handed to two independent operators. Each share
+    /// proves its part of the evaluation, and the client verifies both proofs
+    /// with [VerifiableClient::batch_finalize_split].
+    pub fn split_key<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        metadata: &Metadata,
+    ) -> Result<(SplitKeyServer<CS>, SplitKeyServer<CS>), InternalError> {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(
+            &metadata.0,
+            Mode::Verifiable,
+        )?);
+        split_key::<CS, _>(rng, &self.sk, &m, Mode::Verifiable)
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<CS: CipherSuite> VerifiableServer<CS>
+where
+    CS::Group: Send + Sync,
+    <CS::Group as Group>::Scalar: Send + Sync,
+{
+    /// Alternative version of [VerifiableServer::batch_evaluate] which spreads
+    /// the work across the threads of the global rayon pool. The scalar
+    /// multiplications of the evaluation and the hashes weighting the
+    /// composite elements of the proof are computed in parallel.
+    pub fn par_batch_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+        metadata: &Metadata,
+    ) -> Result<VerifiableServerBatchEvaluateResult<CS>, InternalError> {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(
+            &metadata.0,
+            Mode::Verifiable,
+        )?);
+        let t = Zeroizing::new(*self.sk + &m);
+        let t_inverted = Zeroizing::new(CS::Group::scalar_invert(&t));
+        check_blinded_elements(blinded_elements)?;
+
+        let messages: Vec<_> = blinded_elements
+            .par_iter()
+            .map(|x| EvaluationElement {
+                value: x.value * &t_inverted,
+            })
+            .collect();
+
+        let g = CS::Group::base_point();
+        let u = g * &t;
+        let (composite_m, composite_z) = par_compute_composites::<CS>(
+            Some(&t),
+            u,
+            messages.par_iter().map(|x| x.value),
+            blinded_elements.par_iter().map(|x| x.value),
+            Mode::Verifiable,
+        )?;
+        let proof = prove::<CS>(
+            random_proof_nonce::<CS, _>(rng),
+            &t,
+            g,
+            u,
+            composite_m,
+            composite_z,
+            Mode::Verifiable,
+        )?;
+
+        Ok(VerifiableServerBatchEvaluateResult { messages, proof })
+    }
+}
+
+impl<CS: CipherSuite> PoprfClient<CS> {
+    /// Computes the third step of the POPRF, in which the client verifies the
+    /// proof against the public key tweaked by `metadata` and unblinds the
+    /// server's message. The `input` has to be the same as the one given to
+    /// [PoprfClient::blind].
+    ///
+    /// Borrows the client, so its blind can be reused for another evaluation
+    /// by mistake, which links the evaluations.
+    #[deprecated(note = "use finalize_once")]
+    pub fn finalize(
+        &self,
+        input: &[u8],
+        evaluation_element: EvaluationElement<CS>,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+    ) -> Result<PoprfClientFinalizeResult<CS>, InternalError> {
+        let inputs = [input];
+        let clients = core::slice::from_ref(self);
+        let messages = core::slice::from_ref(&evaluation_element);
+
+        let mut batch_result =
+            Self::batch_finalize(&inputs, clients, messages, proof, pk, metadata)?;
+        let output = batch_result
+            .next()
+            .ok_or(InternalError::MismatchedLengthsForCompositeInputs)??;
+        Ok(PoprfClientFinalizeResult { output })
+    }
+
+    /// Like [PoprfClient::finalize], but consumes the client, so that its blind
+    /// can't be reused for another evaluation
+    pub fn finalize_once(
+        self,
+        input: &[u8],
+        evaluation_element: EvaluationElement<CS>,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+    ) -> Result<PoprfClientFinalizeResult<CS>, InternalError> {
+        self.finalize_inner(&[input], evaluation_element, proof, pk, metadata)
+    }
+
+    /// Alternative version of [PoprfClient::finalize] for an input made of
+    /// several parts, see [Client::blind_parts]
+    #[deprecated(note = "use finalize_parts_once")]
+    pub fn finalize_parts(
+        &self,
+        input: &[&[u8]],
+        evaluation_element: EvaluationElement<CS>,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+    ) -> Result<PoprfClientFinalizeResult<CS>, InternalError> {
+        self.finalize_inner(input, evaluation_element, proof, pk, metadata)
+    }
+
+    /// Like [PoprfClient::finalize_parts], but consumes the client
+    pub fn finalize_parts_once(
+        self,
+        input: &[&[u8]],
+        evaluation_element: EvaluationElement<CS>,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+    ) -> Result<PoprfClientFinalizeResult<CS>, InternalError> {
+        self.finalize_inner(input, evaluation_element, proof, pk, metadata)
+    }
+
+    fn finalize_inner(
+        &self,
+        input: &[&[u8]],
+        evaluation_element: EvaluationElement<CS>,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+    ) -> Result<PoprfClientFinalizeResult<CS>, InternalError> {
+        check_input_binding_parts::<CS>(&self.input_binding, input, &self.blind, Mode::Poprf)?;
+
+        let clients = core::slice::from_ref(self);
+        let messages = core::slice::from_ref(&evaluation_element);
+        let verified = verify_batch_elements(
+            iter::once(self.blinded_element),
+            iter::once(evaluation_element.value),
+            pk,
+            proof,
+            &metadata.0,
+            Mode::Poprf,
+        )?;
+        let unblinded_element = unblind_poprf_verified(clients, messages, verified)
+            .next()
+            .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?;
+        let output = Zeroizing::new(finalize_after_unblind_with_context::<CS>(
+            input,
+            &unblinded_element,
+            &metadata.0,
+            None,
+            Mode::Poprf,
+        )?);
+        Ok(PoprfClientFinalizeResult { output })
+    }
+
+    /// Allows for batching of the finalization of multiple [PoprfClient] and
+    /// [EvaluationElement] pairs, which were all evaluated with the same
+    /// metadata. The inputs have to be given in the same order as the clients
+    /// they were blinded with.
+    #[allow(clippy::type_complexity)]
+    pub fn batch_finalize<'a, I, II: ?Sized, IC: ?Sized, IM: ?Sized>(
+        inputs: &'a II,
+        clients: &'a IC,
+        messages: &'a IM,
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &'a Metadata,
+    ) -> Result<
+        impl Iterator<
+                Item = Result<
+                    Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>,
+                    InternalError,
+                >,
+            > + 'a,
+        InternalError,
+    >
+    where
+        CS: 'a,
+        I: 'a + AsRef<[u8]>,
+        &'a II: 'a + IntoIterator<Item = I>,
+        &'a IC: 'a + IntoIterator<Item = &'a PoprfClient<CS>>,
+        &'a IM: 'a + IntoIterator<Item = &'a EvaluationElement<CS>>,
+    {
+        check_input_bindings(inputs, clients, Mode::Poprf)?;
+
+        let verified = verify_batch_elements(
+            clients.into_iter().map(|x| x.blinded_element),
+            messages.into_iter().map(|x| x.value),
+            pk,
+            proof,
+            &metadata.0,
+            Mode::Poprf,
+        )?;
+
+        Ok(inputs
+            .into_iter()
+            .zip(unblind_poprf_verified(clients, messages, verified))
+            .map(move |(input, unblinded_element)| {
+                finalize_after_unblind::<CS>(
+                    input.as_ref(),
+                    &unblinded_element,
+                    &metadata.0,
+                    Mode::Poprf,
+                )
+                .map(Zeroizing::new)
+            }))
+    }
+
+    /// Alternative version of [PoprfClient::batch_finalize] for a fixed number
+    /// of clients, which returns the outputs in an array and doesn't allocate
+    #[allow(clippy::type_complexity)]
+    pub fn batch_finalize_array<I: AsRef<[u8]>, const N: usize>(
+        inputs: &[I; N],
+        clients: &[Self; N],
+        messages: &[EvaluationElement<CS>; N],
+        proof: Proof<CS>,
+        pk: CS::Group,
+        metadata: &Metadata,
+    ) -> Result<[Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>; N], InternalError>
+    {
+        collect_array(Self::batch_finalize(
+            inputs, clients, messages, proof, pk, metadata,
+        )?)
+    }
+}
+
+impl<CS: CipherSuite> PoprfServer<CS> {
+    /// Computes the second step of the POPRF. The key is tweaked by
+    /// `metadata`, and the proof shows that the message was evaluated with the
+    /// tweaked key, whose public key the client derives from
+    /// [PoprfServer::get_public_key] and the same `metadata`.
+    pub fn evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_element: BlindedElement<CS>,
+        metadata: &Metadata,
+    ) -> Result<PoprfServerEvaluateResult<CS>, InternalError> {
+        let PoprfServerBatchEvaluateResult {
+            mut messages,
+            proof,
+        } = self.batch_evaluate(rng, core::slice::from_ref(&blinded_element), metadata)?;
+        let message = messages
+            .pop()
+            .ok_or(InternalError::MismatchedLengthsForCompositeInputs)?;
+
+        Ok(PoprfServerEvaluateResult { message, proof })
+    }
+
+    /// Allows for batching of the evaluation of multiple [BlindedElement]
+    /// messages from a [PoprfClient] with the same metadata
+    pub fn batch_evaluate<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+        metadata: &Metadata,
+    ) -> Result<PoprfServerBatchEvaluateResult<CS>, InternalError> {
+        let (messages, proof) = self.batch_evaluate_inner(rng, blinded_elements, metadata)?;
+        Ok(PoprfServerBatchEvaluateResult { messages, proof })
+    }
+
+    /// Alternative version of [PoprfServer::batch_evaluate] for a fixed number
+    /// of [BlindedElement]s, which returns the messages in an array instead of
+    /// a [Vec] and doesn't allocate
+    pub fn batch_evaluate_array<R: RngCore + CryptoRng, const N: usize>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>; N],
+        metadata: &Metadata,
+    ) -> Result<PoprfServerBatchEvaluateArrayResult<CS, N>, InternalError> {
+        let (messages, proof): (ArrayVec<_, N>, _) =
+            self.batch_evaluate_inner(rng, blinded_elements, metadata)?;
+        Ok(PoprfServerBatchEvaluateArrayResult {
+            messages: collect_array(messages.into_iter().map(Ok))?,
+            proof,
+        })
+    }
+
+    // Collects the messages into `C`, a `Vec` or an `ArrayVec`
+    fn batch_evaluate_inner<R, C>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+        metadata: &Metadata,
+    ) -> Result<(C, Proof<CS>), InternalError>
+    where
+        R: RngCore + CryptoRng,
+        C: iter::FromIterator<EvaluationElement<CS>> + AsRef<[EvaluationElement<CS>]>,
+    {
+        let m = Zeroizing::new(compute_metadata_scalar::<CS>(&metadata.0, Mode::Poprf)?);
+        let t = Zeroizing::new(*self.sk + &m);
+        // The tweaked key is zero if the metadata hashes to `-sk`, which can't
+        // be inverted
+        if CS::Group::ct_equal_scalar(&t, &CS::Group::scalar_zero()) {
+            return Err(InternalError::ZeroScalarError);
+        }
+        let t_inverted = Zeroizing::new(CS::Group::scalar_invert(&t));
+        check_blinded_elements(blinded_elements)?;
+
+        let messages: C = blinded_elements
+            .iter()
+            .map(|blinded_element| {
+                let value = blinded_element.value * &t_inverted;
+                trace!("evaluation_element", value.to_arr());
+                EvaluationElement { value }
+            })
+            .collect();
+
+        let g = CS::Group::base_point();
+        let u = g * &t;
+        let proof = generate_proof(
+            random_proof_nonce::<CS, _>(rng),
+            &t,
+            g,
+            u,
+            messages.as_ref().iter().map(|x| x.value),
+            blinded_elements.iter().map(|x| x.value),
+            Mode::Poprf,
+        )?;
+
+        Ok((messages, proof))
+    }
+}
+
+impl<CS: CipherSuite> SplitKeyServer<CS> {
+    /// Returns `true` for the first share, which evaluates the
+    /// [BlindedElement]s of the clients
+    pub fn is_first(&self) -> bool {
+        self.base.ct_equal(&CS::Group::base_point())
+    }
+
+    /// Retrieves the public key of the share. The public key of the first
+    /// share has to be given to [VerifiableClient::batch_finalize_split].
+    pub fn get_public_key(&self) -> CS::Group {
+        self.pk
+    }
+
+    /// Evaluates the [BlindedElement]s of the clients with the first share.
+    /// The result has to be passed to the second share with
+    /// [SplitKeyServer::evaluate_second], the partial elements alone don't
+    /// let the clients compute their outputs.
+    pub fn evaluate_first<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        blinded_elements: &[BlindedElement<CS>],
+    ) -> Result<SplitKeyFirstEvaluateResult<CS>, InternalError> {
+        if !self.is_first() {
+            return Err(InternalError::SplitKeyShareError);
+        }
+        check_blinded_elements(blinded_elements)?;
+
+        let partial_elements = self.evaluate_share(blinded_elements.iter().map(|x| x.value));
+        let proof = self.prove_share(
+            rng,
+            &partial_elements,
+            blinded_elements.iter().map(|x| x.value),
+        )?;
+
+        Ok(SplitKeyFirstEvaluateResult {
+            partial_elements,
+            proof,
+        })
+    }
+
+    /// Evaluates the result of [SplitKeyServer::evaluate_first] with the
+    /// second share, which produces the messages for the clients
+    pub fn evaluate_second<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        first: SplitKeyFirstEvaluateResult<CS>,
+    ) -> Result<SplitKeyEvaluateResult<CS>, InternalError> {
+        if self.is_first() {
+            return Err(InternalError::SplitKeyShareError);
+        }
+
+        let SplitKeyFirstEvaluateResult {
+            partial_elements,
+            proof: first_proof,
+        } = first;
+
+        let messages = self.evaluate_share(partial_elements.iter().map(|x| x.value));
+        let second_proof =
+            self.prove_share(rng, &messages, partial_elements.iter().map(|x| x.value))?;
+
+        let proof = match (first_proof, second_proof) {
+            (Some(first), Some(second)) => Some(SplitKeyProof {
+                partial_elements,
+                first,
+                second,
+            }),
+            (None, None) => None,
+            _ => return Err(InternalError::IncompatibleModeError),
+        };
+
+        Ok(SplitKeyEvaluateResult { messages, proof })
+    }
+
+    fn evaluate_share(
+        &self,
+        elements: impl Iterator<Item = CS::Group>,
+    ) -> Vec<EvaluationElement<CS>> {
+        let t_inverted = Zeroizing::new(CS::Group::scalar_invert(&self.t));
+        elements
+            .map(|element| EvaluationElement {
+                value: element * &t_inverted,
+            })
+            .collect()
+    }
+
+    // Proves that the share evaluated `elements` into `evaluated`, only in
+    // verifiable mode
+    fn prove_share<R: RngCore + CryptoRng>(
+        &self,
+        rng: &mut R,
+        evaluated: &[EvaluationElement<CS>],
+        elements: impl Iterator<Item = CS::Group>,
+    ) -> Result<Option<Proof<CS>>, InternalError> {
+        if self.mode != Mode::Verifiable {
+            return Ok(None);
+        }
+
+        generate_proof(
+            random_proof_nonce::<CS, _>(rng),
+            &self.t,
+            self.base,
+            self.pk,
+            evaluated.iter().map(|x| x.value),
+            elements,
+            Mode::Verifiable,
+        )
+        .map(Some)
+    }
+}
+
+impl<CS: CipherSuite> BlindedElement<CS> {
+    /// Constructs the message from a raw group element, for integrators which
+    /// don't obtain it from [NonVerifiableClient::blind],
+    /// [VerifiableClient::blind] or [BlindedElement::deserialize]. Fails with
+    /// [InternalError::IdentityElementError] if `value` is the identity
+    /// element. Every value of a supported group is in its prime-order
+    /// subgroup, so no other check is needed.
+    pub fn from_value_checked(value: CS::Group) -> Result<Self, InternalError> {
+        check_element::<CS>(&value)?;
+        Ok(Self { value })
+    }
+}
+
+impl<CS: CipherSuite> EvaluationElement<CS> {
+    /// Constructs the message from a raw group element, for integrators which
+    /// don't obtain it from a server evaluation or
+    /// [EvaluationElement::deserialize]. Fails with
+    /// [InternalError::IdentityElementError] if `value` is the identity
+    /// element. Every value of a supported group is in its prime-order
+    /// subgroup, so no other check is needed.
+    pub fn from_value_checked(value: CS::Group) -> Result<Self, InternalError> {
+        check_element::<CS>(&value)?;
+        Ok(Self { value })
+    }
+}
+
+impl<CS: CipherSuite> Proof<CS> {
+    /// Verifies the proof of a [VerifiableServer] over a batch of
+    /// `blinded_elements` and the `evaluation_elements` it returned for them,
+    /// in the same order, against its public key `pk` tweaked by `metadata`.
+    /// This doesn't need the blinds of the clients, so anyone holding the
+    /// transcript, like an auditor, can check that the server evaluated it
+    /// with its key.
+    ///
+    /// Fails with [InternalError::ProofVerificationError] if the proof doesn't
+    /// verify.
+    pub fn verify(
+        &self,
+        pk: CS::Group,
+        blinded_elements: &[BlindedElement<CS>],
+        evaluation_elements: &[EvaluationElement<CS>],
+        metadata: &Metadata,
+    ) -> Result<(), InternalError> {
+        self.verify_in_mode(
+            pk,
+            blinded_elements,
+            evaluation_elements,
+            metadata,
+            Mode::Verifiable,
+        )
+    }
+
+    /// Verifies the proof of a [PoprfServer], like [Proof::verify]
+    pub fn verify_poprf(
+        &self,
+        pk: CS::Group,
+        blinded_elements: &[BlindedElement<CS>],
+        evaluation_elements: &[EvaluationElement<CS>],
+        metadata: &Metadata,
+    ) -> Result<(), InternalError> {
+        self.verify_in_mode(
+            pk,
+            blinded_elements,
+            evaluation_elements,
+            metadata,
+            Mode::Poprf,
+        )
+    }
+
+    fn verify_in_mode(
+        &self,
+        pk: CS::Group,
+        blinded_elements: &[BlindedElement<CS>],
+        evaluation_elements: &[EvaluationElement<CS>],
+        metadata: &Metadata,
+        mode: Mode,
+    ) -> Result<(), InternalError> {
+        verify_batch_elements(
+            blinded_elements.iter().map(|x| x.value),
+            evaluation_elements.iter().map(|x| x.value),
+            pk,
+            self.clone(),
+            &metadata.0,
+            mode,
+        )
+        .map(|_| ())
+    }
+}
+
+impl<CS: CipherSuite> NonVerifiableClientFinalizeResult<CS> {
+    /// Derives `okm.len()` bytes from the output, for applications that need a
+    /// longer output or several independent sub-keys
+    ///
+    /// The output is used as the pseudorandom key of HKDF-Expand with the
+    /// hash of the ciphersuite, and `info` separates the derived keys from
+    /// each other. At most 255 times the output size can be derived.
+    pub fn expand(&self, info: &[u8], okm: &mut [u8]) -> Result<(), InternalError> {
+        expand_output::<CS>(&self.output, info, okm)
+    }
+}
+
+impl<CS: CipherSuite> VerifiableClientFinalizeResult<CS> {
+    /// Derives `okm.len()` bytes from the output, for applications that need a
+    /// longer output or several independent sub-keys
+    ///
+    /// The output is used as the pseudorandom key of HKDF-Expand with the
+    /// hash of the ciphersuite, and `info` separates the derived keys from
+    /// each other. At most 255 times the output size can be derived.
+    pub fn expand(&self, info: &[u8], okm: &mut [u8]) -> Result<(), InternalError> {
+        expand_output::<CS>(&self.output, info, okm)
+    }
+}
+
+impl<CS: CipherSuite> PoprfClientFinalizeResult<CS> {
+    /// Derives `okm.len()` bytes from the output, for applications that need a
+    /// longer output or several independent sub-keys
+    ///
+    /// The output is used as the pseudorandom key of HKDF-Expand with the
+    /// hash of the ciphersuite, and `info` separates the derived keys from
+    /// each other. At most 255 times the output size can be derived.
+    pub fn expand(&self, info: &[u8], okm: &mut [u8]) -> Result<(), InternalError> {
+        expand_output::<CS>(&self.output, info, okm)
+    }
+}
+
+/////////////////////////
+// Optional Parameters //
+//==================== //
+/////////////////////////
+
+/// Allows for implementations to specify an optional sequence of
+/// public bytes that must be agreed-upon by the client and server
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Metadata(pub Vec<u8>);
+
+impl Metadata {
+    /// Specifies no metadata (the default option)
+    pub fn none() -> Self {
+        Self::default()
+    }
+}
+
+/////////////////////////
+// Convenience Structs //
+//==================== //
+/////////////////////////
+
+/// Contains the fields that are returned by a client blind
+#[must_use]
+pub struct ClientBlindResult<CS: CipherSuite, M: ProtocolMode<CS>> {
+    /// The state to be persisted on the client
+    pub state: Client<CS, M>,
+    /// The message to send to the server
+    pub message: BlindedElement<CS>,
+}
+
+/// Contains the fields that are returned by a non-verifiable client blind
+pub type NonVerifiableClientBlindResult<CS> = ClientBlindResult<CS, Base>;
+
+/// Contains the fields that are returned by a non-verifiable server evaluate
+#[must_use]
+pub struct NonVerifiableServerEvaluateResult<CS: CipherSuite> {
+    /// The message to send to the client
+    pub message: EvaluationElement<CS>,
+}
+
+/// Contains the fields that are returned by a non-verifiable client finalize
+#[must_use]
+pub struct NonVerifiableClientFinalizeResult<CS: CipherSuite> {
+    /// The output of the protocol
+    pub output: Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>,
+}
+
+/// Contains the fields that are returned by a verifiable client blind
+pub type VerifiableClientBlindResult<CS> = ClientBlindResult<CS, Verifiable>;
+
+/// Contains the fields that are returned by a verifiable server evaluate
+#[must_use]
+pub struct VerifiableServerEvaluateResult<CS: CipherSuite> {
+    /// The message to send to the client
+    pub message: EvaluationElement<CS>,
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+}
+
+/// Contains the fields that are returned by a verifiable server batch evaluate
+#[must_use]
+pub struct VerifiableServerBatchEvaluateResult<CS: CipherSuite> {
+    /// The messages to send to the client
+    pub messages: Vec<EvaluationElement<CS>>,
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+}
+
+/// Contains the fields that are returned by a verifiable server batch evaluate
+/// of a fixed number of elements
+#[must_use]
+pub struct VerifiableServerBatchEvaluateArrayResult<CS: CipherSuite, const N: usize> {
+    /// The messages to send to the client
+    pub messages: [EvaluationElement<CS>; N],
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+}
+
+/// Contains the fields that are returned by a verifiable server batch evaluate
+/// prepare
+#[must_use]
+pub struct VerifiableServerBatchEvaluatePrepareResult<
+    'a,
+    CS: 'a + CipherSuite,
+    I: Iterator<Item = &'a BlindedElement<CS>>,
+> {
+    /// The prepared evaluation elements, to be passed to
+    /// [VerifiableServer::batch_evaluate_finish]
+    #[allow(clippy::type_complexity)]
+    pub prepared_evaluation_elements: Map<
+        Zip<I, Repeat<Zeroizing<<CS::Group as Group>::Scalar>>>,
+        fn(
+            (&BlindedElement<CS>, Zeroizing<<CS::Group as Group>::Scalar>),
+        ) -> PreparedEvaluationElement<CS>,
+    >,
+    /// The prepared `t`, to be passed to
+    /// [VerifiableServer::batch_evaluate_finish]
+    pub t: PreparedTscalar<CS>,
+}
+
+/// Contains the fields that are returned by a verifiable server batch evaluate
+/// finish
+#[must_use]
+pub struct VerifiableServerBatchEvaluateFinishResult<'b, CS: 'b + CipherSuite, IE: ?Sized>
+where
+    &'b IE: IntoIterator<Item = &'b PreparedEvaluationElement<CS>>,
+{
+    /// The messages to send to the client
+    #[allow(clippy::type_complexity)]
+    pub messages: Map<
+        <&'b IE as IntoIterator>::IntoIter,
+        fn(&PreparedEvaluationElement<CS>) -> EvaluationElement<CS>,
+    >,
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+}
+
+/// Contains the fields that are returned by [SplitKeyServer::evaluate_first]
+#[must_use]
+pub struct SplitKeyFirstEvaluateResult<CS: CipherSuite> {
+    /// The partially evaluated elements, to send to the second share
+    pub partial_elements: Vec<EvaluationElement<CS>>,
+    /// The proof of the first share, only produced in verifiable mode
+    pub proof: Option<Proof<CS>>,
+}
+
+/// Contains the fields that are returned by [SplitKeyServer::evaluate_second]
+#[must_use]
+pub struct SplitKeyEvaluateResult<CS: CipherSuite> {
+    /// The messages to send to the clients
+    pub messages: Vec<EvaluationElement<CS>>,
+    /// The proofs of both shares, only produced in verifiable mode
+    pub proof: Option<SplitKeyProof<CS>>,
+}
+
+/// Contains the fields that are returned by a verifiable client finalize
+#[must_use]
+pub struct VerifiableClientFinalizeResult<CS: CipherSuite> {
+    /// The output of the protocol
+    pub output: Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>,
+}
+
+/// Contains the fields that are returned by a POPRF client blind
+pub type PoprfClientBlindResult<CS> = ClientBlindResult<CS, Poprf>;
+
+/// Contains the fields that are returned by a POPRF server evaluate
+#[must_use]
+pub struct PoprfServerEvaluateResult<CS: CipherSuite> {
+    /// The message to send to the client
+    pub message: EvaluationElement<CS>,
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+}
+
+/// Contains the fields that are returned by a POPRF server batch evaluate
+#[must_use]
+pub struct PoprfServerBatchEvaluateResult<CS: CipherSuite> {
+    /// The messages to send to the client
+    pub messages: Vec<EvaluationElement<CS>>,
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+}
+
+/// Contains the fields that are returned by a POPRF server batch evaluate of a
+/// fixed number of elements
+#[must_use]
+pub struct PoprfServerBatchEvaluateArrayResult<CS: CipherSuite, const N: usize> {
+    /// The messages to send to the client
+    pub messages: [EvaluationElement<CS>; N],
+    /// The proof for the client to verify
+    pub proof: Proof<CS>,
+}
+
+/// Contains the fields that are returned by a POPRF client finalize
+#[must_use]
+pub struct PoprfClientFinalizeResult<CS: CipherSuite> {
+    /// The output of the protocol
+    pub output: Zeroizing<GenericArray<u8, <CS::Hash as Digest>::OutputSize>>,
+}
+
+///////////////////////////////////////////////
+// Inner functions and Trait Implementations //
+// ========================================= //
+///////////////////////////////////////////////
+
+// Inner function for blind. Returns the blind scalar and the blinded element
+pub(crate) fn blind<CS: CipherSuite, R: RngCore + CryptoRng>(
+    input: &[&[u8]],
+    blinding_factor_rng: &mut R,
+    mode: Mode,
+) -> Result<(<CS::Group as Group>::Scalar, CS::Group), InternalError> {
+    check_input_parts_len::<CS>(input)?;
+
+    // Choose a random scalar that must be non-zero
+    let blind = <CS::Group as Group>::random_nonzero_scalar(blinding_factor_rng);
+    let blinded_element = deterministic_blind_unchecked::<CS>(input, &blind, mode)?;
+    Ok((blind, blinded_element))
+}
+
+// Inner function for blind_with_pool. Returns the blind scalar and the blinded
+// element
+fn pooled_blind<CS: CipherSuite, R: RngCore + CryptoRng>(
+    input: &[u8],
+    pool: &mut ScalarPool<CS, R>,
+    mode: Mode,
+) -> Result<(<CS::Group as Group>::Scalar, CS::Group), InternalError> {
+    check_input_len::<CS>(input)?;
+
+    let blind = pool.take();
+    let blinded_element = deterministic_blind_unchecked::<CS>(&[input], &blind, mode)?;
+    Ok((blind, blinded_element))
+}
+
+// Blinds the input, given as parts which are hashed as if concatenated, with the
+// given scalar, without checking the input length
+pub(crate) fn deterministic_blind_unchecked<CS: CipherSuite>(
+    input: &[&[u8]],
+    blind: &<CS::Group as Group>::Scalar,
+    mode: Mode,
+) -> Result<CS::Group, InternalError> {
+    let context_string = get_context_string::<CS>(mode)?;
+    let hashed_point =
+        CS::Group::hash_to_curve::<CS>(input, &[STR_HASH_TO_GROUP, &context_string])?;
+    let blinded_element = hashed_point * blind;
+    trace!("hashed_point", hashed_point.to_arr());
+    trace!("blind", CS::Group::scalar_as_bytes(*blind));
+    trace!("blinded_element", blinded_element.to_arr());
+    Ok(blinded_element)
+}
+
+// Fails if two collections of a batch don't have the same length
+pub(crate) fn check_batch_lengths(left: usize, right: usize) -> Result<(), InternalError> {
+    if left != right {
+        return Err(InternalError::BatchMismatch { left, right });
+    }
+
+    Ok(())
+}
+
+// Enforces the input length limit of the ciphersuite
+pub(crate) fn check_input_len<CS: CipherSuite>(input: &[u8]) -> Result<(), InternalError> {
+    check_input_parts_len::<CS>(&[input])
+}
+
+// Like `check_input_len`, for an input given as parts
+fn check_input_parts_len<CS: CipherSuite>(input: &[&[u8]]) -> Result<(), InternalError> {
+    match input_parts_len(input) {
+        Some(len) if len <= CS::MAX_INPUT_LEN => Ok(()),
+        _ => Err(InternalError::InputLengthError),
+    }
+}
+
+// The length of an input given as parts, if it doesn't overflow
+fn input_parts_len(input: &[&[u8]]) -> Option<usize> {
+    input
+        .iter()
+        .try_fold(0_usize, |len, part| len.checked_add(part.len()))
+}
+
+// Enforces the metadata length limit of the ciphersuite
+pub(crate) fn check_metadata_len<CS: CipherSuite>(info: &[u8]) -> Result<(), InternalError> {
+    if info.len() > CS::MAX_METADATA_LEN {
+        return Err(InternalError::MetadataLengthError);
+    }
+
+    Ok(())
+}
+
+// Rejects elements which a message may not contain
+fn check_element<CS: CipherSuite>(value: &CS::Group) -> Result<(), InternalError> {
+    if value.is_identity() {
+        return Err(InternalError::IdentityElementError);
+    }
+
+    Ok(())
+}
+
+// Rejects messages which a server mustn't evaluate. Every supported group has
+// prime order, so the identity is its only element of small order. Its
+// evaluation doesn't depend on the key, so a client sending it is either
+// broken or probing the server
+pub(crate) fn check_blinded_elements<'a, CS: 'a + CipherSuite>(
+    blinded_elements: impl IntoIterator<Item = &'a BlindedElement<CS>>,
+) -> Result<(), InternalError> {
+    if blinded_elements
+        .into_iter()
+        .any(|blinded_element| blinded_element.value.is_identity())
+    {
+        return Err(InternalError::InvalidElement);
+    }
+
+    Ok(())
+}
+
+// Fails if the client doesn't trust the public key
+fn check_key_policy<CS: CipherSuite>(
+    policy: &impl KeyPolicy<CS>,
+    pk: &CS::Group,
+) -> Result<(), InternalError> {
+    if !policy.is_trusted(pk) {
+        return Err(InternalError::UntrustedPublicKey);
+    }
+
+    Ok(())
+}
+
+// Hashes the input together with the blind, so that the result doesn't reveal
+// whether two client states are bound to the same input
+pub(crate) fn compute_input_binding<CS: CipherSuite>(
+    input: &[u8],
+    blind: &<CS::Group as Group>::Scalar,
+    mode: Mode,
+) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    compute_input_binding_parts::<CS>(&[input], blind, mode)
+}
+
+// Like `compute_input_binding`, for an input given as parts, which is bound as
+// if the parts were concatenated
+fn compute_input_binding_parts<CS: CipherSuite>(
+    input: &[&[u8]],
+    blind: &<CS::Group as Group>::Scalar,
+    mode: Mode,
+) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    let context_string = get_context_string::<CS>(mode)?;
+    let input_len = i2osp_2(input_parts_len(input).ok_or(InternalError::SerializationError)?)?;
+    let dst = [STR_INPUT_BINDING, &context_string];
+    let mut blind = CS::Group::scalar_as_bytes(*blind);
+
+    let input_binding = match input {
+        [input] => CS::Group::hash_to_scalar::<CS>(&[&input_len, input, &blind], &dst),
+        // Only splicing more than one part into the message needs an allocation
+        _ => {
+            let msg: Vec<&[u8]> = iter::once(&input_len[..])
+                .chain(input.iter().copied())
+                .chain(iter::once(&blind[..]))
+                .collect();
+            CS::Group::hash_to_scalar::<CS>(&msg, &dst)
+        }
+    };
+    blind.as_mut_slice().zeroize();
+    input_binding
+}
+
+// Collects exactly `N` items into an array without allocating, failing on the
+// first error
+fn collect_array<T, const N: usize>(
+    iter: impl IntoIterator<Item = Result<T, InternalError>>,
+) -> Result<[T; N], InternalError> {
+    iter.into_iter()
+        .take(N)
+        .collect::<Result<ArrayVec<T, N>, _>>()?
+        .into_inner()
+        .map_err(|items| InternalError::BatchMismatch {
+            left: N,
+            right: items.len(),
+        })
+}
+
+// Fails if the client state is bound to a different input
+fn check_input_binding<CS: CipherSuite>(
+    input_binding: &Option<<CS::Group as Group>::Scalar>,
+    input: &[u8],
+    blind: &<CS::Group as Group>::Scalar,
+    mode: Mode,
+) -> Result<(), InternalError> {
+    check_input_binding_parts::<CS>(input_binding, &[input], blind, mode)
+}
+
+// Like `check_input_binding`, for an input given as parts
+fn check_input_binding_parts<CS: CipherSuite>(
+    input_binding: &Option<<CS::Group as Group>::Scalar>,
+    input: &[&[u8]],
+    blind: &<CS::Group as Group>::Scalar,
+    mode: Mode,
+) -> Result<(), InternalError> {
+    match input_binding {
+        Some(input_binding)
+            if !CS::Group::ct_equal_scalar(
+                input_binding,
+                &compute_input_binding_parts::<CS>(input, blind, mode)?,
+            ) =>
+        {
+            Err(InternalError::InputMismatchError)
+        }
+        _ => Ok(()),
+    }
+}
+
+// Checks the input bindings of a batch of clients, pairing each client with the
+// input at the same position. Fails if there are more inputs than clients or
+// vice versa.
+fn check_input_bindings<'a, CS: 'a + CipherSuite, M: 'a + ProtocolMode<CS>, I: AsRef<[u8]>>(
+    inputs: impl IntoIterator<Item = I>,
+    clients: impl IntoIterator<Item = &'a Client<CS, M>>,
+    mode: Mode,
+) -> Result<(), InternalError> {
+    let mut inputs = inputs.into_iter();
+    let mut clients = clients.into_iter();
+    let mut paired = 0;
+
+    loop {
+        match (inputs.next(), clients.next()) {
+            (Some(input), Some(client)) => check_input_binding::<CS>(
+                &client.input_binding,
+                input.as_ref(),
+                &client.blind,
+                mode,
+            )?,
+            (None, None) => return Ok(()),
+            (input, client) => {
+                return Err(batch_mismatch(paired, input.is_some(), client.is_some()))
+            }
+        }
+        paired += 1;
+    }
+}
+
+// The error for two iterators of a batch which yielded `paired` pairs before
+// one of them ran out. The rest of the longer one isn't drained, it may be
+// unbounded, so it is reported with one item more than the shorter one.
+fn batch_mismatch(paired: usize, left: bool, right: bool) -> InternalError {
+    InternalError::BatchMismatch {
+        left: paired + usize::from(left),
+        right: paired + usize::from(right),
+    }
+}
+
+// Compares two optional input bindings, the bound values in constant time
+fn ct_equal_input_binding<CS: CipherSuite>(
+    a: &Option<<CS::Group as Group>::Scalar>,
+    b: &Option<<CS::Group as Group>::Scalar>,
+) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => CS::Group::ct_equal_scalar(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+// Inner function for FullEvaluate. Returns the output of `input`, computed
+// with the private key instead of through a blinded evaluation
+fn full_evaluate<CS: CipherSuite>(
+    sk: &<CS::Group as Group>::Scalar,
+    input: &[u8],
+    info: &[u8],
+    mode: Mode,
+) -> Result<GenericArray<u8, <CS::Hash as Digest>::OutputSize>, InternalError> {
+    check_input_len::<CS>(input)?;
+
+    let m = Zeroizing::new(compute_metadata_scalar::<CS>(info, mode)?);
+    let hashed_point = hash_to_group::<CS>(input, mode)?;
+
+    let t = Zeroizing::new(*sk + &m);
+    let t_inverted = Zeroizing::new(CS::Group::scalar_invert(&t));
+    let evaluated_element = ZeroizingElement(hashed_point * &t_inverted);
+
+    finalize_after_unblind::<CS>(input, &evaluated_element, info, mode)
+}
+
+// Inner function for DeriveKeyPair. Returns the private key
+fn derive_private_key<CS: CipherSuite>(
+    seed: &[u8],
+    mode: Mode,
+) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    hash_to_scalar::<CS>(seed, mode)
+}
+
+// Computes the scalar `m` which binds the metadata to the key
+pub(crate) fn compute_metadata_scalar<CS: CipherSuite>(
+    info: &[u8],
+    mode: Mode,
+) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    check_metadata_len::<CS>(info)?;
+
+    let context_string = get_context_string::<CS>(mode)?;
+    let context = [STR_CONTEXT, &context_string, &i2osp_2(info.len())?, info];
+    let dst = [STR_HASH_TO_SCALAR, &context_string];
+    let m = CS::Group::hash_to_scalar::<CS>(&context, &dst)?;
+    trace!("metadata_scalar", CS::Group::scalar_as_bytes(m));
+    Ok(m)
+}
+
+// Splits `t = sk + m` multiplicatively into a random share and the share
+// completing it, see `SplitKeyServer`
+fn split_key<CS: CipherSuite, R: RngCore + CryptoRng>(
+    rng: &mut R,
+    sk: &<CS::Group as Group>::Scalar,
+    m: &<CS::Group as Group>::Scalar,
+    mode: Mode,
+) -> Result<(SplitKeyServer<CS>, SplitKeyServer<CS>), InternalError> {
+    let t = Zeroizing::new(*sk + m);
+    if CS::Group::ct_equal_scalar(&t, &CS::Group::scalar_zero()) {
+        return Err(InternalError::ZeroScalarError);
+    }
+
+    let t1 = Zeroizing::new(CS::Group::random_nonzero_scalar(rng));
+    let t2 = Zeroizing::new(*t * &Zeroizing::new(CS::Group::scalar_invert(&t1)));
+
+    let g = CS::Group::base_point();
+    let u1 = g * &t1;
+
+    Ok((
+        SplitKeyServer {
+            t: Secret::new(*t1)?,
+            base: g,
+            pk: u1,
+            mode,
+        },
+        SplitKeyServer {
+            t: Secret::new(*t2)?,
+            base: u1,
+            pk: g * &t,
+            mode,
+        },
+    ))
+}
+
+fn verifiable_unblind<'a, CS: 'a + CipherSuite, IC: ?Sized, IM: ?Sized>(
+    clients: &'a IC,
+    messages: &'a IM,
+    pk: CS::Group,
+    proof: Proof<CS>,
+    info: &[u8],
+) -> Result<impl Iterator<Item = ZeroizingElement<CS::Group>> + 'a, InternalError>
+where
+    &'a IC: 'a + IntoIterator<Item = &'a VerifiableClient<CS>>,
+    &'a IM: 'a + IntoIterator<Item = &'a EvaluationElement<CS>>,
+{
+    let verified = verify_batch(clients, messages, pk, proof, info)?;
+
+    Ok(unblind_verified(clients, messages, verified))
+}
+
+// Verifies the proof of a batch against the public key
+fn verify_batch<'a, CS: 'a + CipherSuite, IC: ?Sized, IM: ?Sized>(
+    clients: &'a IC,
+    messages: &'a IM,
+    pk: CS::Group,
+    proof: Proof<CS>,
+    info: &[u8],
+) -> Result<VerifiedProof, InternalError>
+where
+    &'a IC: 'a + IntoIterator<Item = &'a VerifiableClient<CS>>,
+    &'a IM: 'a + IntoIterator<Item = &'a EvaluationElement<CS>>,
+{
+    verify_batch_elements(
+        clients.into_iter().map(|x| x.blinded_element),
+        messages.into_iter().map(|x| x.value),
+        pk,
+        proof,
+        info,
+        Mode::Verifiable,
+    )
+}
+
+// Verifies the proof of a batch of blinded and evaluated elements against the
+// public key
+pub(crate) fn verify_batch_elements<CS: CipherSuite>(
+    blinded_elements: impl Iterator<Item = CS::Group>,
+    evaluation_elements: impl Iterator<Item = CS::Group>,
+    pk: CS::Group,
+    proof: Proof<CS>,
+    info: &[u8],
+    mode: Mode,
+) -> Result<VerifiedProof, InternalError> {
+    // Both supported groups have prime order, so the identity is the only
+    // element of low order
+    if pk.is_identity() {
+        return Err(InternalError::InvalidPublicKey);
+    }
+
+    let m = compute_metadata_scalar::<CS>(info, mode)?;
+
+    let g = CS::Group::base_point();
+    let t = g * &m;
+    let u = t + &pk;
+
+    verify_proof(g, u, evaluation_elements, blinded_elements, proof, mode)
+}
+
+// Unblinds evaluation elements, which is only possible with the token returned
+// by a successful `verify_proof`. Takes the clients and messages either by
+// reference or by value.
+fn unblind_verified<CS: CipherSuite, IC, IM>(
+    clients: IC,
+    messages: IM,
+    _: VerifiedProof,
+) -> impl Iterator<Item = ZeroizingElement<CS::Group>>
+where
+    IC: IntoIterator,
+    IC::Item: Borrow<VerifiableClient<CS>>,
+    IM: IntoIterator,
+    IM::Item: Borrow<EvaluationElement<CS>>,
+{
+    clients.into_iter().zip(messages).map(|(client, x)| {
+        let blind_inverted = Zeroizing::new(CS::Group::scalar_invert(&client.borrow().blind));
+        ZeroizingElement(x.borrow().value * &blind_inverted)
+    })
+}
+
+// Like `unblind_verified`, but inverts all blinds at once with
+// `Group::invert_scalars_batch`, for callers which allocate anyway
+fn unblind_verified_batch<CS: CipherSuite, IC, IM>(
+    clients: IC,
+    messages: IM,
+    _: VerifiedProof,
+) -> impl Iterator<Item = ZeroizingElement<CS::Group>>
+where
+    IC: IntoIterator,
+    IC::Item: Borrow<VerifiableClient<CS>>,
+    IM: IntoIterator,
+    IM::Item: Borrow<EvaluationElement<CS>>,
+{
+    let mut blinds_inverted: Zeroizing<Vec<_>> = Zeroizing::new(
+        clients
+            .into_iter()
+            .map(|client| client.borrow().blind)
+            .collect(),
+    );
+    CS::Group::invert_scalars_batch(&mut blinds_inverted);
+
+    let len = blinds_inverted.len();
+    messages
+        .into_iter()
+        .zip(0..len)
+        .map(move |(x, index)| ZeroizingElement(x.borrow().value * &blinds_inverted[index]))
+}
+
+// Counterpart of `unblind_verified` for the clients of the POPRF
+fn unblind_poprf_verified<'a, CS: 'a + CipherSuite, IC: ?Sized, IM: ?Sized>(
+    clients: &'a IC,
+    messages: &'a IM,
+    _: VerifiedProof,
+) -> impl Iterator<Item = ZeroizingElement<CS::Group>> + 'a
+where
+    &'a IC: 'a + IntoIterator<Item = &'a PoprfClient<CS>>,
+    &'a IM: 'a + IntoIterator<Item = &'a EvaluationElement<CS>>,
+{
+    clients.into_iter().zip(messages).map(|(client, x)| {
+        let blind_inverted = Zeroizing::new(CS::Group::scalar_invert(&client.blind));
+        ZeroizingElement(x.value * &blind_inverted)
+    })
+}
+
+#[allow(clippy::many_single_char_names)]
+pub(crate) fn generate_proof<CS: CipherSuite>(
+    nonce: impl ProofNonce<CS>,
+    k: &<CS::Group as Group>::Scalar,
+    a: CS::Group,
+    b: CS::Group,
+    cs: impl Iterator<Item = CS::Group>,
+    ds: impl Iterator<Item = CS::Group>,
+    mode: Mode,
+) -> Result<Proof<CS>, InternalError> {
+    let (m, z) = compute_composites::<CS>(Some(k), b, cs, ds, mode)?;
+
+    prove::<CS>(nonce, k, a, b, m, z, mode)
+}
+
+// Second half of `generate_proof`, split off so the large frame of the point
+// arithmetic isn't on the stack while the composites are computed
+fn prove<CS: CipherSuite>(
+    nonce: impl ProofNonce<CS>,
+    k: &<CS::Group as Group>::Scalar,
+    a: CS::Group,
+    b: CS::Group,
+    m: CS::Group,
+    z: CS::Group,
+    mode: Mode,
+) -> Result<Proof<CS>, InternalError> {
+    let r = Zeroizing::new(nonce(k, m, z)?);
+    let t2 = a * &r;
+    let t3 = m * &r;
+
+    let c_scalar = compute_challenge::<CS>(b, m, z, t2, t3, mode)?;
+    let s_scalar = *r - &Zeroizing::new(c_scalar * k);
+    trace!("proof_c", CS::Group::scalar_as_bytes(c_scalar));
+    trace!("proof_s", CS::Group::scalar_as_bytes(s_scalar));
+
+    Ok(Proof { c_scalar, s_scalar })
+}
+
+// Produces the nonce `r` of a proof, given the private key and the composite
+// elements `M` and `Z`
+pub(crate) trait ProofNonce<CS: CipherSuite>:
+    FnOnce(
+    &<CS::Group as Group>::Scalar,
+    CS::Group,
+    CS::Group,
+) -> Result<<CS::Group as Group>::Scalar, InternalError>
+{
+}
+
+impl<CS: CipherSuite, F> ProofNonce<CS> for F where
+    F: FnOnce(
+        &<CS::Group as Group>::Scalar,
+        CS::Group,
+        CS::Group,
+    ) -> Result<<CS::Group as Group>::Scalar, InternalError>
+{
+}
+
+// Samples the nonce from the given RNG
+pub(crate) fn random_proof_nonce<CS: CipherSuite, R: RngCore + CryptoRng>(
+    rng: &mut R,
+) -> impl '_ + ProofNonce<CS> {
+    move |_: &_, _, _| Ok(CS::Group::random_nonzero_scalar(rng))
+}
+
+// Takes the nonce from the given pool
+fn pooled_proof_nonce<CS: CipherSuite, R: RngCore + CryptoRng>(
+    pool: &mut ScalarPool<CS, R>,
+) -> impl '_ + ProofNonce<CS> {
+    move |_: &_, _, _| Ok(pool.take())
+}
+
+// Derives the nonce from the private key, the composite elements and a
+// personalization string
+fn derandomized_proof_nonce<CS: CipherSuite>(personalization: &[u8]) -> impl '_ + ProofNonce<CS> {
+    move |k: &<CS::Group as Group>::Scalar, m: CS::Group, z: CS::Group| {
+        let context_string = get_context_string::<CS>(Mode::Verifiable)?;
+        let scalar_len = i2osp_2_len::<<CS::Group as Group>::ScalarLen>();
+        let elem_len = i2osp_2_len::<<CS::Group as Group>::ElemLen>();
+        let personalization_len = i2osp_2(personalization.len())?;
+        let nonce_dst_len = i2osp_2(STR_NONCE.len() + context_string.len())?;
+
+        let mut k = CS::Group::scalar_as_bytes(*k);
+        let m = m.to_arr();
+        let z = z.to_arr();
+
+        let h2_input = [
+            &scalar_len,
+            k.as_slice(),
+            &elem_len,
+            &m,
+            &elem_len,
+            &z,
+            &personalization_len,
+            personalization,
+            &nonce_dst_len,
+            STR_NONCE,
+            &context_string,
+        ];
+        let hash_to_scalar_dst = [STR_HASH_TO_SCALAR, &context_string];
+
+        let r = CS::Group::hash_to_scalar::<CS>(&h2_input, &hash_to_scalar_dst);
+        k.as_mut_slice().zeroize();
+        let r = r?;
+
+        // A zero nonce would reveal the private key
+        if CS::Group::ct_equal_scalar(&r, &CS::Group::scalar_zero()) {
+            return Err(InternalError::ZeroScalarError);
+        }
+
+        Ok(r)
+    }
+}
+
+#[allow(clippy::many_single_char_names)]
+// Returned by a successful proof verification, and required to unblind the
+// evaluation elements, so the result of the verification can't be ignored. Only
+// constructed by `verify_challenge`.
+#[must_use]
+pub(crate) struct VerifiedProof(());
+
+pub(crate) fn verify_proof<CS: CipherSuite>(
+    a: CS::Group,
+    b: CS::Group,
+    cs: impl Iterator<Item = CS::Group>,
+    ds: impl Iterator<Item = CS::Group>,
+    proof: Proof<CS>,
+    mode: Mode,
+) -> Result<VerifiedProof, InternalError> {
+    let (m, z) = compute_composites::<CS>(None, b, cs, ds, mode)?;
+
+    verify_challenge::<CS>(a, b, m, z, proof, mode)
+}
+
+// Second half of `verify_proof`, split off so the large frame of the point
+// arithmetic isn't on the stack while the composites are computed
+fn verify_challenge<CS: CipherSuite>(
+    a: CS::Group,
+    b: CS::Group,
+    m: CS::Group,
+    z: CS::Group,
+    proof: Proof<CS>,
+    mode: Mode,
+) -> Result<VerifiedProof, InternalError> {
+    // The proof and the elements are public, so variable time is fine
+    let scalars = [proof.s_scalar, proof.c_scalar];
+    let t2 = CS::Group::vartime_multiscalar_mul(&scalars, &[a, b]);
+    let t3 = CS::Group::vartime_multiscalar_mul(&scalars, &[m, z]);
+
+    let c = compute_challenge::<CS>(b, m, z, t2, t3, mode)?;
+
+    match CS::Group::ct_equal_scalar(&c, &proof.c_scalar) {
+        true => Ok(VerifiedProof(())),
+        false => Err(InternalError::ProofVerificationError),
+    }
+}
+
+// Computes the challenge scalar shared by proof generation and verification
+#[allow(clippy::many_single_char_names)]
+fn compute_challenge<CS: CipherSuite>(
+    b: CS::Group,
+    m: CS::Group,
+    z: CS::Group,
+    t2: CS::Group,
+    t3: CS::Group,
+    mode: Mode,
+) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    let context_string = get_context_string::<CS>(mode)?;
+    let elem_len = i2osp_2_len::<<CS::Group as Group>::ElemLen>();
+    let challenge_dst_len = i2osp_2(STR_CHALLENGE.len() + context_string.len())?;
+
+    let b = b.to_arr();
+    let m = m.to_arr();
+    let z = z.to_arr();
+    let t2 = t2.to_arr();
+    let t3 = t3.to_arr();
+
+    let h2_input = [
+        &elem_len,
+        b.as_slice(),
+        &elem_len,
+        &m,
+        &elem_len,
+        &z,
+        &elem_len,
+        &t2,
+        &elem_len,
+        &t3,
+        &challenge_dst_len,
+        STR_CHALLENGE,
+        &context_string,
+    ];
+    let hash_to_scalar_dst = [STR_HASH_TO_SCALAR, &context_string];
+    trace!("challenge_input", h2_input.concat());
+
+    let c = CS::Group::hash_to_scalar::<CS>(&h2_input, &hash_to_scalar_dst)?;
+    trace!("challenge", CS::Group::scalar_as_bytes(c));
+    Ok(c)
+}
+
+pub(crate) fn finalize_after_unblind<CS: CipherSuite>(
+    input: &[u8],
+    unblinded_element: &CS::Group,
+    info: &[u8],
+    mode: Mode,
+) -> Result<GenericArray<u8, <CS::Hash as Digest>::OutputSize>, InternalError> {
+    finalize_after_unblind_with_context::<CS>(&[input], unblinded_element, info, None, mode)
+}
+
+// Like `finalize_after_unblind`, but with the input given as parts and an
+// optional application context, which is hashed after the info and changes the
+// domain separation tag to "FinalizeContext-"
+fn finalize_after_unblind_with_context<CS: CipherSuite>(
+    input: &[&[u8]],
+    unblinded_element: &CS::Group,
+    info: &[u8],
+    context: Option<&[u8]>,
+    mode: Mode,
+) -> Result<GenericArray<u8, <CS::Hash as Digest>::OutputSize>, InternalError> {
+    check_input_parts_len::<CS>(input)?;
+    check_metadata_len::<CS>(info)?;
+
+    let context_string = get_context_string::<CS>(mode)?;
+    let elem_len = i2osp_2_len::<<CS::Group as Group>::ElemLen>();
+    let mut unblinded_element = unblinded_element.to_arr();
+    trace!("unblinded_element", &unblinded_element);
+
+    // Updating the hash in place instead of chaining avoids a copy of its
+    // state per part, which adds up on the stack of unoptimized builds
+    let mut hash = CS::Hash::new();
+    finalize_transcript(
+        input,
+        info,
+        context,
+        elem_len,
+        &unblinded_element,
+        &context_string,
+        &mut |part| hash.update(part),
+    )?;
+    trace!("finalize_input", {
+        let mut transcript = Vec::new();
+        let _ = finalize_transcript(
+            input,
+            info,
+            context,
+            elem_len,
+            &unblinded_element,
+            &context_string,
+            &mut |part| transcript.extend_from_slice(part),
+        );
+        transcript
+    });
+    let output = hash.finalize();
+    unblinded_element.as_mut_slice().zeroize();
+    trace!("output", &output);
+
+    Ok(output)
+}
+
+// Expands the output of Finalize with HKDF-Expand, using it as the
+// pseudorandom key. It's already uniformly random, so HKDF-Extract is skipped
+fn expand_output<CS: CipherSuite>(
+    output: &GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+    info: &[u8],
+    okm: &mut [u8],
+) -> Result<(), InternalError> {
+    Hkdf::<CS::Hash>::from_prk(output)
+        .map_err(|_| InternalError::OutputLengthError)?
+        .expand(info, okm)
+        .map_err(|_| InternalError::OutputLengthError)
+}
+
+// Feeds the transcript hashed by Finalize to `update` part by part. Only
+// handles byte strings, so it's compiled once instead of once per ciphersuite
+fn finalize_transcript(
+    input: &[&[u8]],
+    info: &[u8],
+    context: Option<&[u8]>,
+    elem_len: [u8; 2],
+    unblinded_element: &[u8],
+    context_string: &[u8],
+    update: &mut dyn FnMut(&[u8]),
+) -> Result<(), InternalError> {
+    let finalize_dst = match context {
+        Some(_) => STR_FINALIZE_CONTEXT,
+        None => STR_FINALIZE,
+    };
+    let finalize_dst_len = i2osp_2(finalize_dst.len() + context_string.len())?;
+    let input_len = i2osp_2(input_parts_len(input).ok_or(InternalError::SerializationError)?)?;
+    let info_len = i2osp_2(info.len())?;
+    let context_len = context.map(|context| i2osp_2(context.len())).transpose()?;
+
+    update(&input_len);
+    input.iter().for_each(|part| update(part));
+    update(&info_len);
+    update(info);
+    if let (Some(context_len), Some(context)) = (context_len, context) {
+        update(&context_len);
+        update(context);
+    }
+    update(&elem_len);
+    update(unblinded_element);
+    update(&finalize_dst_len);
+    update(finalize_dst);
+    update(context_string);
+
+    Ok(())
+}
+
+fn compute_composites<CS: CipherSuite>(
+    k_option: Option<&<CS::Group as Group>::Scalar>,
+    b: CS::Group,
+    mut c_iter: impl Iterator<Item = CS::Group>,
+    mut d_iter: impl Iterator<Item = CS::Group>,
+    mode: Mode,
+) -> Result<(CS::Group, CS::Group), InternalError> {
+    // Every element is hashed with its index as a 2-byte integer, so reject
+    // batches known to have more than 2^16 elements before doing any work
+    i2osp_2(c_iter.size_hint().0.saturating_sub(1))?;
+
+    let mut composites = Composites::<CS>::new(b, mode)?;
+
+    // Otherwise the pairs are counted while they are absorbed, so the
+    // iterators don't have to know their lengths up front, and hashing fails
+    // past the 2^16th pair
+    let mut paired = 0;
+    loop {
+        match (c_iter.next(), d_iter.next()) {
+            (Some(c), Some(d)) => composites.absorb(c, d, k_option.is_none())?,
+            (None, None) => break,
+            (c, d) => return Err(batch_mismatch(paired, c.is_some(), d.is_some())),
+        }
+        paired += 1;
+    }
+
+    let m = composites.m;
+    let z = match k_option {
+        Some(k) => m * k,
+        None => composites.z,
+    };
+    trace!("composite_m", m.to_arr());
+    trace!("composite_z", z.to_arr());
+
+    Ok((m, z))
+}
+
+// Like `compute_composites`, but weights the pairs of elements on the threads
+// of the rayon pool and sums them up in a parallel reduction
+#[cfg(feature = "parallel")]
+fn par_compute_composites<CS: CipherSuite>(
+    k_option: Option<&<CS::Group as Group>::Scalar>,
+    b: CS::Group,
+    cs: impl IndexedParallelIterator<Item = CS::Group>,
+    ds: impl IndexedParallelIterator<Item = CS::Group>,
+    mode: Mode,
+) -> Result<(CS::Group, CS::Group), InternalError>
+where
+    CS::Group: Send + Sync,
+    <CS::Group as Group>::Scalar: Send + Sync,
+{
+    check_batch_lengths(cs.len(), ds.len())?;
+    i2osp_2(cs.len().saturating_sub(1))?;
+
+    let composites = Composites::<CS>::new(b, mode)?;
+    let identity = CS::Group::identity();
+
+    let (m, z) = cs
+        .zip(ds)
+        .enumerate()
+        .map(|(index, (c, d))| {
+            let di = composites.weight(index, c, d)?;
+            let z = match k_option {
+                Some(_) => identity,
+                None => d * &di,
+            };
+            Ok((c * &di, z))
+        })
+        .try_reduce(
+            || (identity, identity),
+            |(m1, z1), (m2, z2)| Ok((m1 + &m2, z1 + &z2)),
+        )?;
+
+    let z = match k_option {
+        Some(k) => m * k,
+        None => z,
+    };
+    trace!("composite_m", m.to_arr());
+    trace!("composite_z", z.to_arr());
+
+    Ok((m, z))
+}
+
+// Accumulates the composite elements `M` and `Z` of a proof one pair of
+// elements at a time
+struct Composites<CS: CipherSuite> {
+    mode: Mode,
+    seed: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+    index: usize,
+    m: CS::Group,
+    z: CS::Group,
+}
+
+impl<CS: CipherSuite> Composites<CS> {
+    fn new(b: CS::Group, mode: Mode) -> Result<Self, InternalError> {
+        let context_string = get_context_string::<CS>(mode)?;
+
+        let mut hash = CS::Hash::new();
+        seed_transcript(
+            i2osp_2_len::<<CS::Group as Group>::ElemLen>(),
+            &b.to_arr(),
+            &context_string,
+            &mut |part| hash.update(part),
+        )?;
+        let seed = hash.finalize();
+        trace!("composite_seed", &seed);
+
+        Ok(Self {
+            mode,
+            seed,
+            index: 0,
+            m: CS::Group::identity(),
+            z: CS::Group::identity(),
+        })
+    }
+
+    // Only the server knows the private key, and can compute `Z` from `M`
+    // instead
+    fn absorb(&mut self, c: CS::Group, d: CS::Group, compute_z: bool) -> Result<(), InternalError> {
+        let di = self.weight(self.index, c, d)?;
+        self.m = c * &di + &self.m;
+
+        if compute_z {
+            self.z = d * &di + &self.z;
+        }
+
+        self.index += 1;
+
+        Ok(())
+    }
+
+    // Computes the weight `d_i` of the pair of elements at `index`, which only
+    // depends on the seed, so the pairs can be weighted in any order
+    fn weight(
+        &self,
+        index: usize,
+        c: CS::Group,
+        d: CS::Group,
+    ) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+        let context_string = get_context_string::<CS>(self.mode)?;
+        let elem_len = i2osp_2_len::<<CS::Group as Group>::ElemLen>();
+        let composite_dst_len = i2osp_2(STR_COMPOSITE.len() + context_string.len())?;
+        let dst = [STR_HASH_TO_SCALAR, &context_string];
+
+        let ci = c.to_arr();
+        let di = d.to_arr();
+        let h2_input = [
+            &i2osp_2(self.seed.len())?,
+            self.seed.as_slice(),
+            &i2osp_2(index)?,
+            &elem_len,
+            &ci,
+            &elem_len,
+            &di,
+            &composite_dst_len,
+            STR_COMPOSITE,
+            &context_string,
+        ];
+        CS::Group::hash_to_scalar::<CS>(&h2_input, &dst)
+    }
+}
+
+// Feeds the transcript hashed into the seed of the composites to `update`, see
+// `finalize_transcript`
+fn seed_transcript(
+    elem_len: [u8; 2],
+    b: &[u8],
+    context_string: &[u8],
+    update: &mut dyn FnMut(&[u8]),
+) -> Result<(), InternalError> {
+    let seed_dst_len = i2osp_2(STR_SEED.len() + context_string.len())?;
+
+    update(&elem_len);
+    update(b);
+    update(&seed_dst_len);
+    update(STR_SEED);
+    update(context_string);
+
+    Ok(())
+}
+
+// Wraps an intermediate secret group element, so that it's reset to the
+// identity when dropped
+pub(crate) struct ZeroizingElement<G: Group>(pub(crate) G);
+
+impl<G: Group> core::ops::Deref for ZeroizingElement<G> {
+    type Target = G;
+
+    fn deref(&self) -> &G {
+        &self.0
+    }
+}
+
+impl<G: Group> Drop for ZeroizingElement<G> {
+    fn drop(&mut self) {
+        Group::zeroize(&mut self.0);
+    }
+}
+
+/// Generates the contextString parameter as defined in
+/// <https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-07.html>, for
+/// protocols which have to reference it in their own transcripts. The
+/// `suite_id` of a ciphersuite is [Group::SUITE_ID] of its group.
+pub fn create_context_string(mode: Mode, suite_id: u16) -> GenericArray<u8, U11> {
+    GenericArray::from(STR_VOPRF)
+        .concat([mode as u8].into())
+        .concat(suite_id.to_be_bytes().into())
+}
+
+/// The domain separation tag used to hash inputs to group elements, the
+/// `HashToGroup-` prefix followed by [create_context_string]
+pub fn create_hash_to_group_dst(mode: Mode, suite_id: u16) -> Vec<u8> {
+    [STR_HASH_TO_GROUP, &create_context_string(mode, suite_id)].concat()
+}
+
+/// The domain separation tag used to hash to scalars, the `HashToScalar-`
+/// prefix followed by [create_context_string]
+pub fn create_hash_to_scalar_dst(mode: Mode, suite_id: u16) -> Vec<u8> {
+    [STR_HASH_TO_SCALAR, &create_context_string(mode, suite_id)].concat()
+}
+
+/// The domain separation tag used to compute the output, the `Finalize-`
+/// prefix followed by [create_context_string]
+pub fn create_finalize_dst(mode: Mode, suite_id: u16) -> Vec<u8> {
+    [STR_FINALIZE, &create_context_string(mode, suite_id)].concat()
+}
+
+/// Hashes `input` to a group element with the domain separation tag of the
+/// given mode, [create_hash_to_group_dst], as the protocol hashes its inputs
+/// before blinding them. Protocols built on top of this crate can use it to
+/// hash to the same elements without rebuilding the context string.
+pub fn hash_to_group<CS: CipherSuite>(
+    input: &[u8],
+    mode: Mode,
+) -> Result<CS::Group, InternalError> {
+    let context_string = get_context_string::<CS>(mode)?;
+    CS::Group::hash_to_curve::<CS>(&[input], &[STR_HASH_TO_GROUP, &context_string])
+}
+
+/// Hashes `input` to a scalar with the domain separation tag of the given mode,
+/// [create_hash_to_scalar_dst], as the protocol derives private keys from
+/// seeds
+pub fn hash_to_scalar<CS: CipherSuite>(
+    input: &[u8],
+    mode: Mode,
+) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    let context_string = get_context_string::<CS>(mode)?;
+    CS::Group::hash_to_scalar::<CS>(&[input], &[STR_HASH_TO_SCALAR, &context_string])
+}
+
+/// Maps an output of the protocol in the given mode to a scalar, e.g. to use it
+/// as a key share or a commitment opening. The `label` separates different
+/// uses of the same output, and the domain separation tag is the
+/// `OutputToScalar-` prefix followed by [create_context_string], so the
+/// result is independent of any other value the protocol hashes to a scalar.
+pub fn output_to_scalar<CS: CipherSuite>(
+    output: &GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+    mode: Mode,
+    label: &[u8],
+) -> Result<<CS::Group as Group>::Scalar, InternalError> {
+    let context_string = get_context_string::<CS>(mode)?;
+
+    CS::Group::hash_to_scalar::<CS>(
+        &[&i2osp_2(label.len())?, label, output],
+        &[STR_OUTPUT_TO_SCALAR, &context_string],
+    )
+}
+
+/// Maps an output of the protocol in the given mode to a group element, like
+/// [output_to_scalar], with the `OutputToGroup-` prefix followed by
+/// [create_context_string] as the domain separation tag
+pub fn output_to_element<CS: CipherSuite>(
+    output: &GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+    mode: Mode,
+    label: &[u8],
+) -> Result<CS::Group, InternalError> {
+    let context_string = get_context_string::<CS>(mode)?;
+
+    CS::Group::hash_to_curve::<CS>(
+        &[&i2osp_2(label.len())?, label, output],
+        &[STR_OUTPUT_TO_GROUP, &context_string],
+    )
+}
+
+fn get_context_string<CS: CipherSuite>(mode: Mode) -> Result<GenericArray<u8, U11>, InternalError> {
+    let suite_id =
+        u16::try_from(CS::Group::SUITE_ID).map_err(|_| InternalError::SerializationError)?;
+    Ok(create_context_string(mode, suite_id))
+}
+
+///////////
+// Tests //
+// ===== //
+///////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::Group;
+    use alloc::vec;
+    use generic_array::GenericArray;
+    use rand::rngs::OsRng;
+
+    fn prf<CS: CipherSuite>(
+        input: &[u8],
+        key: <CS::Group as Group>::Scalar,
+        info: &[u8],
+        mode: Mode,
+    ) -> GenericArray<u8, <CS::Hash as Digest>::OutputSize> {
+        let dst = [STR_HASH_TO_GROUP, &get_context_string::<CS>(mode).unwrap()];
+        let point = CS::Group::hash_to_curve::<CS>(&[input], &dst).unwrap();
+
+        let context = [
+            STR_CONTEXT,
+            &get_context_string::<CS>(mode).unwrap(),
+            &i2osp_2(info.len()).unwrap(),
+            info,
+        ];
+        let dst = [STR_HASH_TO_SCALAR, &get_context_string::<CS>(mode).unwrap()];
+        let m = <CS::Group as Group>::hash_to_scalar::<CS>(&context, &dst).unwrap();
+
+        let res = point * &<CS::Group as Group>::scalar_invert(&(key + &m));
+
+        finalize_after_unblind::<CS>(input, &res, info, mode).unwrap()
+    }
+
+    fn base_retrieval<CS: CipherSuite>() {
+        let input = b"input";
+        let info = b"info";
+        let mut rng = OsRng;
+        let client_blind_result = NonVerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
+        let server = NonVerifiableServer::<CS>::new(&mut rng).unwrap();
+        let server_result = server
+            .evaluate(client_blind_result.message, &Metadata(info.to_vec()))
+            .unwrap();
+        let client_finalize_result = client_blind_result
+            .state
+            .finalize_once(&input[..], server_result.message, &Metadata(info.to_vec()))
+            .unwrap();
+        let res2 = prf::<CS>(&input[..], server.get_private_key(), info, Mode::Base);
+        assert_eq!(*client_finalize_result.output, res2);
+    }
+
+    fn context_binding<CS: CipherSuite>() {
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+
+        let server = NonVerifiableServer::<CS>::new(&mut rng).unwrap();
+        let finalize = |context: Option<&[u8]>| {
+            let client_blind_result =
+                NonVerifiableClient::<CS>::blind(b"input", &mut OsRng).unwrap();
+            let server_result = server
+                .evaluate(client_blind_result.message, &metadata)
+                .unwrap();
+            match context {
+                Some(context) => client_blind_result.state.finalize_with_context_once(
+                    b"input",
+                    server_result.message,
+                    &metadata,
+                    context,
+                ),
+                None => client_blind_result.state.finalize_once(
+                    b"input",
+                    server_result.message,
+                    &metadata,
+                ),
+            }
+            .unwrap()
+            .output
+        };
+
+        // The output only depends on the context, not on the blind
+        let output = finalize(Some(b"session 1"));
+        assert_eq!(output, finalize(Some(b"session 1")));
+        assert_ne!(output, finalize(Some(b"session 2")));
+        assert_ne!(finalize(Some(b"")), finalize(None));
+        assert_eq!(
+            *finalize(None),
+            prf::<CS>(b"input", server.get_private_key(), b"info", Mode::Base)
+        );
+
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let finalize = |context: &[u8]| {
+            let client_blind_result = VerifiableClient::<CS>::blind(b"input", &mut OsRng).unwrap();
+            let server_result = server
+                .evaluate(&mut OsRng, client_blind_result.message, &metadata)
+                .unwrap();
+            client_blind_result
+                .state
+                .finalize_with_context_once(
+                    b"input",
+                    server_result.message,
+                    server_result.proof,
+                    server.get_public_key(),
+                    &metadata,
+                    context,
+                )
+                .unwrap()
+                .output
+        };
+
+        let output = finalize(b"session 1");
+        assert_eq!(output, finalize(b"session 1"));
+        assert_ne!(output, finalize(b"session 2"));
+        assert_ne!(
+            *finalize(b""),
+            prf::<CS>(
+                b"input",
+                server.get_private_key(),
+                b"info",
+                Mode::Verifiable
+            )
+        );
+    }
+
+    fn verifiable_retrieval<CS: CipherSuite>() {
+        let input = b"input";
+        let info = b"info";
+        let mut rng = OsRng;
+        let client_blind_result = VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let server_result = server
+            .evaluate(
+                &mut rng,
+                client_blind_result.message,
+                &Metadata(info.to_vec()),
+            )
+            .unwrap();
+        let client_finalize_result = client_blind_result
+            .state
+            .finalize_once(
+                &input[..],
+                server_result.message,
+                server_result.proof,
+                server.get_public_key(),
+                &Metadata(info.to_vec()),
+            )
+            .unwrap();
+        let res2 = prf::<CS>(&input[..], server.get_private_key(), info, Mode::Verifiable);
+        assert_eq!(*client_finalize_result.output, res2);
+    }
+
+    fn poprf_retrieval<CS: CipherSuite>() {
+        let inputs = [&b"input"[..], b"other input"];
+        let info = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let server = PoprfServer::<CS>::deserialize(&server.serialize()).unwrap();
+
+        let mut clients = vec![];
+        let mut messages = vec![];
+        for input in &inputs {
+            let client_blind_result = PoprfClient::<CS>::blind(input, &mut rng).unwrap();
+            let state = PoprfClient::<CS>::deserialize(&client_blind_result.state.serialize());
+            assert!(state.unwrap() == client_blind_result.state);
+            clients.push(client_blind_result.state);
+            messages.push(client_blind_result.message);
+        }
+
+        let server_result = server.batch_evaluate(&mut rng, &messages, &info).unwrap();
+        let outputs: Vec<_> = PoprfClient::batch_finalize(
+            &inputs,
+            &clients,
+            &server_result.messages,
+            server_result.proof,
+            server.get_public_key(),
+            &info,
+        )
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+        for (input, output) in inputs.iter().zip(outputs) {
+            let res2 = prf::<CS>(input, server.get_private_key(), &info.0, Mode::Poprf);
+            assert_eq!(*output, res2);
+            assert_eq!(*output, server.full_evaluate(input, &info).unwrap());
+            // The mode is part of the domain separation
+            assert_ne!(
+                *output,
+                prf::<CS>(input, server.get_private_key(), &info.0, Mode::Verifiable)
+            );
+        }
+
+        let client_blind_result = PoprfClient::<CS>::blind(inputs[0], &mut rng).unwrap();
+        let server_result = server
+            .evaluate(&mut rng, client_blind_result.message, &info)
+            .unwrap();
+        let client_finalize_result = client_blind_result
+            .state
+            .finalize_once(
+                inputs[0],
+                server_result.message,
+                server_result.proof,
+                server.get_public_key(),
+                &info,
+            )
+            .unwrap();
+        assert_eq!(
+            *client_finalize_result.output,
+            prf::<CS>(inputs[0], server.get_private_key(), &info.0, Mode::Poprf)
+        );
+    }
+
+    fn poprf_wrong_metadata<CS: CipherSuite>() {
+        let input = b"input";
+        let mut rng = OsRng;
+        let client_blind_result = PoprfClient::<CS>::blind(&input[..], &mut rng).unwrap();
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let server_result = server
+            .evaluate(
+                &mut rng,
+                client_blind_result.message,
+                &Metadata(b"info".to_vec()),
+            )
+            .unwrap();
+
+        // The proof is against the public key tweaked by the metadata the
+        // server used
+        let client_finalize_result = client_blind_result.state.finalize_once(
+            &input[..],
+            server_result.message,
+            server_result.proof,
+            server.get_public_key(),
+            &Metadata(b"other info".to_vec()),
+        );
+        assert!(client_finalize_result.is_err());
+    }
+
+    fn verifiable_bad_public_key<CS: CipherSuite>() {
+        let input = b"input";
+        let info = b"info";
+        let mut rng = OsRng;
+        let client_blind_result = VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let server_result = server
+            .evaluate(
+                &mut rng,
+                client_blind_result.message,
+                &Metadata(info.to_vec()),
+            )
+            .unwrap();
+        let wrong_pk = {
+            // Choose a group element that is unlikely to be the right public key
+            CS::Group::hash_to_curve::<CS>(&[b"msg"], &[b"dst"]).unwrap()
+        };
+        let client_finalize_result = client_blind_result.state.finalize_once(
+            &input[..],
+            server_result.message,
+            server_result.proof,
+            wrong_pk,
+            &Metadata(info.to_vec()),
+        );
+        assert!(client_finalize_result.is_err());
+    }
+
+    fn verifiable_identity_public_key<CS: CipherSuite>() {
+        let input = b"input";
+        let mut rng = OsRng;
+        let client_blind_result = VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let server_result = server
+            .evaluate(&mut rng, client_blind_result.message, &Metadata::none())
+            .unwrap();
+        let client_finalize_result = client_blind_result.state.finalize_once(
+            &input[..],
+            server_result.message,
+            server_result.proof,
+            CS::Group::identity(),
+            &Metadata::none(),
+        );
+        assert!(matches!(
+            client_finalize_result,
+            Err(InternalError::InvalidPublicKey)
+        ));
+    }
+
+    fn verifiable_invalid_proof<CS: CipherSuite>() {
+        let input = b"input";
+        let mut rng = OsRng;
+        let client_blind_result = VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let server_result = server
+            .evaluate(&mut rng, client_blind_result.message, &Metadata::none())
+            .unwrap();
+        let proof = server_result.proof.serialize();
+        let scalar_len = <CS::Group as Group>::ScalarLen::USIZE;
+        let zero = vec![0; scalar_len];
+        // Larger than the order of every supported group
+        let non_canonical = vec![0xff; scalar_len];
+
+        for invalid_proof in &[
+            [&non_canonical[..], &proof[scalar_len..]].concat(),
+            [&proof[..scalar_len], &non_canonical[..]].concat(),
+        ] {
+            assert!(matches!(
+                Proof::<CS>::deserialize(invalid_proof),
+                Err(InternalError::InvalidProofError)
+            ));
+        }
+
+        // Zero is in the range of both scalars, so proofs containing it
+        // round-trip, but are rejected by verification
+        for zero_proof in &[
+            [&zero[..], &proof[scalar_len..]].concat(),
+            [&proof[..scalar_len], &zero[..]].concat(),
+            [&zero[..], &zero[..]].concat(),
+        ] {
+            let zero_proof = Proof::<CS>::deserialize(zero_proof).unwrap();
+            assert!(Proof::<CS>::deserialize(&zero_proof.serialize()).unwrap() == zero_proof);
+            let client_finalize_result = client_blind_result.state.clone().finalize_once(
+                &input[..],
+                server_result.message.clone(),
+                zero_proof,
+                server.get_public_key(),
+                &Metadata::none(),
+            );
+            assert!(matches!(
+                client_finalize_result,
+                Err(InternalError::ProofVerificationError)
+            ));
+        }
+    }
+
+    fn versioned_state<CS: CipherSuite>() {
+        use crate::serialization::StateVersion;
+
+        let input = b"input";
+        let metadata = Metadata::none();
+        let mut rng = OsRng;
+
+        // States of voprf 0.1.0 end with the input, and are migrated into
+        // states bound to it
+        let server = NonVerifiableServer::<CS>::deserialize_versioned(
+            &NonVerifiableServer::<CS>::new(&mut rng)
+                .unwrap()
+                .serialize(),
+            StateVersion::V0_1,
+        )
+        .unwrap();
+        let client_blind_result = NonVerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
+        let legacy_state = [
+            CS::Group::scalar_as_bytes(client_blind_result.state.blind).as_slice(),
+            &input[..],
+        ]
+        .concat();
+        let state =
+            NonVerifiableClient::<CS>::deserialize_versioned(&legacy_state, StateVersion::V0_1)
+                .unwrap();
+        let server_result = server
+            .evaluate(client_blind_result.message, &metadata)
+            .unwrap();
+        assert!(matches!(
+            state
+                .clone()
+                .finalize_once(b"other input", server_result.message.clone(), &metadata),
+            Err(InternalError::InputMismatchError)
+        ));
+        let client_finalize_result = state
+            .finalize_once(&input[..], server_result.message, &metadata)
+            .unwrap();
+        let res2 = prf::<CS>(&input[..], server.get_private_key(), &[], Mode::Base);
+        assert_eq!(*client_finalize_result.output, res2);
+
+        let server = VerifiableServer::<CS>::deserialize_versioned(
+            &VerifiableServer::<CS>::new(&mut rng).unwrap().serialize(),
+            StateVersion::V0_1,
+        )
+        .unwrap();
+        let client_blind_result = VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
+        let legacy_state = [
+            CS::Group::scalar_as_bytes(client_blind_result.state.blind).as_slice(),
+            &client_blind_result.state.blinded_element.to_arr(),
+            &input[..],
+        ]
+        .concat();
+        let state =
+            VerifiableClient::<CS>::deserialize_versioned(&legacy_state, StateVersion::V0_1)
+                .unwrap();
+        assert!(
+            VerifiableClient::<CS>::deserialize_versioned(
+                &state.serialize(),
+                StateVersion::Current
+            )
+            .unwrap()
+                == state
+        );
+        let server_result = server
+            .evaluate(&mut rng, client_blind_result.message, &metadata)
+            .unwrap();
+        let client_finalize_result = state
+            .finalize_once(
+                &input[..],
+                server_result.message,
+                server_result.proof,
+                server.get_public_key(),
+                &metadata,
+            )
+            .unwrap();
+        let res2 = prf::<CS>(&input[..], server.get_private_key(), &[], Mode::Verifiable);
+        assert_eq!(*client_finalize_result.output, res2);
+    }
+
+    fn input_binding<CS: CipherSuite>() {
+        let input = b"input";
+        let metadata = Metadata::none();
+        let mut rng = OsRng;
+
+        let server = NonVerifiableServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result =
+            NonVerifiableClient::<CS>::blind_with_input_binding(&input[..], &mut rng).unwrap();
+        // The binding survives persisting the state
+        let state =
+            NonVerifiableClient::<CS>::deserialize(&client_blind_result.state.serialize()).unwrap();
+        let server_result = server
+            .evaluate(client_blind_result.message, &metadata)
+            .unwrap();
+        assert!(matches!(
+            state
+                .clone()
+                .finalize_once(b"other input", server_result.message.clone(), &metadata),
+            Err(InternalError::InputMismatchError)
+        ));
+        let client_finalize_result = state
+            .finalize_once(&input[..], server_result.message, &metadata)
+            .unwrap();
+        let res2 = prf::<CS>(&input[..], server.get_private_key(), &[], Mode::Base);
+        assert_eq!(*client_finalize_result.output, res2);
+
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let bound_client_blind_result =
+            VerifiableClient::<CS>::blind_with_input_binding(&input[..], &mut rng).unwrap();
+        let client_blind_result = VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
+        let clients = [
+            VerifiableClient::<CS>::deserialize(&bound_client_blind_result.state.serialize())
+                .unwrap(),
+            client_blind_result.state,
+        ];
+        let server_result = server
+            .batch_evaluate(
+                &mut rng,
+                &[
+                    bound_client_blind_result.message,
+                    client_blind_result.message,
+                ],
+                &metadata,
+            )
+            .unwrap();
+        // Only the first client is bound to its input
+        assert!(matches!(
+            VerifiableClient::batch_finalize(
+                &[&b"other input"[..], &input[..]],
+                &clients,
+                &server_result.messages,
+                server_result.proof.clone(),
+                server.get_public_key(),
+                &metadata,
+            ),
+            Err(InternalError::InputMismatchError)
+        ));
+        let outputs = VerifiableClient::batch_finalize(
+            &[&input[..], &b"other input"[..]],
+            &clients,
+            &server_result.messages,
+            server_result.proof,
+            server.get_public_key(),
+            &metadata,
+        )
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        let res2 = prf::<CS>(&input[..], server.get_private_key(), &[], Mode::Verifiable);
+        assert_eq!(*outputs[0], res2);
+
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result =
+            PoprfClient::<CS>::blind_with_input_binding(&input[..], &mut rng).unwrap();
+        let state = PoprfClient::<CS>::deserialize(&client_blind_result.state.serialize()).unwrap();
+        let server_result = server
+            .evaluate(&mut rng, client_blind_result.message, &metadata)
+            .unwrap();
+        assert!(matches!(
+            state.clone().finalize_once(
+                b"other input",
+                server_result.message.clone(),
+                server_result.proof.clone(),
+                server.get_public_key(),
+                &metadata,
+            ),
+            Err(InternalError::InputMismatchError)
+        ));
+        let client_finalize_result = state
+            .finalize_once(
+                &input[..],
+                server_result.message,
+                server_result.proof,
+                server.get_public_key(),
+                &metadata,
+            )
+            .unwrap();
+        assert_eq!(
+            *client_finalize_result.output,
+            server.full_evaluate(&input[..], &metadata).unwrap()
+        );
+    }
+
+    fn prepared_metadata<CS: CipherSuite>() {
+        let input = b"input";
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+
+        let server = NonVerifiableServer::<CS>::new(&mut rng).unwrap();
+        let prepared = NonVerifiableServer::<CS>::prepare_metadata(&metadata).unwrap();
+        let client_blind_result = NonVerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
+        let server_result = server
+            .evaluate(client_blind_result.message.clone(), &metadata)
+            .unwrap();
+        let prepared_server_result = server
+            .evaluate_with_prepared_metadata(client_blind_result.message.clone(), &prepared)
+            .unwrap();
+        assert_eq!(
+            server_result.message.serialize(),
+            prepared_server_result.message.serialize()
+        );
+
+        let verifiable_server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let verifiable_prepared = VerifiableServer::<CS>::prepare_metadata(&metadata).unwrap();
+        let verifiable_client_blind_result =
+            VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
+        let verifiable_server_result = verifiable_server
+            .evaluate_with_prepared_metadata(
+                &mut rng,
+                verifiable_client_blind_result.message,
+                &verifiable_prepared,
+            )
+            .unwrap();
+        let _client_finalize_result = verifiable_client_blind_result
+            .state
+            .finalize_once(
+                &input[..],
+                verifiable_server_result.message,
+                verifiable_server_result.proof,
+                verifiable_server.get_public_key(),
+                &metadata,
+            )
+            .unwrap();
+
+        // Metadata prepared for one mode can't be used in the other
+        assert!(matches!(
+            server.evaluate_with_prepared_metadata(
+                client_blind_result.message.clone(),
+                &verifiable_prepared
+            ),
+            Err(InternalError::IncompatibleModeError)
+        ));
+        assert!(matches!(
+            verifiable_server.evaluate_with_prepared_metadata(
+                &mut rng,
+                client_blind_result.message,
+                &prepared
+            ),
+            Err(InternalError::IncompatibleModeError)
+        ));
+    }
+
+    fn verifiable_batch_resume<CS: CipherSuite>() {
+        let info = b"info";
+        let mut rng = OsRng;
+        let mut inputs = vec![];
+        let mut client_states = vec![];
+        let mut client_messages = vec![];
+        for i in 0..10u8 {
+            let input = vec![i; 32];
+            let client_blind_result = VerifiableClient::<CS>::blind(&input, &mut rng).unwrap();
+            inputs.push(input);
+            client_states.push(client_blind_result.state);
+            client_messages.push(client_blind_result.message);
+        }
+
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let metadata = Metadata(info.to_vec());
+
+        // Prepare the first half, and checkpoint it
+        let prepare_result = server
+            .batch_evaluate_prepare(client_messages[..5].iter(), &metadata)
+            .unwrap();
+        let checkpoint: Vec<_> = prepare_result
+            .prepared_evaluation_elements
+            .map(|element| element.serialize())
+            .collect();
+        let t = prepare_result.t.serialize();
+
+        // Resume from the checkpoint
+        let mut prepared_evaluation_elements: Vec<_> = checkpoint
+            .iter()
+            .map(|bytes| PreparedEvaluationElement::<CS>::deserialize(bytes).unwrap())
+            .collect();
+        let prepare_result = server
+            .batch_evaluate_prepare(client_messages[5..].iter(), &metadata)
+            .unwrap();
+        prepared_evaluation_elements.extend(prepare_result.prepared_evaluation_elements);
+        assert_eq!(
+            PreparedTscalar::<CS>::deserialize(&t).unwrap().serialize(),
+            prepare_result.t.serialize()
+        );
+
+        let finish_result = VerifiableServer::batch_evaluate_finish(
+            &mut rng,
+            &client_messages,
+            &prepared_evaluation_elements,
+            &PreparedTscalar::<CS>::deserialize(&t).unwrap(),
+        )
+        .unwrap();
+        let messages: Vec<_> = finish_result.messages.collect();
+        let client_finalize_result = VerifiableClient::batch_finalize(
+            &inputs,
+            &client_states,
+            &messages,
+            finish_result.proof,
+            server.get_public_key(),
+            &metadata,
+        )
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>();
+        assert!(client_finalize_result.is_ok());
+    }
+
+    fn verifiable_batch_retrieval<CS: CipherSuite>() {
+        let info = b"info";
+        let mut rng = OsRng;
+        let mut inputs = vec![];
+        let mut client_states = vec![];
+        let mut client_messages = vec![];
+        let num_iterations = 10;
+        for _ in 0..num_iterations {
+            let mut input = vec![0u8; 32];
+            rng.fill_bytes(&mut input);
+            let client_blind_result = VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
+            inputs.push(input);
+            client_states.push(client_blind_result.state);
+            client_messages.push(client_blind_result.message);
+        }
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let server_result = server
+            .batch_evaluate(&mut rng, &client_messages, &Metadata(info.to_vec()))
+            .unwrap();
+        let metadata = Metadata(info.to_vec());
+        let client_finalize_result = VerifiableClient::batch_finalize(
+            &inputs,
+            &client_states,
+            &server_result.messages,
+            server_result.proof,
+            server.get_public_key(),
+            &metadata,
+        )
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        let mut res2 = vec![];
+        for input in inputs.iter().take(num_iterations) {
+            let output = prf::<CS>(&input[..], server.get_private_key(), info, Mode::Verifiable);
+            res2.push(Zeroizing::new(output));
+        }
+        assert_eq!(client_finalize_result, res2);
+    }
+
+    fn verifiable_batch_superset<CS: CipherSuite>() {
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+        let inputs = [b"input 1", b"input 2", b"input 3", b"input 4"];
+        let client_blind_results = inputs
+            .iter()
+            .map(|input| VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap())
+            .collect::<Vec<_>>();
+        let batch_blinded_elements = client_blind_results
+            .iter()
+            .map(|client_blind_result| client_blind_result.message.clone())
+            .collect::<Vec<_>>();
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let server_result = server
+            .batch_evaluate(&mut rng, &batch_blinded_elements, &metadata)
+            .unwrap();
+
+        // The client only owns the fourth and the second element of the batch
+        let own_inputs = [&inputs[3][..], &inputs[1][..]];
+        let clients = [
+            client_blind_results[3].state.clone(),
+            client_blind_results[1].state.clone(),
+        ];
+        let outputs = VerifiableClient::batch_finalize_superset(
+            &own_inputs,
+            &clients,
+            &batch_blinded_elements,
+            &server_result.messages,
+            server_result.proof.clone(),
+            server.get_public_key(),
+            &metadata,
+        )
+        .unwrap();
+        let res2 = own_inputs
+            .iter()
+            .map(|input| prf::<CS>(input, server.get_private_key(), b"info", Mode::Verifiable))
+            .map(Zeroizing::new)
+            .collect::<Vec<_>>();
+        assert_eq!(outputs, res2);
+
+        // The proof doesn't verify over a part of the batch
+        assert!(matches!(
+            VerifiableClient::batch_finalize_superset(
+                &own_inputs,
+                &clients,
+                &batch_blinded_elements[1..],
+                &server_result.messages[1..],
+                server_result.proof.clone(),
+                server.get_public_key(),
+                &metadata,
+            ),
+            Err(InternalError::ProofVerificationError)
+        ));
+        assert!(matches!(
+            VerifiableClient::batch_finalize_superset(
+                &own_inputs,
+                &clients,
+                &batch_blinded_elements[..3],
+                &server_result.messages[..3],
+                server_result.proof,
+                server.get_public_key(),
+                &metadata,
+            ),
+            Err(InternalError::MissingFromBatchError)
+        ));
+    }
+
+    fn split_key_evaluation<CS: CipherSuite>() {
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+
+        // Base mode
+        let client_blind_result = NonVerifiableClient::<CS>::blind(b"input", &mut rng).unwrap();
+        let server = NonVerifiableServer::<CS>::new(&mut rng).unwrap();
+        let (first, second) = server.split_key(&mut rng, &metadata).unwrap();
+        let first_result = first
+            .evaluate_first(&mut rng, &[client_blind_result.message])
+            .unwrap();
+        assert!(first_result.proof.is_none());
+        let mut second_result = second.evaluate_second(&mut rng, first_result).unwrap();
+        assert!(second_result.proof.is_none());
+        let client_finalize_result = client_blind_result
+            .state
+            .finalize_once(b"input", second_result.messages.remove(0), &metadata)
+            .unwrap();
+        let res2 = prf::<CS>(b"input", server.get_private_key(), b"info", Mode::Base);
+        assert_eq!(*client_finalize_result.output, res2);
+
+        // Verifiable mode
+        let inputs = [b"input 1", b"input 2"];
+        let client_blind_results = inputs
+            .iter()
+            .map(|input| VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap())
+            .collect::<Vec<_>>();
+        let blinded_elements = client_blind_results
+            .iter()
+            .map(|client_blind_result| client_blind_result.message.clone())
+            .collect::<Vec<_>>();
+        let clients = client_blind_results
+            .iter()
+            .map(|client_blind_result| client_blind_result.state.clone())
+            .collect::<Vec<_>>();
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let (first, second) = server.split_key(&mut rng, &metadata).unwrap();
+        assert!(first.is_first() && !second.is_first());
+        assert!(SplitKeyServer::<CS>::deserialize(&second.serialize()).unwrap() == second);
+
+        // The shares have to be used in order
+        assert!(matches!(
+            second.evaluate_first(&mut rng, &blinded_elements),
+            Err(InternalError::SplitKeyShareError)
+        ));
+
+        let first_result = first.evaluate_first(&mut rng, &blinded_elements).unwrap();
+        let second_result = second.evaluate_second(&mut rng, first_result).unwrap();
+        let proof = second_result.proof.unwrap();
+        assert!(SplitKeyProof::<CS>::deserialize(&proof.serialize()).unwrap() == proof);
+        let outputs = VerifiableClient::batch_finalize_split(
+            &inputs,
+            &clients,
+            &second_result.messages,
+            proof.clone(),
+            first.get_public_key(),
+            server.get_public_key(),
+            &metadata,
+        )
+        .unwrap();
+        let res2 = inputs
+            .iter()
+            .map(|input| {
+                prf::<CS>(
+                    &input[..],
+                    server.get_private_key(),
+                    b"info",
+                    Mode::Verifiable,
+                )
+            })
+            .map(Zeroizing::new)
+            .collect::<Vec<_>>();
+        assert_eq!(outputs, res2);
+
+        // The public key of the first share has to match its proof
+        let (other_first, _) = server.split_key(&mut rng, &metadata).unwrap();
+        assert!(matches!(
+            VerifiableClient::batch_finalize_split(
+                &inputs,
+                &clients,
+                &second_result.messages,
+                proof,
+                other_first.get_public_key(),
+                server.get_public_key(),
+                &metadata,
+            ),
+            Err(InternalError::ProofVerificationError)
+        ));
+    }
+
+    fn verifiable_batch_owned<CS: CipherSuite>() {
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+        let inputs = [b"input 1", b"input 2", b"input 3"];
+        let client_blind_results = inputs
+            .iter()
+            .map(|input| VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap())
+            .collect::<Vec<_>>();
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let client_messages = client_blind_results
+            .iter()
+            .map(|client_blind_result| client_blind_result.message.clone())
+            .collect::<Vec<_>>();
+        let server_result = server
+            .batch_evaluate(&mut rng, &client_messages, &metadata)
+            .unwrap();
+
+        // Adapters produced inline, consuming the client states
+        let client_finalize_result = VerifiableClient::batch_finalize_owned(
+            inputs.iter().map(|input| &input[..]),
+            client_blind_results
+                .into_iter()
+                .map(|client_blind_result| client_blind_result.state),
+            server_result.messages,
+            server_result.proof,
+            server.get_public_key(),
+            &metadata,
+        )
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        let res2 = inputs
+            .iter()
+            .map(|input| {
+                prf::<CS>(
+                    &input[..],
+                    server.get_private_key(),
+                    &metadata.0,
+                    Mode::Verifiable,
+                )
+            })
+            .map(Zeroizing::new)
+            .collect::<Vec<_>>();
+        assert_eq!(client_finalize_result, res2);
+    }
+
+    fn verifiable_streaming<CS: CipherSuite>() {
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+        let inputs = [b"input 1", b"input 2", b"input 3"];
+        let client_blind_results = inputs
+            .iter()
+            .map(|input| VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap())
+            .collect::<Vec<_>>();
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let client_messages = client_blind_results
+            .iter()
+            .map(|client_blind_result| client_blind_result.message.clone())
+            .collect::<Vec<_>>();
+        let server_result = server
+            .batch_evaluate(&mut rng, &client_messages, &metadata)
+            .unwrap();
+
+        let mut verifier = StreamingVerifier::new(server.get_public_key(), &metadata).unwrap();
+        for ((input, client_blind_result), message) in inputs
+            .iter()
+            .zip(&client_blind_results)
+            .zip(&server_result.messages)
+        {
+            verifier
+                .absorb(&input[..], &client_blind_result.state, message)
+                .unwrap();
+        }
+        let client_finalize_result = verifier.finish(server_result.proof.clone()).unwrap();
+        let res2 = inputs
+            .iter()
+            .map(|input| {
+                prf::<CS>(
+                    &input[..],
+                    server.get_private_key(),
+                    &metadata.0,
+                    Mode::Verifiable,
+                )
+            })
+            .map(Zeroizing::new)
+            .collect::<Vec<_>>();
+        assert_eq!(client_finalize_result, res2);
+
+        // Absorbing the elements out of order fails the proof
+        let mut verifier = StreamingVerifier::new(server.get_public_key(), &metadata).unwrap();
+        for index in [1, 0, 2].iter().copied() {
+            verifier
+                .absorb(
+                    &inputs[index][..],
+                    &client_blind_results[index].state,
+                    &server_result.messages[index],
+                )
+                .unwrap();
+        }
+        assert!(matches!(
+            verifier.finish(server_result.proof),
+            Err(InternalError::ProofVerificationError)
+        ));
+    }
+
+    fn verifiable_proof_builder<CS: CipherSuite>() {
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+        let inputs = [b"input 1", b"input 2", b"input 3"];
+        let client_blind_results = inputs
+            .iter()
+            .map(|input| VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap())
+            .collect::<Vec<_>>();
+        let clients = client_blind_results
+            .iter()
+            .map(|client_blind_result| client_blind_result.state.clone())
+            .collect::<Vec<_>>();
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+
+        let mut builder = ProofBuilder::new(&server, &metadata).unwrap();
+        let messages = client_blind_results
+            .iter()
+            .map(|client_blind_result| builder.evaluate(&client_blind_result.message).unwrap())
+            .collect::<Vec<_>>();
+        let proof = builder.finish(&mut rng).unwrap();
+        let client_finalize_result = VerifiableClient::batch_finalize(
+            &inputs,
+            &clients,
+            &messages,
+            proof,
+            server.get_public_key(),
+            &metadata,
+        )
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        let res2 = inputs
+            .iter()
+            .map(|input| {
+                prf::<CS>(
+                    &input[..],
+                    server.get_private_key(),
+                    &metadata.0,
+                    Mode::Verifiable,
+                )
+            })
+            .map(Zeroizing::new)
+            .collect::<Vec<_>>();
+        assert_eq!(client_finalize_result, res2);
+
+        // Pushing pairs evaluated in one go proves the same batch
+        let mut builder = ProofBuilder::new(&server, &metadata).unwrap();
+        for (client_blind_result, message) in client_blind_results.iter().zip(&messages) {
+            builder.push(&client_blind_result.message, message).unwrap();
+        }
+        let proof = builder.finish(&mut rng).unwrap();
+        assert!(VerifiableClient::batch_finalize(
+            &inputs,
+            &clients,
+            &messages,
+            proof.clone(),
+            server.get_public_key(),
+            &metadata,
+        )
+        .is_ok());
+
+        // The proof doesn't cover a different batch
+        assert!(matches!(
+            VerifiableClient::batch_finalize(
+                &inputs[..2],
+                &clients[..2],
+                &messages[..2],
+                proof,
+                server.get_public_key(),
+                &metadata,
+            ),
+            Err(InternalError::ProofVerificationError)
+        ));
+    }
+
+    fn evaluate_with_keys<CS: CipherSuite>() {
+        let input = b"input";
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+
+        let servers = [
+            NonVerifiableServer::<CS>::new(&mut rng).unwrap(),
+            NonVerifiableServer::<CS>::new(&mut rng).unwrap(),
+        ];
+        let client_blind_result = NonVerifiableClient::<CS>::blind(input, &mut rng).unwrap();
+        let results = NonVerifiableServer::evaluate_with_keys(
+            &servers,
+            &client_blind_result.message,
+            &metadata,
+        )
+        .unwrap();
+        assert_eq!(results.len(), servers.len());
+        for (server, result) in servers.iter().zip(results) {
+            let output = client_blind_result
+                .state
+                .clone()
+                .finalize_once(input, result.message, &metadata)
+                .unwrap()
+                .output;
+            assert_eq!(*output, server.full_evaluate(input, &metadata).unwrap());
+        }
+
+        let servers = [
+            VerifiableServer::<CS>::new(&mut rng).unwrap(),
+            VerifiableServer::<CS>::new(&mut rng).unwrap(),
+        ];
+        let client_blind_result = VerifiableClient::<CS>::blind(input, &mut rng).unwrap();
+        let results = VerifiableServer::evaluate_with_keys(
+            &mut rng,
+            &servers,
+            &client_blind_result.message,
+            &metadata,
+        )
+        .unwrap();
+        assert_eq!(results.len(), servers.len());
+        for (server, result) in servers.iter().zip(results) {
+            let output = client_blind_result
+                .state
+                .clone()
+                .finalize_once(
+                    input,
+                    result.message,
+                    result.proof,
+                    server.get_public_key(),
+                    &metadata,
+                )
+                .unwrap()
+                .output;
+            assert_eq!(*output, server.full_evaluate(input, &metadata).unwrap());
+        }
+    }
+
+    fn proof_verification<CS: CipherSuite>() {
+        let inputs = [&b"input"[..], b"other input"];
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+        let messages: Vec<_> = inputs
+            .iter()
+            .map(|input| {
+                VerifiableClient::<CS>::blind(input, &mut rng)
+                    .unwrap()
+                    .message
+            })
+            .collect();
+
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let result = server
+            .batch_evaluate(&mut rng, &messages, &metadata)
+            .unwrap();
+        let pk = server.get_public_key();
+        result
+            .proof
+            .verify(pk, &messages, &result.messages, &metadata)
+            .unwrap();
+        // The proof is bound to the public key, the metadata, the mode and the
+        // order of the elements
+        let other_pk = VerifiableServer::<CS>::new(&mut rng)
+            .unwrap()
+            .get_public_key();
+        assert!(matches!(
+            result
+                .proof
+                .verify(other_pk, &messages, &result.messages, &metadata),
+            Err(InternalError::ProofVerificationError)
+        ));
+        assert!(result
+            .proof
+            .verify(pk, &messages, &result.messages, &Metadata::none())
+            .is_err());
+        assert!(result
+            .proof
+            .verify_poprf(pk, &messages, &result.messages, &metadata)
+            .is_err());
+        let reversed: Vec<_> = result.messages.iter().rev().cloned().collect();
+        assert!(result
+            .proof
+            .verify(pk, &messages, &reversed, &metadata)
+            .is_err());
+
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let result = server
+            .batch_evaluate(&mut rng, &messages, &metadata)
+            .unwrap();
+        result
+            .proof
+            .verify_poprf(
+                server.get_public_key(),
+                &messages,
+                &result.messages,
+                &metadata,
+            )
+            .unwrap();
+    }
+
+    fn consuming_finalize<CS: CipherSuite>() {
+        let input = b"input";
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+
+        let server = NonVerifiableServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result = NonVerifiableClient::<CS>::blind(input, &mut rng).unwrap();
+        let message = server
+            .evaluate(client_blind_result.message, &metadata)
+            .unwrap()
+            .message;
+        // The deprecated borrowing variant still computes the same output
+        #[allow(deprecated)]
+        let borrowed = client_blind_result
+            .state
+            .finalize(input, message.clone(), &metadata)
+            .unwrap()
+            .output;
+        let output = client_blind_result
+            .state
+            .finalize_once(input, message, &metadata)
+            .unwrap()
+            .output;
+        assert_eq!(output, borrowed);
+        assert_eq!(*output, server.full_evaluate(input, &metadata).unwrap());
+
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result = VerifiableClient::<CS>::blind(input, &mut rng).unwrap();
+        let result = server
+            .evaluate(&mut rng, client_blind_result.message, &metadata)
+            .unwrap();
+        let output = client_blind_result
+            .state
+            .finalize_once(
+                input,
+                result.message,
+                result.proof,
+                server.get_public_key(),
+                &metadata,
+            )
+            .unwrap()
+            .output;
+        assert_eq!(*output, server.full_evaluate(input, &metadata).unwrap());
+
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result = PoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        let result = server
+            .evaluate(&mut rng, client_blind_result.message, &metadata)
+            .unwrap();
+        let output = client_blind_result
+            .state
+            .finalize_once(
+                input,
+                result.message,
+                result.proof,
+                server.get_public_key(),
+                &metadata,
+            )
+            .unwrap()
+            .output;
+        assert_eq!(*output, server.full_evaluate(input, &metadata).unwrap());
+    }
+
+    fn client_message<CS: CipherSuite>() {
+        let input = b"input";
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+
+        // The message is sent again from the state, after a round trip
+        // through serialization
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result = VerifiableClient::<CS>::blind(input, &mut rng).unwrap();
+        let state =
+            VerifiableClient::<CS>::deserialize(&client_blind_result.state.serialize()).unwrap();
+        let message = state.message();
+        assert!(message.value.ct_equal(&client_blind_result.message.value));
+        drop(client_blind_result);
+        let result = server.evaluate(&mut rng, message, &metadata).unwrap();
+        let output = state
+            .finalize_once(
+                input,
+                result.message,
+                result.proof,
+                server.get_public_key(),
+                &metadata,
+            )
+            .unwrap()
+            .output;
+        assert_eq!(*output, server.full_evaluate(input, &metadata).unwrap());
+
+        let client_blind_result = PoprfClient::<CS>::blind(input, &mut rng).unwrap();
+        assert!(client_blind_result
+            .state
+            .message()
+            .value
+            .ct_equal(&client_blind_result.message.value));
+    }
+
+    fn server_mode_conversion<CS: CipherSuite>() {
+        let input = b"input";
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+
+        let server = NonVerifiableServer::<CS>::new_from_seed(b"seed").unwrap();
+        let sk = server.get_private_key();
+        let verifiable = VerifiableServer::try_from(server).unwrap();
+        assert!(CS::Group::ct_equal_scalar(
+            &verifiable.get_private_key(),
+            &sk
+        ));
+        assert!(verifiable
+            .get_public_key()
+            .ct_equal(&(CS::Group::base_point() * &sk)));
+
+        // The converted server is a working verifiable server
+        let client_blind_result = VerifiableClient::<CS>::blind(input, &mut rng).unwrap();
+        let result = verifiable
+            .evaluate(&mut rng, client_blind_result.message, &metadata)
+            .unwrap();
+        let output = client_blind_result
+            .state
+            .finalize_once(
+                input,
+                result.message,
+                result.proof,
+                verifiable.get_public_key(),
+                &metadata,
+            )
+            .unwrap()
+            .output;
+        assert_eq!(*output, verifiable.full_evaluate(input, &metadata).unwrap());
+
+        let server = verifiable.into_non_verifiable().unwrap();
+        assert!(CS::Group::ct_equal_scalar(&server.get_private_key(), &sk));
+        let server = NonVerifiableServer::try_from(server.into_verifiable().unwrap()).unwrap();
+        assert!(CS::Group::ct_equal_scalar(&server.get_private_key(), &sk));
+    }
+
+    fn prepared_server<CS: CipherSuite>() {
+        let inputs = [&b"input"[..], b"other input"];
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+
+        let server = NonVerifiableServer::<CS>::new(&mut rng).unwrap();
+        let prepared = server.with_metadata(&metadata).unwrap();
+        let client_blind_result = NonVerifiableClient::<CS>::blind(inputs[0], &mut rng).unwrap();
+        let message = prepared
+            .evaluate(&client_blind_result.message)
+            .unwrap()
+            .message;
+        let expected = server
+            .evaluate(client_blind_result.message.clone(), &metadata)
+            .unwrap()
+            .message;
+        assert!(message.value.ct_equal(&expected.value));
+        let messages = prepared
+            .batch_evaluate(&[client_blind_result.message])
+            .unwrap();
+        assert!(messages[0].value.ct_equal(&expected.value));
+
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let prepared = server.with_metadata(&metadata).unwrap();
+        let client_blind_result = VerifiableClient::<CS>::blind(inputs[0], &mut rng).unwrap();
+        let result = prepared
+            .evaluate(&mut rng, &client_blind_result.message)
+            .unwrap();
+        let output = client_blind_result
+            .state
+            .finalize_once(
+                inputs[0],
+                result.message,
+                result.proof,
+                server.get_public_key(),
+                &metadata,
+            )
+            .unwrap()
+            .output;
+        assert_eq!(*output, server.full_evaluate(inputs[0], &metadata).unwrap());
+
+        let server = PoprfServer::<CS>::new(&mut rng).unwrap();
+        let prepared = server.with_metadata(&metadata).unwrap();
+        let mut clients = vec![];
+        let mut messages = vec![];
+        for input in &inputs {
+            let client_blind_result = PoprfClient::<CS>::blind(input, &mut rng).unwrap();
+            clients.push(client_blind_result.state);
+            messages.push(client_blind_result.message);
+        }
+        let result = prepared.batch_evaluate(&mut rng, &messages).unwrap();
+        let outputs: Vec<_> = PoprfClient::batch_finalize(
+            &inputs,
+            &clients,
+            &result.messages,
+            result.proof,
+            server.get_public_key(),
+            &metadata,
+        )
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+        for (input, output) in inputs.iter().zip(outputs) {
+            assert_eq!(*output, server.full_evaluate(input, &metadata).unwrap());
+        }
+    }
+
+    fn public_hash_functions<CS: CipherSuite>() {
+        let input = b"input";
+        let mut rng = OsRng;
+
+        // The blinded element is the hashed input times the blind
+        let client_blind_result = NonVerifiableClient::<CS>::blind(input, &mut rng).unwrap();
+        let hashed_point = hash_to_group::<CS>(input, Mode::Base).unwrap();
+        assert!((hashed_point * &client_blind_result.state.get_blind())
+            .ct_equal(&client_blind_result.message.value));
+        assert!(!hashed_point.ct_equal(&hash_to_group::<CS>(input, Mode::Verifiable).unwrap()));
+
+        // Private keys are the seed hashed to a scalar
+        let server = VerifiableServer::<CS>::new_from_seed(input).unwrap();
+        assert!(CS::Group::ct_equal_scalar(
+            &server.get_private_key(),
+            &hash_to_scalar::<CS>(input, Mode::Verifiable).unwrap(),
+        ));
+    }
+
+    fn verify_finalize<CS: CipherSuite>() {
+        let input = b"input";
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+
+        let server = NonVerifiableServer::<CS>::new(&mut rng).unwrap();
+        let client_blind_result = NonVerifiableClient::<CS>::blind(input, &mut rng).unwrap();
+        let server_result = server
+            .evaluate(client_blind_result.message, &metadata)
+            .unwrap();
+        let output = client_blind_result
+            .state
+            .finalize_once(input, server_result.message, &metadata)
+            .unwrap()
+            .output;
+        assert!(server.verify_finalize(input, &metadata, &output).unwrap());
+        assert!(!server
+            .verify_finalize(b"other input", &metadata, &output)
+            .unwrap());
+        assert!(!server
+            .verify_finalize(input, &Metadata::none(), &output)
+            .unwrap());
+        assert!(!server
+            .verify_finalize(input, &metadata, &output[1..])
+            .unwrap());
+
+        // The output depends on the mode
+        let server = VerifiableServer::<CS>::new_with_key(&CS::Group::scalar_as_bytes(
+            server.get_private_key(),
+        ))
+        .unwrap();
+        assert!(!server.verify_finalize(input, &metadata, &output).unwrap());
+        let output = server.full_evaluate(input, &metadata).unwrap();
+        assert!(server.verify_finalize(input, &metadata, &output).unwrap());
+    }
+
+    fn precomputed_evaluations<CS: CipherSuite>() {
+        let metadata = Metadata(b"info".to_vec());
+        let mut rng = OsRng;
+        let domain = [&b"input 1"[..], b"input 2", b"input 3"];
+
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let precomputed = server
+            .precompute_evaluations(domain.iter(), &metadata)
+            .unwrap();
+        assert_eq!(precomputed.len(), domain.len());
+
+        // Matches the output of a blinded evaluation
+        let client_blind_result = VerifiableClient::<CS>::blind(b"input 2", &mut rng).unwrap();
+        let server_result = server
+            .evaluate(&mut rng, client_blind_result.message, &metadata)
+            .unwrap();
+        let client_finalize_result = client_blind_result
+            .state
+            .finalize_once(
+                b"input 2",
+                server_result.message,
+                server_result.proof,
+                server.get_public_key(),
+                &metadata,
+            )
+            .unwrap();
+        assert_eq!(
+            precomputed.get(b"input 2"),
+            Some(&*client_finalize_result.output)
+        );
+        assert!(precomputed.contains_output(&client_finalize_result.output));
+
+        // Inputs outside of the domain are computed on demand
+        assert!(precomputed.get(b"input 4").is_none());
+        assert_eq!(
+            server
+                .full_evaluate_precomputed(&precomputed, b"input 4")
+                .unwrap(),
+            prf::<CS>(
+                b"input 4",
+                server.get_private_key(),
+                &metadata.0,
+                Mode::Verifiable
+            )
+        );
+        assert!(!precomputed.contains_output(&prf::<CS>(
+            b"input 4",
+            server.get_private_key(),
+            &metadata.0,
+            Mode::Verifiable
+        )));
+
+        let base_server = NonVerifiableServer::<CS>::new(&mut rng).unwrap();
+        let base_precomputed = base_server
+            .precompute_evaluations(domain.iter(), &metadata)
+            .unwrap();
+        assert_eq!(
+            base_server
+                .full_evaluate_precomputed(&base_precomputed, b"input 1")
+                .unwrap(),
+            prf::<CS>(
+                b"input 1",
+                base_server.get_private_key(),
+                &metadata.0,
+                Mode::Base
+            )
+        );
+        assert!(matches!(
+            base_server.full_evaluate_precomputed(&precomputed, b"input 1"),
+            Err(InternalError::IncompatibleModeError)
+        ));
+    }
+
+    fn verifiable_batch_bad_public_key<CS: CipherSuite>() {
+        let info = b"info";
+        let mut rng = OsRng;
+        let mut inputs = vec![];
+        let mut client_states = vec![];
+        let mut client_messages = vec![];
+        let num_iterations = 10;
+        for _ in 0..num_iterations {
+            let mut input = vec![0u8; 32];
+            rng.fill_bytes(&mut input);
+            let client_blind_result = VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
+            inputs.push(input);
             client_states.push(client_blind_result.state);
             client_messages.push(client_blind_result.message);
         }
@@ -933,47 +5606,275 @@ mod tests {
         let server_result = server
             .batch_evaluate(&mut rng, &client_messages, &Metadata(info.to_vec()))
             .unwrap();
-        let batch_finalize_input = BatchFinalizeInput::new(client_states, server_result.messages);
         let wrong_pk = {
             // Choose a group element that is unlikely to be the right public key
-            CS::Group::hash_to_curve::<CS::Hash>(b"msg", b"dst").unwrap()
+            CS::Group::hash_to_curve::<CS>(&[b"msg"], &[b"dst"]).unwrap()
         };
+        let metadata = Metadata(info.to_vec());
         let client_finalize_result = VerifiableClient::batch_finalize(
-            batch_finalize_input,
+            &inputs,
+            &client_states,
+            &server_result.messages,
             server_result.proof,
             wrong_pk,
-            &Metadata(info.to_vec()),
+            &metadata,
         );
         assert!(client_finalize_result.is_err());
     }
 
-    fn base_inversion_unsalted<CS: CipherSuite>() {
+    fn verifiable_derandomized_retrieval<CS: CipherSuite>() {
+        let input = b"input";
+        let info = b"info";
+        let mut rng = OsRng;
+        let client_blind_result = VerifiableClient::<CS>::blind(&input[..], &mut rng).unwrap();
+        let server = VerifiableServer::<CS>::new(&mut rng).unwrap();
+        let server_result = server
+            .evaluate_derandomized(
+                client_blind_result.message.clone(),
+                &Metadata(info.to_vec()),
+                b"personalization",
+            )
+            .unwrap();
+
+        // The proof only depends on the key, the inputs and the personalization
+        let server_result_2 = server
+            .evaluate_derandomized(
+                client_blind_result.message.clone(),
+                &Metadata(info.to_vec()),
+                b"personalization",
+            )
+            .unwrap();
+        assert_eq!(
+            server_result.proof.serialize(),
+            server_result_2.proof.serialize()
+        );
+        let server_result_3 = server
+            .evaluate_derandomized(
+                client_blind_result.message,
+                &Metadata(info.to_vec()),
+                b"other personalization",
+            )
+            .unwrap();
+        assert_ne!(
+            server_result.proof.serialize(),
+            server_result_3.proof.serialize()
+        );
+
+        let client_finalize_result = client_blind_result
+            .state
+            .finalize_once(
+                &input[..],
+                server_result.message,
+                server_result.proof,
+                server.get_public_key(),
+                &Metadata(info.to_vec()),
+            )
+            .unwrap();
+        let res2 = prf::<CS>(&input[..], server.get_private_key(), info, Mode::Verifiable);
+        assert_eq!(*client_finalize_result.output, res2);
+    }
+
+    fn base_inversion_unsalted<CS: CipherSuite>() {
+        let mut rng = OsRng;
+        let mut input = alloc::vec![0u8; 64];
+        rng.fill_bytes(&mut input);
+        let info = b"info";
+        let client_blind_result = NonVerifiableClient::<CS>::blind(&input, &mut rng).unwrap();
+        let client_finalize_result = client_blind_result
+            .state
+            .finalize_once(
+                &input,
+                EvaluationElement {
+                    value: client_blind_result.message.value,
+                },
+                &Metadata(info.to_vec()),
+            )
+            .unwrap();
+
+        let dst = [
+            STR_HASH_TO_GROUP,
+            &get_context_string::<CS>(Mode::Base).unwrap(),
+        ];
+        let point = CS::Group::hash_to_curve::<CS>(&[&input], &dst).unwrap();
+        let res2 = finalize_after_unblind::<CS>(&input, &point, info, Mode::Base).unwrap();
+
+        assert_eq!(*client_finalize_result.output, res2);
+    }
+
+    #[test]
+    fn test_from_value_checked() {
+        use crate::tests::Ristretto255Sha512;
+        use curve25519_dalek::ristretto::RistrettoPoint;
+
+        let value = RistrettoPoint::base_point();
+        assert_eq!(
+            BlindedElement::<Ristretto255Sha512>::from_value_checked(value)
+                .unwrap()
+                .value,
+            value
+        );
+        assert_eq!(
+            EvaluationElement::<Ristretto255Sha512>::from_value_checked(value)
+                .unwrap()
+                .value,
+            value
+        );
+        assert!(matches!(
+            BlindedElement::<Ristretto255Sha512>::from_value_checked(RistrettoPoint::identity()),
+            Err(InternalError::IdentityElementError)
+        ));
+        assert!(matches!(
+            EvaluationElement::<Ristretto255Sha512>::from_value_checked(RistrettoPoint::identity()),
+            Err(InternalError::IdentityElementError)
+        ));
+    }
+
+    #[test]
+    fn test_constant_time_eq() -> Result<(), InternalError> {
+        use crate::tests::Ristretto255Sha512;
+
+        let mut rng = OsRng;
+        let client = VerifiableClient::<Ristretto255Sha512>::blind(b"input", &mut rng)?.state;
+        let other_client = VerifiableClient::<Ristretto255Sha512>::blind(b"input", &mut rng)?.state;
+        let bound_client = VerifiableClient::<Ristretto255Sha512> {
+            input_binding: Some(compute_input_binding::<Ristretto255Sha512>(
+                b"input",
+                &client.blind,
+                Mode::Verifiable,
+            )?),
+            ..client.clone()
+        };
+        assert_eq!(client, client.clone());
+        assert_ne!(client, other_client);
+        assert_ne!(client, bound_client);
+        assert_eq!(bound_client, bound_client.clone());
+
+        let server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng)?;
+        let other_server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng)?;
+        assert_eq!(server, server.clone());
+        assert_ne!(server, other_server);
+
+        let server_result = server.evaluate(
+            &mut rng,
+            BlindedElement {
+                value: client.blinded_element,
+            },
+            &Metadata::none(),
+        )?;
+        let other_server_result = other_server.evaluate(
+            &mut rng,
+            BlindedElement {
+                value: client.blinded_element,
+            },
+            &Metadata::none(),
+        )?;
+        assert_eq!(server_result.proof, server_result.proof.clone());
+        assert_ne!(server_result.proof, other_server_result.proof);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_to_group() -> Result<(), InternalError> {
+        use crate::tests::Ristretto255Sha512;
+
         let mut rng = OsRng;
-        let mut input = alloc::vec![0u8; 64];
-        rng.fill_bytes(&mut input);
-        let info = b"info";
-        let client_blind_result = NonVerifiableClient::<CS>::blind(&input, &mut rng).unwrap();
-        let client_finalize_result = client_blind_result
+        let server = NonVerifiableServer::<Ristretto255Sha512>::new(&mut rng)?;
+        let client_blind_result = NonVerifiableClient::blind(b"input", &mut rng)?;
+        let server_result = server.evaluate(client_blind_result.message, &Metadata::none())?;
+        let output = client_blind_result
             .state
-            .finalize(
-                EvaluationElement {
-                    value: client_blind_result.message.value,
-                },
-                &Metadata(info.to_vec()),
-            )
-            .unwrap();
+            .finalize_once(b"input", server_result.message, &Metadata::none())?
+            .output;
 
-        let dst = [
-            STR_HASH_TO_GROUP,
-            &get_context_string::<CS>(Mode::Base).unwrap(),
-        ]
-        .concat();
-        let point = CS::Group::hash_to_curve::<CS::Hash>(&input, &dst).unwrap();
-        let res2 = finalize_after_unblind::<CS>(&[(input.to_vec(), point)], info, Mode::Base)
-            .unwrap()[0]
-            .clone();
+        let scalar = output_to_scalar::<Ristretto255Sha512>(&output, Mode::Base, b"share")?;
+        assert_eq!(
+            scalar,
+            output_to_scalar::<Ristretto255Sha512>(&output, Mode::Base, b"share")?
+        );
+        assert_ne!(
+            scalar,
+            output_to_scalar::<Ristretto255Sha512>(&output, Mode::Base, b"opening")?
+        );
+        assert_ne!(
+            scalar,
+            output_to_scalar::<Ristretto255Sha512>(&output, Mode::Verifiable, b"share")?
+        );
+
+        let element = output_to_element::<Ristretto255Sha512>(&output, Mode::Base, b"share")?;
+        assert!(!element.is_identity());
+        assert_ne!(
+            element,
+            output_to_element::<Ristretto255Sha512>(&output, Mode::Base, b"opening")?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_context_string() {
+        use crate::tests::Ristretto255Sha512;
+
+        assert_eq!(
+            create_context_string(Mode::Verifiable, 0x0003).as_slice(),
+            b"VOPRF07-\x01\x00\x03"
+        );
+        assert_eq!(
+            create_context_string(Mode::Base, 0x0001),
+            get_context_string::<Ristretto255Sha512>(Mode::Base).unwrap()
+        );
+        assert_eq!(
+            create_hash_to_group_dst(Mode::Base, 0x0001),
+            b"HashToGroup-VOPRF07-\x00\x00\x01"
+        );
+        assert_eq!(
+            create_hash_to_scalar_dst(Mode::Verifiable, 0x0001),
+            b"HashToScalar-VOPRF07-\x01\x00\x01"
+        );
+        assert_eq!(
+            create_finalize_dst(Mode::Base, 0x0001),
+            b"Finalize-VOPRF07-\x00\x00\x01"
+        );
+    }
+
+    #[cfg(feature = "serialize")]
+    #[test]
+    fn test_human_readable_serde() -> Result<(), InternalError> {
+        use crate::tests::Ristretto255Sha512;
+
+        let mut rng = OsRng;
+        let server = VerifiableServer::<Ristretto255Sha512>::new(&mut rng)?;
+        let client_blind_result =
+            VerifiableClient::<Ristretto255Sha512>::blind(b"input", &mut rng)?;
+        let message = client_blind_result.message;
+
+        // Human-readable formats get the base64 encoding of the bytes, binary
+        // formats the bytes themselves
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json, base64::encode(message.serialize()));
+        let deserialized: BlindedElement<Ristretto255Sha512> =
+            serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, message);
+        let bytes = bincode::serialize(&message).unwrap();
+        assert!(bytes.ends_with(&message.serialize()));
+        let deserialized: BlindedElement<Ristretto255Sha512> =
+            bincode::deserialize(&bytes).unwrap();
+        assert_eq!(deserialized, message);
 
-        assert_eq!(client_finalize_result.output, res2);
+        let proof = server.evaluate(&mut rng, message, &Metadata::none())?.proof;
+        let json = serde_json::to_string(&proof).unwrap();
+        assert_eq!(
+            json,
+            alloc::format!("\"{}\"", base64::encode(proof.serialize()))
+        );
+        let deserialized: Proof<Ristretto255Sha512> = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, proof);
+
+        // Invalid encodings are rejected
+        assert!(serde_json::from_str::<Proof<Ristretto255Sha512>>("\"not base64\"").is_err());
+        assert!(serde_json::from_str::<Proof<Ristretto255Sha512>>("[0, 1]").is_err());
+
+        Ok(())
     }
 
     #[test]
@@ -981,23 +5882,777 @@ mod tests {
         use crate::tests::Ristretto255Sha512;
 
         base_retrieval::<Ristretto255Sha512>();
+        context_binding::<Ristretto255Sha512>();
         base_inversion_unsalted::<Ristretto255Sha512>();
         verifiable_retrieval::<Ristretto255Sha512>();
+        poprf_retrieval::<Ristretto255Sha512>();
+        poprf_wrong_metadata::<Ristretto255Sha512>();
         verifiable_batch_retrieval::<Ristretto255Sha512>();
+        verifiable_batch_owned::<Ristretto255Sha512>();
+        verifiable_batch_superset::<Ristretto255Sha512>();
+        split_key_evaluation::<Ristretto255Sha512>();
+        verifiable_streaming::<Ristretto255Sha512>();
+        verifiable_proof_builder::<Ristretto255Sha512>();
+        precomputed_evaluations::<Ristretto255Sha512>();
+        prepared_server::<Ristretto255Sha512>();
+        server_mode_conversion::<Ristretto255Sha512>();
+        proof_verification::<Ristretto255Sha512>();
+        consuming_finalize::<Ristretto255Sha512>();
+        client_message::<Ristretto255Sha512>();
+        public_hash_functions::<Ristretto255Sha512>();
+        verify_finalize::<Ristretto255Sha512>();
+        evaluate_with_keys::<Ristretto255Sha512>();
         verifiable_bad_public_key::<Ristretto255Sha512>();
+        verifiable_identity_public_key::<Ristretto255Sha512>();
+        verifiable_invalid_proof::<Ristretto255Sha512>();
         verifiable_batch_bad_public_key::<Ristretto255Sha512>();
+        verifiable_derandomized_retrieval::<Ristretto255Sha512>();
+        input_binding::<Ristretto255Sha512>();
+        versioned_state::<Ristretto255Sha512>();
+        prepared_metadata::<Ristretto255Sha512>();
+        verifiable_batch_resume::<Ristretto255Sha512>();
+
+        // Hashes other than SHA-2 work with the default `expand_message_xmd`
+        {
+            use crate::tests::{Ristretto255Blake2b, Ristretto255Sha3_512};
+
+            base_retrieval::<Ristretto255Sha3_512>();
+            verifiable_retrieval::<Ristretto255Sha3_512>();
+            poprf_retrieval::<Ristretto255Sha3_512>();
+            poprf_wrong_metadata::<Ristretto255Sha3_512>();
+            verifiable_batch_retrieval::<Ristretto255Sha3_512>();
+
+            base_retrieval::<Ristretto255Blake2b>();
+            verifiable_retrieval::<Ristretto255Blake2b>();
+            poprf_retrieval::<Ristretto255Blake2b>();
+            poprf_wrong_metadata::<Ristretto255Blake2b>();
+            verifiable_batch_retrieval::<Ristretto255Blake2b>();
+        }
+
+        #[cfg(feature = "shake256")]
+        {
+            use crate::Ristretto255Shake256;
+
+            base_retrieval::<Ristretto255Shake256>();
+            context_binding::<Ristretto255Shake256>();
+            verifiable_retrieval::<Ristretto255Shake256>();
+            poprf_retrieval::<Ristretto255Shake256>();
+            poprf_wrong_metadata::<Ristretto255Shake256>();
+            verifiable_batch_retrieval::<Ristretto255Shake256>();
+            verifiable_derandomized_retrieval::<Ristretto255Shake256>();
+            prepared_metadata::<Ristretto255Shake256>();
+        }
 
         #[cfg(feature = "p256")]
         {
             use crate::tests::P256Sha256;
 
             base_retrieval::<P256Sha256>();
+            context_binding::<P256Sha256>();
             base_inversion_unsalted::<P256Sha256>();
             verifiable_retrieval::<P256Sha256>();
+            poprf_retrieval::<P256Sha256>();
+            poprf_wrong_metadata::<P256Sha256>();
             verifiable_batch_retrieval::<P256Sha256>();
+            verifiable_batch_owned::<P256Sha256>();
+            verifiable_batch_superset::<P256Sha256>();
+            split_key_evaluation::<P256Sha256>();
+            verifiable_streaming::<P256Sha256>();
+            verifiable_proof_builder::<P256Sha256>();
+            precomputed_evaluations::<P256Sha256>();
+            prepared_server::<P256Sha256>();
+            server_mode_conversion::<P256Sha256>();
+            proof_verification::<P256Sha256>();
+            consuming_finalize::<P256Sha256>();
+            client_message::<P256Sha256>();
+            public_hash_functions::<P256Sha256>();
+            verify_finalize::<P256Sha256>();
+            evaluate_with_keys::<P256Sha256>();
             verifiable_bad_public_key::<P256Sha256>();
+            verifiable_identity_public_key::<P256Sha256>();
+            verifiable_invalid_proof::<P256Sha256>();
             verifiable_batch_bad_public_key::<P256Sha256>();
+            verifiable_derandomized_retrieval::<P256Sha256>();
+            input_binding::<P256Sha256>();
+            versioned_state::<P256Sha256>();
+            prepared_metadata::<P256Sha256>();
+            verifiable_batch_resume::<P256Sha256>();
+        }
+
+        #[cfg(feature = "mock")]
+        {
+            use crate::tests::MockSha256;
+
+            base_retrieval::<MockSha256>();
+            context_binding::<MockSha256>();
+            base_inversion_unsalted::<MockSha256>();
+            verifiable_retrieval::<MockSha256>();
+            poprf_retrieval::<MockSha256>();
+            poprf_wrong_metadata::<MockSha256>();
+            verifiable_batch_retrieval::<MockSha256>();
+            verifiable_batch_owned::<MockSha256>();
+            verifiable_batch_superset::<MockSha256>();
+            split_key_evaluation::<MockSha256>();
+            verifiable_streaming::<MockSha256>();
+            verifiable_proof_builder::<MockSha256>();
+            precomputed_evaluations::<MockSha256>();
+            prepared_server::<MockSha256>();
+            server_mode_conversion::<MockSha256>();
+            proof_verification::<MockSha256>();
+            consuming_finalize::<MockSha256>();
+            client_message::<MockSha256>();
+            public_hash_functions::<MockSha256>();
+            verify_finalize::<MockSha256>();
+            evaluate_with_keys::<MockSha256>();
+            verifiable_bad_public_key::<MockSha256>();
+            verifiable_identity_public_key::<MockSha256>();
+            verifiable_invalid_proof::<MockSha256>();
+            verifiable_batch_bad_public_key::<MockSha256>();
+            verifiable_derandomized_retrieval::<MockSha256>();
+            input_binding::<MockSha256>();
+            versioned_state::<MockSha256>();
+            prepared_metadata::<MockSha256>();
+            verifiable_batch_resume::<MockSha256>();
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_length_limits() -> Result<(), InternalError> {
+        struct Limited;
+        impl CipherSuite for Limited {
+            type Group = curve25519_dalek::ristretto::RistrettoPoint;
+            type Hash = sha2::Sha512;
+
+            const MAX_INPUT_LEN: usize = 4;
+            const MAX_METADATA_LEN: usize = 2;
+        }
+
+        let mut rng = OsRng;
+        let metadata = Metadata(b"ab".to_vec());
+        let long_metadata = Metadata(b"abc".to_vec());
+
+        assert!(matches!(
+            NonVerifiableClient::<Limited>::blind(b"input", &mut rng),
+            Err(InternalError::InputLengthError)
+        ));
+        assert!(matches!(
+            VerifiableClient::<Limited>::blind(b"input", &mut rng),
+            Err(InternalError::InputLengthError)
+        ));
+
+        let server = NonVerifiableServer::<Limited>::new(&mut rng)?;
+        let client_blind_result = NonVerifiableClient::<Limited>::blind(b"inpu", &mut rng)?;
+        assert!(matches!(
+            server.evaluate(client_blind_result.message.clone(), &long_metadata),
+            Err(InternalError::MetadataLengthError)
+        ));
+        let server_result = server.evaluate(client_blind_result.message, &metadata)?;
+        assert!(matches!(
+            client_blind_result.state.clone().finalize_once(
+                b"inpu",
+                server_result.message.clone(),
+                &long_metadata
+            ),
+            Err(InternalError::MetadataLengthError)
+        ));
+        assert!(matches!(
+            client_blind_result.state.clone().finalize_once(
+                b"input",
+                server_result.message.clone(),
+                &metadata
+            ),
+            Err(InternalError::InputLengthError)
+        ));
+        let _client_finalize_result =
+            client_blind_result
+                .state
+                .finalize_once(b"inpu", server_result.message, &metadata)?;
+
+        let server = VerifiableServer::<Limited>::new(&mut rng)?;
+        let client_blind_result = VerifiableClient::<Limited>::blind(b"inpu", &mut rng)?;
+        assert!(matches!(
+            server.evaluate(
+                &mut rng,
+                client_blind_result.message.clone(),
+                &long_metadata
+            ),
+            Err(InternalError::MetadataLengthError)
+        ));
+        let server_result = server.evaluate(&mut rng, client_blind_result.message, &metadata)?;
+        assert!(matches!(
+            client_blind_result.state.finalize_once(
+                b"inpu",
+                server_result.message,
+                server_result.proof,
+                server.get_public_key(),
+                &long_metadata,
+            ),
+            Err(InternalError::MetadataLengthError)
+        ));
+
+        Ok(())
+    }
+
+    // A batch of 2^16 + 1 elements can't be indexed on 16-bit targets
+    #[cfg(not(target_pointer_width = "16"))]
+    #[test]
+    fn test_batch_size_limit() {
+        use crate::tests::Ristretto255Sha512;
+        use curve25519_dalek::ristretto::RistrettoPoint;
+
+        let b = RistrettoPoint::base_point();
+        let elements = || (0..65537_usize).map(|_| RistrettoPoint::base_point());
+
+        // Rejected before any element is hashed
+        assert!(matches!(
+            compute_composites::<Ristretto255Sha512>(
+                None,
+                b,
+                elements(),
+                elements(),
+                Mode::Verifiable
+            ),
+            Err(InternalError::SerializationError)
+        ));
+        assert!(compute_composites::<Ristretto255Sha512>(
+            None,
+            b,
+            elements().take(1),
+            elements().take(1),
+            Mode::Verifiable
+        )
+        .is_ok());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_batch() -> Result<(), InternalError> {
+        use crate::tests::Ristretto255Sha512;
+
+        type CS = Ristretto255Sha512;
+
+        let mut rng = OsRng;
+        let server = VerifiableServer::<CS>::new(&mut rng)?;
+        let metadata = Metadata(b"metadata".to_vec());
+        let inputs: Vec<_> = (0..100_u8).map(|i| vec![i; 10]).collect();
+        let mut clients = vec![];
+        let mut blinded_elements = vec![];
+        for input in &inputs {
+            let blind = VerifiableClient::<CS>::blind(input, &mut rng)?;
+            clients.push(blind.state);
+            blinded_elements.push(blind.message);
+        }
+
+        let result = server.par_batch_evaluate(&mut rng, &blinded_elements, &metadata)?;
+        let expected = server.batch_evaluate(&mut rng, &blinded_elements, &metadata)?;
+        assert_eq!(result.messages, expected.messages);
+
+        // Proofs from either side are accepted by the other
+        let outputs = VerifiableClient::par_batch_finalize(
+            &inputs,
+            &clients,
+            &result.messages,
+            result.proof.clone(),
+            server.get_public_key(),
+            &metadata,
+        )?;
+        let expected_outputs = VerifiableClient::batch_finalize(
+            &inputs,
+            &clients,
+            &result.messages,
+            result.proof.clone(),
+            server.get_public_key(),
+            &metadata,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(outputs, expected_outputs);
+        assert_eq!(
+            VerifiableClient::par_batch_finalize(
+                &inputs,
+                &clients,
+                &expected.messages,
+                expected.proof,
+                server.get_public_key(),
+                &metadata,
+            )?,
+            outputs,
+        );
+
+        // A proof for a different batch or metadata is rejected
+        assert!(matches!(
+            VerifiableClient::par_batch_finalize(
+                &inputs[1..],
+                &clients[1..],
+                &result.messages[1..],
+                result.proof.clone(),
+                server.get_public_key(),
+                &metadata,
+            ),
+            Err(InternalError::ProofVerificationError)
+        ));
+        assert!(matches!(
+            VerifiableClient::par_batch_finalize(
+                &inputs,
+                &clients,
+                &result.messages,
+                result.proof.clone(),
+                server.get_public_key(),
+                &Metadata::none(),
+            ),
+            Err(InternalError::ProofVerificationError)
+        ));
+        assert!(matches!(
+            VerifiableClient::par_batch_finalize(
+                &inputs,
+                &clients,
+                &result.messages[1..],
+                result.proof.clone(),
+                server.get_public_key(),
+                &metadata,
+            ),
+            Err(InternalError::BatchMismatch {
+                left: 99,
+                right: 100
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_arrays() -> Result<(), InternalError> {
+        use crate::tests::Ristretto255Sha512;
+
+        type CS = Ristretto255Sha512;
+
+        let mut rng = OsRng;
+        let metadata = Metadata(b"metadata".to_vec());
+        let inputs = [&b"input 1"[..], b"input 2"];
+
+        let server = VerifiableServer::<CS>::new(&mut rng)?;
+        let first = VerifiableClient::<CS>::blind(inputs[0], &mut rng)?;
+        let second = VerifiableClient::<CS>::blind(inputs[1], &mut rng)?;
+        let clients = [first.state, second.state];
+        let messages = [first.message, second.message];
+
+        let result = server.batch_evaluate_array(&mut rng, &messages, &metadata)?;
+        let expected = server.batch_evaluate(&mut rng, &messages, &metadata)?;
+        assert_eq!(result.messages[..], expected.messages[..]);
+        let outputs = VerifiableClient::batch_finalize_array(
+            &inputs,
+            &clients,
+            &result.messages,
+            result.proof.clone(),
+            server.get_public_key(),
+            &metadata,
+        )?;
+        let expected_outputs = VerifiableClient::batch_finalize(
+            &inputs,
+            &clients,
+            &result.messages,
+            result.proof,
+            server.get_public_key(),
+            &metadata,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(outputs[..], expected_outputs[..]);
+
+        let server = PoprfServer::<CS>::new(&mut rng)?;
+        let first = PoprfClient::<CS>::blind(inputs[0], &mut rng)?;
+        let second = PoprfClient::<CS>::blind(inputs[1], &mut rng)?;
+        let clients = [first.state, second.state];
+        let messages = [first.message, second.message];
+
+        let result = server.batch_evaluate_array(&mut rng, &messages, &metadata)?;
+        let expected = server.batch_evaluate(&mut rng, &messages, &metadata)?;
+        assert_eq!(result.messages[..], expected.messages[..]);
+        let outputs = PoprfClient::batch_finalize_array(
+            &inputs,
+            &clients,
+            &result.messages,
+            result.proof.clone(),
+            server.get_public_key(),
+            &metadata,
+        )?;
+        for (input, output) in inputs.iter().zip(&outputs) {
+            assert_eq!(server.full_evaluate(input, &metadata)?, **output);
+        }
+
+        // A proof for the messages in a different order is rejected
+        assert!(PoprfClient::batch_finalize_array(
+            &inputs,
+            &clients,
+            &[result.messages[1].clone(), result.messages[0].clone()],
+            result.proof,
+            server.get_public_key(),
+            &metadata,
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    // Yields its items without knowing their number up front, like a decoder
+    // reading from the network
+    struct Unsized<T>(Vec<T>);
+
+    impl<'a, T> IntoIterator for &'a Unsized<T> {
+        type Item = &'a T;
+        type IntoIter = iter::Filter<core::slice::Iter<'a, T>, fn(&&T) -> bool>;
+
+        fn into_iter(self) -> Self::IntoIter {
+            self.0.iter().filter(|_| true)
+        }
+    }
+
+    // Yields its item forever
+    struct Unbounded<T>(T);
+
+    impl<'a, T> IntoIterator for &'a Unbounded<T> {
+        type Item = &'a T;
+        type IntoIter = iter::Repeat<&'a T>;
+
+        fn into_iter(self) -> Self::IntoIter {
+            iter::repeat(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_unsized_batch_iterators() -> Result<(), InternalError> {
+        use crate::tests::Ristretto255Sha512;
+
+        type CS = Ristretto255Sha512;
+
+        let mut rng = OsRng;
+        let metadata = Metadata::none();
+        let inputs = [&b"input 1"[..], b"input 2", b"input 3"];
+
+        let server = VerifiableServer::<CS>::new(&mut rng)?;
+        let mut clients = vec![];
+        let mut messages = vec![];
+        for input in &inputs {
+            let blind = VerifiableClient::<CS>::blind(input, &mut rng)?;
+            clients.push(blind.state);
+            messages.push(blind.message);
+        }
+        let messages = Unsized(messages);
+
+        let VerifiableServerBatchEvaluatePrepareResult {
+            prepared_evaluation_elements,
+            t,
+        } = server.batch_evaluate_prepare(messages.into_iter(), &metadata)?;
+        let prepared_evaluation_elements = Unsized(prepared_evaluation_elements.collect());
+        let VerifiableServerBatchEvaluateFinishResult {
+            messages: evaluation_elements,
+            proof,
+        } = VerifiableServer::batch_evaluate_finish(
+            &mut rng,
+            &messages,
+            &prepared_evaluation_elements,
+            &t,
+        )?;
+        let evaluation_elements = Unsized(evaluation_elements.collect());
+
+        let inputs = Unsized(inputs.to_vec());
+        let clients = Unsized(clients);
+        let outputs = VerifiableClient::batch_finalize(
+            &inputs,
+            &clients,
+            &evaluation_elements,
+            proof.clone(),
+            server.get_public_key(),
+            &metadata,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+        for (input, output) in inputs.0.iter().zip(outputs) {
+            assert_eq!(server.full_evaluate(input, &metadata)?, *output);
+        }
+
+        // More inputs or messages than clients are still detected
+        let mut more_inputs = inputs.0.clone();
+        more_inputs.push(b"input 4");
+        assert!(matches!(
+            VerifiableClient::batch_finalize(
+                &Unsized(more_inputs),
+                &clients,
+                &evaluation_elements,
+                proof.clone(),
+                server.get_public_key(),
+                &metadata,
+            ),
+            Err(InternalError::BatchMismatch { left: 4, right: 3 })
+        ));
+        // An unbounded iterator fails right away, without being drained
+        assert!(matches!(
+            VerifiableClient::batch_finalize(
+                &Unbounded(b"input 1"),
+                &clients,
+                &evaluation_elements,
+                proof.clone(),
+                server.get_public_key(),
+                &metadata,
+            ),
+            Err(InternalError::BatchMismatch { left: 4, right: 3 })
+        ));
+        let mut more_elements = evaluation_elements.0.clone();
+        more_elements.push(more_elements[0].clone());
+        assert!(matches!(
+            VerifiableClient::batch_finalize(
+                &inputs,
+                &clients,
+                &Unsized(more_elements),
+                proof,
+                server.get_public_key(),
+                &metadata,
+            ),
+            Err(InternalError::BatchMismatch { left: 4, right: 3 })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_input_parts() -> Result<(), InternalError> {
+        use crate::tests::Ristretto255Sha512;
+        use rand::{rngs::StdRng, SeedableRng};
+
+        type CS = Ristretto255Sha512;
+
+        let mut rng = OsRng;
+        let metadata = Metadata(b"metadata".to_vec());
+        let input = b"first field, second field";
+        let parts = [&input[..12], &input[12..], &[]];
+
+        // The parts are blinded as if they were concatenated
+        let blind = VerifiableClient::<CS>::blind(input, &mut StdRng::seed_from_u64(0))?;
+        let blind_parts =
+            VerifiableClient::<CS>::blind_parts(&parts, &mut StdRng::seed_from_u64(0))?;
+        assert_eq!(blind.message, blind_parts.message);
+
+        let server = VerifiableServer::<CS>::new(&mut rng)?;
+        let result = server.evaluate(&mut rng, blind_parts.message, &metadata)?;
+        let output = blind_parts.state.finalize_parts_once(
+            &parts,
+            result.message,
+            result.proof,
+            server.get_public_key(),
+            &metadata,
+        )?;
+        assert_eq!(*output.output, server.full_evaluate(input, &metadata)?);
+
+        let server = NonVerifiableServer::<CS>::new(&mut rng)?;
+        let blind = NonVerifiableClient::<CS>::blind_parts(&parts, &mut rng)?;
+        let result = server.evaluate(blind.message, &metadata)?;
+        let output = blind
+            .state
+            .finalize_parts_once(&parts, result.message, &metadata)?;
+        assert_eq!(*output.output, server.full_evaluate(input, &metadata)?);
+
+        let server = PoprfServer::<CS>::new(&mut rng)?;
+        let blind = PoprfClient::<CS>::blind_parts(&parts, &mut rng)?;
+        let result = server.evaluate(&mut rng, blind.message, &metadata)?;
+        let output = blind.state.finalize_parts_once(
+            &parts,
+            result.message,
+            result.proof,
+            server.get_public_key(),
+            &metadata,
+        )?;
+        assert_eq!(*output.output, server.full_evaluate(input, &metadata)?);
+
+        // A state bound to the concatenated input accepts the parts, but not
+        // different ones
+        let blind = NonVerifiableClient::<CS>::blind_with_input_binding(input, &mut rng)?;
+        let server = NonVerifiableServer::<CS>::new(&mut rng)?;
+        let result = server.evaluate(blind.message, &metadata)?;
+        assert!(blind
+            .state
+            .clone()
+            .finalize_parts_once(&parts, result.message.clone(), &metadata)
+            .is_ok());
+        assert!(matches!(
+            blind
+                .state
+                .finalize_parts_once(&[&input[..12]], result.message, &metadata),
+            Err(InternalError::InputMismatchError)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_expand() -> Result<(), InternalError> {
+        use crate::tests::Ristretto255Sha512;
+
+        type CS = Ristretto255Sha512;
+
+        let mut rng = OsRng;
+        let input = b"input";
+        let metadata = Metadata(b"metadata".to_vec());
+        let server = PoprfServer::<CS>::new(&mut rng)?;
+        let blind = PoprfClient::<CS>::blind(input, &mut rng)?;
+        let result = server.evaluate(&mut rng, blind.message, &metadata)?;
+        let output = blind.state.finalize_once(
+            input,
+            result.message,
+            result.proof,
+            server.get_public_key(),
+            &metadata,
+        )?;
+
+        // HKDF-Expand output is a prefix of any longer expansion with the same
+        // info, and independent of it with a different info
+        let mut short = [0; 32];
+        let mut long = [0; 100];
+        let mut other = [0; 32];
+        output.expand(b"encryption", &mut short)?;
+        output.expand(b"encryption", &mut long)?;
+        output.expand(b"authentication", &mut other)?;
+        assert_eq!(short, long[..32]);
+        assert_ne!(short, other);
+        assert_ne!(long[..64], output.output[..]);
+
+        let mut too_long = vec![0; 255 * 64 + 1];
+        assert!(matches!(
+            output.expand(b"", &mut too_long),
+            Err(InternalError::OutputLengthError)
+        ));
+        output.expand(b"", &mut too_long[..255 * 64])?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reject_identity_blinded_element() -> Result<(), InternalError> {
+        use crate::rfc9497::{OprfServer, PoprfServer as RfcPoprfServer, VoprfServer};
+        use crate::tests::Ristretto255Sha512;
+
+        type CS = Ristretto255Sha512;
+
+        let mut rng = OsRng;
+        let metadata = Metadata(b"metadata".to_vec());
+        let identity = BlindedElement::<CS> {
+            value: <CS as CipherSuite>::Group::identity(),
+        };
+        let valid = VerifiableClient::<CS>::blind(b"input", &mut rng)?.message;
+        let batch = [valid.clone(), identity.clone()];
+        let invalid =
+            |result: Result<_, InternalError>| matches!(result, Err(InternalError::InvalidElement));
+
+        let server = NonVerifiableServer::<CS>::new(&mut rng)?;
+        assert!(invalid(
+            server.evaluate(identity.clone(), &metadata).map(|_| ())
+        ));
+        let prepared = server.with_metadata(&metadata)?;
+        assert!(invalid(prepared.evaluate(&identity).map(|_| ())));
+        assert!(invalid(prepared.batch_evaluate(&batch).map(|_| ())));
+
+        let server = VerifiableServer::<CS>::new(&mut rng)?;
+        assert!(invalid(
+            server
+                .evaluate(&mut rng, identity.clone(), &metadata)
+                .map(|_| ())
+        ));
+        assert!(invalid(
+            server
+                .batch_evaluate(&mut rng, &batch, &metadata)
+                .map(|_| ())
+        ));
+        assert!(invalid(
+            server
+                .batch_evaluate_array(&mut rng, &batch, &metadata)
+                .map(|_| ())
+        ));
+        assert!(invalid(
+            server
+                .batch_evaluate_prepare(batch.iter(), &metadata)
+                .map(|_| ())
+        ));
+        // Elements prepared from a valid batch don't pass with the identity
+        let valid_batch = [valid.clone(), valid.clone()];
+        let VerifiableServerBatchEvaluatePrepareResult {
+            prepared_evaluation_elements,
+            t,
+        } = server.batch_evaluate_prepare(valid_batch.iter(), &metadata)?;
+        let prepared_evaluation_elements: Vec<_> = prepared_evaluation_elements.collect();
+        assert!(invalid(
+            VerifiableServer::batch_evaluate_finish(
+                &mut rng,
+                &batch,
+                &prepared_evaluation_elements,
+                &t,
+            )
+            .map(|_| ())
+        ));
+        let mut builder = ProofBuilder::new(&server, &metadata)?;
+        assert!(invalid(builder.evaluate(&identity).map(|_| ())));
+        let (first, _) = server.split_key(&mut rng, &metadata)?;
+        assert!(invalid(first.evaluate_first(&mut rng, &batch).map(|_| ())));
+
+        let server = PoprfServer::<CS>::new(&mut rng)?;
+        assert!(invalid(
+            server
+                .batch_evaluate(&mut rng, &batch, &metadata)
+                .map(|_| ())
+        ));
+
+        let server = OprfServer::<CS>::new(&mut rng)?;
+        assert!(invalid(server.blind_evaluate(&identity).map(|_| ())));
+        let server = VoprfServer::<CS>::new(&mut rng)?;
+        assert!(invalid(
+            server.batch_blind_evaluate(&mut rng, &batch).map(|_| ())
+        ));
+        let server = RfcPoprfServer::<CS>::new(&mut rng)?;
+        assert!(invalid(
+            server
+                .batch_blind_evaluate(&mut rng, &batch, &metadata)
+                .map(|_| ())
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_redacted_debug() -> Result<(), InternalError> {
+        use crate::rfc9497::OprfClient;
+        use crate::tests::Ristretto255Sha512;
+        use alloc::format;
+
+        type CS = Ristretto255Sha512;
+
+        let mut rng = OsRng;
+        let server = NonVerifiableServer::<CS>::new(&mut rng)?;
+        let sk = format!("{:?}", server.get_private_key());
+        let client = VerifiableClient::<CS>::blind_with_input_binding(b"input", &mut rng)?.state;
+        let blind = format!("{:?}", client.get_blind());
+        let rfc9497_client = OprfClient::<CS>::blind(b"input", &mut rng)?.state;
+        let rfc9497_blind = format!("{:?}", rfc9497_client.blind);
+
+        let key = PrivateKey::<CS>::new(&mut rng)?;
+        let prepared = server.with_metadata(&Metadata::none())?;
+        let prepared_metadata =
+            NonVerifiableServer::<CS>::prepare_metadata(&Metadata(b"metadata".to_vec()))?;
+
+        let debug = [
+            (format!("{:?}", server), sk),
+            (format!("{:?}", key), format!("{:?}", *key.value)),
+            (format!("{:?}", prepared), format!("{:?}", *prepared.t)),
+            (
+                format!("{:?}", prepared_metadata),
+                format!("{:?}", prepared_metadata.m),
+            ),
+            (format!("{:?}", client), blind),
+            (format!("{:?}", rfc9497_client), rfc9497_blind),
+        ];
+        for (debug, secret) in &debug {
+            // Only the `danger` feature prints secrets
+            assert_eq!(debug.contains(secret.as_str()), cfg!(feature = "danger"));
+            assert_eq!(debug.contains("<redacted>"), !cfg!(feature = "danger"));
         }
+        #[cfg(not(feature = "danger"))]
+        assert!(debug[0].0.contains("sk: \"<redacted>\""));
 
         Ok(())
     }